@@ -0,0 +1,147 @@
+//! Benchmarks for the work done on every frame, every track change and
+//! every chunk of a stream.
+//!
+//! Run with `cargo bench`; criterion compares each run against the last
+//! one, so a regression in the spectrum or the playing screen shows up as
+//! a slower frame.
+
+use std::hint::black_box;
+use std::io::{Read, Write};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ratatui::{backend::TestBackend, buffer::Buffer, layout::Rect, widgets::Widget, Frame, Terminal};
+use stream_download::storage::{memory::MemoryStorageProvider, StorageProvider};
+
+use soma_player::api::somafm::{parse_channels, parse_current_song, parse_pls_content};
+use soma_player::audio::chunks::ChunkStorageProvider;
+use soma_player::config::AppConfig;
+use soma_player::models::{format_track, parse_track_info, AudioSpectrum, Channel, TrackInfo};
+use soma_player::ui::channel_list::render_channel_selection;
+use soma_player::ui::player::render_playing_ui;
+use soma_player::ui::spectrum::SpectrumWidget;
+use soma_player::ui::{AppState, UIState};
+
+/// Seconds between frames at 30 FPS
+const FRAME: f32 = 1.0 / 30.0;
+
+/// Draws frames into an in-memory terminal, as the player would on screen.
+struct Headless {
+    terminal: Terminal<TestBackend>,
+}
+
+impl Headless {
+    fn new(width: u16, height: u16) -> Self {
+        Self { terminal: Terminal::new(TestBackend::new(width, height)).expect("test backend") }
+    }
+
+    fn draw(&mut self, render: impl FnOnce(&mut Frame)) {
+        self.terminal.draw(render).expect("draw frame");
+    }
+}
+
+fn channels() -> Vec<Channel> {
+    parse_channels(include_str!("../src/api/fixtures/channels_current.json")).expect("fixture parses")
+}
+
+fn playing_state() -> AppState {
+    let mut app = AppState::new();
+    app.ui_state = UIState::Playing;
+    app.volume = Some(70);
+    app.spectrum.set_playback(true, false);
+    app
+}
+
+fn track() -> TrackInfo {
+    TrackInfo { artist: "Thievery Corporation".to_string(), title: "Lebanese Blonde".to_string() }
+}
+
+fn spectrum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spectrum");
+    for bands in [12, 64] {
+        let mut spectrum = AudioSpectrum::new(bands);
+        spectrum.set_playback(true, false);
+        group.bench_with_input(BenchmarkId::new("step", bands), &bands, |b, _| {
+            b.iter(|| spectrum.step(black_box(FRAME)))
+        });
+
+        let area = Rect::new(0, 0, 120, 16);
+        let mut buffer = Buffer::empty(area);
+        group.bench_with_input(BenchmarkId::new("step_and_render", bands), &bands, |b, _| {
+            b.iter(|| {
+                spectrum.step(FRAME);
+                SpectrumWidget::new(&spectrum).render(area, &mut buffer);
+                black_box(&buffer);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn screens(c: &mut Criterion) {
+    let channels = channels();
+    let channel = &channels[0];
+    let config = AppConfig::default();
+    let track = track();
+
+    let mut group = c.benchmark_group("render");
+    for (width, height) in [(80, 24), (200, 60)] {
+        let size = format!("{}x{}", width, height);
+        let mut app = playing_state();
+        let mut terminal = Headless::new(width, height);
+        group.bench_function(BenchmarkId::new("playing", &size), |b| {
+            b.iter(|| {
+                app.spectrum.step(FRAME);
+                terminal.draw(|frame| render_playing_ui(frame, channel, &track, &config, &app));
+            })
+        });
+
+        let mut app = playing_state();
+        app.ui_state = UIState::SelectingChannel;
+        app.selected_index = channels.len() / 2;
+        group.bench_function(BenchmarkId::new("channel_selection", &size), |b| {
+            b.iter(|| terminal.draw(|frame| render_channel_selection(frame, &channels, channel, &track, &app, &config)))
+        });
+    }
+    group.finish();
+}
+
+fn metadata(c: &mut Criterion) {
+    let mut group = c.benchmark_group("metadata");
+    group.bench_function("parse_track_info", |b| {
+        b.iter(|| parse_track_info(black_box("Thievery Corporation - Lebanese Blonde - 2002 Remaster")))
+    });
+    let track = track();
+    group.bench_function("format_track", |b| {
+        b.iter(|| format_track(black_box("{artist} — {title} ({channel})"), "Groove Salad", &track))
+    });
+    let pls = "[playlist]\nnumberofentries=2\nFile1=https://ice1.somafm.com/groovesalad-256-mp3\nTitle1=SomaFM: Groove Salad\nLength1=-1\nFile2=https://ice2.somafm.com/groovesalad-256-mp3\nVersion=2\n";
+    group.bench_function("parse_pls_content", |b| b.iter(|| parse_pls_content(black_box(pls))));
+    let song = r#"{"songs": [{"title": "Lebanese Blonde", "artist": "Thievery Corporation", "album": "The Mirror Conspiracy", "date": "1736100000"}]}"#;
+    group.bench_function("parse_current_song", |b| b.iter(|| parse_current_song(black_box(song))));
+    let body = include_str!("../src/api/fixtures/channels_current.json");
+    group.bench_function("parse_channels", |b| b.iter(|| parse_channels(black_box(body))));
+    group.finish();
+}
+
+/// Writes 4 MiB of stream through `provider`'s storage in network-sized
+/// pieces and reads it back in decoder-sized ones, as a download would.
+fn store_and_read(provider: impl StorageProvider) {
+    let (mut reader, mut writer) = provider.into_reader_writer(None).expect("storage");
+    let piece = [0x55u8; 16 * 1024];
+    let mut buf = [0u8; 4096];
+    for _ in 0..256 {
+        writer.write_all(&piece).expect("write");
+        while reader.read(&mut buf).expect("read") > 0 {}
+    }
+    black_box(&buf);
+}
+
+fn stream(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stream");
+    group.bench_function("chunk_storage", |b| b.iter(|| store_and_read(ChunkStorageProvider::default())));
+    group.bench_function("memory_storage", |b| b.iter(|| store_and_read(MemoryStorageProvider)));
+    group.finish();
+}
+
+criterion_group!(benches, spectrum, screens, metadata, stream);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! Feeds arbitrary response headers to the ICY header parsing the player
+//! does on connecting, up to the stream details it shows.
+//!
+//! The input is read as `name: value` lines; lines that are not valid
+//! header names or values are skipped, as an HTTP client would reject them.
+
+#![no_main]
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use icy_metadata::IcyHeaders;
+use libfuzzer_sys::fuzz_target;
+use soma_player::models::StreamInfo;
+
+fuzz_target!(|data: &[u8]| {
+    let mut headers = HeaderMap::new();
+    for line in data.split(|&byte| byte == b'\n') {
+        let Some(colon) = line.iter().position(|&byte| byte == b':') else {
+            continue;
+        };
+        let (name, value) = (&line[..colon], line[colon + 1..].trim_ascii());
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name), HeaderValue::from_bytes(value)) {
+            headers.append(name, value);
+        }
+    }
+
+    let icy_headers = IcyHeaders::parse_from_headers(&headers);
+    let info = StreamInfo::new(
+        icy_headers.name(),
+        icy_headers.genre(),
+        icy_headers.description(),
+        icy_headers.station_url(),
+        icy_headers.bitrate(),
+    );
+    let _ = info.summary();
+    let _ = icy_headers.metadata_interval();
+});
@@ -0,0 +1,36 @@
+//! Feeds an arbitrary stream through the ICY metadata reader and the track
+//! parsing behind it, as the player and channel monitor do with the audio
+//! stream.
+//!
+//! The first two bytes pick the metadata interval and the third the size
+//! of the reads, so blocks straddle reads in every way. The stream ends
+//! wherever the input does, part way through a read included.
+
+#![no_main]
+
+use std::io::{Cursor, Read};
+use std::num::NonZeroUsize;
+
+use icy_metadata::IcyMetadataReader;
+use libfuzzer_sys::fuzz_target;
+use soma_player::audio::icy::WholeReads;
+use soma_player::models::{format_track, parse_track_info};
+
+fuzz_target!(|data: &[u8]| {
+    let [a, b, read_size, stream @ ..] = data else {
+        return;
+    };
+    let interval = NonZeroUsize::new(u16::from_le_bytes([*a, *b]) as usize);
+    let mut reader = IcyMetadataReader::new(WholeReads::new(Cursor::new(stream.to_vec())), interval, |metadata| {
+        if let Ok(metadata) = metadata
+            && let Some(stream_title) = metadata.stream_title()
+        {
+            let track = parse_track_info(stream_title);
+            assert!(!track.artist.is_empty());
+            let _ = format_track("{artist} - {title} ({channel})", "Groove Salad", &track);
+        }
+    });
+
+    let mut buf = vec![0u8; *read_size as usize + 1];
+    while reader.read(&mut buf).is_ok() {}
+});
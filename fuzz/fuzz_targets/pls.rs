@@ -0,0 +1,15 @@
+//! Feeds arbitrary bytes to the `.pls` playlist parser, which reads
+//! whatever a playlist URL returns.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use soma_player::api::somafm::parse_pls_content;
+
+fuzz_target!(|data: &[u8]| {
+    let contents = String::from_utf8_lossy(data);
+    if let Some(url) = parse_pls_content(&contents) {
+        assert!(url.starts_with("http"));
+        assert_eq!(url.trim(), url);
+    }
+});
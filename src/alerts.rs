@@ -0,0 +1,230 @@
+//! Track alerts for artists and titles on a wishlist.
+//!
+//! Alert patterns are configured in the `[alerts]` section. A plain pattern
+//! matches as a case-insensitive substring; a pattern wrapped in slashes is
+//! a case-insensitive regular expression. Every channel's track is checked
+//! once per change, whether it is the channel being played or a monitored
+//! one.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use soma_player::alerts::AlertPattern;
+//!
+//! let pattern = AlertPattern::parse("/^boards of/").unwrap();
+//! assert!(pattern.is_match("Boards of Canada"));
+//! assert!(!pattern.is_match("Aphex Twin"));
+//! ```
+
+use std::collections::HashMap;
+
+use log::{info, warn};
+use regex::{Regex, RegexBuilder};
+
+use crate::config::AlertConfig;
+use crate::models::{Channel, TrackInfo};
+use crate::notifications::{run_hook, send_desktop_notification};
+
+/// A single artist or title pattern.
+#[derive(Debug, Clone)]
+pub enum AlertPattern {
+    /// Case-insensitive substring, stored lowercased
+    Substring(String),
+    /// Case-insensitive regular expression
+    Regex(Regex),
+}
+
+impl AlertPattern {
+    /// Parses a pattern, treating `/.../` as a regular expression.
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err("Empty alert pattern".to_string());
+        }
+
+        if let Some(expr) = pattern
+            .strip_prefix('/')
+            .and_then(|p| p.strip_suffix('/'))
+            .filter(|p| !p.is_empty())
+        {
+            RegexBuilder::new(expr)
+                .case_insensitive(true)
+                .build()
+                .map(AlertPattern::Regex)
+                .map_err(|e| format!("Invalid alert pattern '{}': {}", pattern, e))
+        } else {
+            Ok(AlertPattern::Substring(pattern.to_lowercase()))
+        }
+    }
+
+    /// Returns whether `text` matches the pattern.
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            AlertPattern::Substring(needle) => text.to_lowercase().contains(needle.as_str()),
+            AlertPattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// A track that matched an alert pattern.
+#[derive(Debug, Clone)]
+pub struct TrackAlert {
+    pub channel_id: String,
+    pub channel_title: String,
+    pub track: TrackInfo,
+    /// The pattern as written in the configuration
+    pub pattern: String,
+}
+
+/// Checks tracks against the configured patterns and announces matches.
+#[derive(Debug, Default)]
+pub struct AlertMatcher {
+    artists: Vec<(String, AlertPattern)>,
+    titles: Vec<(String, AlertPattern)>,
+    notify: bool,
+    hook: Option<String>,
+    /// Last track checked on each channel, so each track alerts only once
+    last_checked: HashMap<String, TrackInfo>,
+}
+
+impl AlertMatcher {
+    /// Builds a matcher from configuration. Invalid patterns are logged and
+    /// skipped.
+    pub fn new(config: &AlertConfig) -> Self {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|p| match AlertPattern::parse(p) {
+                    Ok(pattern) => Some((p.trim().to_string(), pattern)),
+                    Err(e) => {
+                        warn!("{}", e);
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        Self {
+            artists: compile(&config.artists),
+            titles: compile(&config.titles),
+            notify: config.notify,
+            hook: config.hook.clone().filter(|h| !h.trim().is_empty()),
+            last_checked: HashMap::new(),
+        }
+    }
+
+    /// Returns whether no patterns are configured.
+    pub fn is_empty(&self) -> bool {
+        self.artists.is_empty() && self.titles.is_empty()
+    }
+
+    /// Checks the track currently on `channel`. Returns an alert only the
+    /// first time a matching track is seen on that channel.
+    pub fn check(&mut self, channel: &Channel, track: &TrackInfo) -> Option<TrackAlert> {
+        if self.is_empty() || track.title == "Loading..." {
+            return None;
+        }
+        if self.last_checked.get(&channel.id) == Some(track) {
+            return None;
+        }
+        self.last_checked.insert(channel.id.clone(), track.clone());
+
+        let pattern = self.matching_pattern(track)?;
+        info!("Alert on {}: {} - {} matches '{}'", channel.id, track.artist, track.title, pattern);
+
+        Some(TrackAlert {
+            channel_id: channel.id.clone(),
+            channel_title: channel.title.clone(),
+            track: track.clone(),
+            pattern: pattern.to_string(),
+        })
+    }
+
+    /// Sends the desktop notification and runs the hook for `alert`.
+    pub fn announce(&self, channel: &Channel, alert: &TrackAlert) {
+        if self.notify {
+            send_desktop_notification(
+                &format!("Alert: {}", alert.channel_title),
+                &format!("{} - {}", alert.track.artist, alert.track.title),
+                None,
+            );
+        }
+
+        if let Some(hook) = &self.hook {
+            run_hook(hook, channel, &alert.track, &[("SOMA_ALERT_PATTERN", &alert.pattern)]);
+        }
+    }
+
+    fn matching_pattern(&self, track: &TrackInfo) -> Option<&str> {
+        self.artists
+            .iter()
+            .find(|(_, pattern)| pattern.is_match(&track.artist))
+            .or_else(|| self.titles.iter().find(|(_, pattern)| pattern.is_match(&track.title)))
+            .map(|(source, _)| source.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(artist: &str, title: &str) -> TrackInfo {
+        TrackInfo {
+            artist: artist.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_pattern_parse() {
+        assert!(matches!(AlertPattern::parse("Aphex"), Ok(AlertPattern::Substring(s)) if s == "aphex"));
+        assert!(matches!(AlertPattern::parse("/^aph/"), Ok(AlertPattern::Regex(_))));
+        assert!(AlertPattern::parse("/[unclosed/").is_err());
+        assert!(AlertPattern::parse("  ").is_err());
+
+        // A lone slash is a substring, not an empty regex
+        assert!(matches!(AlertPattern::parse("/"), Ok(AlertPattern::Substring(_))));
+    }
+
+    #[test]
+    fn test_pattern_matching_is_case_insensitive() {
+        assert!(AlertPattern::parse("boards").unwrap().is_match("Boards of Canada"));
+        assert!(AlertPattern::parse("/CANADA$/").unwrap().is_match("Boards of Canada"));
+        assert!(!AlertPattern::parse("/^canada/").unwrap().is_match("Boards of Canada"));
+    }
+
+    #[test]
+    fn test_matcher_alerts_once_per_track() {
+        let config = AlertConfig {
+            artists: vec!["boards of canada".to_string(), "/[bad/".to_string()],
+            titles: vec!["/^roygbiv$/".to_string()],
+            ..Default::default()
+        };
+        let mut matcher = AlertMatcher::new(&config);
+        assert_eq!(matcher.artists.len(), 1);
+
+        let drone = Channel::named("dronezone", "Drone Zone");
+        let alert = matcher.check(&drone, &track("Boards of Canada", "Dayvan Cowboy")).unwrap();
+        assert_eq!(alert.pattern, "boards of canada");
+        assert!(matcher.check(&drone, &track("Boards of Canada", "Dayvan Cowboy")).is_none());
+
+        // The same track on another channel alerts again
+        assert!(matcher.check(&Channel::named("lush", "Lush"), &track("Boards of Canada", "Dayvan Cowboy")).is_some());
+
+        assert!(matcher.check(&drone, &track("Someone Else", "Something")).is_none());
+        let alert = matcher.check(&drone, &track("Boards of Canada", "Roygbiv")).unwrap();
+        assert_eq!(alert.pattern, "boards of canada");
+        let alert = matcher.check(&drone, &track("Cover Band", "ROYGBIV")).unwrap();
+        assert_eq!(alert.pattern, "/^roygbiv$/");
+    }
+
+    #[test]
+    fn test_matcher_ignores_loading_placeholder() {
+        let config = AlertConfig {
+            titles: vec!["loading".to_string()],
+            ..Default::default()
+        };
+        let mut matcher = AlertMatcher::new(&config);
+        assert!(matcher.check(&Channel::named("groovesalad", "Groove Salad"), &TrackInfo::default()).is_none());
+    }
+}
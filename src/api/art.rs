@@ -0,0 +1,194 @@
+//! Channel logos cached on disk, shown as the icon of desktop
+//! notifications.
+//!
+//! Logos are kept in `art/` in the state directory, named after the
+//! channel ID, and a logo once fetched is never fetched again. By default
+//! a channel's logo is fetched the first time the channel is played; with
+//! `prefetch_art` every channel's logo is fetched in the background at
+//! startup, [`PREFETCH_CONCURRENCY`] at a time. Either way the requests go
+//! through the [`ApiClient`], so they are spaced out and retried like API
+//! requests.
+
+use std::fs;
+use std::path::PathBuf;
+
+use futures_util::StreamExt;
+use log::{debug, warn};
+use tokio::task::JoinHandle;
+
+use crate::api::ApiClient;
+use crate::models::Channel;
+use crate::state_file::get_state_directory;
+
+/// Logos fetched at the same time while prefetching
+pub const PREFETCH_CONCURRENCY: usize = 2;
+
+/// Directory of cached channel logos.
+#[derive(Debug, Clone)]
+pub struct ArtCache {
+    /// `None` disables the cache
+    dir: Option<PathBuf>,
+}
+
+impl Default for ArtCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArtCache {
+    /// Uses `art/` in the state directory, or nothing if there is no state
+    /// directory.
+    pub fn new() -> Self {
+        let dir = get_state_directory()
+            .map(|dir| dir.join("art"))
+            .map_err(|e| warn!("Channel art disabled: {}", e))
+            .ok();
+        Self { dir }
+    }
+
+    /// Uses the directory at `dir`.
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir: Some(dir) }
+    }
+
+    /// A cache that never stores anything.
+    pub fn disabled() -> Self {
+        Self { dir: None }
+    }
+
+    /// Where the logo of `channel` is stored, keeping the extension of its
+    /// URL; `None` for channels without a logo.
+    fn file(&self, channel: &Channel) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let url = channel.image.as_deref().filter(|url| !url.trim().is_empty())?;
+        let name: String = channel.id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        let extension = url
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+            .filter(|extension| matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "gif"))
+            .unwrap_or_else(|| "png".to_string());
+        (!name.is_empty()).then(|| dir.join(format!("{}.{}", name, extension)))
+    }
+
+    /// Path of the logo of `channel`, once it was fetched.
+    pub fn cached(&self, channel: &Channel) -> Option<PathBuf> {
+        self.file(channel).filter(|path| path.is_file())
+    }
+
+    /// Fetches the logo of `channel` unless it is cached, and returns its
+    /// path.
+    pub async fn fetch(&self, api: &ApiClient, channel: &Channel) -> Option<PathBuf> {
+        let path = self.file(channel)?;
+        if path.is_file() {
+            return Some(path);
+        }
+        let url = channel.image.as_deref()?;
+        let result = match api.fetch_bytes(url).await {
+            Ok(image) => write_atomically(&path, &image),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => {
+                debug!("Cached the logo of {} in {}", channel.id, path.display());
+                Some(path)
+            }
+            Err(e) => {
+                warn!("Failed to fetch the logo of {}: {}", channel.id, e);
+                None
+            }
+        }
+    }
+
+    /// Fetches the logo of `channel` in the background unless it is cached.
+    pub fn fetch_in_background(&self, api: &ApiClient, channel: &Channel) {
+        if self.file(channel).is_none_or(|path| path.is_file()) {
+            return;
+        }
+        let (cache, api, channel) = (self.clone(), api.clone(), channel.clone());
+        tokio::spawn(async move {
+            cache.fetch(&api, &channel).await;
+        });
+    }
+
+    /// Fetches the logos of `channels` that are not cached yet in the
+    /// background, [`PREFETCH_CONCURRENCY`] at a time.
+    pub fn prefetch(&self, api: ApiClient, channels: &[Channel]) -> JoinHandle<()> {
+        let missing: Vec<Channel> = channels
+            .iter()
+            .filter(|channel| self.file(channel).is_some_and(|path| !path.is_file()))
+            .cloned()
+            .collect();
+        let cache = self.clone();
+        tokio::spawn(async move {
+            if !missing.is_empty() {
+                debug!("Prefetching {} channel logos", missing.len());
+            }
+            futures_util::stream::iter(&missing)
+                .for_each_concurrent(PREFETCH_CONCURRENCY, |channel| {
+                    let (cache, api) = (&cache, &api);
+                    async move {
+                        cache.fetch(api, channel).await;
+                    }
+                })
+                .await;
+        })
+    }
+}
+
+/// Writes `contents` next to `path` first, so a logo is never read half
+/// written.
+fn write_atomically(path: &std::path::Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let partial = path.with_extension("part");
+    fs::write(&partial, contents)
+        .and_then(|()| fs::rename(&partial, path))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::cache::ChannelCache;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_logos_are_fetched_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            // Answers a single request only
+            if let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = [0u8; 1024];
+                let _ = socket.read(&mut buffer).await;
+                let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\nlogo").await;
+            }
+        });
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = ArtCache::at(dir.path().join("art"));
+        let api = ApiClient::new().with_disk_cache(ChannelCache::disabled());
+        let groovesalad = Channel {
+            image: Some(format!("{}/img/groovesalad120.JPG", base_url)),
+            ..Channel::named("groovesalad", "Groove Salad")
+        };
+        assert_eq!(cache.cached(&groovesalad), None);
+
+        let path = dir.path().join("art").join("groovesalad.jpg");
+        assert_eq!(cache.fetch(&api, &groovesalad).await, Some(path.clone()));
+        assert_eq!(fs::read(&path).unwrap(), b"logo");
+        assert_eq!(cache.fetch(&api, &groovesalad).await, Some(path.clone()));
+        assert_eq!(cache.cached(&groovesalad), Some(path));
+
+        assert_eq!(cache.fetch(&api, &Channel::named("dronezone", "Drone Zone")).await, None);
+        assert_eq!(ArtCache::disabled().cached(&groovesalad), None);
+    }
+}
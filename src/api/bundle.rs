@@ -0,0 +1,139 @@
+//! Extra stations from a user-provided channel bundle (`--channels-file`).
+//!
+//! A bundle lists channels in the same shape as the SomaFM API, either as
+//! JSON (`{"channels": [...]}` or a bare array) or as TOML with one
+//! `[[channels]]` table per station and `[[channels.playlists]]` for its
+//! streams. Playlist URLs may point at `.pls` playlists or directly at a
+//! stream. Bundled channels are appended to the SomaFM list; entries whose
+//! ID is already taken are skipped.
+//!
+//! ```toml
+//! [[channels]]
+//! id = "myradio"
+//! title = "My Radio"
+//! description = "A station from elsewhere"
+//!
+//! [[channels.playlists]]
+//! url = "https://radio.example.com/stream.mp3"
+//! format = "mp3"
+//! quality = "high"
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::models::{Channel, SomaFmResponse};
+
+/// A bare JSON array of channels, the alternative to [`SomaFmResponse`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonBundle {
+    Wrapped(SomaFmResponse),
+    Bare(Vec<Channel>),
+}
+
+/// Parses a bundle, as TOML when `toml` is set and as JSON otherwise.
+/// Channels without an ID or a playlist URL are skipped with a warning, and
+/// a missing title falls back to the ID.
+pub fn parse_bundle(contents: &str, toml: bool) -> Result<Vec<Channel>, String> {
+    let channels = if toml {
+        ::toml::from_str::<SomaFmResponse>(contents)
+            .map_err(|e| e.to_string())?
+            .channels
+    } else {
+        match serde_json::from_str::<JsonBundle>(contents).map_err(|e| e.to_string())? {
+            JsonBundle::Wrapped(response) => response.channels,
+            JsonBundle::Bare(channels) => channels,
+        }
+    };
+
+    Ok(channels
+        .into_iter()
+        .filter_map(|mut channel| {
+            channel.id = channel.id.trim().to_string();
+            channel.playlists.retain(|playlist| !playlist.url.trim().is_empty());
+            if channel.id.is_empty() || channel.playlists.is_empty() {
+                warn!("Skipping bundled channel '{}' without an ID or stream URL", channel.title);
+                return None;
+            }
+            if channel.title.trim().is_empty() {
+                channel.title = channel.id.clone();
+            }
+            Some(channel)
+        })
+        .collect())
+}
+
+/// Reads the bundle at `path`, as TOML for a `.toml` file and JSON
+/// otherwise.
+pub fn load_bundle(path: &Path) -> Result<Vec<Channel>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read channel bundle {}: {}", path.display(), e))?;
+    let toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    parse_bundle(&contents, toml).map_err(|e| format!("Invalid channel bundle {}: {}", path.display(), e))
+}
+
+/// Appends the bundled channels whose IDs are not taken yet and returns how
+/// many were added.
+pub fn merge_bundle(channels: &mut Vec<Channel>, bundle: &[Channel]) -> usize {
+    let mut added = 0;
+    for channel in bundle {
+        if let Some(known) = channels.iter().find(|known| known.id == channel.id) {
+            // A cached list saved during an earlier session may already
+            // hold the bundled station itself
+            let same_streams = known.playlists.iter().map(|p| &p.url).eq(channel.playlists.iter().map(|p| &p.url));
+            if !same_streams {
+                warn!("Skipping bundled channel '{}', the ID is already taken", channel.id);
+            }
+            continue;
+        }
+        channels.push(channel.clone());
+        added += 1;
+    }
+    if added > 0 {
+        info!("Added {} channels from the channel bundle", added);
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_merge_bundles() {
+        let toml = r#"
+[[channels]]
+id = "myradio"
+
+[[channels.playlists]]
+url = "https://radio.example.com/stream.mp3"
+
+[[channels]]
+id = "groovesalad"
+title = "Another Groove Salad"
+playlists = [{ url = "https://example.com/gs.pls" }]
+
+[[channels]]
+id = "nostream"
+"#;
+        let bundle = parse_bundle(toml, true).unwrap();
+        assert_eq!(bundle.len(), 2);
+        assert_eq!(bundle[0].title, "myradio");
+
+        let json = r#"[{"id": "jsonradio", "title": "JSON Radio", "playlists": [{"url": "https://example.com/a.mp3"}]}]"#;
+        assert_eq!(parse_bundle(json, false).unwrap()[0].title, "JSON Radio");
+        let wrapped = format!(r#"{{"channels": {}}}"#, json);
+        assert_eq!(parse_bundle(&wrapped, false).unwrap().len(), 1);
+        assert!(parse_bundle("not json", false).is_err());
+
+        let mut channels = parse_bundle(r#"[{"id": "groovesalad", "playlists": [{"url": "https://api.somafm.com/groovesalad.pls"}]}]"#, false).unwrap();
+        assert_eq!(merge_bundle(&mut channels, &bundle), 1);
+        let ids: Vec<&str> = channels.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["groovesalad", "myradio"]);
+        assert_eq!(channels[0].title, "groovesalad");
+    }
+}
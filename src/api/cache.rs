@@ -0,0 +1,283 @@
+//! Last known channel list, for starting while the API is unavailable.
+//!
+//! Every successfully fetched list is written to `channels.json` in the
+//! state directory. When the API cannot be reached or returns no channels,
+//! [`ApiClient::load_channels`](crate::api::ApiClient::load_channels) falls
+//! back to that copy so the player can still start; the streams themselves
+//! are served separately and often keep working.
+//!
+//! Comparing a fresh list with the cached one also tells which channels
+//! SomaFM added since the last session. [`NewChannels`] remembers those for
+//! a few sessions so the channel lists can mark them as new.
+//!
+//! The channel played last is kept in `last_channel.json` next to the list,
+//! with the stream servers its playlist resolved to and when, so
+//! `--autostart` can connect to it while the channel list is still being
+//! fetched.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::models::Channel;
+use crate::state_file::get_state_directory;
+
+/// Where a channel list came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelSource {
+    /// Fetched from the API just now
+    Live,
+    /// Read from the cache because the API was unavailable
+    Cached,
+    /// Read from the cache to start playing right away, while the fresh
+    /// list loads in the background
+    Refreshing,
+}
+
+/// The channel played last, as stored in `last_channel.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastChannel {
+    pub channel: Channel,
+    /// URL of the playlist played
+    pub playlist_url: String,
+    /// Stream URLs the playlist resolved to, empty for a direct stream
+    #[serde(default)]
+    pub stream_urls: Vec<String>,
+    /// Unix timestamp of when the stream URLs were resolved, 0 if unknown
+    #[serde(default)]
+    pub saved_at: i64,
+}
+
+/// On-disk copy of the last fetched channel list.
+#[derive(Debug, Clone)]
+pub struct ChannelCache {
+    /// `None` disables the cache
+    path: Option<PathBuf>,
+}
+
+impl Default for ChannelCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChannelCache {
+    /// Uses `channels.json` in the state directory, or nothing if there is
+    /// no state directory.
+    pub fn new() -> Self {
+        let path = get_state_directory()
+            .map(|dir| dir.join("channels.json"))
+            .map_err(|e| warn!("Channel cache disabled: {}", e))
+            .ok();
+        Self { path }
+    }
+
+    /// Uses the file at `path`.
+    pub fn at(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+
+    /// A cache that never stores anything.
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    /// Stores `channels` as the last known list. Empty lists are not stored.
+    pub fn save(&self, channels: &[Channel]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if channels.is_empty() {
+            return;
+        }
+        let result = serde_json::to_string(channels)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => debug!("Cached {} channels in {}", channels.len(), path.display()),
+            Err(e) => warn!("Failed to cache the channel list: {}", e),
+        }
+    }
+
+    /// Reads the last known list, if there is a non-empty one.
+    pub fn load(&self) -> Option<Vec<Channel>> {
+        let path = self.path.as_ref()?;
+        let contents = fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<Vec<Channel>>(&contents) {
+            Ok(channels) if !channels.is_empty() => Some(channels),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Ignoring unreadable channel cache {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Stores `last` as the channel played last.
+    pub fn save_last(&self, last: &LastChannel) {
+        let Some(path) = self.last_path() else {
+            return;
+        };
+        let result = serde_json::to_string(last)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to save the last channel: {}", e);
+        }
+    }
+
+    /// Reads the channel played last, if one was stored.
+    pub fn load_last(&self) -> Option<LastChannel> {
+        let path = self.last_path()?;
+        let contents = fs::read_to_string(&path).ok()?;
+        serde_json::from_str(&contents)
+            .map_err(|e| warn!("Ignoring unreadable last channel {}: {}", path.display(), e))
+            .ok()
+    }
+
+    fn last_path(&self) -> Option<PathBuf> {
+        self.path.as_ref().map(|path| path.with_file_name("last_channel.json"))
+    }
+}
+
+/// Number of sessions a newly added channel stays marked as new
+pub const NEW_CHANNEL_SESSIONS: u32 = 5;
+
+/// Channels added since an earlier session, stored in `new_channels.json`
+/// in the state directory with the number of sessions left to mark them.
+#[derive(Debug, Clone, Default)]
+pub struct NewChannels {
+    /// `None` keeps the marks in memory only
+    path: Option<PathBuf>,
+    sessions_left: BTreeMap<String, u32>,
+}
+
+impl NewChannels {
+    /// Loads the marks from the state directory.
+    pub fn load() -> Self {
+        match get_state_directory() {
+            Ok(dir) => Self::at(dir.join("new_channels.json")),
+            Err(e) => {
+                warn!("New channel tracking not saved: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Loads the marks from the file at `path`.
+    pub fn at(path: PathBuf) -> Self {
+        let sessions_left = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), sessions_left }
+    }
+
+    /// Starts a session with `channels`, given the cached list from before
+    /// it was loaded. Earlier marks count down by one session, and channels
+    /// missing from the cached list are marked. Without a cached list,
+    /// nothing can be told apart and nothing is marked.
+    pub fn start_session(&mut self, previous: Option<&[Channel]>, channels: &[Channel]) {
+        self.sessions_left.retain(|_, left| {
+            *left = left.saturating_sub(1);
+            *left > 0
+        });
+        if let Some(previous) = previous {
+            let added: Vec<&str> = channels
+                .iter()
+                .filter(|channel| !previous.iter().any(|known| known.id == channel.id))
+                .map(|channel| channel.id.as_str())
+                .collect();
+            self.mark(&added);
+        }
+        self.save();
+    }
+
+    /// Marks channels that were added while the player is running.
+    pub fn add(&mut self, ids: &[&str]) {
+        if !ids.is_empty() {
+            self.mark(ids);
+            self.save();
+        }
+    }
+
+    fn mark(&mut self, ids: &[&str]) {
+        for id in ids {
+            debug!("Marking {} as a new channel", id);
+            self.sessions_left.insert(id.to_string(), NEW_CHANNEL_SESSIONS);
+        }
+    }
+
+    /// IDs of the channels currently marked as new.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.sessions_left.keys().map(String::as_str)
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string(&self.sessions_left)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to save new channels: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_channels_stay_marked_for_a_few_sessions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("new_channels.json");
+        let known = vec![Channel::named("groovesalad", "Groove Salad"), Channel::named("dronezone", "Drone Zone")];
+        let mut fresh = known.clone();
+        fresh.push(Channel::named("vaporwaves", "Vaporwaves"));
+
+        // Nothing to compare with on the very first run
+        let mut new_channels = NewChannels::at(path.clone());
+        new_channels.start_session(None, &known);
+        assert_eq!(new_channels.ids().count(), 0);
+
+        new_channels.start_session(Some(&known), &fresh);
+        assert_eq!(new_channels.ids().collect::<Vec<_>>(), ["vaporwaves"]);
+
+        for _ in 1..NEW_CHANNEL_SESSIONS {
+            let mut next_session = NewChannels::at(path.clone());
+            next_session.start_session(Some(&fresh), &fresh);
+            assert_eq!(next_session.ids().collect::<Vec<_>>(), ["vaporwaves"]);
+        }
+        let mut next_session = NewChannels::at(path);
+        next_session.start_session(Some(&fresh), &fresh);
+        assert_eq!(next_session.ids().count(), 0);
+    }
+
+    #[test]
+    fn test_last_channel_is_kept_next_to_the_list() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = ChannelCache::at(dir.path().join("channels.json"));
+        assert!(cache.load_last().is_none());
+
+        let last = LastChannel {
+            channel: Channel::named("groovesalad", "Groove Salad"),
+            playlist_url: "https://somafm.com/groovesalad.pls".to_string(),
+            stream_urls: vec!["https://ice1.somafm.com/groovesalad-128-mp3".to_string()],
+            saved_at: 1_760_000_000,
+        };
+        cache.save_last(&last);
+        let loaded = cache.load_last().unwrap();
+        assert_eq!(loaded.channel.id, "groovesalad");
+        assert_eq!((loaded.playlist_url, loaded.stream_urls), (last.playlist_url.clone(), last.stream_urls.clone()));
+        assert!(dir.path().join("last_channel.json").exists());
+
+        ChannelCache::disabled().save_last(&last);
+        assert!(ChannelCache::disabled().load_last().is_none());
+    }
+}
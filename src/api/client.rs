@@ -0,0 +1,655 @@
+//! Client for the SomaFM API and playlists.
+//!
+//! [`ApiClient`] owns the HTTP client, the API base URL, the channel list
+//! caches and a rate limiter, so every request to SomaFM goes through one
+//! place. It is cheap to clone; clones share the caches and the limiter.
+//!
+//! - Requests are spaced at least [`MIN_REQUEST_INTERVAL`] apart.
+//! - A fetched channel list is reused for [`CHANNEL_LIST_TTL`] and saved to
+//!   a [`ChannelCache`] on disk as a fallback for when the API is down.
+//! - Resolved `.pls` playlists are remembered for [`PLAYLIST_TTL`], and
+//!   those of the channel played last across sessions.
+//! - Stream servers that failed to connect are skipped for
+//!   [`FAILED_STREAM_TTL`] when a playlist lists other servers.
+//! - Requests failing for a transient reason are retried with a jittered
+//!   backoff, and repeated failures of API requests open a
+//!   [`CircuitBreaker`] that fails further ones right away for
+//!   [`BREAKER_COOLDOWN`]. Playlists and logos on other servers are not
+//!   counted.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use soma_player::api::ApiClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let api = ApiClient::new();
+//! let channels = api.fetch_channels().await?;
+//! println!("{} channels", channels.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use log::{debug, warn};
+
+use crate::api::cache::{ChannelCache, ChannelSource, LastChannel};
+use crate::api::retry::{BreakerState, CircuitBreaker, RetryPolicy};
+use crate::api::{parse_channels, parse_current_song, parse_pls_urls};
+use crate::models::{Channel, Playlist, StreamQuality, TrackInfo};
+
+/// Base URL of the public SomaFM API
+pub const DEFAULT_BASE_URL: &str = "https://api.somafm.com";
+
+/// Minimum time between two requests to SomaFM
+pub const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a fetched channel list is reused instead of fetched again
+pub const CHANNEL_LIST_TTL: Duration = Duration::from_secs(10);
+
+/// How long a resolved `.pls` playlist is reused
+pub const PLAYLIST_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How long a stream server that failed is tried after the playlist's others
+pub const FAILED_STREAM_TTL: Duration = Duration::from_secs(2 * 60);
+
+/// Failed requests in a row that open the circuit breaker
+pub const BREAKER_THRESHOLD: u32 = 3;
+
+/// How long an open circuit breaker fails requests before letting one through
+pub const BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Timeout for API requests and for stream probes to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// State shared between clones of a client
+type Shared<T> = Arc<Mutex<T>>;
+
+/// Shared client for SomaFM API, playlist and stream probe requests.
+#[derive(Clone)]
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    disk_cache: ChannelCache,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+    channels: Shared<Option<(Instant, Vec<Channel>)>>,
+    playlists: Shared<HashMap<String, (Instant, Vec<String>)>>,
+    /// When each stream URL last failed to connect
+    failed_streams: Shared<HashMap<String, Instant>>,
+}
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiClient {
+    /// Client for the public API, caching the channel list in the state
+    /// directory.
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| {
+                warn!("Falling back to the default HTTP client: {}", e);
+                reqwest::Client::new()
+            });
+        Self {
+            http,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            disk_cache: ChannelCache::new(),
+            limiter: Arc::new(RateLimiter::new(MIN_REQUEST_INTERVAL)),
+            retry: RetryPolicy::default(),
+            breaker: Arc::new(CircuitBreaker::new(BREAKER_THRESHOLD, BREAKER_COOLDOWN)),
+            channels: Arc::new(Mutex::new(None)),
+            playlists: Arc::new(Mutex::new(HashMap::new())),
+            failed_streams: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Uses another API server, e.g. a mirror or a test server.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Uses a preconfigured HTTP client, e.g. one going through a proxy.
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Uses another on-disk channel cache.
+    pub fn with_disk_cache(mut self, cache: ChannelCache) -> Self {
+        self.disk_cache = cache;
+        self
+    }
+
+    /// Spaces requests at least `interval` apart.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.limiter = Arc::new(RateLimiter::new(interval));
+        self
+    }
+
+    /// Retries failed requests according to `policy`.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Opens the circuit breaker after `threshold` failed requests in a row,
+    /// for `cooldown`.
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.breaker = Arc::new(CircuitBreaker::new(threshold, cooldown));
+        self
+    }
+
+    /// Whether requests to SomaFM currently go through
+    pub fn breaker_state(&self) -> BreakerState {
+        self.breaker.state(Instant::now())
+    }
+
+    /// Base URL API requests go to
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Fetches the list of SomaFM channels, reusing a list fetched less than
+    /// [`CHANNEL_LIST_TTL`] ago.
+    pub async fn fetch_channels(&self) -> Result<Vec<Channel>, Box<dyn std::error::Error>> {
+        if let Some((fetched, channels)) = &*lock(&self.channels)
+            && fetched.elapsed() < CHANNEL_LIST_TTL
+        {
+            debug!("Reusing channel list fetched {:?} ago", fetched.elapsed());
+            return Ok(channels.clone());
+        }
+
+        let body = self.fetch_channels_json().await?;
+        let channels = parse_channels(&body)?;
+
+        *lock(&self.channels) = Some((Instant::now(), channels.clone()));
+        Ok(channels)
+    }
+
+    /// Fetches the channel list response as it is, without parsing it.
+    pub async fn fetch_channels_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let url = format!("{}/channels.json", self.base_url);
+        self.get_text(&url).await
+    }
+
+    /// Fetches the track `channel_id` is playing from its recently played
+    /// songs, for streams without ICY metadata.
+    pub async fn fetch_current_song(&self, channel_id: &str) -> Result<Option<TrackInfo>, Box<dyn std::error::Error>> {
+        let url = format!("{}/songs/{}.json", self.base_url, channel_id);
+        let body = self.get_text(&url).await?;
+        Ok(parse_current_song(&body)?)
+    }
+
+    /// Fetches the channel list, falling back to the list cached on disk
+    /// when the API fails or returns no channels.
+    pub async fn load_channels(&self) -> Result<(Vec<Channel>, ChannelSource), Box<dyn std::error::Error>> {
+        let error = match self.fetch_channels().await {
+            Ok(channels) if !channels.is_empty() => {
+                self.disk_cache.save(&channels);
+                return Ok((channels, ChannelSource::Live));
+            }
+            Ok(_) => "SomaFM returned no channels".to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        match self.disk_cache.load() {
+            Some(channels) => {
+                warn!("Using cached channel list: {}", error);
+                Ok((channels, ChannelSource::Cached))
+            }
+            None => Err(format!("{} and no cached channel list is available", error).into()),
+        }
+    }
+
+    /// The channel list cached on disk, as written by the last successful
+    /// fetch.
+    pub fn cached_channels(&self) -> Option<Vec<Channel>> {
+        self.disk_cache.load()
+    }
+
+    /// Stores `channels` as the last known list on disk.
+    pub fn save_channel_cache(&self, channels: &[Channel]) {
+        self.disk_cache.save(channels);
+    }
+
+    /// Stores `channel`, playing at `quality`, as the channel played last,
+    /// with the stream servers its playlist resolved to.
+    pub fn save_last_channel(&self, channel: &Channel, quality: StreamQuality) {
+        let Some(playlist) = channel.preferred_playlist(quality) else {
+            return;
+        };
+        let stream_urls = lock(&self.playlists)
+            .get(&playlist.url)
+            .map(|(_, urls)| urls.clone())
+            .unwrap_or_default();
+        self.disk_cache.save_last(&LastChannel {
+            channel: channel.clone(),
+            playlist_url: playlist.url.clone(),
+            stream_urls,
+            saved_at: Utc::now().timestamp(),
+        });
+    }
+
+    /// The channel played last in an earlier session, as saved.
+    pub fn last_channel(&self) -> Option<LastChannel> {
+        self.disk_cache.load_last()
+    }
+
+    /// Picks up `last` to play it again. Its playlist counts as resolved
+    /// when it was saved, so connecting to it needs no request to SomaFM
+    /// unless that was [`PLAYLIST_TTL`] or longer ago.
+    pub fn restore_last_channel(&self, last: LastChannel) -> Channel {
+        let resolved = u64::try_from(Utc::now().timestamp() - last.saved_at)
+            .ok()
+            .map(Duration::from_secs)
+            .filter(|age| *age < PLAYLIST_TTL)
+            .and_then(|age| Instant::now().checked_sub(age));
+        match resolved {
+            Some(resolved) if !last.stream_urls.is_empty() => {
+                lock(&self.playlists)
+                    .entry(last.playlist_url)
+                    .or_insert((resolved, last.stream_urls));
+            }
+            Some(_) => {}
+            None => debug!("Stream servers of {} are stale, resolving them again", last.channel.id),
+        }
+        last.channel
+    }
+
+    /// Fetches a .pls playlist file and returns its first stream URL that
+    /// did not fail recently.
+    pub async fn parse_pls_playlist(&self, pls_url: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let cached = lock(&self.playlists)
+            .get(pls_url)
+            .filter(|(resolved, _)| resolved.elapsed() < PLAYLIST_TTL)
+            .map(|(_, urls)| urls.clone());
+        let urls = match cached {
+            Some(urls) => urls,
+            None => {
+                let pls_content = self.get_text(pls_url).await?;
+                let urls = parse_pls_urls(&pls_content);
+                if urls.is_empty() {
+                    return Err("No valid stream URL found in .pls playlist".into());
+                }
+                lock(&self.playlists).insert(pls_url.to_string(), (Instant::now(), urls.clone()));
+                urls
+            }
+        };
+
+        // With every server failing, the one that failed longest ago
+        let failed = self.failed_streams();
+        let url = urls
+            .iter()
+            .min_by_key(|url| failed.get(*url).copied())
+            .ok_or("No valid stream URL found in .pls playlist")?;
+        Ok(url.clone())
+    }
+
+    /// Remembers that `stream_url` failed to connect, so it is tried last
+    /// for [`FAILED_STREAM_TTL`].
+    pub fn mark_stream_failed(&self, stream_url: &str) {
+        debug!("Skipping {} for {:?}", stream_url, FAILED_STREAM_TTL);
+        lock(&self.failed_streams).insert(stream_url.to_string(), Instant::now());
+    }
+
+    /// Another server of the playlist `stream_url` was resolved from that
+    /// did not fail recently.
+    pub fn failover_url(&self, stream_url: &str) -> Option<String> {
+        let failed = self.failed_streams();
+        lock(&self.playlists)
+            .values()
+            .find(|(_, urls)| urls.iter().any(|url| url == stream_url))
+            .and_then(|(_, urls)| urls.iter().find(|url| *url != stream_url && !failed.contains_key(*url)))
+            .cloned()
+    }
+
+    /// Stream URLs that failed less than [`FAILED_STREAM_TTL`] ago
+    fn failed_streams(&self) -> HashMap<String, Instant> {
+        let mut failed = lock(&self.failed_streams);
+        failed.retain(|_, at| at.elapsed() < FAILED_STREAM_TTL);
+        failed.clone()
+    }
+
+    /// Returns the direct stream URL for the channel's playlist closest to
+    /// `quality`, resolving `.pls` playlists.
+    pub async fn resolve_stream_url(&self, channel: &Channel, quality: StreamQuality) -> Result<String, Box<dyn std::error::Error>> {
+        let playlist = channel.preferred_playlist(quality)
+            .ok_or("No playable stream URL found for this channel.")?;
+        self.resolve_playlist_url(playlist).await
+    }
+
+    /// Returns the direct stream URL of `playlist`, resolving `.pls` playlists.
+    pub async fn resolve_playlist_url(&self, playlist: &Playlist) -> Result<String, Box<dyn std::error::Error>> {
+        if playlist.url.ends_with(".pls") {
+            self.parse_pls_playlist(&playlist.url).await
+        } else {
+            Ok(playlist.url.clone())
+        }
+    }
+
+    /// Probes a channel's primary stream and returns the time taken to receive
+    /// the response headers.
+    ///
+    /// `.pls` playlists are resolved first; only the headers of the actual
+    /// stream are awaited, so no audio data is downloaded.
+    pub async fn check_channel_stream(&self, channel: &Channel) -> Result<Duration, Box<dyn std::error::Error>> {
+        let stream_url = self.resolve_stream_url(channel, StreamQuality::default()).await?;
+
+        let started = Instant::now();
+        let response = self.http.get(&stream_url).send().await?;
+        let latency = started.elapsed();
+        response.error_for_status()?;
+
+        Ok(latency)
+    }
+
+    /// Fetches a file from SomaFM as it is, e.g. a channel logo.
+    pub async fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(self.get(url).await?.to_vec())
+    }
+
+    /// Fetches `url` as text.
+    async fn get_text(&self, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(String::from_utf8_lossy(&self.get(url).await?).into_owned())
+    }
+
+    /// Whether `url` is a request to the API itself rather than to a
+    /// playlist or logo server.
+    fn is_api_url(&self, url: &str) -> bool {
+        url.strip_prefix(&self.base_url)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    /// Fetches `url`, retrying transient failures. Requests to the API go
+    /// through the circuit breaker; those to other servers neither wait on
+    /// it nor count towards opening it.
+    async fn get(&self, url: &str) -> Result<bytes::Bytes, Box<dyn std::error::Error>> {
+        let guarded = self.is_api_url(url);
+        let record = |success| {
+            if guarded {
+                self.breaker.record(success, Instant::now());
+            }
+        };
+        if guarded && !self.breaker.allow(Instant::now()) {
+            let retry_in = match self.breaker_state() {
+                BreakerState::Open { retry_in } => retry_in,
+                BreakerState::Closed => Duration::ZERO,
+            };
+            return Err(format!(
+                "SomaFM API unavailable after repeated failures, next attempt in {}s",
+                retry_in.as_secs().max(1)
+            ).into());
+        }
+
+        let started = Instant::now();
+        let mut retry = 0;
+        loop {
+            self.limiter.wait().await;
+            let error = match self.try_get(url).await {
+                Ok(body) => {
+                    record(true);
+                    return Ok(body);
+                }
+                Err(e) => e,
+            };
+
+            // Errors the server answered with, like a 404, say nothing
+            // about whether the API is up
+            if !is_transient(&error) {
+                record(true);
+                return Err(error.into());
+            }
+
+            retry += 1;
+            let delay = self.retry.delay(retry, rand::random());
+            if !self.retry.allows(retry, started.elapsed(), delay) {
+                record(false);
+                return Err(error.into());
+            }
+            debug!("Retrying {} in {:?} after: {}", url, delay, error);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn try_get(&self, url: &str) -> Result<bytes::Bytes, reqwest::Error> {
+        self.http.get(url).send().await?.error_for_status()?.bytes().await
+    }
+}
+
+/// Whether a request failing with `error` may work when tried again.
+fn is_transient(error: &reqwest::Error) -> bool {
+    match error.status() {
+        Some(status) => status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+        None => error.is_timeout() || error.is_connect() || error.is_request() || error.is_body(),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Spaces out requests by a minimum interval.
+struct RateLimiter {
+    interval: Duration,
+    next: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+impl RateLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// Waits until the next request may be sent.
+    async fn wait(&self) {
+        let mut next = self.next.lock().await;
+        tokio::time::sleep_until(*next).await;
+        *next = tokio::time::Instant::now() + self.interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves `body` for every request and counts the requests.
+    async fn serve(body: &'static str) -> (String, Arc<AtomicUsize>) {
+        serve_statuses(&[], body).await
+    }
+
+    /// Answers the first requests with `statuses`, then serves `body`.
+    async fn serve_statuses(statuses: &'static [&'static str], body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        tokio::spawn({
+            let requests = Arc::clone(&requests);
+            async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let request = requests.fetch_add(1, Ordering::SeqCst);
+                    let mut buffer = [0u8; 1024];
+                    let _ = socket.read(&mut buffer).await;
+                    let (status, body) = match statuses.get(request) {
+                        Some(status) => (*status, ""),
+                        None => ("200 OK", body),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                }
+            }
+        });
+        (base_url, requests)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_channels_reuses_recent_list() {
+        let (base_url, requests) = serve(r#"{"channels": [{"id": "groovesalad", "title": "Groove Salad"}]}"#).await;
+        let api = ApiClient::new()
+            .with_base_url(&base_url)
+            .with_disk_cache(ChannelCache::disabled());
+
+        assert_eq!(api.fetch_channels().await.unwrap()[0].id, "groovesalad");
+        assert_eq!(api.clone().fetch_channels().await.unwrap().len(), 1);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_load_channels_falls_back_to_disk_cache() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = ChannelCache::at(dir.path().join("channels.json"));
+        let (base_url, _) = serve(r#"{"channels": [{"id": "dronezone"}]}"#).await;
+        let (channels, source) = ApiClient::new()
+            .with_base_url(&base_url)
+            .with_disk_cache(cache.clone())
+            .load_channels()
+            .await
+            .unwrap();
+        assert_eq!((channels.len(), source), (1, ChannelSource::Live));
+
+        let (base_url, _) = serve(r#"{"channels": []}"#).await;
+        let api = ApiClient::new().with_base_url(&base_url).with_disk_cache(cache);
+        let (channels, source) = api.load_channels().await.unwrap();
+        assert_eq!(channels[0].id, "dronezone");
+        assert_eq!(source, ChannelSource::Cached);
+
+        let api = api.with_disk_cache(ChannelCache::disabled());
+        assert!(api.load_channels().await.is_err());
+    }
+
+    fn quick_retries() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            budget: Duration::from_secs(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let (base_url, requests) = serve_statuses(&["503 Service Unavailable", "502 Bad Gateway"], r#"{"channels": [{"id": "groovesalad"}]}"#).await;
+        let api = ApiClient::new()
+            .with_base_url(&base_url)
+            .with_disk_cache(ChannelCache::disabled())
+            .with_retry_policy(quick_retries());
+        assert_eq!(api.fetch_channels().await.unwrap()[0].id, "groovesalad");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // Errors the API answers with deliberately are not retried
+        let (base_url, requests) = serve_statuses(&["404 Not Found"], "{}").await;
+        let api = api.with_base_url(&base_url);
+        assert!(api.fetch_current_song("nosuchchannel").await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_falls_back_to_disk_cache() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = ChannelCache::at(dir.path().join("channels.json"));
+        cache.save(&parse_channels(r#"{"channels": [{"id": "dronezone"}]}"#).unwrap());
+        let (base_url, requests) = serve_statuses(&["500 Internal Server Error"; 4], "{}").await;
+        let api = ApiClient::new()
+            .with_base_url(&base_url)
+            .with_disk_cache(cache)
+            .with_retry_policy(RetryPolicy::none())
+            .with_circuit_breaker(2, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            let (channels, source) = api.load_channels().await.unwrap();
+            assert_eq!((channels[0].id.as_str(), source), ("dronezone", ChannelSource::Cached));
+        }
+        // The third load is answered by the open breaker, without a request
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert!(matches!(api.breaker_state(), BreakerState::Open { .. }));
+        let error = api.fetch_channels_json().await.unwrap_err().to_string();
+        assert!(error.contains("SomaFM API unavailable"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_other_servers_do_not_open_breaker() {
+        let (logo_url, requests) = serve_statuses(&["500 Internal Server Error"; 3], "").await;
+        let api = ApiClient::new()
+            .with_disk_cache(ChannelCache::disabled())
+            .with_retry_policy(RetryPolicy::none())
+            .with_circuit_breaker(1, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert!(api.fetch_bytes(&format!("{}/logo.png", logo_url)).await.is_err());
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(api.breaker_state(), BreakerState::Closed);
+        assert!(api.is_api_url(&format!("{}/channels.json", DEFAULT_BASE_URL)));
+        assert!(!api.is_api_url("https://api.somafm.com.example/channels.json"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_stream_servers_are_tried_last() {
+        let (base_url, requests) = serve("[playlist]\nFile1=http://ice1.example/groovesalad\nFile2=http://ice2.example/groovesalad\n").await;
+        let api = ApiClient::new().with_disk_cache(ChannelCache::disabled());
+        let pls_url = format!("{}/groovesalad.pls", base_url);
+        assert_eq!(api.parse_pls_playlist(&pls_url).await.unwrap(), "http://ice1.example/groovesalad");
+
+        api.mark_stream_failed("http://ice1.example/groovesalad");
+        assert_eq!(api.failover_url("http://ice1.example/groovesalad").as_deref(), Some("http://ice2.example/groovesalad"));
+        assert_eq!(api.parse_pls_playlist(&pls_url).await.unwrap(), "http://ice2.example/groovesalad");
+
+        // Once every server failed, the one that failed first comes back first
+        api.mark_stream_failed("http://ice2.example/groovesalad");
+        assert_eq!(api.failover_url("http://ice2.example/groovesalad"), None);
+        assert_eq!(api.parse_pls_playlist(&pls_url).await.unwrap(), "http://ice1.example/groovesalad");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_restore_skips_stale_stream_servers() {
+        let api = ApiClient::new().with_disk_cache(ChannelCache::disabled());
+        let saved = |playlist_url: &str, age: Duration| LastChannel {
+            channel: Channel::named("groovesalad", "Groove Salad"),
+            playlist_url: playlist_url.to_string(),
+            stream_urls: vec!["http://ice1.example/groovesalad".to_string()],
+            saved_at: Utc::now().timestamp() - age.as_secs() as i64,
+        };
+
+        let channel = api.restore_last_channel(saved("https://somafm.com/fresh.pls", Duration::from_secs(600)));
+        assert_eq!(channel.id, "groovesalad");
+        api.restore_last_channel(saved("https://somafm.com/stale.pls", PLAYLIST_TTL * 24));
+
+        let playlists = lock(&api.playlists);
+        // The saved age counts towards the TTL
+        let (resolved, _) = &playlists["https://somafm.com/fresh.pls"];
+        assert!(resolved.elapsed() >= Duration::from_secs(600));
+        assert!(!playlists.contains_key("https://somafm.com/stale.pls"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_spaced_out() {
+        let limiter = RateLimiter::new(Duration::from_millis(40));
+        let started = Instant::now();
+        for _ in 0..3 {
+            limiter.wait().await;
+        }
+        assert!(started.elapsed() >= Duration::from_millis(80));
+    }
+}
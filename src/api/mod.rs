@@ -1,3 +1,11 @@
 pub mod somafm;
+pub mod refresh;
+pub mod cache;
+pub mod client;
+pub mod songs;
+pub mod bundle;
+pub mod retry;
+pub mod art;
 
 pub use somafm::*;
+pub use client::ApiClient;
@@ -0,0 +1,150 @@
+//! Periodic background refresh of the channel list.
+//!
+//! Listener counts and the last played track change constantly, so the
+//! channel list fetched at startup goes stale. [`ChannelRefresher`] refetches
+//! it on an interval and [`merge_channels`] folds the result into the list
+//! in place, keeping channel order (and therefore list selection) stable.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, warn};
+
+use crate::api::ApiClient;
+use crate::models::Channel;
+
+/// Fetches the channel list in the background on a fixed interval.
+pub struct ChannelRefresher {
+    stop: Arc<AtomicBool>,
+}
+
+impl ChannelRefresher {
+    /// Starts refreshing through `api` every `interval`, handing each
+    /// fetched list to `on_update`. Must be called within a Tokio runtime.
+    pub fn start<F>(api: ApiClient, interval: Duration, on_update: F) -> Self
+    where
+        F: Fn(Vec<Channel>) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn({
+            let stop = Arc::clone(&stop);
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                // The list was just fetched at startup
+                ticker.tick().await;
+
+                loop {
+                    ticker.tick().await;
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match api.fetch_channels().await {
+                        Ok(channels) => {
+                            debug!("Refreshed {} channels", channels.len());
+                            on_update(channels);
+                        }
+                        Err(e) => warn!("Failed to refresh channel list: {}", e),
+                    }
+                }
+            }
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for ChannelRefresher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Merges a freshly fetched channel list into `channels`.
+///
+/// Known channels are updated in place and new channels are appended, so
+/// indices into the list stay valid. Channels missing from the fresh list
+/// are kept for the rest of the session. Returns whether anything changed.
+pub fn merge_channels(channels: &mut Vec<Channel>, fresh: Vec<Channel>) -> bool {
+    let mut changed = false;
+
+    for update in fresh {
+        match channels.iter_mut().find(|c| c.id == update.id) {
+            Some(channel) => {
+                if channel.title != update.title
+                    || channel.description != update.description
+                    || channel.listeners != update.listeners
+                    || channel.last_playing != update.last_playing
+                {
+                    channel.title = update.title;
+                    channel.description = update.description;
+                    channel.listeners = update.listeners;
+                    channel.last_playing = update.last_playing;
+                    changed = true;
+                }
+            }
+            None => {
+                debug!("New channel appeared: {}", update.id);
+                channels.push(update);
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_updates_in_place() {
+        let groovesalad = Channel {
+            listeners: Some("100".to_string()),
+            ..Channel::named("groovesalad", "Groove Salad")
+        };
+        let dronezone = Channel {
+            listeners: Some("50".to_string()),
+            ..Channel::named("dronezone", "Drone Zone")
+        };
+        let mut channels = vec![groovesalad.clone(), dronezone.clone()];
+
+        let busier = Channel {
+            listeners: Some("60".to_string()),
+            ..dronezone
+        };
+        let fresh = vec![busier, groovesalad];
+        assert!(merge_channels(&mut channels, fresh));
+
+        // Order is preserved even though the fresh list is ordered differently
+        assert_eq!(channels[0].id, "groovesalad");
+        assert_eq!(channels[1].id, "dronezone");
+        assert_eq!(channels[1].listener_count(), Some(60));
+    }
+
+    #[test]
+    fn test_merge_appends_and_keeps_removed() {
+        let groovesalad = Channel {
+            listeners: Some("100".to_string()),
+            ..Channel::named("groovesalad", "Groove Salad")
+        };
+        let mut channels = vec![groovesalad.clone(), Channel::named("dronezone", "Drone Zone")];
+
+        let fresh = vec![groovesalad, Channel::named("lush", "Lush")];
+        assert!(merge_channels(&mut channels, fresh));
+        let ids: Vec<&str> = channels.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["groovesalad", "dronezone", "lush"]);
+    }
+
+    #[test]
+    fn test_merge_reports_no_change() {
+        let groovesalad = Channel {
+            listeners: Some("100".to_string()),
+            ..Channel::named("groovesalad", "Groove Salad")
+        };
+        let mut channels = vec![groovesalad.clone()];
+        assert!(!merge_channels(&mut channels, vec![groovesalad]));
+    }
+}
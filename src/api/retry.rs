@@ -0,0 +1,163 @@
+//! Retries and a circuit breaker for requests to SomaFM.
+//!
+//! A request failing for a transient reason, e.g. a timeout, a dropped
+//! connection or a 5xx answer, is retried after an exponentially growing,
+//! randomly jittered delay, as long as the [`RetryPolicy`] allows another
+//! attempt within its time budget. Requests that still fail count towards
+//! the [`CircuitBreaker`]; after several in a row it stops requests to the
+//! API for a cooldown, so a SomaFM outage costs one quick error instead of
+//! a round of timeouts each time, and callers fall back to cached data.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often and how long a request is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included
+    pub attempts: u32,
+    /// Longest delay before the first retry; it doubles with each further one
+    pub base_delay: Duration,
+    /// Longest delay before any retry
+    pub max_delay: Duration,
+    /// No retry is started once this long has passed since the first attempt
+    pub budget: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_millis(300),
+            max_delay: Duration::from_secs(3),
+            budget: Duration::from_secs(15),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt, never retried
+    pub fn none() -> Self {
+        Self { attempts: 1, ..Self::default() }
+    }
+
+    /// Delay before retry number `retry`, counted from 1, with `jitter`
+    /// between 0 and 1 picking a point of the full range ("full jitter"),
+    /// so clients failing together do not retry together.
+    pub fn delay(&self, retry: u32, jitter: f64) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        ceiling.mul_f64(jitter.clamp(0.0, 1.0))
+    }
+
+    /// Whether retry number `retry` may start `delay` from now, `elapsed`
+    /// after the first attempt.
+    pub fn allows(&self, retry: u32, elapsed: Duration, delay: Duration) -> bool {
+        retry < self.attempts && elapsed + delay <= self.budget
+    }
+}
+
+/// Whether requests to the API are let through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    /// Requests fail right away until the next trial request in `retry_in`
+    Open { retry_in: Duration },
+}
+
+/// Stops requests to a failing API for a while.
+///
+/// `threshold` failed requests in a row open the breaker for `cooldown`.
+/// After that one trial request is let through per cooldown; its success
+/// closes the breaker again.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<Breaker>,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    opened: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self { threshold: threshold.max(1), cooldown, state: Mutex::new(Breaker::default()) }
+    }
+
+    /// Whether a request may be sent at `now`. Letting a trial request
+    /// through starts the next cooldown.
+    pub fn allow(&self, now: Instant) -> bool {
+        let mut state = self.lock();
+        match state.opened {
+            None => true,
+            Some(opened) if now.duration_since(opened) >= self.cooldown => {
+                state.opened = Some(now);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Records the outcome of a request sent at `now`.
+    pub fn record(&self, success: bool, now: Instant) {
+        let mut state = self.lock();
+        if success {
+            *state = Breaker::default();
+            return;
+        }
+        state.failures += 1;
+        if state.failures >= self.threshold {
+            state.opened = Some(now);
+        }
+    }
+
+    pub fn state(&self, now: Instant) -> BreakerState {
+        match self.lock().opened {
+            Some(opened) => BreakerState::Open { retry_in: self.cooldown.saturating_sub(now.duration_since(opened)) },
+            None => BreakerState::Closed,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Breaker> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delays_and_breaker() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1, 1.0), Duration::from_millis(300));
+        assert_eq!(policy.delay(2, 0.5), Duration::from_millis(300));
+        assert_eq!(policy.delay(10, 1.0), Duration::from_secs(3));
+        assert_eq!(policy.delay(1, 0.0), Duration::ZERO);
+        assert!(policy.allows(2, Duration::from_secs(1), Duration::from_secs(1)));
+        assert!(!policy.allows(3, Duration::ZERO, Duration::ZERO));
+        assert!(!policy.allows(1, Duration::from_secs(14), Duration::from_secs(2)));
+
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        let start = Instant::now();
+        breaker.record(false, start);
+        assert!(breaker.allow(start));
+        breaker.record(false, start);
+        assert_eq!(breaker.state(start + Duration::from_secs(10)), BreakerState::Open { retry_in: Duration::from_secs(20) });
+        assert!(!breaker.allow(start + Duration::from_secs(10)));
+
+        // One trial request per cooldown, closing the breaker once it works
+        let later = start + Duration::from_secs(30);
+        assert!(breaker.allow(later));
+        assert!(!breaker.allow(later));
+        breaker.record(true, later);
+        assert_eq!(breaker.state(later), BreakerState::Closed);
+        assert!(breaker.allow(later));
+    }
+}
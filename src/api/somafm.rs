@@ -1,39 +1,230 @@
-use crate::models::{Channel, SomaFmResponse};
+//! Parsing of SomaFM API responses and playlists.
 
-const SOMAFM_API_URL: &str = "https://api.somafm.com/channels.json";
+use std::collections::{BTreeMap, BTreeSet};
 
-/// Fetches the list of SomaFM channels from the API.
-pub async fn fetch_channels() -> Result<Vec<Channel>, Box<dyn std::error::Error>> {
-    let response = reqwest::get(SOMAFM_API_URL).await?.json::<SomaFmResponse>().await?;
-    Ok(response.channels)
-}
+use log::warn;
 
-/// Parses a .pls playlist file and returns the first stream URL
-pub async fn parse_pls_playlist(pls_url: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let response = client.get(pls_url).send().await?;
-    let pls_content = response.text().await?;
-    
-    // Parse the .pls file to find File1, File2, etc.
-    for line in pls_content.lines() {
-        let line = line.trim();
-        if line.starts_with("File") && line.contains("=") {
-            if let Some(url) = line.split('=').nth(1) {
-                let url = url.trim();
-                if url.starts_with("http") {
-                    return Ok(url.to_string());
-                }
+use crate::models::{Channel, Playlist, TrackInfo};
+
+/// Channel fields the API sends that the player has no use for
+const UNUSED_CHANNEL_FIELDS: &[&str] =
+    &["dj", "djmail", "largeimage", "xlimage", "twitter", "updated", "preroll"];
+
+/// Parses the API's channel list, skipping channels that lack an ID or
+/// cannot be read instead of failing the whole list.
+pub fn parse_channels(body: &str) -> Result<Vec<Channel>, String> {
+    let response: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| format!("Invalid channel list: {}", e))?;
+    let entries = response
+        .get("channels")
+        .and_then(|c| c.as_array())
+        .ok_or("Channel list response has no channels")?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| match serde_json::from_value::<Channel>(entry.clone()) {
+            Ok(channel) if !channel.id.trim().is_empty() => Some(channel),
+            Ok(_) => {
+                warn!("Skipping channel without an ID");
+                None
+            }
+            Err(e) => {
+                warn!("Skipping unreadable channel: {}", e);
+                None
             }
+        })
+        .map(|mut channel| {
+            if channel.title.trim().is_empty() {
+                channel.title = channel.id.clone();
+            }
+            channel
+        })
+        .collect())
+}
+
+/// How a channel list compares with the fields [`Channel`] knows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaReport {
+    /// Entries in the list
+    pub entries: usize,
+    /// Entries [`parse_channels`] could read
+    pub parsed: usize,
+    /// Fields neither read nor known to be unused, as `field` or
+    /// `playlists.field`, with the number of entries they appear in
+    pub unknown_fields: BTreeMap<String, usize>,
+}
+
+impl SchemaReport {
+    /// Whether every entry was read and no field is new.
+    pub fn is_clean(&self) -> bool {
+        self.parsed == self.entries && self.unknown_fields.is_empty()
+    }
+}
+
+/// Names of the fields a serialized value of `T` has.
+fn field_names<T: serde::Serialize>(value: &T) -> BTreeSet<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+        _ => BTreeSet::new(),
+    }
+}
+
+/// Checks a channel list response against the fields [`Channel`] and
+/// [`Playlist`] read, for spotting changes to the API.
+pub fn check_channel_schema(body: &str) -> Result<SchemaReport, String> {
+    let playlist = Playlist { url: String::new(), format: String::new(), quality: String::new() };
+    let channel = Channel {
+        id: String::new(),
+        title: String::new(),
+        description: String::new(),
+        genre: String::new(),
+        playlists: vec![playlist.clone()],
+        listeners: None,
+        last_playing: None,
+        image: None,
+    };
+    let mut known_channel = field_names(&channel);
+    known_channel.extend(UNUSED_CHANNEL_FIELDS.iter().map(|field| field.to_string()));
+    let known_playlist = field_names(&playlist);
+
+    let response: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| format!("Invalid channel list: {}", e))?;
+    let entries = response
+        .get("channels")
+        .and_then(|c| c.as_array())
+        .ok_or("Channel list response has no channels")?;
+
+    let mut report = SchemaReport {
+        entries: entries.len(),
+        parsed: parse_channels(body)?.len(),
+        ..SchemaReport::default()
+    };
+    for entry in entries.iter().filter_map(|entry| entry.as_object()) {
+        let mut unknown: BTreeSet<String> = entry.keys().filter(|key| !known_channel.contains(*key)).cloned().collect();
+        let playlists = entry.get("playlists").and_then(|p| p.as_array()).into_iter().flatten();
+        for fields in playlists.filter_map(|playlist| playlist.as_object()) {
+            unknown.extend(fields.keys().filter(|key| !known_playlist.contains(*key)).map(|key| format!("playlists.{}", key)));
+        }
+        for field in unknown {
+            *report.unknown_fields.entry(field).or_default() += 1;
         }
     }
-    
-    Err("No valid stream URL found in .pls playlist".into())
+    Ok(report)
+}
+
+/// Parses the API's recently played songs of a channel into the track
+/// playing now, the first entry. `None` when the list is empty.
+pub fn parse_current_song(body: &str) -> Result<Option<TrackInfo>, String> {
+    let response: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| format!("Invalid song list: {}", e))?;
+    let songs = response
+        .get("songs")
+        .and_then(|s| s.as_array())
+        .ok_or("Song list response has no songs")?;
+
+    Ok(songs.first().map(|song| {
+        let field = |name: &str| song.get(name).and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+        let (artist, title) = (field("artist"), field("title"));
+        TrackInfo {
+            artist: if artist.is_empty() { TrackInfo::default().artist } else { artist },
+            title,
+        }
+    }))
+}
+
+/// Extracts the first HTTP stream URL from the contents of a .pls playlist
+pub fn parse_pls_content(pls_content: &str) -> Option<String> {
+    parse_pls_urls(pls_content).into_iter().next()
+}
+
+/// Extracts every HTTP stream URL from the contents of a .pls playlist, in
+/// order; SomaFM lists the same stream on several ice servers.
+pub fn parse_pls_urls(pls_content: &str) -> Vec<String> {
+    // Parse the .pls file to find File1, File2, etc. Some servers prefix the
+    // file with a byte order mark, and stream URLs may carry `=` in queries.
+    pls_content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("File"))
+        .filter_map(|line| line.split_once('='))
+        .map(|(_, url)| url.trim())
+        .filter(|url| url.starts_with("http"))
+        .map(str::to_string)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_channels_tolerates_schema_changes() {
+        let body = r#"{"channels": [
+            {"id": "groovesalad", "title": "Groove Salad", "description": "Chill",
+             "playlists": [{"url": "https://somafm.com/groovesalad256.pls", "format": "mp3", "quality": "highest"}],
+             "renamedField": 1},
+            {"id": "dronezone", "playlists": [{"url": "https://somafm.com/dronezone.pls"}]},
+            {"title": "No ID"},
+            {"id": 42}
+        ]}"#;
+
+        let channels = parse_channels(body).unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].description, "Chill");
+        assert_eq!(channels[1].title, "dronezone");
+        assert_eq!(channels[1].playlists[0].format, "");
+
+        assert_eq!(parse_channels(r#"{"channels": []}"#).unwrap().len(), 0);
+        assert!(parse_channels(r#"{"stations": []}"#).is_err());
+        assert!(parse_channels("<html>").is_err());
+    }
+
+    #[test]
+    fn test_recorded_channel_lists() {
+        let legacy = parse_channels(include_str!("fixtures/channels_legacy.json")).unwrap();
+        assert_eq!(legacy.len(), 2);
+        assert_eq!(legacy[0].listener_count(), Some(1427));
+        assert_eq!(legacy[0].genre, "ambient|electronica");
+        assert_eq!(legacy[1].playlists.len(), 2);
+        assert!(legacy.iter().all(|channel| channel.last_playing.is_none()));
+
+        let current = parse_channels(include_str!("fixtures/channels_current.json")).unwrap();
+        assert_eq!(current.len(), 2);
+        assert_eq!(current[0].last_playing.as_deref(), Some("Thievery Corporation - Lebanese Blonde"));
+        assert_eq!(current[0].primary_playlist().unwrap().url, "https://api.somafm.com/groovesalad.pls");
+        for fixture in [include_str!("fixtures/channels_legacy.json"), include_str!("fixtures/channels_current.json")] {
+            assert!(check_channel_schema(fixture).unwrap().is_clean());
+        }
+
+        // Added, renamed and retyped fields
+        let drift = include_str!("fixtures/channels_drift.json");
+        let channels = parse_channels(drift).unwrap();
+        let ids: Vec<&str> = channels.iter().map(|channel| channel.id.as_str()).collect();
+        assert_eq!(ids, ["lush", "spacestation", "beatblender"]);
+        assert_eq!(channels[1].title, "spacestation");
+        let report = check_channel_schema(drift).unwrap();
+        assert_eq!((report.entries, report.parsed), (5, 3));
+        let unknown: Vec<&str> = report.unknown_fields.keys().map(String::as_str).collect();
+        assert_eq!(unknown, ["artwork", "playlists.bitrate", "tags"]);
+    }
+
+    #[test]
+    fn test_parse_current_song() {
+        let body = r#"{"id": "groovesalad", "songs": [
+            {"title": "Paranoid Android", "artist": "Radiohead", "album": "OK Computer", "date": "1700000300"},
+            {"title": "Older", "artist": "Someone", "date": "1700000000"}
+        ]}"#;
+        let track = parse_current_song(body).unwrap().unwrap();
+        assert_eq!(track.artist, "Radiohead");
+        assert_eq!(track.title, "Paranoid Android");
+
+        let track = parse_current_song(r#"{"songs": [{"title": "Station ID"}]}"#).unwrap().unwrap();
+        assert_eq!(track.artist, "Unknown");
+        assert_eq!(parse_current_song(r#"{"songs": []}"#).unwrap(), None);
+        assert!(parse_current_song("<html>").is_err());
+    }
+
     #[test]
     fn test_parse_pls_playlist_valid() {
         let pls_content = r#"
@@ -48,22 +239,13 @@ Length2=-1
 Version=2
 "#;
         
-        // Simulate the parsing logic
-        let mut found_url = None;
-        for line in pls_content.lines() {
-            let line = line.trim();
-            if line.starts_with("File") && line.contains("=") {
-                if let Some(url) = line.split('=').nth(1) {
-                    let url = url.trim();
-                    if url.starts_with("http") {
-                        found_url = Some(url.to_string());
-                        break;
-                    }
-                }
-            }
-        }
+        let found_url = parse_pls_content(pls_content);
         
         assert_eq!(found_url, Some("http://ice1.somafm.com/groovesalad-256-mp3".to_string()));
+        assert_eq!(parse_pls_urls(pls_content), [
+            "http://ice1.somafm.com/groovesalad-256-mp3",
+            "http://ice2.somafm.com/groovesalad-256-mp3",
+        ]);
     }
 
     #[test]
@@ -74,19 +256,7 @@ NumberOfEntries=0
 Version=2
 "#;
         
-        let mut found_url = None;
-        for line in pls_content.lines() {
-            let line = line.trim();
-            if line.starts_with("File") && line.contains("=") {
-                if let Some(url) = line.split('=').nth(1) {
-                    let url = url.trim();
-                    if url.starts_with("http") {
-                        found_url = Some(url.to_string());
-                        break;
-                    }
-                }
-            }
-        }
+        let found_url = parse_pls_content(pls_content);
         
         assert_eq!(found_url, None);
     }
@@ -102,28 +272,67 @@ Length1=-1
 Version=2
 "#;
         
-        let mut found_url = None;
-        for line in pls_content.lines() {
-            let line = line.trim();
-            if line.starts_with("File") && line.contains("=") {
-                if let Some(url) = line.split('=').nth(1) {
-                    let url = url.trim();
-                    if url.starts_with("http") {
-                        found_url = Some(url.to_string());
-                        break;
-                    }
+        let found_url = parse_pls_content(pls_content);
+        
+        assert_eq!(found_url, None);
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Lines a .pls file may hold besides the stream entries
+        fn other_line() -> impl Strategy<Value = String> {
+            prop_oneof![
+                Just("[playlist]".to_string()),
+                Just(String::new()),
+                "Title[0-9]=\\PC{0,20}",
+                "Length[0-9]=-?[0-9]{1,3}",
+                "NumberOfEntries=[0-9]",
+                "Version=2",
+                "; \\PC{0,20}",
+            ]
+        }
+
+        fn stream_url() -> impl Strategy<Value = String> {
+            "https?://[a-z0-9.]{1,20}(:[0-9]{2,5})?(/[a-zA-Z0-9._-]{0,12}){0,3}(\\?[a-z]{1,5}=[a-z0-9]{1,5}(&[a-z]{1,5}=[a-z0-9]{0,5})?)?"
+        }
+
+        proptest! {
+            #[test]
+            fn never_panics(contents in "\\PC*(\n\\PC*){0,5}") {
+                if let Some(url) = parse_pls_content(&contents) {
+                    prop_assert!(url.starts_with("http"));
                 }
             }
+
+            #[test]
+            fn finds_the_first_stream(
+                before in proptest::collection::vec(other_line(), 0..5),
+                after in proptest::collection::vec(other_line(), 0..5),
+                urls in proptest::collection::vec(stream_url(), 1..4),
+                space in "[ \t]{0,2}",
+                newline in prop_oneof![Just("\n"), Just("\r\n")],
+                bom in any::<bool>(),
+            ) {
+                let files = urls.iter().enumerate().map(|(i, url)| format!("{}File{}{}={}{}", space, i + 1, space, space, url));
+                let lines: Vec<String> = before.iter().cloned().chain(files).chain(after.iter().cloned()).collect();
+                let contents = format!("{}{}", if bom { "\u{feff}" } else { "" }, lines.join(newline));
+                prop_assert_eq!(parse_pls_content(&contents), Some(urls[0].clone()));
+            }
+
+            #[test]
+            fn ignores_playlists_without_streams(lines in proptest::collection::vec(other_line(), 0..8)) {
+                prop_assert_eq!(parse_pls_content(&lines.join("\n")), None);
+            }
         }
-        
-        assert_eq!(found_url, None);
     }
 
     #[test]
     fn test_api_url_constant() {
-        assert_eq!(SOMAFM_API_URL, "https://api.somafm.com/channels.json");
+        assert_eq!(crate::api::client::DEFAULT_BASE_URL, "https://api.somafm.com");
     }
 
-    // Note: Integration tests for fetch_channels() would require network access
-    // These should be in a separate integration test file or use mocking
+    // Note: Requests to the API are tested against a local server in the
+    // client module
 }
@@ -0,0 +1,63 @@
+//! Track information from the SomaFM API.
+//!
+//! Some stream variants send no ICY metadata, which would leave the player
+//! showing "Loading..." forever. For those, [`SongPoller`] asks the API
+//! which song the channel is playing on an interval and publishes it like
+//! the metadata would have been.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::sync::watch;
+
+use crate::api::ApiClient;
+use crate::models::TrackInfo;
+
+/// Polls the current song of one channel in the background.
+pub struct SongPoller {
+    stop: Arc<AtomicBool>,
+}
+
+impl SongPoller {
+    /// Starts asking `api` for the song on `channel_id` every `interval`,
+    /// publishing changes to `track_info`. Must be called within a Tokio
+    /// runtime.
+    pub fn start(api: ApiClient, channel_id: String, interval: Duration, track_info: watch::Sender<TrackInfo>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        debug!("Polling the song list of {} every {:?}", channel_id, interval);
+
+        tokio::spawn({
+            let stop = Arc::clone(&stop);
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                loop {
+                    ticker.tick().await;
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match api.fetch_current_song(&channel_id).await {
+                        Ok(Some(track)) => {
+                            track_info.send_if_modified(|current| {
+                                let changed = *current != track;
+                                *current = track;
+                                changed
+                            });
+                        }
+                        Ok(None) => debug!("No songs listed for {}", channel_id),
+                        Err(e) => warn!("Failed to fetch the song playing on {}: {}", channel_id, e),
+                    }
+                }
+            }
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for SongPoller {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,256 @@
+//! Recordings played back in the TUI (`soma-player --archive <PATH>`).
+//!
+//! Files made by `soma-player record`, or any MP3 or AAC file, stand in for
+//! the channel list: each becomes a [`Channel`] with a single `file://`
+//! playlist, which tells playback to read it from disk instead of
+//! connecting to a stream. The track is taken from the file's ID3v2 tag,
+//! as written by `record --tag`, or else from the name `record
+//! --split-tracks` gave it.
+//!
+//! Stream recordings have no header telling their length, so for MP3 it is
+//! estimated from the bitrate of the first frame; SomaFM streams at a
+//! constant bitrate, which makes the estimate exact enough for a position
+//! bar.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::models::{Channel, Playlist, TrackInfo, parse_track_info};
+
+/// File extensions played as recordings
+const EXTENSIONS: &[&str] = &["mp3", "aac", "m4a", "ogg", "flac", "wav"];
+
+/// Prefix of the playlist URL of a recording
+const FILE_URL: &str = "file://";
+
+/// Bytes searched for the first MP3 frame after the tag
+const FRAME_SEARCH: usize = 16 * 1024;
+
+/// A recording found on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub path: PathBuf,
+    pub track: TrackInfo,
+    /// Album of the tag, `SomaFM <channel>` for tagged recordings
+    pub album: Option<String>,
+    /// Length, when it can be told without decoding the file
+    pub duration: Option<Duration>,
+}
+
+impl Recording {
+    /// Reads the tag and length of the file at `path`.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let len = file.metadata().map(|m| m.len()).unwrap_or_default();
+
+        let mut header = [0u8; 10];
+        let tag = match file.read_exact(&mut header) {
+            Ok(()) if header.starts_with(b"ID3") => {
+                let mut tag = header.to_vec();
+                tag.resize(10 + synchsafe_value(&header[6..10]) as usize, 0);
+                file.read_exact(&mut tag[10..]).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                Some(tag)
+            }
+            _ => None,
+        };
+        let tags = tag.as_deref().map(read_tag).unwrap_or_default();
+
+        // The audio after the tag, or from the start without one
+        let audio_start = tag.as_ref().map_or(0, Vec::len);
+        let mut head = if tag.is_some() { Vec::new() } else { header.to_vec() };
+        file.take(FRAME_SEARCH as u64).read_to_end(&mut head).map_err(|e| e.to_string())?;
+        let duration = mp3_bitrate(&head).map(|kbps| {
+            Duration::from_secs_f64(len.saturating_sub(audio_start as u64) as f64 * 8.0 / (f64::from(kbps) * 1000.0))
+        });
+
+        let track = match (tags.title, tags.artist) {
+            (Some(title), Some(artist)) => TrackInfo { artist, title },
+            (Some(title), None) => TrackInfo { artist: "Unknown".to_string(), title },
+            _ => parse_track_info(&track_name(path)),
+        };
+        Ok(Self { path: path.to_path_buf(), track, album: tags.album, duration })
+    }
+
+    /// The recording as an entry of the channel list.
+    pub fn channel(&self) -> Channel {
+        let name = self.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let format = self.path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+        let length = self.duration.map(|d| format!(", {}:{:02}", d.as_secs() / 60, d.as_secs() % 60));
+        Channel {
+            id: name.clone(),
+            title: name,
+            description: format!(
+                "{}{}",
+                self.album.as_deref().unwrap_or("Recording"),
+                length.unwrap_or_default()
+            ),
+            genre: String::new(),
+            playlists: vec![Playlist {
+                url: format!("{}{}", FILE_URL, self.path.display()),
+                format,
+                quality: String::new(),
+            }],
+            listeners: None,
+            last_playing: Some(format!("{} - {}", self.track.artist, self.track.title)),
+            image: None,
+        }
+    }
+}
+
+/// The recordings at `path`: the file itself, or the audio files in the
+/// directory sorted by name.
+pub fn find_recordings(path: &Path) -> Result<Vec<Recording>, String> {
+    if !path.is_dir() {
+        return Ok(vec![Recording::open(path)?]);
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| {
+            file.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        })
+        .collect();
+    if files.is_empty() {
+        return Err(format!("No recordings in {}", path.display()));
+    }
+    files.sort();
+    files.iter().map(|file| Recording::open(file)).collect()
+}
+
+/// The file a recording's channel plays, `None` for real channels.
+pub fn recording_path(channel: &Channel) -> Option<PathBuf> {
+    let url = channel.playlists.first()?.url.strip_prefix(FILE_URL)?;
+    Some(PathBuf::from(url))
+}
+
+/// `Artist - Title` from a file name like `out-003 Artist - Title.mp3`, as
+/// `record --split-tracks` names its files.
+fn track_name(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let numbered = |prefix: &str| {
+        prefix.rsplit_once('-').is_some_and(|(_, n)| n.len() == 3 && n.bytes().all(|b| b.is_ascii_digit()))
+    };
+    match stem.split_once(' ') {
+        Some((prefix, name)) if numbered(prefix) => name.to_string(),
+        _ => stem,
+    }
+}
+
+/// Text frames of an ID3v2 tag.
+#[derive(Debug, Default)]
+struct Tags {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+}
+
+/// Reads the title, artist and album of an ID3v2.3 or 2.4 tag.
+fn read_tag(tag: &[u8]) -> Tags {
+    let mut tags = Tags::default();
+    let version = tag.get(3).copied().unwrap_or_default();
+    let mut frames = tag.get(10..).unwrap_or_default();
+    while frames.len() >= 10 && frames[0] != 0 {
+        let size = match version {
+            4 => synchsafe_value(&frames[4..8]),
+            _ => u32::from_be_bytes([frames[4], frames[5], frames[6], frames[7]]),
+        } as usize;
+        let Some(body) = frames.get(10..10 + size) else {
+            break;
+        };
+        let text = || decode_text(body).filter(|text| !text.is_empty());
+        match &frames[..4] {
+            b"TIT2" => tags.title = text(),
+            b"TPE1" => tags.artist = text(),
+            b"TALB" => tags.album = text(),
+            _ => {}
+        }
+        frames = &frames[10 + size..];
+    }
+    tags
+}
+
+/// Decodes a text frame: its encoding byte, then the text.
+fn decode_text(body: &[u8]) -> Option<String> {
+    let (&encoding, text) = body.split_first()?;
+    let text = match encoding {
+        0 => text.iter().map(|&b| char::from(b)).collect(),
+        1 | 2 => {
+            let big_endian = encoding == 2 || text.starts_with(&[0xFE, 0xFF]);
+            let text = if encoding == 1 { text.get(2..)? } else { text };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => return None,
+    };
+    Some(text.trim_end_matches('\0').to_string())
+}
+
+/// Reads an ID3v2 synchsafe integer (7 bits per byte).
+fn synchsafe_value(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |value, &b| (value << 7) | u32::from(b & 0x7f))
+}
+
+/// Bitrate in kbps of the first MPEG layer III frame in `head`.
+fn mp3_bitrate(head: &[u8]) -> Option<u32> {
+    const MPEG1: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const MPEG2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+    head.windows(3).find_map(|frame| {
+        let (version, layer) = ((frame[1] >> 3) & 3, (frame[1] >> 1) & 3);
+        if frame[0] != 0xFF || frame[1] & 0xE0 != 0xE0 || version == 1 || layer != 1 {
+            return None;
+        }
+        let table = if version == 3 { &MPEG1 } else { &MPEG2 };
+        table.get(usize::from(frame[2] >> 4)).copied().filter(|&kbps| kbps > 0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::id3v2_tag;
+
+    #[test]
+    fn test_recordings_read_their_tag_or_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        // A 128 kbps MPEG-1 layer III frame header, then 16 000 bytes: 1s
+        let mut audio = vec![0xFF, 0xFB, 0x90, 0x00];
+        audio.resize(16_000, 0);
+
+        let mut tagged = id3v2_tag("Alone in Kyoto", "Air", "SomaFM Groove Salad");
+        tagged.extend_from_slice(&audio);
+        fs::write(dir.path().join("set.mp3"), tagged).unwrap();
+        fs::write(dir.path().join("out-002 Boards of Canada - Roygbiv.mp3"), &audio).unwrap();
+        fs::write(dir.path().join("notes.txt"), "not audio").unwrap();
+
+        let recordings = find_recordings(dir.path()).unwrap();
+        assert_eq!(recordings.len(), 2);
+        assert_eq!(recordings[0].track, TrackInfo { artist: "Boards of Canada".to_string(), title: "Roygbiv".to_string() });
+        assert_eq!(recordings[0].album, None);
+        assert_eq!(recordings[1].track, TrackInfo { artist: "Air".to_string(), title: "Alone in Kyoto".to_string() });
+        assert_eq!(recordings[1].album.as_deref(), Some("SomaFM Groove Salad"));
+        assert_eq!(recordings[1].duration, Some(Duration::from_secs(1)));
+
+        let channel = recordings[1].channel();
+        assert_eq!(channel.description, "SomaFM Groove Salad, 0:01");
+        assert_eq!(recording_path(&channel), Some(dir.path().join("set.mp3")));
+        assert!(find_recordings(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_mp3_bitrate() {
+        assert_eq!(mp3_bitrate(&[0x00, 0xFF, 0xFB, 0xD0, 0x00]), Some(256));
+        // MPEG-2 at 64 kbps
+        assert_eq!(mp3_bitrate(&[0xFF, 0xF3, 0x80, 0x00]), Some(64));
+        // ADTS (AAC) headers are not layer III
+        assert_eq!(mp3_bitrate(&[0xFF, 0xF1, 0x50, 0x80]), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Download buffer of a live stream, in fixed-size chunks.
+//!
+//! stream-download's `MemoryStorage` keeps a stream in one `Vec` that grows
+//! with every write. A live stream has no length to size it by, so over an
+//! hour of listening it is reallocated and copied a few dozen times on its
+//! way to tens of megabytes, leaving freed blocks of every size behind.
+//! [`ChunkStorageProvider`] keeps the stream in chunks of [`CHUNK_SIZE`]
+//! instead: written audio never moves, and the chunks of a closed stream go
+//! back to a pool that the next connection takes them from, so reconnecting
+//! and switching channels reuse the same memory.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use stream_download::storage::StorageProvider;
+
+/// Bytes per chunk
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Most chunks kept for reuse, 4 MiB
+const MAX_POOLED: usize = 64;
+
+type Chunk = Box<[u8]>;
+
+/// Pool every stream takes its chunks from.
+static SHARED_POOL: LazyLock<ChunkPool> = LazyLock::new(ChunkPool::default);
+
+/// Chunks of closed streams, waiting to be reused.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkPool {
+    free: Arc<Mutex<Vec<Chunk>>>,
+}
+
+impl ChunkPool {
+    /// Number of chunks waiting to be reused
+    pub fn pooled(&self) -> usize {
+        self.free.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn take(&self) -> Chunk {
+        let reused = self.free.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        reused.unwrap_or_else(|| vec![0; CHUNK_SIZE].into_boxed_slice())
+    }
+
+    fn give_back(&self, chunks: Vec<Chunk>) {
+        let mut free = self.free.lock().unwrap_or_else(|e| e.into_inner());
+        let room = MAX_POOLED.saturating_sub(free.len());
+        free.extend(chunks.into_iter().take(room));
+    }
+}
+
+/// Creates the [`ChunkStorage`] of a stream from the shared pool.
+#[derive(Debug, Clone)]
+pub struct ChunkStorageProvider {
+    pool: ChunkPool,
+}
+
+impl Default for ChunkStorageProvider {
+    fn default() -> Self {
+        Self { pool: SHARED_POOL.clone() }
+    }
+}
+
+impl StorageProvider for ChunkStorageProvider {
+    type Reader = ChunkStorage;
+    type Writer = ChunkStorage;
+
+    fn into_reader_writer(self, _content_length: Option<u64>) -> io::Result<(ChunkStorage, ChunkStorage)> {
+        let chunks = Arc::new(RwLock::new(Chunks { chunks: Vec::new(), len: 0, pool: self.pool }));
+        let reader = ChunkStorage { chunks: chunks.clone(), position: 0 };
+        let writer = ChunkStorage { chunks, position: 0 };
+        Ok((reader, writer))
+    }
+}
+
+/// The chunks of one stream, returned to the pool once both its reader and
+/// its writer are gone.
+#[derive(Debug)]
+struct Chunks {
+    chunks: Vec<Chunk>,
+    /// Bytes written, up to the furthest write
+    len: usize,
+    pool: ChunkPool,
+}
+
+impl Drop for Chunks {
+    fn drop(&mut self) {
+        self.pool.give_back(std::mem::take(&mut self.chunks));
+    }
+}
+
+/// Reading or writing end of a stream's chunks, each with its own position.
+#[derive(Debug)]
+pub struct ChunkStorage {
+    chunks: Arc<RwLock<Chunks>>,
+    position: usize,
+}
+
+impl Read for ChunkStorage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let chunks = self.chunks.read().unwrap_or_else(|e| e.into_inner());
+        let end = chunks.len.min(self.position + buf.len());
+        let mut read = 0;
+        while self.position < end {
+            let start = self.position % CHUNK_SIZE;
+            let len = (CHUNK_SIZE - start).min(end - self.position);
+            let chunk = &chunks.chunks[self.position / CHUNK_SIZE];
+            buf[read..read + len].copy_from_slice(&chunk[start..start + len]);
+            read += len;
+            self.position += len;
+        }
+        Ok(read)
+    }
+}
+
+impl Write for ChunkStorage {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut chunks = self.chunks.write().unwrap_or_else(|e| e.into_inner());
+        let mut written = 0;
+        while written < buf.len() {
+            let index = self.position / CHUNK_SIZE;
+            while chunks.chunks.len() <= index {
+                let chunk = chunks.pool.take();
+                chunks.chunks.push(chunk);
+            }
+            let start = self.position % CHUNK_SIZE;
+            let len = (CHUNK_SIZE - start).min(buf.len() - written);
+            chunks.chunks[index][start..start + len].copy_from_slice(&buf[written..written + len]);
+            written += len;
+            self.position += len;
+        }
+        chunks.len = chunks.len.max(self.position);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ChunkStorage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(position) => (position as i64, 0),
+            SeekFrom::Current(offset) => (self.position as i64, offset),
+            SeekFrom::End(offset) => (self.chunks.read().unwrap_or_else(|e| e.into_inner()).len as i64, offset),
+        };
+        let position = base
+            .checked_add(offset)
+            .filter(|position| *position >= 0)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the stream"))?;
+        self.position = position as usize;
+        Ok(self.position as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_span_boundaries_and_return_to_the_pool() {
+        let pool = ChunkPool::default();
+        let provider = ChunkStorageProvider { pool: pool.clone() };
+        let (mut reader, mut writer) = provider.into_reader_writer(None).unwrap();
+
+        // Two and a half chunks, written in pieces that straddle chunk ends
+        let stream: Vec<u8> = (0..CHUNK_SIZE * 5 / 2).map(|i| (i % 251) as u8).collect();
+        for piece in stream.chunks(CHUNK_SIZE / 3 + 7) {
+            writer.write_all(piece).unwrap();
+        }
+
+        let mut read = Vec::new();
+        let mut buf = vec![0; CHUNK_SIZE / 2 + 3];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                len => read.extend_from_slice(&buf[..len]),
+            }
+        }
+        assert_eq!(read, stream);
+
+        reader.seek(SeekFrom::Start(CHUNK_SIZE as u64 - 1)).unwrap();
+        let mut two = [0; 2];
+        reader.read_exact(&mut two).unwrap();
+        assert_eq!(two, stream[CHUNK_SIZE - 1..CHUNK_SIZE + 1]);
+        assert!(reader.seek(SeekFrom::Current(-(CHUNK_SIZE as i64) * 2)).is_err());
+
+        // The chunks are only given back once both ends are gone
+        drop(writer);
+        assert_eq!(pool.pooled(), 0);
+        drop(reader);
+        assert_eq!(pool.pooled(), 3);
+    }
+}
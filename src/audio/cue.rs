@@ -0,0 +1,85 @@
+//! Short tones announcing that the stream dropped or came back.
+//!
+//! The tones are generated on the fly, so no sound files need to be
+//! shipped. They are played next to the stream rather than queued after
+//! it, so they are heard even while the stream is stalled.
+
+use std::time::Duration;
+
+use rodio::buffer::SamplesBuffer;
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Length of each note of a cue
+const NOTE_LENGTH: Duration = Duration::from_millis(120);
+
+/// Fade at both ends of a note, avoiding clicks
+const FADE: Duration = Duration::from_millis(10);
+
+/// Peak level of a cue at full volume
+const LEVEL: f32 = 0.25;
+
+/// Events announced with a tone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    /// The stream stopped delivering audio
+    Dropped,
+    /// Audio is playing again after a drop or a device change
+    Reconnected,
+}
+
+impl Cue {
+    /// Note frequencies in Hz, falling for bad news and rising for good
+    fn notes(self) -> &'static [f32] {
+        match self {
+            Cue::Dropped => &[660.0, 440.0],
+            Cue::Reconnected => &[440.0, 660.0],
+        }
+    }
+
+    /// How long the cue plays.
+    pub fn duration(self) -> Duration {
+        NOTE_LENGTH * self.notes().len() as u32
+    }
+
+    /// Mono samples of the cue, scaled by `volume` (0.0-1.0).
+    pub fn source(self, volume: f32) -> SamplesBuffer<f32> {
+        let note_samples = (NOTE_LENGTH.as_secs_f32() * SAMPLE_RATE as f32) as usize;
+        let fade_samples = (FADE.as_secs_f32() * SAMPLE_RATE as f32) as usize;
+        let level = LEVEL * volume.clamp(0.0, 1.0);
+
+        let samples = self
+            .notes()
+            .iter()
+            .flat_map(|&frequency| {
+                (0..note_samples).map(move |i| {
+                    let edge = i.min(note_samples - 1 - i);
+                    let envelope = (edge as f32 / fade_samples as f32).min(1.0);
+                    let phase = i as f32 * frequency / SAMPLE_RATE as f32;
+                    (phase * std::f32::consts::TAU).sin() * level * envelope
+                })
+            })
+            .collect::<Vec<f32>>();
+        SamplesBuffer::new(1, SAMPLE_RATE, samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::Source;
+
+    #[test]
+    fn test_cue_is_short_and_quiet() {
+        let cue = Cue::Dropped.source(1.0);
+        assert_eq!(cue.total_duration(), Some(Cue::Dropped.duration()));
+
+        let samples: Vec<f32> = cue.collect();
+        assert!(samples.iter().all(|s| s.abs() <= LEVEL));
+        // Notes fade in and out instead of starting at full level
+        assert_eq!(samples[0], 0.0);
+        assert!(samples.iter().any(|s| s.abs() > LEVEL * 0.9));
+
+        assert!(Cue::Reconnected.source(0.0).all(|s| s == 0.0));
+    }
+}
@@ -0,0 +1,296 @@
+//! Choosing how a stream is decoded.
+//!
+//! rodio's [`Decoder`](rodio::Decoder) probes the stream itself and plays
+//! MP3 fine, but cannot play the AAC streams SomaFM offers as `aacp`. Built
+//! with the `symphonia-decoder` feature, the player can instead decode
+//! through Symphonia directly with [`SymphoniaSource`], which adds AAC and
+//! passes the exact sample rate, channel count and packet sizes of the
+//! codec on to the stages after it. Which backend decodes a stream is
+//! picked from its `Content-Type` unless the `decoder` setting forces one.
+//!
+//! Recordings played from disk always go through [`SymphoniaSource`], which
+//! can seek in MP3 files where rodio's decoder cannot; without the feature
+//! it still plays MP3, only AAC is missing.
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+pub use self::symphonia_source::SymphoniaSource;
+
+/// Content types rodio's own decoders cannot play
+const SYMPHONIA_CONTENT_TYPES: &[&str] = &["audio/aac", "audio/aacp", "audio/x-aac", "audio/mp4", "audio/x-m4a"];
+
+/// Which decoder plays a stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecoderBackend {
+    /// Symphonia for AAC streams when it is built in, rodio otherwise
+    #[default]
+    Auto,
+    /// rodio's decoders for every stream
+    Rodio,
+    /// Symphonia for every stream, if built with `symphonia-decoder`
+    Symphonia,
+}
+
+impl DecoderBackend {
+    /// The backend, `Rodio` or `Symphonia`, to decode a stream sent as
+    /// `content_type` with.
+    pub fn resolve(self, content_type: Option<&str>) -> DecoderBackend {
+        let backend = match self {
+            DecoderBackend::Auto if content_type.is_some_and(needs_symphonia) => DecoderBackend::Symphonia,
+            DecoderBackend::Auto => DecoderBackend::Rodio,
+            backend => backend,
+        };
+        if backend == DecoderBackend::Symphonia && !cfg!(feature = "symphonia-decoder") {
+            if self == DecoderBackend::Symphonia {
+                warn!("Built without the symphonia-decoder feature, decoding with rodio");
+            }
+            return DecoderBackend::Rodio;
+        }
+        backend
+    }
+}
+
+/// Whether the MIME type of a `Content-Type` header is one rodio cannot
+/// decode.
+fn needs_symphonia(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    SYMPHONIA_CONTENT_TYPES.contains(&mime.as_str())
+}
+
+mod symphonia_source {
+    use std::fs::File;
+    use std::io::{Read, Seek};
+    use std::path::Path;
+    use std::time::Duration;
+
+    use log::{debug, info, warn};
+    use rodio::source::SeekError;
+    use rodio::Source;
+    use symphonia::core::audio::{Channels, SampleBuffer, SignalSpec};
+    use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_NULL};
+    use symphonia::core::errors::Error;
+    use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+    use symphonia::core::io::{MediaSource, MediaSourceStream};
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    /// Consecutive packets that may fail to decode before the stream is
+    /// given up on
+    const MAX_DECODE_ERRORS: usize = 10;
+
+    /// A network stream handed to Symphonia; its length is not known.
+    struct StreamSource<R>(R);
+
+    impl<R: Read> Read for StreamSource<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl<R: Seek> Seek for StreamSource<R> {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    impl<R: Read + Seek + Send + Sync> MediaSource for StreamSource<R> {
+        fn is_seekable(&self) -> bool {
+            true
+        }
+
+        fn byte_len(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    /// A source decoding a stream with Symphonia.
+    pub struct SymphoniaSource {
+        format: Box<dyn FormatReader>,
+        decoder: Box<dyn Decoder>,
+        track_id: u32,
+        params: CodecParameters,
+        spec: SignalSpec,
+        /// Interleaved samples of the last decoded packet
+        buffer: Option<SampleBuffer<f32>>,
+        position: usize,
+    }
+
+    impl SymphoniaSource {
+        /// Decodes the network stream `reader`, helped by its `content_type`.
+        pub fn new<R>(reader: R, content_type: Option<&str>) -> Result<Self, String>
+        where
+            R: Read + Seek + Send + Sync + 'static,
+        {
+            let mut hint = Hint::new();
+            if let Some(content_type) = content_type {
+                hint.mime_type(content_type.split(';').next().unwrap_or_default().trim());
+            }
+            let stream = MediaSourceStream::new(Box::new(StreamSource(reader)), Default::default());
+            Self::probe(stream, hint)
+        }
+
+        /// Opens the audio file at `path`, helped by its extension.
+        pub fn open(path: &Path) -> Result<Self, String> {
+            let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            let mut hint = Hint::new();
+            if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+                hint.with_extension(extension);
+            }
+            Self::probe(MediaSourceStream::new(Box::new(file), Default::default()), hint)
+        }
+
+        /// Probes `stream` and decodes the first packet so its format is
+        /// known.
+        fn probe(stream: MediaSourceStream, hint: Hint) -> Result<Self, String> {
+            let probed = symphonia::default::get_probe()
+                .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+                .map_err(|e| format!("Unrecognized stream format: {}", e))?;
+            let track = probed
+                .format
+                .tracks()
+                .iter()
+                .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+                .ok_or("No audio track in the stream")?;
+            let params = track.codec_params.clone();
+            let decoder = symphonia::default::get_codecs()
+                .make(&params, &DecoderOptions::default())
+                .map_err(|e| format!("Unsupported codec: {}", e))?;
+
+            let mut source = Self {
+                track_id: track.id,
+                format: probed.format,
+                decoder,
+                params,
+                spec: SignalSpec::new(0, Channels::FRONT_LEFT),
+                buffer: None,
+                position: 0,
+            };
+            if !source.decode_next() {
+                return Err("No audio decoded from the stream".to_string());
+            }
+            let codec = symphonia::default::get_codecs()
+                .get_codec(source.params.codec)
+                .map_or("unknown codec", |codec| codec.short_name);
+            info!(
+                "Decoding {} with Symphonia: {} Hz, {} channels",
+                codec,
+                source.spec.rate,
+                source.spec.channels.count()
+            );
+            Ok(source)
+        }
+
+        /// Decodes the next packet of the track into the buffer. Returns
+        /// `false` at the end of the stream or when it cannot be decoded.
+        fn decode_next(&mut self) -> bool {
+            let mut errors = 0;
+            loop {
+                let packet = match self.format.next_packet() {
+                    Ok(packet) => packet,
+                    Err(Error::ResetRequired) => {
+                        debug!("Stream parameters changed, resetting the decoder");
+                        self.decoder.reset();
+                        continue;
+                    }
+                    Err(e) => {
+                        debug!("Stream ended: {}", e);
+                        return false;
+                    }
+                };
+                if packet.track_id() != self.track_id {
+                    continue;
+                }
+                match self.decoder.decode(&packet) {
+                    Ok(decoded) => {
+                        self.spec = *decoded.spec();
+                        let needed = decoded.capacity() * self.spec.channels.count();
+                        let buffer = match &mut self.buffer {
+                            Some(buffer) if buffer.capacity() >= needed => buffer,
+                            buffer => buffer.insert(SampleBuffer::new(decoded.capacity() as u64, self.spec)),
+                        };
+                        buffer.copy_interleaved_ref(decoded);
+                        self.position = 0;
+                        if !buffer.is_empty() {
+                            return true;
+                        }
+                    }
+                    // A damaged packet, e.g. right after connecting
+                    Err(Error::DecodeError(e)) if errors < MAX_DECODE_ERRORS => {
+                        debug!("Skipping undecodable packet: {}", e);
+                        errors += 1;
+                    }
+                    Err(e) => {
+                        warn!("Failed to decode the stream: {}", e);
+                        return false;
+                    }
+                }
+            }
+        }
+
+        fn samples(&self) -> &[f32] {
+            self.buffer.as_ref().map_or(&[], |buffer| buffer.samples())
+        }
+    }
+
+    impl Iterator for SymphoniaSource {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            let sample = *self.samples().get(self.position)?;
+            self.position += 1;
+            // Decode ahead, so the frame length is known at every sample
+            if self.position == self.samples().len() {
+                self.decode_next();
+            }
+            Some(sample)
+        }
+    }
+
+    impl Source for SymphoniaSource {
+        fn current_frame_len(&self) -> Option<usize> {
+            Some(self.samples().len() - self.position)
+        }
+
+        fn channels(&self) -> u16 {
+            self.spec.channels.count() as u16
+        }
+
+        fn sample_rate(&self) -> u32 {
+            self.spec.rate
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            let time = self.params.time_base?.calc_time(self.params.n_frames?);
+            Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+        }
+
+        fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+            let to = SeekTo::Time { time: pos.as_secs_f64().into(), track_id: Some(self.track_id) };
+            self.format
+                .seek(SeekMode::Coarse, to)
+                .map_err(|e| SeekError::Other(Box::new(e)))?;
+            self.decoder.reset();
+            self.position = self.samples().len();
+            self.decode_next();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_follows_content_type() {
+        let aac = Some("audio/aacp; charset=binary");
+        let expected = if cfg!(feature = "symphonia-decoder") { DecoderBackend::Symphonia } else { DecoderBackend::Rodio };
+        assert_eq!(DecoderBackend::Auto.resolve(aac), expected);
+        assert_eq!(DecoderBackend::Auto.resolve(Some("audio/mpeg")), DecoderBackend::Rodio);
+        assert_eq!(DecoderBackend::Auto.resolve(None), DecoderBackend::Rodio);
+        assert_eq!(DecoderBackend::Rodio.resolve(aac), DecoderBackend::Rodio);
+        assert_eq!(DecoderBackend::Symphonia.resolve(Some("audio/mpeg")), expected);
+    }
+}
@@ -0,0 +1,255 @@
+//! Output device selection and loss detection.
+//!
+//! When an output device disappears (typically a Bluetooth headset
+//! disconnecting), the audio stream silently stops consuming samples. The
+//! [`DeviceMonitor`] notices this either through the device no longer being
+//! listed among the output devices or through playback position stalling
+//! while audio is buffered, and the player then rebuilds its sink according
+//! to the configured [`DeviceLossPolicy`]. Switching the system's default
+//! output is not a loss; playback stays on the device it started on.
+//!
+//! The output is opened in the configuration the device supports that is
+//! closest to the stream's format (see [`negotiate_output`]), so audio is
+//! only resampled when the device cannot play the stream's sample rate.
+
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use rodio::cpal::traits::HostTrait;
+use rodio::cpal::{SampleRate, SupportedStreamConfig, SupportedStreamConfigRange};
+use rodio::{Device, DeviceTrait, OutputStream, OutputStreamHandle};
+use serde::{Deserialize, Serialize};
+
+/// How long playback position may stand still with audio buffered before
+/// the device is considered lost
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What to do when the output device disappears during playback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceLossPolicy {
+    /// Suspend playback until the original device comes back
+    #[default]
+    Pause,
+    /// Continue immediately on whatever the default device is now
+    SwitchToDefault,
+}
+
+/// Returns the current default output device together with its name.
+pub fn default_output_device() -> Option<(Device, String)> {
+    let device = rodio::cpal::default_host().default_output_device()?;
+    let name = device.name().unwrap_or_else(|_| "unknown".to_string());
+    Some((device, name))
+}
+
+/// An output device with the configurations it supports, listed up front
+/// so opening it for a stream only has to pick one of them.
+pub struct OutputDevice {
+    pub device: Device,
+    pub name: String,
+    supported: Vec<SupportedStreamConfigRange>,
+    default: Option<SupportedStreamConfig>,
+}
+
+impl OutputDevice {
+    /// Lists the configurations `device`, called `name`, supports.
+    pub fn probe(device: Device, name: String) -> Self {
+        let supported = match device.supported_output_configs() {
+            Ok(configs) => configs.collect(),
+            Err(e) => {
+                debug!("Cannot list the output configurations: {}", e);
+                Vec::new()
+            }
+        };
+        let default = device.default_output_config().ok();
+        Self { device, name, supported, default }
+    }
+
+    /// The current default output device, probed.
+    pub fn default_output() -> Option<Self> {
+        default_output_device().map(|(device, name)| Self::probe(device, name))
+    }
+}
+
+/// Returns the output device called `name`, if it is still connected.
+pub fn find_output_device(name: &str) -> Option<Device> {
+    let mut devices = rodio::cpal::default_host().output_devices().ok()?;
+    devices.find(|device| device.name().is_ok_and(|device_name| device_name == name))
+}
+
+/// Picks the output configuration for a stream of `channels` at
+/// `sample_rate` among the device's `supported` ranges: one playing the
+/// stream as it is if there is any, then one with the same channel count at
+/// the nearest rate, then the device's `default`. Sample formats are
+/// preferred as cpal's default heuristics order them.
+pub fn negotiate_output(
+    supported: &[SupportedStreamConfigRange],
+    default: Option<SupportedStreamConfig>,
+    channels: u16,
+    sample_rate: u32,
+) -> Option<SupportedStreamConfig> {
+    let best = |ranges: &mut dyn Iterator<Item = &SupportedStreamConfigRange>| {
+        ranges.max_by(|a, b| a.cmp_default_heuristics(b)).copied()
+    };
+    let rate = SampleRate(sample_rate);
+    let same_channels = || supported.iter().filter(|range| range.channels() == channels);
+
+    let plays_as_is = |range: &&SupportedStreamConfigRange| (range.min_sample_rate()..=range.max_sample_rate()).contains(&rate);
+    if let Some(range) = best(&mut same_channels().filter(plays_as_is)) {
+        return Some(range.with_sample_rate(rate));
+    }
+    let distance = |range: &SupportedStreamConfigRange| {
+        let nearest = rate.clamp(range.min_sample_rate(), range.max_sample_rate());
+        nearest.0.abs_diff(sample_rate)
+    };
+    if let Some(closest) = same_channels().map(distance).min() {
+        let range = best(&mut same_channels().filter(|range| distance(range) == closest))?;
+        let nearest = rate.clamp(range.min_sample_rate(), range.max_sample_rate());
+        return Some(range.with_sample_rate(nearest));
+    }
+    default.or_else(|| best(&mut supported.iter()).map(|range| range.with_max_sample_rate()))
+}
+
+/// Opens `output` for a stream of `channels` at `sample_rate`, in the
+/// configuration [`negotiate_output`] picks, or in the device's default
+/// configuration if that fails.
+pub fn open_output(output: &OutputDevice, channels: u16, sample_rate: u32) -> Result<(OutputStream, OutputStreamHandle), String> {
+    let device = &output.device;
+    if let Some(config) = negotiate_output(&output.supported, output.default.clone(), channels, sample_rate) {
+        let resampling = if config.sample_rate().0 == sample_rate { "no resampling" } else { "resampling" };
+        info!(
+            "Output: {} channels at {} Hz ({}) for a {} channel {} Hz stream, {}",
+            config.channels(),
+            config.sample_rate().0,
+            config.sample_format(),
+            channels,
+            sample_rate,
+            resampling
+        );
+        match OutputStream::try_from_device_config(device, config) {
+            Ok(output) => return Ok(output),
+            Err(e) => warn!("Failed to open the negotiated output, using the default: {}", e),
+        }
+    }
+    OutputStream::try_from_device(device).map_err(|e| e.to_string())
+}
+
+/// Watches the device a sink was opened on.
+#[derive(Debug)]
+pub struct DeviceMonitor {
+    device_name: String,
+    last_position: Duration,
+    last_progress: Instant,
+}
+
+impl DeviceMonitor {
+    /// Starts monitoring playback on the device called `device_name`.
+    pub fn new(device_name: String) -> Self {
+        Self {
+            device_name,
+            last_position: Duration::ZERO,
+            last_progress: Instant::now(),
+        }
+    }
+
+    /// Name of the monitored device
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Records the sink's current position. Returns `true` when the position
+    /// has not advanced for too long while playback should be running,
+    /// i.e. not paused and with audio buffered; an empty buffer is the
+    /// network's fault, not the device's.
+    pub fn is_stalled(&mut self, position: Duration, running: bool) -> bool {
+        if !running || position != self.last_position {
+            self.last_position = position;
+            self.last_progress = Instant::now();
+            return false;
+        }
+        self.last_progress.elapsed() >= STALL_TIMEOUT
+    }
+
+    /// Returns whether the monitored device is no longer connected.
+    pub fn device_gone(&self) -> bool {
+        find_output_device(&self.device_name).is_none()
+    }
+
+    /// Picks the device to rebuild the sink on, or `None` to keep waiting.
+    pub fn replacement(&self, policy: DeviceLossPolicy) -> Option<(Device, String)> {
+        match policy {
+            DeviceLossPolicy::SwitchToDefault => default_output_device(),
+            DeviceLossPolicy::Pause => {
+                find_output_device(&self.device_name).map(|device| (device, self.device_name.clone()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_serialization() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper {
+            policy: DeviceLossPolicy,
+        }
+
+        let wrapper: Wrapper = toml::from_str("policy = \"switch_to_default\"").unwrap();
+        assert_eq!(wrapper.policy, DeviceLossPolicy::SwitchToDefault);
+
+        let wrapper: Wrapper = toml::from_str("policy = \"pause\"").unwrap();
+        assert_eq!(wrapper.policy, DeviceLossPolicy::Pause);
+
+        assert_eq!(DeviceLossPolicy::default(), DeviceLossPolicy::Pause);
+    }
+
+    #[test]
+    fn test_output_negotiation() {
+        use rodio::cpal::{SampleFormat, SupportedBufferSize};
+
+        let range = |channels, min, max, format| {
+            SupportedStreamConfigRange::new(channels, SampleRate(min), SampleRate(max), SupportedBufferSize::Unknown, format)
+        };
+        let supported = [
+            range(2, 48_000, 48_000, SampleFormat::I16),
+            range(2, 44_100, 96_000, SampleFormat::I32),
+            range(2, 44_100, 96_000, SampleFormat::F32),
+            range(6, 8_000, 192_000, SampleFormat::F32),
+        ];
+        let negotiate = |channels, rate| negotiate_output(&supported, None, channels, rate).unwrap();
+
+        // Played as it is, in the preferred sample format
+        let config = negotiate(2, 44_100);
+        assert_eq!((config.channels(), config.sample_rate().0, config.sample_format()), (2, 44_100, SampleFormat::F32));
+        assert_eq!(negotiate(6, 22_050).sample_rate().0, 22_050);
+
+        // Resampled to the nearest rate with the same channels
+        assert_eq!(negotiate(2, 32_000).sample_rate().0, 44_100);
+
+        // No stereo at all: the default, or else the best range
+        let default = supported[0].with_max_sample_rate();
+        assert_eq!(negotiate_output(&supported, Some(default.clone()), 1, 44_100), Some(default));
+        assert_eq!(negotiate(1, 44_100).channels(), 2);
+        assert_eq!(negotiate_output(&[], None, 2, 44_100), None);
+    }
+
+    #[test]
+    fn test_stall_detection() {
+        let mut monitor = DeviceMonitor::new("test".to_string());
+        assert!(!monitor.is_stalled(Duration::from_secs(1), true));
+
+        // Pretend the position last moved long ago
+        monitor.last_progress = Instant::now() - STALL_TIMEOUT;
+        assert!(monitor.is_stalled(Duration::from_secs(1), true));
+
+        // Paused playback never counts as stalled
+        assert!(!monitor.is_stalled(Duration::from_secs(1), false));
+
+        // Progress resets the timer
+        monitor.last_progress = Instant::now() - STALL_TIMEOUT;
+        assert!(!monitor.is_stalled(Duration::from_secs(2), true));
+    }
+}
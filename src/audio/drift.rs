@@ -0,0 +1,331 @@
+//! Keeps the playback buffer from drifting away from the live stream.
+//!
+//! The server and the local sound card run on separate clocks. Over a long
+//! session the difference makes the download buffer either grow (latency to
+//! live keeps increasing) or shrink until playback underruns. The
+//! [`BufferGauge`] tracks how many stream bytes have been downloaded versus
+//! consumed by the decoder, and the [`DriftGuard`] turns that occupancy into
+//! a playback speed within ±0.5% to steer it back.
+//!
+//! The same gauge counts underruns, reads that had to wait for the network,
+//! and is the source of the [`DownloadProgress`](crate::audio::progress::DownloadProgress)
+//! reported to the UI. [`BufferLimits`] bound how much it may hold before
+//! the player reconnects to start over with an empty buffer.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::SelfMonitorConfig;
+
+/// Largest speed correction applied, as a fraction of normal speed
+pub const MAX_CORRECTION: f32 = 0.005;
+
+/// How far occupancy may stray from the target before correcting
+const TOLERANCE: Duration = Duration::from_secs(3);
+
+/// A read blocking at least this long was waiting for the network
+const UNDERRUN_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Byte counters shared between the downloader and the decoder.
+#[derive(Debug, Clone)]
+pub struct BufferGauge {
+    downloaded: Arc<AtomicU64>,
+    consumed: Arc<AtomicU64>,
+    underruns: Arc<AtomicU32>,
+    bytes_per_second: u64,
+    prefetch_bytes: u64,
+}
+
+impl BufferGauge {
+    /// Creates a gauge for a stream of `bitrate` kbps.
+    pub fn new(bitrate: u32) -> Self {
+        Self {
+            downloaded: Arc::new(AtomicU64::new(0)),
+            consumed: Arc::new(AtomicU64::new(0)),
+            underruns: Arc::new(AtomicU32::new(0)),
+            bytes_per_second: (u64::from(bitrate) * 1000 / 8).max(1),
+            prefetch_bytes: 0,
+        }
+    }
+
+    /// Sets the amount of audio fetched before playback starts, which
+    /// [`prefetch_fill`](Self::prefetch_fill) is relative to.
+    pub fn with_prefetch(mut self, bytes: u64) -> Self {
+        self.prefetch_bytes = bytes;
+        self
+    }
+
+    /// Records the total number of bytes downloaded so far.
+    pub fn set_downloaded(&self, position: u64) {
+        self.downloaded.store(position, Ordering::Relaxed);
+    }
+
+    /// Records the decoder's read position in the stream.
+    pub fn set_consumed(&self, position: u64) {
+        self.consumed.store(position, Ordering::Relaxed);
+    }
+
+    /// Total number of bytes downloaded so far.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded.load(Ordering::Relaxed)
+    }
+
+    /// Bytes downloaded but not yet read by the decoder.
+    pub fn buffered_bytes(&self) -> u64 {
+        let downloaded = self.downloaded.load(Ordering::Relaxed);
+        let consumed = self.consumed.load(Ordering::Relaxed);
+        downloaded.saturating_sub(consumed)
+    }
+
+    /// Audio downloaded but not yet played, as playback time.
+    pub fn occupancy(&self) -> Duration {
+        Duration::from_millis(self.buffered_bytes() * 1000 / self.bytes_per_second)
+    }
+
+    /// Buffered bytes relative to the prefetch size, from 0.0 to 1.0.
+    pub fn prefetch_fill(&self) -> f32 {
+        if self.prefetch_bytes == 0 {
+            return 1.0;
+        }
+        (self.buffered_bytes() as f32 / self.prefetch_bytes as f32).min(1.0)
+    }
+
+    /// Records a read that ran out of downloaded audio.
+    pub fn record_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of underruns since connecting.
+    pub fn underruns(&self) -> u32 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+}
+
+/// Bounds on the stream buffer beyond which the player reconnects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferLimits {
+    /// Most audio that may be buffered ahead of playback
+    pub max_buffered: Option<Duration>,
+    /// Most stream bytes one connection may keep in memory
+    pub max_stored: Option<u64>,
+}
+
+impl BufferLimits {
+    pub fn from_config(config: &SelfMonitorConfig) -> Self {
+        Self {
+            max_buffered: (config.max_buffer_secs > 0).then(|| Duration::from_secs(config.max_buffer_secs)),
+            max_stored: (config.max_stream_mb > 0).then(|| config.max_stream_mb * 1024 * 1024),
+        }
+    }
+
+    /// Describes how the gauge's buffer is out of bounds, if it is.
+    pub fn check(&self, gauge: &BufferGauge) -> Option<String> {
+        if let Some(max) = self.max_buffered
+            && gauge.occupancy() > max
+        {
+            return Some(format!("{:?} of audio buffered, more than {:?}", gauge.occupancy(), max));
+        }
+        // Downloaded audio stays in memory for the whole connection
+        if let Some(max) = self.max_stored
+            && gauge.downloaded() > max
+        {
+            return Some(format!("{} MiB of stream held in memory", gauge.downloaded() / 1024 / 1024));
+        }
+        None
+    }
+}
+
+/// Reader wrapper that reports its position and underruns to a
+/// [`BufferGauge`].
+#[derive(Debug)]
+pub struct GaugedReader<R> {
+    inner: R,
+    position: u64,
+    gauge: BufferGauge,
+}
+
+impl<R> GaugedReader<R> {
+    pub fn new(inner: R, gauge: BufferGauge) -> Self {
+        Self { inner, position: 0, gauge }
+    }
+}
+
+impl<R: Read> Read for GaugedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let started = Instant::now();
+        let read = self.inner.read(buf)?;
+        // The first read waits for the prefetch, which is not an underrun
+        if self.position > 0 && started.elapsed() >= UNDERRUN_THRESHOLD {
+            self.gauge.record_underrun();
+        }
+        self.position += read as u64;
+        self.gauge.set_consumed(self.position);
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for GaugedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        self.gauge.set_consumed(self.position);
+        Ok(self.position)
+    }
+}
+
+/// Chooses a playback speed that keeps buffer occupancy near a target.
+///
+/// Correction starts once occupancy leaves the tolerance band around the
+/// target and stops once it is back at the target, so the speed does not
+/// flip back and forth at the band's edges.
+#[derive(Debug)]
+pub struct DriftGuard {
+    target: Duration,
+    speed: f32,
+}
+
+impl DriftGuard {
+    /// Creates a guard aiming for `target` worth of buffered audio.
+    pub fn new(target: Duration) -> Self {
+        Self { target, speed: 1.0 }
+    }
+
+    /// Moves the target to the current occupancy, e.g. after a pause where
+    /// falling behind live was intended.
+    pub fn rebase(&mut self, occupancy: Duration) {
+        self.target = self.target.max(occupancy);
+        self.speed = 1.0;
+    }
+
+    /// Returns the playback speed to use for the current occupancy.
+    pub fn update(&mut self, occupancy: Duration) -> f32 {
+        let above = occupancy > self.target + TOLERANCE;
+        let below = occupancy + TOLERANCE < self.target;
+
+        self.speed = if above {
+            1.0 + MAX_CORRECTION
+        } else if below {
+            1.0 - MAX_CORRECTION
+        } else if (self.speed > 1.0 && occupancy <= self.target)
+            || (self.speed < 1.0 && occupancy >= self.target)
+        {
+            1.0
+        } else {
+            self.speed
+        };
+        self.speed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_gauge_occupancy() {
+        let gauge = BufferGauge::new(128);
+        let mut reader = GaugedReader::new(Cursor::new(vec![0u8; 64_000]), gauge.clone());
+
+        gauge.set_downloaded(64_000);
+        assert_eq!(gauge.occupancy(), Duration::from_secs(4));
+
+        let mut buf = [0u8; 16_000];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(gauge.occupancy(), Duration::from_secs(3));
+
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(gauge.occupancy(), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_gauge_prefetch_fill() {
+        let gauge = BufferGauge::new(128).with_prefetch(32_000);
+        assert_eq!(gauge.prefetch_fill(), 0.0);
+
+        gauge.set_downloaded(48_000);
+        gauge.set_consumed(32_000);
+        assert_eq!(gauge.buffered_bytes(), 16_000);
+        assert_eq!(gauge.prefetch_fill(), 0.5);
+
+        gauge.set_consumed(0);
+        assert_eq!(gauge.prefetch_fill(), 1.0);
+    }
+
+    #[test]
+    fn test_buffer_limits() {
+        let gauge = BufferGauge::new(128);
+        gauge.set_downloaded(16_000 * 60);
+        gauge.set_consumed(16_000 * 50);
+        assert_eq!(BufferLimits::default().check(&gauge), None);
+
+        let limits = BufferLimits { max_buffered: Some(Duration::from_secs(10)), max_stored: None };
+        assert_eq!(limits.check(&gauge), None);
+        gauge.set_consumed(16_000 * 49);
+        assert!(limits.check(&gauge).is_some());
+
+        let limits = BufferLimits { max_buffered: None, max_stored: Some(16_000 * 60) };
+        assert_eq!(limits.check(&gauge), None);
+        gauge.set_downloaded(16_000 * 61);
+        assert!(limits.check(&gauge).is_some());
+    }
+
+    /// Yields one byte per read, each after waiting for the network.
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            std::thread::sleep(UNDERRUN_THRESHOLD);
+            buf[0] = 0;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_blocking_reads_count_as_underruns() {
+        let gauge = BufferGauge::new(128);
+        let mut reader = GaugedReader::new(SlowReader, gauge.clone());
+        let mut buf = [0u8; 1];
+
+        // Waiting for the prefetch on the first read does not count
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(gauge.underruns(), 0);
+
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(gauge.underruns(), 1);
+    }
+
+    #[test]
+    fn test_guard_corrects_with_hysteresis() {
+        let secs = Duration::from_secs;
+        let mut guard = DriftGuard::new(secs(5));
+
+        assert_eq!(guard.update(secs(5)), 1.0);
+        assert_eq!(guard.update(secs(7)), 1.0);
+
+        // Buffer grew too much: play slightly faster until back on target
+        assert_eq!(guard.update(secs(9)), 1.0 + MAX_CORRECTION);
+        assert_eq!(guard.update(secs(6)), 1.0 + MAX_CORRECTION);
+        assert_eq!(guard.update(secs(5)), 1.0);
+
+        // Buffer drained: play slightly slower until back on target
+        assert_eq!(guard.update(secs(1)), 1.0 - MAX_CORRECTION);
+        assert_eq!(guard.update(secs(4)), 1.0 - MAX_CORRECTION);
+        assert_eq!(guard.update(secs(5)), 1.0);
+    }
+
+    #[test]
+    fn test_guard_rebase_after_pause() {
+        let secs = Duration::from_secs;
+        let mut guard = DriftGuard::new(secs(5));
+        assert_eq!(guard.update(secs(60)), 1.0 + MAX_CORRECTION);
+
+        guard.rebase(secs(60));
+        assert_eq!(guard.update(secs(60)), 1.0);
+
+        // Rebasing never lowers the target below the initial buffer
+        guard.rebase(secs(1));
+        assert_eq!(guard.update(secs(62)), 1.0);
+    }
+}
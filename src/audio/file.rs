@@ -0,0 +1,161 @@
+//! Playing recordings from disk.
+//!
+//! A recording is decoded with [`SymphoniaSource`], so unlike a live stream
+//! it can be sought in both directions, and it reports where playback is
+//! instead of download progress. It answers the same [`PlayerCommand`]s as
+//! [`play_channel`](crate::audio::play_channel); the ones that only make
+//! sense for a stream are logged and ignored.
+
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+use rodio::{Sink, Source};
+use tokio::sync::{mpsc, watch};
+
+use crate::archive::Recording;
+use crate::audio::decoder::SymphoniaSource;
+use crate::audio::device::{open_output, OutputDevice};
+use crate::audio::isolation::{PanicGuard, PanicSlot};
+use crate::audio::limiter::{LimiterControl, LimiterStage};
+use crate::audio::player::{DEVICE_CHECK_INTERVAL, FADE, boost, fade_out, sink_volume};
+use crate::audio::stereo::{StereoControl, StereoStage};
+use crate::audio::{PlaybackPosition, PlaybackStatus, PlayerCommand, PlayerUpdate};
+use crate::errors::{AudioError, PlayerError, PlayerResult};
+use crate::models::TrackInfo;
+
+/// Plays `recording` until it ends.
+///
+/// Its track is published on `track_info` right away, as a recording
+/// carries no ICY metadata. The estimated duration of the recording is
+/// used for the position bar when the decoder cannot tell the length.
+/// Reports the position through `on_update` every second and after each
+/// seek.
+///
+/// # Returns
+///
+/// Returns `Ok(false)` when the recording ends or playback is quit.
+pub async fn play_file<F>(
+    recording: &Recording,
+    track_info: watch::Sender<TrackInfo>,
+    mut rx: mpsc::UnboundedReceiver<PlayerCommand>,
+    volume: Option<u8>,
+    on_update: F,
+    stereo: StereoControl,
+    limiter: LimiterControl,
+) -> PlayerResult<bool>
+where
+    F: Fn(PlayerUpdate),
+{
+    let path = &recording.path;
+    let channel = recording.channel();
+    info!("Playing recording {}", path.display());
+    track_info.send_replace(recording.track.clone());
+
+    let source = SymphoniaSource::open(path).map_err(|e| {
+        error!("Failed to open recording: {}", e);
+        PlayerError::Audio(AudioError::DecodingError(e))
+    })?;
+    let duration = source.total_duration().or(recording.duration);
+    let output = OutputDevice::default_output().ok_or_else(|| {
+        error!("No audio output device available");
+        PlayerError::Audio(AudioError::DeviceUnavailable)
+    })?;
+    let (_stream, handle) = open_output(&output, source.channels(), source.sample_rate()).map_err(|e| {
+        error!("Failed to open audio stream: {}", e);
+        PlayerError::Audio(AudioError::OutputInit(e))
+    })?;
+    let sink = Sink::try_new(&handle).map_err(|e| {
+        error!("Failed to create audio sink: {}", e);
+        PlayerError::Audio(AudioError::SinkCreation(e.to_string()))
+    })?;
+    info!("Audio output: {}", output.name);
+
+    let mut volume = volume;
+    let mut duck = 1.0;
+    let mut paused = false;
+    if let Some(level) = volume {
+        on_update(PlayerUpdate::VolumeChanged(level));
+    }
+    sink.set_volume(sink_volume(volume, duck));
+    limiter.set_gain(boost(volume));
+
+    let panics = PanicSlot::default();
+    let boosted = LimiterStage::new(source.fade_in(FADE), limiter.clone());
+    sink.append(PanicGuard::new(StereoStage::new(boosted, stereo), panics.clone()));
+    let position = |sink: &Sink| PlaybackPosition { position: sink.get_pos(), duration };
+    on_update(PlayerUpdate::Position(position(&sink)));
+
+    let url = &channel.playlists[0].url;
+    let mut tick = tokio::time::interval(DEVICE_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            cmd = rx.recv() => {
+                match cmd {
+                    Some(PlayerCommand::Quit) | None => {
+                        info!("Received quit command");
+                        if !paused {
+                            fade_out(&sink, sink_volume(volume, duck)).await;
+                        }
+                        return Ok(false);
+                    }
+                    Some(PlayerCommand::SetVolume(vol)) => {
+                        volume = Some(vol);
+                        sink.set_volume(sink_volume(volume, duck));
+                        limiter.set_gain(boost(volume));
+                        on_update(PlayerUpdate::VolumeChanged(vol));
+                    }
+                    Some(PlayerCommand::Duck(factor)) => {
+                        duck = factor.clamp(0.0, 1.0);
+                        sink.set_volume(sink_volume(volume, duck));
+                    }
+                    // A recording has nothing to disconnect from, so stopping pauses it
+                    Some(PlayerCommand::Pause | PlayerCommand::Stop) => {
+                        sink.pause();
+                        paused = true;
+                        on_update(PlayerUpdate::Paused);
+                    }
+                    Some(PlayerCommand::Resume) => {
+                        sink.play();
+                        paused = false;
+                        on_update(PlayerUpdate::Resumed);
+                    }
+                    Some(PlayerCommand::SeekBack(secs)) => {
+                        seek(&sink, sink.get_pos().saturating_sub(Duration::from_secs(secs)));
+                        on_update(PlayerUpdate::Position(position(&sink)));
+                    }
+                    Some(PlayerCommand::SeekForward(secs)) => {
+                        let target = sink.get_pos() + Duration::from_secs(secs);
+                        // Stop short of the end rather than ending the recording
+                        seek(&sink, duration.map_or(target, |duration| target.min(duration.saturating_sub(FADE))));
+                        on_update(PlayerUpdate::Position(position(&sink)));
+                    }
+                    Some(PlayerCommand::QueryStatus(reply)) => {
+                        let _ = reply.send(PlaybackStatus::new(&channel, url, volume, paused, false));
+                    }
+                    Some(PlayerCommand::JumpToLive | PlayerCommand::SwitchStream(_) | PlayerCommand::SwitchChannel(_)) => {
+                        debug!("Ignoring a stream command while playing a recording");
+                    }
+                }
+            },
+            _ = tick.tick() => {
+                if let Some(message) = panics.take() {
+                    error!("Audio decoding panicked: {}", message);
+                    std::panic::resume_unwind(Box::new(message));
+                }
+                if sink.empty() {
+                    info!("Recording {} ended", path.display());
+                    return Ok(false);
+                }
+                on_update(PlayerUpdate::Position(position(&sink)));
+            }
+        }
+    }
+}
+
+/// Moves playback of `sink` to `target`.
+fn seek(sink: &Sink, target: Duration) {
+    match sink.try_seek(target) {
+        Ok(()) => debug!("Seeked to {:?}", target),
+        Err(e) => warn!("Cannot seek in this recording: {}", e),
+    }
+}
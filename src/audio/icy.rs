@@ -0,0 +1,187 @@
+//! Guards the ICY metadata reader against short reads.
+//!
+//! `icy_metadata::IcyMetadataReader` (0.2) assumes the reader below it
+//! fills every buffer it asks for. When a read comes back short, which a
+//! network stream does whenever less has arrived than was asked for, it
+//! carries on from the start of the caller's buffer, overwriting audio it
+//! already returned, and slices past the end of the buffer once the next
+//! metadata block is further away than the buffer is long, panicking the
+//! decoder thread. [`WholeReads`] sits between the two and fills each
+//! buffer completely, or fails, so neither can happen.
+//!
+//! [`IcyStream`] reads a stream straight off the connection for readers
+//! that never seek, such as recordings and the metadata-only connections
+//! of the channel monitor and casting, so none of it stays in memory.
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures_util::TryStreamExt;
+use icy_metadata::{IcyHeaders, IcyMetadataReader};
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+/// Reader wrapper that only returns whole buffers.
+///
+/// Reads are repeated until the buffer is full. The end of the stream is
+/// reported as [`io::ErrorKind::UnexpectedEof`] rather than a short or
+/// empty read, which the metadata reader would also mishandle; for a live
+/// stream that is a dropped connection, so at most the fragment of audio
+/// before it is lost. Decoders take the error as the end of the stream.
+#[derive(Debug)]
+pub struct WholeReads<R> {
+    inner: R,
+}
+
+impl<R> WholeReads<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Read for WholeReads<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended")),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
+    }
+}
+
+impl<R: Seek> Seek for WholeReads<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Audio of a live stream, read from the HTTP body as it arrives with the
+/// ICY metadata taken out. Unlike the player's connection nothing is kept
+/// once read, so it can stay open for hours.
+///
+/// Reads block on the network and must happen off the Tokio runtime, e.g.
+/// in `spawn_blocking`. The end of the stream is reported as
+/// [`io::ErrorKind::UnexpectedEof`] when it carries metadata.
+pub struct IcyStream {
+    reader: Box<dyn Read + Send>,
+    has_metadata: bool,
+}
+
+impl IcyStream {
+    /// Connects to `stream_url`, asking for ICY metadata. `on_title` is
+    /// called with every stream title read.
+    pub async fn connect<F>(stream_url: &str, on_title: F) -> Result<Self, reqwest::Error>
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        let response = reqwest::Client::new()
+            .get(stream_url)
+            .header("Icy-MetaData", "1")
+            .send()
+            .await?
+            .error_for_status()?;
+        let metadata_interval = IcyHeaders::parse_from_headers(response.headers()).metadata_interval();
+
+        let body = SyncIoBridge::new(StreamReader::new(response.bytes_stream().map_err(io::Error::other)));
+        let reader: Box<dyn Read + Send> = match metadata_interval {
+            Some(interval) => Box::new(IcyMetadataReader::new(WholeReads::new(body), Some(interval), move |metadata| {
+                if let Ok(md) = metadata
+                    && let Some(title) = md.stream_title()
+                {
+                    on_title(title);
+                }
+            })),
+            None => Box::new(body),
+        };
+        Ok(Self { reader, has_metadata: metadata_interval.is_some() })
+    }
+
+    /// Whether the stream interleaves ICY metadata with the audio
+    pub fn has_metadata(&self) -> bool {
+        self.has_metadata
+    }
+
+    /// Reads the stream only for its metadata until it ends or `stop` is
+    /// set. Blocks like any read.
+    pub fn discard_audio(mut self, stop: &AtomicBool) -> io::Result<()> {
+        // Audio is discarded; reading only drives the metadata callback
+        let mut buf = [0u8; 8 * 1024];
+        while !stop.load(Ordering::Relaxed) {
+            match self.read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for IcyStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icy_metadata::IcyMetadataReader;
+    use std::num::NonZeroUsize;
+    use std::sync::{Arc, Mutex};
+
+    /// Hands out at most three bytes per read, like a slow connection
+    struct Trickle(io::Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(3);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_metadata_survives_short_reads() {
+        // Two blocks of 8 audio bytes, each followed by metadata
+        let title = b"StreamTitle='Boards of Canada - Roygbiv';";
+        let mut metadata = vec![0u8; 48];
+        metadata[..title.len()].copy_from_slice(title);
+        let mut stream = Vec::new();
+        for audio in [b"AAAAAAAA", b"BBBBBBBB"] {
+            stream.extend_from_slice(audio);
+            stream.push(3);
+            stream.extend_from_slice(&metadata);
+        }
+        stream.extend_from_slice(b"CCCC");
+
+        let titles = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&titles);
+        let mut reader = IcyMetadataReader::new(
+            WholeReads::new(Trickle(io::Cursor::new(stream))),
+            NonZeroUsize::new(8),
+            move |metadata| {
+                if let Some(title) = metadata.ok().and_then(|md| md.stream_title().map(str::to_string)) {
+                    seen.lock().unwrap().push(title);
+                }
+            },
+        );
+
+        let mut audio = Vec::new();
+        let mut buf = [0u8; 5];
+        let end = loop {
+            match reader.read(&mut buf) {
+                Ok(read) => audio.extend_from_slice(&buf[..read]),
+                Err(e) => break e,
+            }
+        };
+
+        assert_eq!(end.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(audio.starts_with(b"AAAAAAAABBBBBBBB"));
+        assert_eq!(*titles.lock().unwrap(), ["Boards of Canada - Roygbiv"; 2]);
+    }
+}
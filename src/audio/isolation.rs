@@ -0,0 +1,148 @@
+//! Containing panics in the audio pipeline.
+//!
+//! Decoding happens on the audio output thread, where a panic would kill
+//! the output without anyone noticing. [`PanicGuard`] catches panics in the
+//! wrapped source, ends the source and leaves the message in a
+//! [`PanicSlot`] for the player to pick up and report.
+
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Message of the first panic caught by a [`PanicGuard`].
+#[derive(Debug, Clone, Default)]
+pub struct PanicSlot(Arc<Mutex<Option<String>>>);
+
+impl PanicSlot {
+    fn set(&self, message: String) {
+        let mut slot = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        slot.get_or_insert(message);
+    }
+
+    /// Takes the caught panic message, if there is one.
+    pub fn take(&self) -> Option<String> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+/// Text of a panic payload.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// A source that ends instead of unwinding when the wrapped source panics.
+pub struct PanicGuard<S> {
+    inner: S,
+    slot: PanicSlot,
+    failed: bool,
+}
+
+impl<S> PanicGuard<S> {
+    pub fn new(inner: S, slot: PanicSlot) -> Self {
+        Self { inner, slot, failed: false }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for PanicGuard<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.failed {
+            return None;
+        }
+        match catch_unwind(AssertUnwindSafe(|| self.inner.next())) {
+            Ok(sample) => sample,
+            Err(payload) => {
+                self.failed = true;
+                self.slot.set(panic_message(payload.as_ref()));
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for PanicGuard<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Yields `samples_left` samples, then panics.
+    struct Exploding {
+        samples_left: usize,
+    }
+
+    impl Iterator for Exploding {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            if self.samples_left == 0 {
+                panic!("decoder exploded");
+            }
+            self.samples_left -= 1;
+            Some(0.5)
+        }
+    }
+
+    impl Source for Exploding {
+        fn current_frame_len(&self) -> Option<usize> {
+            None
+        }
+
+        fn channels(&self) -> u16 {
+            1
+        }
+
+        fn sample_rate(&self) -> u32 {
+            44100
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_panic_ends_source_and_is_recorded() {
+        let slot = PanicSlot::default();
+        let samples: Vec<f32> = PanicGuard::new(Exploding { samples_left: 2 }, slot.clone()).collect();
+
+        assert_eq!(samples, vec![0.5, 0.5]);
+        assert_eq!(slot.take().as_deref(), Some("decoder exploded"));
+        assert_eq!(slot.take(), None);
+    }
+}
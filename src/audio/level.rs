@@ -0,0 +1,258 @@
+//! Evening out quiet and loud channels.
+//!
+//! A [`LevelTap`] measures the RMS level of the decoded stream, before the
+//! volume, the boost and the limiter touch it. The player reads the level
+//! once a second through the shared [`LevelMeter`] and [`ChannelLevels`]
+//! keeps a long-running average per channel in `channel_levels.json` in
+//! the state directory. Once a channel was heard for [`MIN_MEASURED`], it
+//! gets an offset lifting its average to [`REFERENCE_DB`], at most
+//! [`MAX_OFFSET_DB`]; with `channel_leveling = "apply"` the offset is
+//! applied through the limiter when the channel starts, so the boost can't
+//! clip. Channels louder than the reference are left alone.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, warn};
+use rodio::source::SeekError;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use crate::state_file::get_state_directory;
+
+/// Average level channels are lifted to, in dBFS RMS
+pub const REFERENCE_DB: f32 = -18.0;
+
+/// Largest offset given to a quiet channel, in dB
+pub const MAX_OFFSET_DB: f32 = 9.0;
+
+/// Listening time before a channel's average is trusted
+pub const MIN_MEASURED: Duration = Duration::from_secs(120);
+
+/// Offsets smaller than this are not worth applying, in dB
+const MIN_OFFSET_DB: f32 = 0.5;
+
+/// Seconds of listening the average follows; older listening fades out, so
+/// a channel that changed its mastering is caught up with
+const AVERAGE_WINDOW: u64 = 3600;
+
+/// Readings below this level are gaps between tracks, not music, in dBFS
+const SILENCE_DB: f32 = -60.0;
+
+/// Samples a [`LevelTap`] sums up before handing them to its meter
+const FLUSH_SAMPLES: u32 = 4096;
+
+/// What to do with the channel levels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelMode {
+    /// Neither measure nor adjust
+    Off,
+    /// Measure, and tell when a channel plays quiet
+    Suggest,
+    /// Measure, and lift quiet channels
+    #[default]
+    Apply,
+}
+
+/// Energy of the samples a [`LevelTap`] let through since the last reading.
+#[derive(Debug, Default)]
+struct Energy {
+    sum: f64,
+    samples: u64,
+}
+
+/// Level measured by a [`LevelTap`], shared with the player.
+#[derive(Debug, Clone, Default)]
+pub struct LevelMeter(Arc<Mutex<Energy>>);
+
+impl LevelMeter {
+    /// RMS level in dBFS since the last call, `None` when too little or
+    /// only silence came through.
+    pub fn take(&self) -> Option<f32> {
+        let energy = std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()));
+        if energy.samples < u64::from(FLUSH_SAMPLES) {
+            return None;
+        }
+        let rms = (energy.sum / energy.samples as f64).sqrt() as f32;
+        let db = 20.0 * rms.max(1e-6).log10();
+        (db > SILENCE_DB).then_some(db)
+    }
+
+    fn add(&self, sum: f64, samples: u32) {
+        let mut energy = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        energy.sum += sum;
+        energy.samples += u64::from(samples);
+    }
+}
+
+/// A source measuring the level of the wrapped source.
+pub struct LevelTap<S> {
+    inner: S,
+    meter: LevelMeter,
+    sum: f64,
+    samples: u32,
+}
+
+impl<S> LevelTap<S> {
+    pub fn new(inner: S, meter: LevelMeter) -> Self {
+        Self { inner, meter, sum: 0.0, samples: 0 }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for LevelTap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        self.sum += f64::from(sample) * f64::from(sample);
+        self.samples += 1;
+        if self.samples == FLUSH_SAMPLES {
+            self.meter.add(std::mem::take(&mut self.sum), std::mem::take(&mut self.samples));
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for LevelTap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
+
+/// Average level of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct ChannelLevel {
+    /// Average RMS level in dBFS
+    db: f32,
+    /// Seconds of audio measured
+    secs: u64,
+}
+
+/// Average levels of the channels played so far.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelLevels {
+    /// `None` keeps the levels in memory only
+    path: Option<PathBuf>,
+    levels: BTreeMap<String, ChannelLevel>,
+}
+
+impl ChannelLevels {
+    /// Loads the levels from the state directory.
+    pub fn load() -> Self {
+        match get_state_directory() {
+            Ok(dir) => Self::at(dir.join("channel_levels.json")),
+            Err(e) => {
+                warn!("Channel levels not saved: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Loads the levels from the file at `path`.
+    pub fn at(path: PathBuf) -> Self {
+        let levels = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), levels }
+    }
+
+    /// Adds a second of `channel_id` measured at `db` to its average.
+    pub fn record(&mut self, channel_id: &str, db: f32) {
+        let level = self.levels.entry(channel_id.to_string()).or_insert(ChannelLevel { db, secs: 0 });
+        level.secs += 1;
+        level.db += (db - level.db) / level.secs.min(AVERAGE_WINDOW) as f32;
+    }
+
+    /// Offset in dB that lifts `channel_id` to [`REFERENCE_DB`], once it
+    /// was measured long enough and is quiet enough to need one.
+    pub fn offset(&self, channel_id: &str) -> Option<f32> {
+        let level = self.levels.get(channel_id)?;
+        if level.secs < MIN_MEASURED.as_secs() {
+            return None;
+        }
+        let offset = (REFERENCE_DB - level.db).min(MAX_OFFSET_DB);
+        (offset >= MIN_OFFSET_DB).then_some(offset)
+    }
+
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string(&self.levels)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => debug!("Saved the levels of {} channels", self.levels.len()),
+            Err(e) => warn!("Failed to save channel levels: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn test_level_tap_measures_rms() {
+        let meter = LevelMeter::default();
+        // A square wave at half scale has an RMS level of -6 dBFS
+        let samples: Vec<f32> = (0..48_000).map(|i| if i % 2 == 0 { 0.5 } else { -0.5 }).collect();
+        let tap = LevelTap::new(SamplesBuffer::new(2, 48_000, samples.clone()), meter.clone());
+        assert_eq!(tap.collect::<Vec<f32>>(), samples);
+        let db = meter.take().unwrap();
+        assert!((db + 6.02).abs() < 0.01, "{}", db);
+        assert_eq!(meter.take(), None);
+
+        let tap = LevelTap::new(SamplesBuffer::new(2, 48_000, vec![0.0; 48_000]), meter.clone());
+        tap.for_each(drop);
+        assert_eq!(meter.take(), None);
+    }
+
+    #[test]
+    fn test_quiet_channels_get_an_offset_once_measured() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("channel_levels.json");
+        let mut levels = ChannelLevels::at(path.clone());
+        for _ in 0..MIN_MEASURED.as_secs() - 1 {
+            levels.record("dronezone", -26.0);
+            levels.record("defcon", -12.0);
+        }
+        assert_eq!(levels.offset("dronezone"), None);
+        levels.record("dronezone", -26.0);
+        levels.record("defcon", -12.0);
+        assert_eq!(levels.offset("dronezone"), Some(8.0));
+        assert_eq!(levels.offset("defcon"), None);
+        assert_eq!(levels.offset("groovesalad"), None);
+
+        // Kept across sessions
+        levels.save();
+        let levels = ChannelLevels::at(path);
+        assert_eq!(levels.offset("dronezone"), Some(8.0));
+    }
+}
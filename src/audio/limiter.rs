@@ -0,0 +1,200 @@
+//! Gain above 100% with a limiter against clipping.
+//!
+//! [`LimiterStage`] applies the boost part of the volume (anything above
+//! 100%) and the channel's leveling offset, then limits peaks to just below full scale. The limiter
+//! follows the signal's peak envelope, attacking instantly and releasing
+//! over [`RELEASE`], so loud passages are turned down smoothly instead of
+//! being clipped.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Highest output level the limiter lets through
+const CEILING: f32 = 0.95;
+
+/// How long the limiter takes to stop reducing gain after a peak
+const RELEASE: Duration = Duration::from_millis(150);
+
+/// Shared gain and limiter status of a [`LimiterStage`].
+#[derive(Debug, Clone)]
+pub struct LimiterControl {
+    /// Gain as `f32` bits
+    gain: Arc<AtomicU32>,
+    /// Factor lifting a quiet channel, on top of the gain, as `f32` bits
+    leveling: Arc<AtomicU32>,
+    /// Lowest factor the limiter scaled the level by since it was last
+    /// read, as `f32` bits
+    reduction: Arc<AtomicU32>,
+}
+
+impl Default for LimiterControl {
+    fn default() -> Self {
+        Self {
+            gain: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+            leveling: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+            reduction: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+        }
+    }
+}
+
+impl LimiterControl {
+    /// Sets the gain applied before limiting; 1.0 bypasses the stage.
+    pub fn set_gain(&self, gain: f32) {
+        self.gain.store(gain.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn gain(&self) -> f32 {
+        f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+
+    /// Sets the factor a quiet channel is lifted by, applied with the gain
+    /// set by [`Self::set_gain`]; 1.0 leaves the channel as it is.
+    pub fn set_leveling(&self, factor: f32) {
+        self.leveling.store(factor.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn leveling(&self) -> f32 {
+        f32::from_bits(self.leveling.load(Ordering::Relaxed))
+    }
+
+    /// Strongest gain reduction since the last call, as the factor the
+    /// level was scaled by; 1.0 when the limiter did not engage.
+    pub fn take_reduction(&self) -> f32 {
+        f32::from_bits(self.reduction.swap(1.0f32.to_bits(), Ordering::Relaxed))
+    }
+}
+
+/// A source boosting another and limiting its peaks.
+pub struct LimiterStage<S> {
+    inner: S,
+    control: LimiterControl,
+    /// Peak envelope of the boosted signal
+    envelope: f32,
+    /// Per-sample envelope decay for the source's sample rate
+    release: f32,
+    release_rate: (u32, u16),
+}
+
+impl<S: Source<Item = f32>> LimiterStage<S> {
+    pub fn new(inner: S, control: LimiterControl) -> Self {
+        let mut stage = Self {
+            inner,
+            control,
+            envelope: 0.0,
+            release: 0.0,
+            release_rate: (0, 0),
+        };
+        stage.update_release();
+        stage
+    }
+
+    /// Recomputes the release decay when the sample rate or channel count
+    /// changes.
+    fn update_release(&mut self) {
+        let rate = (self.inner.sample_rate(), self.inner.channels());
+        if rate != self.release_rate {
+            let samples = RELEASE.as_secs_f32() * rate.0 as f32 * rate.1.max(1) as f32;
+            self.release = (-1.0 / samples.max(1.0)).exp();
+            self.release_rate = rate;
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for LimiterStage<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.update_release();
+        let sample = self.inner.next()?;
+        let gain = self.control.gain() * self.control.leveling();
+        if gain <= 1.0 && self.envelope <= CEILING {
+            return Some(sample * gain);
+        }
+
+        let boosted = sample * gain;
+        self.envelope = boosted.abs().max(self.envelope * self.release);
+        if self.envelope > CEILING {
+            let factor = CEILING / self.envelope;
+            // Positive floats order the same as their bits
+            self.control.reduction.fetch_min(factor.to_bits(), Ordering::Relaxed);
+            Some(boosted * factor)
+        } else {
+            Some(boosted)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for LimiterStage<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.envelope = 0.0;
+        self.inner.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn process(control: &LimiterControl, samples: Vec<f32>) -> Vec<f32> {
+        LimiterStage::new(SamplesBuffer::new(2, 44100, samples), control.clone()).collect()
+    }
+
+    #[test]
+    fn test_unity_gain_passes_through() {
+        let control = LimiterControl::default();
+        assert_eq!(process(&control, vec![0.5, -0.9, 0.1]), vec![0.5, -0.9, 0.1]);
+        assert_eq!(control.take_reduction(), 1.0);
+    }
+
+    #[test]
+    fn test_quiet_signal_is_boosted() {
+        let control = LimiterControl::default();
+        control.set_gain(1.5);
+        let output = process(&control, vec![0.2, -0.4]);
+        assert!((output[0] - 0.3).abs() < 1e-6);
+        assert!((output[1] + 0.6).abs() < 1e-6);
+        assert_eq!(control.take_reduction(), 1.0);
+
+        // Leveling adds to the boost
+        control.set_leveling(2.0);
+        let output = process(&control, vec![0.1]);
+        assert!((output[0] - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_peaks_are_limited_below_ceiling() {
+        let control = LimiterControl::default();
+        control.set_gain(2.0);
+        let output = process(&control, vec![0.9, -0.8, 0.7, 0.1, 0.1]);
+        assert!(output.iter().all(|s| s.abs() <= CEILING + 1e-6));
+        // Gain stays reduced for a while after the peak instead of jumping back
+        assert!(output[3] < 0.2 && output[3] > 0.0);
+        assert!((control.take_reduction() - CEILING / 1.8).abs() < 1e-6);
+        assert_eq!(control.take_reduction(), 1.0);
+    }
+}
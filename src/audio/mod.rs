@@ -1,3 +1,20 @@
 pub mod player;
+pub mod device;
+pub mod drift;
+pub mod stereo;
+pub mod limiter;
+pub mod level;
+pub mod progress;
+pub mod cue;
+pub mod wake;
+pub mod isolation;
+pub mod watchdog;
+pub mod decoder;
+pub mod icy;
+pub mod chunks;
+pub mod file;
 
 pub use player::*;
+pub use device::DeviceLossPolicy;
+pub use decoder::DecoderBackend;
+pub use file::play_file;
@@ -2,82 +2,327 @@
 //!
 //! This module handles the core audio functionality including:
 //! - Connecting to audio streams
-//! - Parsing ICY metadata for track information
+//! - Parsing ICY metadata for track information, or polling the SomaFM API
+//!   for it when a stream sends none
 //! - Real-time volume control
 //! - Command-based playback control
+//! - Rebuilding the output when the audio device is lost
+//! - Opening the output in the stream's format when the device supports it
+//! - Fading out the old channel and fading in the new one on a switch
+//! - Correcting clock drift between the server and the sound card
+//! - Stereo balance and mono downmix
+//! - Volume above 100% through a limiter
+//! - Measuring the stream's level, to lift quiet channels
+//! - Reporting download progress and resetting the latency behind live
+//! - Confirming every volume change it applies
+//! - Optional tones when the stream drops or comes back
+//! - Reconnecting after system sleep or when the stream goes silent
+//! - Reconnecting when the stream buffer grows past its limits
+//! - Catching panics in the decoder on the audio output thread
+//! - Decoding with rodio or Symphonia depending on the stream's format
+//! - Optionally rebuilding the pipeline when it stops producing audio
 //!
 //! # Examples
 //!
 //! ```rust,no_run
-//! use soma_player::audio::{play_channel, PlayerCommand};
-//! use soma_player::models::{Channel, TrackInfo};
-//! use tokio::sync::{mpsc, Mutex};
-//! use std::sync::Arc;
+//! use soma_player::api::ApiClient;
+//! use soma_player::audio::{play_channel, PlaybackControls, PlaybackOptions, PlayerCommand};
+//! use soma_player::models::{Channel, StreamQuality, TrackInfo};
+//! use tokio::sync::{mpsc, watch};
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 //! let channel = Channel {
 //!     id: "groovesalad".to_string(),
 //!     title: "Groove Salad".to_string(),
 //!     description: "A nicely chilled plate of ambient beats".to_string(),
-//!     playlists: vec![],
+//!     ..Channel::default()
 //! };
 //!
-//! let track_info = Arc::new(Mutex::new(TrackInfo::default()));
+//! let (track_info, _track_updates) = watch::channel(TrackInfo::default());
 //! let (tx, rx) = mpsc::unbounded_channel();
-//! let volume = Some(75);
+//! let options = PlaybackOptions { volume: Some(75), quality: StreamQuality::High, ..PlaybackOptions::default() };
 //!
 //! // Start playback
-//! let result = play_channel(&channel, track_info, rx, volume).await;
+//! let api = ApiClient::new();
+//! let result = play_channel(
+//!     &api, &channel, track_info, rx, &options, PlaybackControls::default(),
+//!     |update| println!("{:?}", update),
+//! ).await;
 //! # Ok(())
 //! # }
 //! ```
 
-use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
-use rodio::{OutputStream, Sink, Decoder};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch};
+use rodio::{OutputStreamHandle, Sink, Decoder, Source};
 use icy_metadata::{IcyHeaders, IcyMetadataReader, RequestIcyMetadata};
 use stream_download::http::reqwest::Client;
 use stream_download::http::HttpStream;
-use stream_download::storage::memory::MemoryStorageProvider;
 use stream_download::{Settings, StreamDownload};
 use log::{debug, error, info, warn};
 
-use crate::models::{Channel, TrackInfo, parse_track_info};
-use crate::api::parse_pls_playlist;
+use crate::models::{Channel, Playlist, StreamInfo, StreamQuality, TrackInfo, track_from_metadata};
+use crate::api::ApiClient;
+use crate::api::songs::SongPoller;
+use crate::audio::cue::Cue;
+use crate::audio::decoder::DecoderBackend;
+use crate::audio::device::{open_output, DeviceLossPolicy, DeviceMonitor, OutputDevice};
+use crate::audio::drift::{BufferGauge, BufferLimits, DriftGuard, GaugedReader};
+use crate::audio::chunks::ChunkStorageProvider;
+use crate::audio::icy::WholeReads;
+use crate::audio::isolation::{PanicGuard, PanicSlot};
+use crate::audio::level::{LevelMeter, LevelTap};
+use crate::audio::limiter::{LimiterControl, LimiterStage};
+use crate::audio::progress::{DownloadProgress, ProgressMeter};
+use crate::audio::wake::{WakeDetector, WakeReason};
+use crate::audio::watchdog::{PcmCounter, PcmTap, Watchdog, WatchdogVerdict};
+use crate::config::{AppConfig, WatchdogConfig};
+use crate::errors::{AudioError, NetworkError, PlayerError, PlayerResult};
+use crate::audio::stereo::{StereoControl, StereoStage};
+
+/// HTTP stream reader with ICY metadata stripped out
+pub type StreamReader = IcyMetadataReader<WholeReads<GaugedReader<StreamDownload<ChunkStorageProvider>>>>;
+
+/// Decoded audio of a stream, from either decoder backend
+pub type StreamSource = Box<dyn Source<Item = f32> + Send>;
+
+/// A stream connected by [`connect_stream`].
+pub struct ConnectedStream {
+    /// The audio bytes
+    pub reader: StreamReader,
+    /// How much downloaded audio has not been read yet
+    pub gauge: BufferGauge,
+    /// Whether the stream sends ICY metadata at all
+    pub has_metadata: bool,
+    /// The `Content-Type` the server sent
+    pub content_type: Option<String>,
+    /// Station details from the ICY headers
+    pub info: StreamInfo,
+}
+
+/// How often the sink and output device are checked and download progress
+/// is reported during playback
+pub(crate) const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often reopening a stream is attempted after the system woke up,
+/// while the network may still be coming back
+const RESUME_ATTEMPTS: u32 = 5;
+
+/// Pause between attempts to reopen a stream after waking up
+const RESUME_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// How long playback fades out before the player stops, quits or switches
+/// channels, and fades in when a stream starts
+pub(crate) const FADE: Duration = Duration::from_millis(500);
+
+/// Why a playback session on one output device ended
+enum SessionEnd {
+    Quit,
+    StreamEnded,
+    DeviceLost,
+    JumpToLive,
+    SwitchStream,
+    /// The connection died, e.g. while the system was asleep
+    Resume,
+    /// The buffer outgrew its limits
+    Trim,
+    /// The watchdog saw no audio; rebuild after waiting this long
+    Watchdog(Duration),
+    Stop,
+    SwitchChannel,
+}
 
 /// Commands that can be sent to control audio playback.
+///
+/// The same commands drive local playback and casting; commands a backend
+/// cannot carry out are logged and ignored.
 #[derive(Debug)]
 pub enum PlayerCommand {
     /// Stop playback and quit
     Quit,
-    /// Change volume (0-100)
+    /// Stop playback and disconnect, but keep accepting commands; `Resume`
+    /// or `SwitchChannel` start playing again at live
+    Stop,
+    /// Change volume (0-100, or higher to boost through the limiter)
     SetVolume(u8),
     /// Pause playback
     Pause,
     /// Resume playback
     Resume,
+    /// Drop the buffered audio and reconnect at the live edge
+    JumpToLive,
+    /// Reconnect using this exact playlist of the channel
+    SwitchStream(Playlist),
+    /// Scale the volume by this factor (0.0-1.0) without changing the
+    /// volume setting, used to duck playback
+    Duck(f32),
+    /// Rewind this many seconds within the buffered audio
+    SeekBack(u64),
+    /// Skip ahead this many seconds, only possible in recordings
+    SeekForward(u64),
+    /// Play another channel without restarting the player
+    SwitchChannel(Channel),
+    /// Reply with the current [`PlaybackStatus`]
+    QueryStatus(oneshot::Sender<PlaybackStatus>),
+}
+
+/// What a running player reports back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerUpdate {
+    /// Download progress, `None` while (re)connecting
+    Progress(Option<DownloadProgress>),
+    /// The volume the player now uses; the player is the authority on the
+    /// volume, so this is what should be shown and saved
+    VolumeChanged(u8),
+    /// Playback was paused
+    Paused,
+    /// Playback was resumed
+    Resumed,
+    /// The stream was (re)connected and announced these details
+    StreamInfo(StreamInfo),
+    /// Where playback of a recording is, reported every second and after
+    /// each seek
+    Position(PlaybackPosition),
+}
+
+/// Position within a recording being played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackPosition {
+    pub position: Duration,
+    /// Length of the recording, when known
+    pub duration: Option<Duration>,
+}
+
+/// State of a running player, the answer to [`PlayerCommand::QueryStatus`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackStatus {
+    pub channel_id: String,
+    pub channel_title: String,
+    /// URL of the stream being played
+    pub stream_url: String,
+    /// Volume setting, `None` for the system volume
+    pub volume: Option<u8>,
+    pub paused: bool,
+    /// Stopped with `PlayerCommand::Stop`
+    pub stopped: bool,
+    /// Audio buffered ahead of playback, when known
+    pub buffered: Option<Duration>,
+}
+
+impl PlaybackStatus {
+    pub fn new(channel: &Channel, stream_url: &str, volume: Option<u8>, paused: bool, stopped: bool) -> Self {
+        Self {
+            channel_id: channel.id.clone(),
+            channel_title: channel.title.clone(),
+            stream_url: stream_url.to_string(),
+            volume,
+            paused,
+            stopped,
+            buffered: None,
+        }
+    }
+}
+
+/// Settings a playback session takes from the config, fixed while it runs.
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackOptions {
+    /// Initial volume (0-100, higher boosts), `None` for the system volume
+    pub volume: Option<u8>,
+    /// Which of the channel's streams to prefer
+    pub quality: StreamQuality,
+    /// What to do when the output device disappears
+    pub device_policy: DeviceLossPolicy,
+    /// Which decoder to use, or `Auto` to choose by content type
+    pub decoder: DecoderBackend,
+    /// Whether to announce drops and reconnects with a tone
+    pub reconnect_cue: bool,
+    /// Buffer size beyond which the stream is reopened
+    pub limits: BufferLimits,
+    /// How often to ask the API for the current track when the stream
+    /// sends no ICY metadata, `None` to never ask
+    pub metadata_poll: Option<Duration>,
+    /// When to rebuild a silent pipeline, `None` to never
+    pub watchdog: Option<WatchdogConfig>,
+}
+
+impl PlaybackOptions {
+    /// The options `config` sets for playing `channel_id`. Only
+    /// `unattended` playback runs under the watchdog.
+    pub fn from_config(config: &AppConfig, channel_id: &str, unattended: bool) -> Self {
+        Self {
+            volume: config.volume,
+            quality: config.quality_for(channel_id),
+            device_policy: config.on_device_loss,
+            decoder: config.decoder,
+            reconnect_cue: config.reconnect_cue,
+            limits: BufferLimits::from_config(&config.self_monitor),
+            metadata_poll: (config.metadata_poll_secs > 0).then(|| Duration::from_secs(config.metadata_poll_secs)),
+            watchdog: unattended.then(|| config.watchdog.clone()),
+        }
+    }
+}
+
+/// Handles to adjust and watch a running player from outside.
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackControls {
+    /// Balance and mono downmix, adjustable while playing
+    pub stereo: StereoControl,
+    /// Reports when the limiter engages
+    pub limiter: LimiterControl,
+    /// Measures the stream before the volume is applied
+    pub level: LevelMeter,
 }
 
 /// Plays a SomaFM channel's audio stream with real-time control.
 ///
 /// This function handles the complete audio playback pipeline:
-/// 1. Resolves playlist URLs (handles .pls files)
-/// 2. Establishes HTTP connection with ICY metadata support
+/// 1. Resolves playlist URLs (handles .pls files), probing the output
+///    device meanwhile
+/// 2. Establishes HTTP connection with ICY metadata support, while the
+///    device probe finishes
 /// 3. Sets up audio decoding and playback
 /// 4. Processes real-time metadata updates
 /// 5. Responds to volume and control commands
+/// 6. Rebuilds the sink if the output device is lost, following the
+///    options' `device_policy`
+/// 7. Reports download progress, including how far behind live playback is
+/// 8. Applies the balance and mono settings of the `stereo` control
+/// 9. Boosts volumes above 100% through a limiter reporting to the
+///    `limiter` control
+/// 10. Reconnects to the same stream after system sleep or when it stops
+///     delivering data
+/// 11. Reconnects with an empty buffer when it grows past the `limits`
+/// 12. With `reconnect_cue`, plays a tone when the stream stalls or ends and
+///     when audio comes back
+/// 13. Polls the SomaFM API for the current track every `metadata_poll` if
+///     the stream sends no ICY metadata
+/// 14. With a `watchdog`, rebuilds the pipeline when it produces no audio,
+///     backing off between attempts and failing once they run out
+/// 15. Decodes each stream with the backend `decoder` picks for its format
+/// 16. Fades each stream in, and fades out before stopping, quitting or
+///     switching channels
+/// 17. Measures the level of the decoded stream into the `level` control
 ///
 /// # Arguments
 ///
+/// * `api` - Client used to resolve the channel's playlists
 /// * `channel` - The SomaFM channel to play
-/// * `track_info` - Shared track information updated with ICY metadata
+/// * `track_info` - Publishes the track information from ICY metadata
 /// * `rx` - Command receiver for controlling playback
-/// * `volume` - Optional initial volume (0-100), defaults to system volume
+/// * `options` - Settings for this session, see [`PlaybackOptions`]
+/// * `controls` - Stereo, limiter and level handles shared with the caller
+/// * `on_update` - Called every second with the download progress, with
+///   `None` progress while (re)connecting, and with every applied volume
 ///
 /// # Returns
 ///
 /// Returns `Ok(false)` when playback stops normally, or an error if playback fails.
 ///
+/// # Panics
+///
+/// A panic while decoding on the audio output thread is caught there and
+/// resumed on the calling thread, so callers only have to guard this call.
+///
 /// # Errors
 ///
 /// This function can return errors for:
@@ -85,180 +330,603 @@ pub enum PlayerCommand {
 /// - Audio device initialization problems
 /// - Stream decoding issues
 /// - Invalid playlist formats
-pub async fn play_channel(
+pub async fn play_channel<F>(
+    api: &ApiClient,
     channel: &Channel, 
-    track_info: Arc<Mutex<TrackInfo>>,
+    track_info: watch::Sender<TrackInfo>,
     mut rx: mpsc::UnboundedReceiver<PlayerCommand>,
-    volume: Option<u8>
-) -> Result<bool, String> {
+    options: &PlaybackOptions,
+    controls: PlaybackControls,
+    on_update: F,
+) -> PlayerResult<bool>
+where
+    F: Fn(PlayerUpdate),
+{
     info!("Starting playback for channel: {}", channel.title);
     
-    let initial_url = channel.playlists
-        .iter()
-        .find(|p| p.format == "mp3" && p.quality == "high") // Prefer high-quality MP3
-        .or_else(|| channel.playlists.iter().find(|p| p.format == "mp3")) // Then any MP3
-        .or_else(|| channel.playlists.first()) // Otherwise, just take the first available
-        .map(|p| &p.url)
-        .ok_or("No playable stream URL found for this channel.")?;
-
-    debug!("Using playlist URL: {}", initial_url);
-
-    // Check if the URL is a .pls playlist file and parse it if needed
-    let stream_url = if initial_url.ends_with(".pls") {
-        debug!("Parsing .pls playlist");
-        parse_pls_playlist(initial_url).await.map_err(|e| {
-            error!("Failed to parse .pls playlist: {}", e);
-            e.to_string()
-        })?
-    } else {
-        initial_url.to_string()
-    };
+    let PlaybackOptions {
+        volume, quality, device_policy, decoder, reconnect_cue, limits, metadata_poll, ref watchdog,
+    } = *options;
+    let PlaybackControls { stereo, limiter, level } = controls;
+    let mut channel = channel.clone();
+    // Initializing the output device and connecting to the stream both
+    // take a while, so the device is probed while the first connection is
+    // made; only opening it waits for the stream's format
+    let device_lookup = tokio::task::spawn_blocking(OutputDevice::default_output);
+    let mut stream_url = api.resolve_stream_url(&channel, quality).await.map_err(|e| {
+        error!("Failed to resolve stream URL: {}", e);
+        PlayerError::Network(NetworkError::ApiConnection(e.to_string()))
+    })?;
+    api.save_last_channel(&channel, quality);
 
     debug!("Final stream URL: {}", stream_url);
 
+    let mut volume = volume;
+    if let Some(level) = volume {
+        on_update(PlayerUpdate::VolumeChanged(level));
+    }
+    let mut duck = 1.0;
+    let mut paused = false;
+    // Announce audio coming back once the output is rebuilt
+    let mut rebuilt = false;
+    let mut resuming = false;
+    let panics = PanicSlot::default();
+    let pcm = PcmCounter::default();
+    let mut watchdog = watchdog.as_ref().and_then(|config| Watchdog::new(config, std::time::Instant::now()));
+    let (connected, output) = tokio::join!(
+        reopen_stream(api, &mut stream_url, &track_info, decoder, 1),
+        device_lookup,
+    );
+    let mut output = output
+        .ok()
+        .flatten()
+        .ok_or_else(|| {
+            error!("No audio output device available");
+            PlayerError::Audio(AudioError::DeviceUnavailable)
+        })?;
+    let mut connected = Some(connected?);
+
+    loop {
+        on_update(PlayerUpdate::Progress(None));
+        let attempts = if std::mem::take(&mut resuming) { RESUME_ATTEMPTS } else { 1 };
+        let (source, gauge, has_metadata, info) = match connected.take() {
+            Some(connected) => connected,
+            None => reopen_stream(api, &mut stream_url, &track_info, decoder, attempts).await?,
+        };
+        on_update(PlayerUpdate::StreamInfo(info));
+        // Without ICY metadata, ask the API what is playing instead
+        let _songs = metadata_poll
+            .filter(|_| !has_metadata)
+            .map(|interval| SongPoller::start(api.clone(), channel.id.clone(), interval, track_info.clone()));
+
+        // Create audio output matching the stream where the device allows
+        let (_stream, handle) = open_output(&output, source.channels(), source.sample_rate())
+            .map_err(|e| {
+                error!("Failed to open audio stream: {}", e);
+                PlayerError::Audio(AudioError::OutputInit(e))
+            })?;
+        let sink = Sink::try_new(&handle)
+            .map_err(|e| {
+                error!("Failed to create audio sink: {}", e);
+                PlayerError::Audio(AudioError::SinkCreation(e.to_string()))
+            })?;
+        info!("Audio output: {}", output.name);
+
+        // Set volume if provided (percent converted to a factor, boosted above 100)
+        if volume.is_some() || duck < 1.0 {
+            let volume_float = sink_volume(volume, duck);
+            sink.set_volume(volume_float);
+            limiter.set_gain(boost(volume));
+            debug!("Set volume to: {:?}% ({})", volume, volume_float);
+        }
+        if paused {
+            sink.pause();
+        }
+        if reconnect_cue && std::mem::take(&mut rebuilt) {
+            play_cue(&handle, Cue::Reconnected, sink_volume(volume, duck));
+        }
+
+        info!("Starting audio playback");
+        let measured = LevelTap::new(source, level.clone());
+        let boosted = LimiterStage::new(measured.fade_in(FADE), limiter.clone());
+        let tapped = PcmTap::new(StereoStage::new(boosted, stereo.clone()), pcm.clone());
+        sink.append(PanicGuard::new(tapped, panics.clone()));
+        if let Some(watchdog) = &mut watchdog {
+            watchdog.rearm(std::time::Instant::now());
+        }
+
+        let mut monitor = DeviceMonitor::new(output.name.clone());
+        let mut drift_guard = DriftGuard::new(gauge.occupancy());
+        let mut meter = ProgressMeter::new(gauge.clone());
+        let mut stalled = false;
+        let mut wake = WakeDetector::new(DEVICE_CHECK_INTERVAL, std::time::Instant::now(), std::time::SystemTime::now());
+        let mut device_check = tokio::time::interval(DEVICE_CHECK_INTERVAL);
+
+        // Wait for a command, the end of the stream, or loss of the device
+        let end = loop {
+            tokio::select! {
+                cmd = rx.recv() => {
+                    match cmd {
+                        Some(PlayerCommand::Quit) | None => {
+                            info!("Received quit command");
+                            break SessionEnd::Quit;
+                        }
+                        Some(PlayerCommand::SetVolume(vol)) => {
+                            volume = Some(vol);
+                            let volume_float = sink_volume(volume, duck);
+                            sink.set_volume(volume_float);
+                            limiter.set_gain(boost(volume));
+                            debug!("Volume changed to: {}% ({})", vol, volume_float);
+                            on_update(PlayerUpdate::VolumeChanged(vol));
+                            // Continue the loop to handle more commands
+                        }
+                        Some(PlayerCommand::Pause) => {
+                            sink.pause();
+                            paused = true;
+                            info!("Playback paused");
+                            on_update(PlayerUpdate::Paused);
+                            // Continue the loop to handle more commands
+                        }
+                        Some(PlayerCommand::Resume) => {
+                            sink.play();
+                            paused = false;
+                            // Stay as far behind live as the pause put us
+                            drift_guard.rebase(gauge.occupancy());
+                            info!("Playback resumed");
+                            on_update(PlayerUpdate::Resumed);
+                            // Continue the loop to handle more commands
+                        }
+                        Some(PlayerCommand::JumpToLive) => {
+                            info!("Jumping to live, dropping {:?} of buffered audio", gauge.occupancy());
+                            break SessionEnd::JumpToLive;
+                        }
+                        Some(PlayerCommand::Duck(factor)) => {
+                            duck = factor.clamp(0.0, 1.0);
+                            sink.set_volume(sink_volume(volume, duck));
+                        }
+                        Some(PlayerCommand::SwitchStream(playlist)) => {
+                            match api.resolve_playlist_url(&playlist).await {
+                                Ok(url) => {
+                                    info!("Switching to {} {} stream {}", playlist.format, playlist.quality, url);
+                                    stream_url = url;
+                                    break SessionEnd::SwitchStream;
+                                }
+                                Err(e) => warn!("Keeping current stream, failed to resolve {}: {}", playlist.url, e),
+                            }
+                        }
+                        Some(PlayerCommand::Stop) => {
+                            info!("Playback stopped");
+                            break SessionEnd::Stop;
+                        }
+                        Some(PlayerCommand::SeekBack(secs)) => {
+                            let target = sink.get_pos().saturating_sub(Duration::from_secs(secs));
+                            match sink.try_seek(target) {
+                                Ok(()) => {
+                                    // Stay as far behind live as the seek put us
+                                    drift_guard.rebase(gauge.occupancy());
+                                    info!("Rewound {}s to {:?}", secs, target);
+                                }
+                                Err(e) => warn!("Cannot rewind this stream: {}", e),
+                            }
+                        }
+                        Some(PlayerCommand::SeekForward(_)) => {
+                            debug!("Cannot skip ahead of a live stream");
+                        }
+                        Some(PlayerCommand::SwitchChannel(next)) => {
+                            if switch_channel(api, &mut channel, &mut stream_url, next, quality, &track_info).await {
+                                break SessionEnd::SwitchChannel;
+                            }
+                        }
+                        Some(PlayerCommand::QueryStatus(reply)) => {
+                            let mut status = PlaybackStatus::new(&channel, &stream_url, volume, paused, false);
+                            status.buffered = Some(gauge.occupancy());
+                            let _ = reply.send(status);
+                        }
+                    }
+                },
+                _ = device_check.tick() => {
+                    if let Some(message) = panics.take() {
+                        error!("Audio decoding panicked: {}", message);
+                        std::panic::resume_unwind(Box::new(message));
+                    }
+                    match wake.check(std::time::Instant::now(), std::time::SystemTime::now(), gauge.downloaded()) {
+                        Some(WakeReason::Slept(slept)) => {
+                            info!("System resumed after about {:?}, reconnecting to {}", slept, channel.title);
+                            break SessionEnd::Resume;
+                        }
+                        Some(WakeReason::Stale(silent)) => {
+                            warn!("No stream data for {:?}, reconnecting to {}", silent, channel.title);
+                            break SessionEnd::Resume;
+                        }
+                        None => {}
+                    }
+                    if let Some(watchdog) = &mut watchdog {
+                        match watchdog.check(std::time::Instant::now(), pcm.samples(), paused) {
+                            WatchdogVerdict::Healthy => {}
+                            WatchdogVerdict::Restart { attempt, delay } => {
+                                warn!("Watchdog: no audio from {}, restart {} in {:?}", channel.title, attempt, delay);
+                                break SessionEnd::Watchdog(delay);
+                            }
+                            WatchdogVerdict::GiveUp => {
+                                error!("Watchdog: no audio from {} after every restart, giving up", channel.title);
+                                return Err(PlayerError::Audio(AudioError::NoAudio(channel.title.clone())));
+                            }
+                        }
+                    }
+                    if let Some(problem) = limits.check(&gauge) {
+                        warn!("Stream buffer out of bounds ({}), reconnecting", problem);
+                        break SessionEnd::Trim;
+                    }
+                    if sink.empty() {
+                        warn!("Audio stream ended unexpectedly");
+                        if reconnect_cue {
+                            play_cue(&handle, Cue::Dropped, sink_volume(volume, duck));
+                            tokio::time::sleep(Cue::Dropped.duration()).await;
+                        }
+                        break SessionEnd::StreamEnded;
+                    }
+                    if monitor.device_gone() {
+                        warn!("Output device {} disconnected", monitor.device_name());
+                        break SessionEnd::DeviceLost;
+                    }
+                    // Running dry is an underrun, reported as stalled progress
+                    if monitor.is_stalled(sink.get_pos(), !paused && gauge.occupancy() > Duration::ZERO) {
+                        warn!("Playback stalled on {}, assuming the device was lost", monitor.device_name());
+                        break SessionEnd::DeviceLost;
+                    }
+                    let progress = meter.sample(std::time::Instant::now());
+                    if reconnect_cue && !paused && progress.stalled != stalled {
+                        let cue = if progress.stalled { Cue::Dropped } else { Cue::Reconnected };
+                        play_cue(&handle, cue, sink_volume(volume, duck));
+                    }
+                    stalled = progress.stalled;
+                    on_update(PlayerUpdate::Progress(Some(progress)));
+                    if !paused {
+                        let speed = drift_guard.update(gauge.occupancy());
+                        if speed != sink.speed() {
+                            debug!("Buffer at {:?}, adjusting playback speed to {}", gauge.occupancy(), speed);
+                            sink.set_speed(speed);
+                        }
+                    }
+                }
+            }
+        };
+
+        // Clean up, without a harsh cut when the music is left or replaced
+        if matches!(end, SessionEnd::Quit | SessionEnd::Stop | SessionEnd::SwitchChannel) && !paused {
+            fade_out(&sink, sink_volume(volume, duck)).await;
+        }
+        sink.stop();
+        drop(sink);
+
+        match end {
+            SessionEnd::Quit | SessionEnd::StreamEnded => {
+                info!("Audio playback stopped");
+                return Ok(false);
+            }
+            SessionEnd::JumpToLive | SessionEnd::SwitchStream | SessionEnd::SwitchChannel | SessionEnd::Trim => {
+                // Reconnect on the same device with an empty buffer
+            }
+            SessionEnd::Resume => {
+                // The network may take a moment to return after waking up
+                resuming = true;
+            }
+            SessionEnd::Watchdog(delay) => {
+                tokio::time::sleep(delay).await;
+                info!("Watchdog: rebuilding the audio pipeline for {}", channel.title);
+            }
+            SessionEnd::Stop => {
+                on_update(PlayerUpdate::Progress(None));
+                loop {
+                    match rx.recv().await {
+                        Some(PlayerCommand::Quit) | None => {
+                            info!("Received quit command while stopped");
+                            return Ok(false);
+                        }
+                        Some(PlayerCommand::Resume) | Some(PlayerCommand::JumpToLive) => {
+                            if std::mem::take(&mut paused) {
+                                on_update(PlayerUpdate::Resumed);
+                            }
+                            break;
+                        }
+                        Some(PlayerCommand::SwitchChannel(next)) => {
+                            if switch_channel(api, &mut channel, &mut stream_url, next, quality, &track_info).await {
+                                if std::mem::take(&mut paused) {
+                                    on_update(PlayerUpdate::Resumed);
+                                }
+                                break;
+                            }
+                        }
+                        Some(PlayerCommand::SwitchStream(playlist)) => {
+                            match api.resolve_playlist_url(&playlist).await {
+                                Ok(url) => stream_url = url,
+                                Err(e) => warn!("Failed to resolve {}: {}", playlist.url, e),
+                            }
+                        }
+                        Some(PlayerCommand::SetVolume(vol)) => {
+                            volume = Some(vol);
+                            on_update(PlayerUpdate::VolumeChanged(vol));
+                        }
+                        Some(PlayerCommand::Duck(factor)) => duck = factor.clamp(0.0, 1.0),
+                        Some(PlayerCommand::QueryStatus(reply)) => {
+                            let _ = reply.send(PlaybackStatus::new(&channel, &stream_url, volume, paused, true));
+                        }
+                        Some(PlayerCommand::Pause | PlayerCommand::Stop | PlayerCommand::SeekBack(_) | PlayerCommand::SeekForward(_)) => {}
+                    }
+                }
+            }
+            SessionEnd::DeviceLost => {
+                info!("Waiting for an output device ({:?} policy)", device_policy);
+                loop {
+                    if let Some((new_device, new_name)) = monitor.replacement(device_policy) {
+                        info!("Rebuilding audio output on {}", new_name);
+                        output = OutputDevice::probe(new_device, new_name);
+                        rebuilt = true;
+                        break;
+                    }
+
+                    tokio::select! {
+                        cmd = rx.recv() => {
+                            match cmd {
+                                Some(PlayerCommand::Quit) | None => {
+                                    info!("Received quit command while waiting for device");
+                                    return Ok(false);
+                                }
+                                Some(PlayerCommand::SetVolume(vol)) => {
+                                    volume = Some(vol);
+                                    on_update(PlayerUpdate::VolumeChanged(vol));
+                                }
+                                Some(PlayerCommand::Duck(factor)) => duck = factor.clamp(0.0, 1.0),
+                                // Applied to the sink once the device returns
+                                Some(PlayerCommand::Pause) => {
+                                    paused = true;
+                                    on_update(PlayerUpdate::Paused);
+                                }
+                                Some(PlayerCommand::Resume) => {
+                                    paused = false;
+                                    on_update(PlayerUpdate::Resumed);
+                                }
+                                // Reconnecting after the device returns starts at live anyway
+                                Some(PlayerCommand::JumpToLive) => {}
+                                Some(PlayerCommand::SwitchStream(playlist)) => {
+                                    match api.resolve_playlist_url(&playlist).await {
+                                        Ok(url) => stream_url = url,
+                                        Err(e) => warn!("Failed to resolve {}: {}", playlist.url, e),
+                                    }
+                                }
+                                Some(PlayerCommand::SwitchChannel(next)) => {
+                                    switch_channel(api, &mut channel, &mut stream_url, next, quality, &track_info).await;
+                                }
+                                Some(PlayerCommand::QueryStatus(reply)) => {
+                                    let _ = reply.send(PlaybackStatus::new(&channel, &stream_url, volume, paused, false));
+                                }
+                                // Nothing is buffered while there is no device
+                                Some(PlayerCommand::Stop | PlayerCommand::SeekBack(_) | PlayerCommand::SeekForward(_)) => {}
+                            }
+                        },
+                        _ = device_check.tick() => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Resolves the stream of `next` and makes it the playing channel, clearing
+/// the track info. Returns `false` and keeps the current channel if the
+/// stream cannot be resolved.
+async fn switch_channel(
+    api: &ApiClient,
+    channel: &mut Channel,
+    stream_url: &mut String,
+    next: Channel,
+    quality: StreamQuality,
+    track_info: &watch::Sender<TrackInfo>,
+) -> bool {
+    match api.resolve_stream_url(&next, quality).await {
+        Ok(url) => {
+            info!("Switching to channel {}", next.title);
+            api.save_last_channel(&next, quality);
+            *channel = next;
+            *stream_url = url;
+            track_info.send_replace(TrackInfo::default());
+            true
+        }
+        Err(e) => {
+            warn!("Keeping {}, failed to resolve {}: {}", channel.title, next.title, e);
+            false
+        }
+    }
+}
+
+/// Plays `cue` next to whatever the sink is playing.
+fn play_cue(handle: &OutputStreamHandle, cue: Cue, volume: f32) {
+    debug!("Playing {:?} cue", cue);
+    if let Err(e) = handle.play_raw(cue.source(volume)) {
+        debug!("Failed to play {:?} cue: {}", cue, e);
+    }
+}
+
+/// Turns the sink down from `volume` to silence over [`FADE`].
+pub(crate) async fn fade_out(sink: &Sink, volume: f32) {
+    const STEPS: u32 = 20;
+    for step in (0..STEPS).rev() {
+        sink.set_volume(volume * step as f32 / STEPS as f32);
+        tokio::time::sleep(FADE / STEPS).await;
+    }
+}
+
+/// Sink volume for a volume setting scaled by the ducking factor; no
+/// setting means full volume. Anything above 100% is left to [`boost`].
+pub(crate) fn sink_volume(volume: Option<u8>, duck: f32) -> f32 {
+    volume.map_or(1.0, |v| v.min(100) as f32 / 100.0) * duck
+}
+
+/// Gain the limiter stage applies for volumes above 100%.
+pub(crate) fn boost(volume: Option<u8>) -> f32 {
+    volume.map_or(1.0, |v| (v as f32 / 100.0).max(1.0))
+}
+
+/// Opens the stream, trying up to `attempts` times. A server that cannot
+/// be reached is marked as failed and another one listed in the same
+/// playlist is tried right away, without counting as an attempt.
+async fn reopen_stream(
+    api: &ApiClient,
+    stream_url: &mut String,
+    track_info: &watch::Sender<TrackInfo>,
+    decoder: DecoderBackend,
+    attempts: u32,
+) -> PlayerResult<(StreamSource, BufferGauge, bool, StreamInfo)> {
+    let mut attempt = 1;
+    loop {
+        match open_stream(stream_url, track_info.clone(), decoder).await {
+            Err(e @ PlayerError::Network(NetworkError::StreamConnection(_))) => {
+                api.mark_stream_failed(stream_url);
+                if let Some(mirror) = api.failover_url(stream_url) {
+                    warn!("{} failed ({}), switching to {}", stream_url, e, mirror);
+                    *stream_url = mirror;
+                } else if attempt < attempts {
+                    warn!("Reconnect attempt {}/{} failed: {}", attempt, attempts, e);
+                    attempt += 1;
+                    tokio::time::sleep(RESUME_RETRY_DELAY).await;
+                } else {
+                    return Err(e);
+                }
+            }
+            Err(e) if attempt < attempts => {
+                warn!("Reconnect attempt {}/{} failed: {}", attempt, attempts, e);
+                attempt += 1;
+                tokio::time::sleep(RESUME_RETRY_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Connects to the stream and builds a decoder that publishes ICY metadata
+/// into `track_info`. The flag tells whether the stream sends any.
+async fn open_stream(
+    stream_url: &str,
+    track_info: watch::Sender<TrackInfo>,
+    decoder: DecoderBackend,
+) -> PlayerResult<(StreamSource, BufferGauge, bool, StreamInfo)> {
+    let stream = connect_stream(stream_url, move |stream_title| {
+        let new_track = track_from_metadata(stream_title);
+        debug!("Updated track info: {} - {}", new_track.artist, new_track.title);
+        // Publishing never blocks, so no update is lost to a busy reader
+        track_info.send_replace(new_track);
+    }).await?;
+
+    let source = decode_stream(stream.reader, stream.content_type.as_deref(), decoder).map_err(|e| {
+        error!("Failed to create audio decoder: {}", e);
+        PlayerError::Audio(AudioError::DecodingError(e))
+    })?;
+    Ok((source, stream.gauge, stream.has_metadata, stream.info))
+}
+
+/// Builds the decoder `backend` picks for a stream sent as `content_type`.
+fn decode_stream(reader: StreamReader, content_type: Option<&str>, backend: DecoderBackend) -> Result<StreamSource, String> {
+    match backend.resolve(content_type) {
+        #[cfg(feature = "symphonia-decoder")]
+        DecoderBackend::Symphonia => {
+            let source = crate::audio::decoder::SymphoniaSource::new(reader, content_type)?;
+            Ok(Box::new(source))
+        }
+        _ => {
+            debug!("Decoding {} with rodio", content_type.unwrap_or("unknown format"));
+            let decoder = Decoder::new(reader).map_err(|e| e.to_string())?;
+            Ok(Box::new(decoder.convert_samples()))
+        }
+    }
+}
+
+/// Connects to a stream with ICY metadata support.
+///
+/// The returned reader yields the raw audio bytes; `on_title` is called
+/// with every stream title found in the interleaved metadata.
+pub async fn connect_stream<F>(stream_url: &str, on_title: F) -> PlayerResult<ConnectedStream>
+where
+    F: Fn(&str) + Send + Sync + 'static,
+{
     // Create HTTP client with ICY metadata support
     let client = Client::builder()
         .request_icy_metadata()
         .build()
         .map_err(|e| {
             error!("Failed to create HTTP client: {}", e);
-            format!("Failed to create HTTP client: {}", e)
+            PlayerError::Network(NetworkError::StreamConnection(e.to_string()))
         })?;
 
     // Create HTTP stream
     let stream = HttpStream::new(client, stream_url.parse().map_err(|e| {
         error!("Invalid URL: {}", e);
-        format!("Invalid URL: {}", e)
+        PlayerError::Network(NetworkError::InvalidUrl(stream_url.to_string()))
     })?)
         .await
         .map_err(|e| {
             error!("Failed to connect to stream: {}", e);
-            format!("Failed to connect to stream: {}", e)
+            PlayerError::Network(NetworkError::StreamConnection(e.to_string()))
         })?;
 
     // Parse ICY headers
     let icy_headers = IcyHeaders::parse_from_headers(stream.headers());
     debug!("ICY headers: {:?}", icy_headers);
+    let info = StreamInfo::new(
+        icy_headers.name(),
+        icy_headers.genre(),
+        icy_headers.description(),
+        icy_headers.station_url(),
+        icy_headers.bitrate(),
+    );
+    if let Some(summary) = info.summary() {
+        info!("Connected to {}", summary);
+    }
+    let content_type = stream
+        .content_type()
+        .as_ref()
+        .map(|content_type| format!("{}/{}", content_type.r#type, content_type.subtype));
     
-    // Use simpler approach with memory storage to avoid bounded storage overflow issues
+    // Unbounded memory storage, so rewinding can go back to the start of
+    // the connection; kept in pooled chunks rather than one growing buffer
     let bitrate = icy_headers.bitrate().unwrap_or(128);
     let prefetch_bytes = bitrate / 8 * 1024 * 5; // 5 seconds buffer
 
     debug!("Bitrate: {} kbps, prefetch: {} bytes", bitrate, prefetch_bytes);
 
-    // Create stream downloader with memory storage (unbounded)
+    let gauge = BufferGauge::new(bitrate).with_prefetch(prefetch_bytes as u64);
+    let download_gauge = gauge.clone();
+
     let reader = StreamDownload::from_stream(
         stream,
-        MemoryStorageProvider,
-        Settings::default().prefetch_bytes(prefetch_bytes as u64),
+        ChunkStorageProvider::default(),
+        Settings::default()
+            .prefetch_bytes(prefetch_bytes as u64)
+            .on_progress(move |_, state| download_gauge.set_downloaded(state.current_position)),
     )
     .await
     .map_err(|e| {
         error!("Failed to create stream downloader: {}", e);
-        format!("Failed to create stream downloader: {}", e)
+        PlayerError::Network(NetworkError::StreamConnection(e.to_string()))
     })?;
 
-    // Create audio output
-    let (_stream, handle) = OutputStream::try_default()
-        .map_err(|e| {
-            error!("Failed to open audio stream: {}", e);
-            format!("Failed to open audio stream: {}", e)
-        })?;
-    let sink = Arc::new(Sink::try_new(&handle)
-        .map_err(|e| {
-            error!("Failed to create audio sink: {}", e);
-            format!("Failed to create audio sink: {}", e)
-        })?);
-
-    // Set volume if provided (0-100 range converted to 0.0-1.0)
-    if let Some(vol) = volume {
-        let volume_float = (vol as f32) / 100.0;
-        sink.set_volume(volume_float);
-        debug!("Set volume to: {}% ({})", vol, volume_float);
+    let has_metadata = icy_headers.metadata_interval().is_some();
+    if !has_metadata {
+        debug!("Stream sends no ICY metadata");
     }
 
-    // Clone track_info for the metadata callback
-    let track_info_clone = Arc::clone(&track_info);
-
     // Create ICY metadata reader with callback
-    let metadata_reader = IcyMetadataReader::new(
-        reader,
+    let reader = IcyMetadataReader::new(
+        WholeReads::new(GaugedReader::new(reader, gauge.clone())),
         icy_headers.metadata_interval(),
         move |metadata| {
-            if let Ok(md) = metadata {
-                if let Some(stream_title) = md.stream_title() {
-                    debug!("New metadata: {}", stream_title);
-                    let new_track = parse_track_info(stream_title);
-                    
-                    // Update track info using try_lock to avoid blocking
-                    // Don't use tokio::spawn in callback as it may not have runtime context
-                    if let Ok(mut track) = track_info_clone.try_lock() {
-                        *track = new_track;
-                        debug!("Updated track info: {} - {}", track.artist, track.title);
-                    } else {
-                        // If try_lock fails, just log it - we'll try again on next metadata
-                        debug!("Could not update track info (mutex locked), will retry on next metadata");
-                    }
-                }
+            if let Ok(md) = metadata
+                && let Some(stream_title) = md.stream_title()
+            {
+                debug!("New metadata: {}", stream_title);
+                on_title(stream_title);
             }
         },
     );
 
-    // Create decoder and start playing
-    let decoder = Decoder::new(metadata_reader)
-        .map_err(|e| {
-            error!("Failed to create audio decoder: {}", e);
-            format!("Failed to create audio decoder: {}", e)
-        })?;
-    
-    info!("Starting audio playback");
-    sink.append(decoder);
-
-    // Create audio playback task
-    let mut audio_task = tokio::task::spawn_blocking({
-        let sink_clone = Arc::clone(&sink);
-        move || {
-            sink_clone.sleep_until_end();
-        }
-    });
-
-    // Wait for either a command or the audio task to complete
-    let result = loop {
-        tokio::select! {
-            cmd = rx.recv() => {
-                match cmd {
-                    Some(PlayerCommand::Quit) | None => {
-                        info!("Received quit command");
-                        break Ok(false); // Quit
-                    }
-                    Some(PlayerCommand::SetVolume(vol)) => {
-                        let volume_float = (vol as f32) / 100.0;
-                        sink.set_volume(volume_float);
-                        debug!("Volume changed to: {}% ({})", vol, volume_float);
-                        // Continue the loop to handle more commands
-                    }
-                    Some(PlayerCommand::Pause) => {
-                        sink.pause();
-                        info!("Playback paused");
-                        // Continue the loop to handle more commands
-                    }
-                    Some(PlayerCommand::Resume) => {
-                        sink.play();
-                        info!("Playback resumed");
-                        // Continue the loop to handle more commands
-                    }
-                }
-            },
-            _ = &mut audio_task => {
-                warn!("Audio stream ended unexpectedly");
-                break Ok(false);
-            }
-        }
-    };
-    
-    // Clean up
-    audio_task.abort();
-    info!("Audio playback stopped");
-    
-    result
+    Ok(ConnectedStream { reader, gauge, has_metadata, content_type, info })
 }
@@ -0,0 +1,110 @@
+//! Download progress of the playing stream.
+//!
+//! While playing, the player samples its [`BufferGauge`] once a second and
+//! reports a [`DownloadProgress`]. The UI takes the buffer fill, the
+//! bandwidth, underruns and the latency behind live from these reports, so
+//! they all agree with what the player actually sees.
+
+use std::time::{Duration, Instant};
+
+use crate::audio::drift::BufferGauge;
+
+/// Snapshot of the stream download.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DownloadProgress {
+    /// Stream bytes fetched since connecting
+    pub bytes_fetched: u64,
+    /// Buffered audio relative to the prefetch size, from 0.0 to 1.0
+    pub prefetch_fill: f32,
+    /// Audio downloaded but not yet played, i.e. how far behind live
+    /// playback is
+    pub buffered: Duration,
+    /// Download rate since the previous snapshot, in bytes per second
+    pub bytes_per_second: u64,
+    /// Underruns since connecting
+    pub underruns: u32,
+    /// Whether playback ran out of audio since the previous snapshot
+    pub stalled: bool,
+}
+
+impl DownloadProgress {
+    /// Download rate in kilobits per second.
+    pub fn kbps(&self) -> u64 {
+        self.bytes_per_second * 8 / 1000
+    }
+}
+
+/// Turns readings of a [`BufferGauge`] into [`DownloadProgress`] snapshots.
+#[derive(Debug)]
+pub struct ProgressMeter {
+    gauge: BufferGauge,
+    last_sample: Instant,
+    last_fetched: u64,
+    last_underruns: u32,
+}
+
+impl ProgressMeter {
+    pub fn new(gauge: BufferGauge) -> Self {
+        Self {
+            last_fetched: gauge.downloaded(),
+            last_underruns: gauge.underruns(),
+            gauge,
+            last_sample: Instant::now(),
+        }
+    }
+
+    /// Takes a snapshot of the download at `now`.
+    pub fn sample(&mut self, now: Instant) -> DownloadProgress {
+        let bytes_fetched = self.gauge.downloaded();
+        let underruns = self.gauge.underruns();
+        let elapsed = now.saturating_duration_since(self.last_sample).as_secs_f64();
+        let bytes_per_second = if elapsed > 0.0 {
+            (bytes_fetched.saturating_sub(self.last_fetched) as f64 / elapsed) as u64
+        } else {
+            0
+        };
+
+        let progress = DownloadProgress {
+            bytes_fetched,
+            prefetch_fill: self.gauge.prefetch_fill(),
+            buffered: self.gauge.occupancy(),
+            bytes_per_second,
+            underruns,
+            stalled: underruns > self.last_underruns,
+        };
+        self.last_sample = now;
+        self.last_fetched = bytes_fetched;
+        self.last_underruns = underruns;
+        progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_reports_bandwidth_and_underruns() {
+        let gauge = BufferGauge::new(128).with_prefetch(80_000);
+        let started = Instant::now();
+        let mut meter = ProgressMeter::new(gauge.clone());
+        meter.last_sample = started;
+
+        gauge.set_downloaded(96_000);
+        gauge.set_consumed(56_000);
+        let progress = meter.sample(started + Duration::from_secs(2));
+        assert_eq!(progress.bytes_fetched, 96_000);
+        assert_eq!(progress.bytes_per_second, 48_000);
+        assert_eq!(progress.kbps(), 384);
+        assert_eq!(progress.prefetch_fill, 0.5);
+        assert_eq!(progress.buffered, Duration::from_millis(2500));
+        assert!(!progress.stalled);
+
+        gauge.record_underrun();
+        let progress = meter.sample(started + Duration::from_secs(3));
+        assert_eq!(progress.bytes_per_second, 0);
+        assert_eq!(progress.underruns, 1);
+        assert!(progress.stalled);
+        assert!(!meter.sample(started + Duration::from_secs(4)).stalled);
+    }
+}
@@ -0,0 +1,165 @@
+//! Stereo balance and mono downmix.
+//!
+//! [`StereoStage`] sits between the decoder and the sink and rewrites each
+//! left/right sample pair according to a [`StereoControl`], which the UI
+//! can change while playing. Sources that are not stereo pass through
+//! unchanged.
+
+use std::sync::atomic::{AtomicBool, AtomicI8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Shared balance and mono settings applied by a [`StereoStage`].
+#[derive(Debug, Clone, Default)]
+pub struct StereoControl {
+    balance: Arc<AtomicI8>,
+    mono: Arc<AtomicBool>,
+}
+
+impl StereoControl {
+    pub fn new(balance: i8, mono: bool) -> Self {
+        let control = Self::default();
+        control.set_balance(balance);
+        control.set_mono(mono);
+        control
+    }
+
+    /// Sets the balance from -100 (left only) to 100 (right only).
+    pub fn set_balance(&self, balance: i8) {
+        self.balance.store(balance.clamp(-100, 100), Ordering::Relaxed);
+    }
+
+    pub fn balance(&self) -> i8 {
+        self.balance.load(Ordering::Relaxed)
+    }
+
+    /// Mixes both channels into one, played on both speakers.
+    pub fn set_mono(&self, mono: bool) {
+        self.mono.store(mono, Ordering::Relaxed);
+    }
+
+    pub fn is_mono(&self) -> bool {
+        self.mono.load(Ordering::Relaxed)
+    }
+
+    /// Applies the settings to one left/right sample pair.
+    fn apply(&self, left: f32, right: f32) -> (f32, f32) {
+        let (left, right) = if self.is_mono() {
+            let mixed = (left + right) / 2.0;
+            (mixed, mixed)
+        } else {
+            (left, right)
+        };
+        // Turning towards one side only attenuates the other
+        let balance = self.balance() as f32 / 100.0;
+        (left * (1.0 - balance.max(0.0)), right * (1.0 + balance.min(0.0)))
+    }
+}
+
+/// A source adjusting the balance and downmix of a stereo source.
+pub struct StereoStage<S> {
+    inner: S,
+    control: StereoControl,
+    /// Right sample of the pair whose left sample was returned last
+    pending: Option<f32>,
+}
+
+impl<S> StereoStage<S> {
+    pub fn new(inner: S, control: StereoControl) -> Self {
+        Self { inner, control, pending: None }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for StereoStage<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.pending.take() {
+            return Some(right);
+        }
+
+        let left = self.inner.next()?;
+        if self.inner.channels() != 2 {
+            return Some(left);
+        }
+        let Some(right) = self.inner.next() else {
+            return Some(left);
+        };
+        let (left, right) = self.control.apply(left, right);
+        self.pending = Some(right);
+        Some(left)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = self.pending.is_some() as usize;
+        let (lower, upper) = self.inner.size_hint();
+        (lower + pending, upper.map(|upper| upper + pending))
+    }
+}
+
+impl<S: Source<Item = f32>> Source for StereoStage<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        let pending = self.pending.is_some() as usize;
+        self.inner.current_frame_len().map(|len| len + pending)
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.pending = None;
+        self.inner.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn process(control: StereoControl, channels: u16, samples: Vec<f32>) -> Vec<f32> {
+        StereoStage::new(SamplesBuffer::new(channels, 44100, samples), control).collect()
+    }
+
+    #[test]
+    fn test_centered_stereo_is_unchanged() {
+        let samples = vec![0.5, -0.25, 0.1, 0.2];
+        assert_eq!(process(StereoControl::default(), 2, samples.clone()), samples);
+    }
+
+    #[test]
+    fn test_balance_attenuates_opposite_side() {
+        let control = StereoControl::new(50, false);
+        assert_eq!(process(control.clone(), 2, vec![1.0, 1.0]), vec![0.5, 1.0]);
+
+        control.set_balance(-100);
+        assert_eq!(process(control.clone(), 2, vec![1.0, 1.0]), vec![1.0, 0.0]);
+
+        control.set_balance(120);
+        assert_eq!(control.balance(), 100);
+    }
+
+    #[test]
+    fn test_mono_downmix() {
+        let control = StereoControl::new(0, true);
+        assert_eq!(process(control, 2, vec![1.0, 0.0, -0.5, 0.5]), vec![0.5, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_mono_sources_pass_through() {
+        let control = StereoControl::new(100, true);
+        assert_eq!(process(control, 1, vec![0.3, 0.6, 0.9]), vec![0.3, 0.6, 0.9]);
+    }
+}
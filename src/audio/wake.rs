@@ -0,0 +1,104 @@
+//! Noticing when a stream died under the player, e.g. across system sleep.
+//!
+//! After a laptop resumes from suspend the HTTP connection is usually gone,
+//! but nothing reports an error: reads simply never return. The
+//! [`WakeDetector`] is checked periodically during playback and notices
+//! either a jump of the wall clock against the monotonic clock, which does
+//! not advance while the system sleeps on most platforms, or a stream that
+//! stopped delivering data.
+
+use std::time::{Duration, Instant, SystemTime};
+
+/// A gap between checks this much longer than expected means the system slept
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// How long the stream may deliver no data before it counts as dead
+pub const STALE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Why the stream should be reopened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WakeReason {
+    /// The system was asleep for about this long
+    Slept(Duration),
+    /// No stream data arrived for this long
+    Stale(Duration),
+}
+
+/// Watches the clocks and the download position between periodic checks.
+#[derive(Debug)]
+pub struct WakeDetector {
+    interval: Duration,
+    last_check: Instant,
+    last_wall: SystemTime,
+    downloaded: u64,
+    last_data: Instant,
+}
+
+impl WakeDetector {
+    /// Creates a detector checked every `interval`.
+    pub fn new(interval: Duration, now: Instant, wall: SystemTime) -> Self {
+        Self {
+            interval,
+            last_check: now,
+            last_wall: wall,
+            downloaded: 0,
+            last_data: now,
+        }
+    }
+
+    /// Records a check at `now`/`wall` with `downloaded` stream bytes so far
+    /// and returns why the stream should be reopened, if it should.
+    pub fn check(&mut self, now: Instant, wall: SystemTime, downloaded: u64) -> Option<WakeReason> {
+        let monotonic = now.saturating_duration_since(self.last_check);
+        // A wall clock set backwards reads as no time passing
+        let real = wall.duration_since(self.last_wall).unwrap_or_default();
+        self.last_check = now;
+        self.last_wall = wall;
+
+        let gap = monotonic.max(real);
+        if gap > self.interval + SLEEP_THRESHOLD {
+            self.last_data = now;
+            return Some(WakeReason::Slept(gap));
+        }
+
+        if downloaded != self.downloaded {
+            self.downloaded = downloaded;
+            self.last_data = now;
+            return None;
+        }
+        let silent = now.saturating_duration_since(self.last_data);
+        (silent >= STALE_TIMEOUT).then_some(WakeReason::Stale(silent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_wall_clock_jump_means_sleep() {
+        let (start, wall) = (Instant::now(), SystemTime::now());
+        let mut detector = WakeDetector::new(SECOND, start, wall);
+
+        assert_eq!(detector.check(start + SECOND, wall + SECOND, 100), None);
+        // The monotonic clock stood still for an hour of suspend
+        let woke = detector.check(start + SECOND * 2, wall + SECOND * 3602, 200);
+        assert_eq!(woke, Some(WakeReason::Slept(SECOND * 3601)));
+        assert_eq!(detector.check(start + SECOND * 3, wall + SECOND * 3603, 300), None);
+    }
+
+    #[test]
+    fn test_stream_without_data_is_stale() {
+        let (start, wall) = (Instant::now(), SystemTime::now());
+        let mut detector = WakeDetector::new(SECOND, start, wall);
+
+        assert_eq!(detector.check(start + SECOND, wall + SECOND, 100), None);
+        let mut result = None;
+        for second in 2..=16 {
+            result = detector.check(start + SECOND * second, wall + SECOND * second, 100);
+        }
+        assert_eq!(result, Some(WakeReason::Stale(STALE_TIMEOUT)));
+    }
+}
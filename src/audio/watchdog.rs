@@ -0,0 +1,190 @@
+//! Restarting playback that stopped producing audio.
+//!
+//! In kiosk mode nobody is around to notice that the music stopped. The
+//! player counts the samples leaving the decoder through a [`PcmTap`], and
+//! the [`Watchdog`] asks for the pipeline to be rebuilt when none were
+//! produced for a while. Consecutive restarts wait exponentially longer
+//! and are bounded, so a stream that is gone for good ends playback with
+//! an error instead of reconnecting in a tight loop.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::info;
+use rodio::source::SeekError;
+use rodio::Source;
+
+use crate::config::WatchdogConfig;
+
+/// Wait before the first restart, doubled for every further attempt
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait between restarts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Number of samples produced, shared between the audio thread and the
+/// player.
+#[derive(Debug, Clone, Default)]
+pub struct PcmCounter(Arc<AtomicU64>);
+
+impl PcmCounter {
+    pub fn samples(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A source counting the samples the wrapped source produces.
+pub struct PcmTap<S> {
+    inner: S,
+    counter: PcmCounter,
+}
+
+impl<S> PcmTap<S> {
+    pub fn new(inner: S, counter: PcmCounter) -> Self {
+        Self { inner, counter }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for PcmTap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        self.counter.0.fetch_add(1, Ordering::Relaxed);
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for PcmTap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
+
+/// What the player should do about the audio output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogVerdict {
+    Healthy,
+    /// Rebuild the pipeline after waiting `delay`; this is restart `attempt`
+    /// in a row
+    Restart { attempt: u32, delay: Duration },
+    /// Every allowed restart failed to bring audio back
+    GiveUp,
+}
+
+/// Watches a [`PcmCounter`] for silence between periodic checks.
+#[derive(Debug)]
+pub struct Watchdog {
+    silence_limit: Duration,
+    max_restarts: u32,
+    samples: u64,
+    last_progress: Instant,
+    restarts: u32,
+}
+
+impl Watchdog {
+    /// Creates a watchdog, or `None` when the configuration disables it.
+    pub fn new(config: &WatchdogConfig, now: Instant) -> Option<Self> {
+        (config.silence_secs > 0).then(|| Self {
+            silence_limit: Duration::from_secs(config.silence_secs),
+            max_restarts: config.max_restarts,
+            samples: 0,
+            last_progress: now,
+            restarts: 0,
+        })
+    }
+
+    /// Starts timing anew, e.g. once a rebuilt pipeline starts playing.
+    pub fn rearm(&mut self, now: Instant) {
+        self.last_progress = now;
+    }
+
+    /// Records a check at `now` with `samples` produced so far. Time spent
+    /// `paused` does not count as silence.
+    pub fn check(&mut self, now: Instant, samples: u64, paused: bool) -> WatchdogVerdict {
+        if samples != self.samples {
+            self.samples = samples;
+            self.last_progress = now;
+            if self.restarts > 0 {
+                info!("Audio recovered after {} watchdog restart(s)", self.restarts);
+                self.restarts = 0;
+            }
+            return WatchdogVerdict::Healthy;
+        }
+        if paused {
+            self.last_progress = now;
+        }
+        if now.saturating_duration_since(self.last_progress) < self.silence_limit {
+            return WatchdogVerdict::Healthy;
+        }
+        if self.restarts >= self.max_restarts {
+            return WatchdogVerdict::GiveUp;
+        }
+        self.restarts += 1;
+        self.last_progress = now;
+        let delay = BASE_BACKOFF
+            .saturating_mul(1 << (self.restarts - 1).min(16))
+            .min(MAX_BACKOFF);
+        WatchdogVerdict::Restart { attempt: self.restarts, delay }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_restarts_back_off_and_give_up() {
+        let config = WatchdogConfig { silence_secs: 10, max_restarts: 3 };
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(&config, start).unwrap();
+
+        assert_eq!(watchdog.check(start + SECOND, 100, false), WatchdogVerdict::Healthy);
+        // Pausing is not silence
+        assert_eq!(watchdog.check(start + SECOND * 30, 100, true), WatchdogVerdict::Healthy);
+
+        let mut now = start + SECOND * 30;
+        let mut delays = Vec::new();
+        for _ in 0..3 {
+            now += SECOND * 10;
+            match watchdog.check(now, 100, false) {
+                WatchdogVerdict::Restart { delay, .. } => delays.push(delay),
+                verdict => panic!("expected a restart, got {:?}", verdict),
+            }
+        }
+        assert_eq!(delays, [SECOND, SECOND * 2, SECOND * 4]);
+        assert_eq!(watchdog.check(now + SECOND * 10, 100, false), WatchdogVerdict::GiveUp);
+
+        // Audio coming back resets the attempts
+        assert_eq!(watchdog.check(now + SECOND * 11, 200, false), WatchdogVerdict::Healthy);
+        assert_eq!(
+            watchdog.check(now + SECOND * 21, 200, false),
+            WatchdogVerdict::Restart { attempt: 1, delay: SECOND }
+        );
+
+        assert!(Watchdog::new(&WatchdogConfig { silence_secs: 0, max_restarts: 3 }, start).is_none());
+    }
+}
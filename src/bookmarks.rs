@@ -0,0 +1,165 @@
+//! Bookmarked moments of the stream (`b` while playing, `B` to review).
+//!
+//! A bookmark notes the channel, the track and the time, with an optional
+//! note, e.g. "find this mix later". Bookmarks are kept in
+//! `~/.config/soma-player/bookmarks.json` next to the config file, oldest
+//! first, and written again on every change.
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local, TimeZone};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::models::{Channel, TrackInfo};
+
+/// A bookmarked moment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub channel_id: String,
+    pub channel_title: String,
+    pub artist: String,
+    pub title: String,
+    /// Unix timestamp (seconds) of the moment
+    pub saved_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Bookmark {
+    /// Bookmarks `track` playing on `channel` at `time`, without a note.
+    pub fn new(channel: &Channel, track: &TrackInfo, time: DateTime<Local>) -> Self {
+        Self {
+            channel_id: channel.id.clone(),
+            channel_title: channel.title.clone(),
+            artist: track.artist.clone(),
+            title: track.title.clone(),
+            saved_at: time.timestamp(),
+            note: None,
+        }
+    }
+
+    /// The moment in local time.
+    pub fn time(&self) -> Option<DateTime<Local>> {
+        Local.timestamp_opt(self.saved_at, 0).single()
+    }
+}
+
+/// `2025-03-01 20:05  Groove Salad  Air - Alone in Kyoto`, and the note if any.
+impl fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(time) = self.time() {
+            write!(f, "{}  ", time.format("%Y-%m-%d %H:%M"))?;
+        }
+        write!(f, "{}  {} - {}", self.channel_title, self.artist, self.title)?;
+        if let Some(note) = &self.note {
+            write!(f, "  ({})", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every bookmark saved so far.
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    /// `None` keeps the bookmarks in memory only
+    path: Option<PathBuf>,
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks from the config directory.
+    pub fn load() -> Self {
+        match AppConfig::config_path() {
+            Ok(path) => Self::at(path.with_file_name("bookmarks.json")),
+            Err(e) => {
+                warn!("Bookmarks not saved: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Loads the bookmarks from the file at `path`. An unreadable file is
+    /// left alone, so a hand edit gone wrong doesn't lose the bookmarks.
+    pub fn at(path: PathBuf) -> Self {
+        let bookmarks = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(bookmarks) => bookmarks,
+                Err(e) => {
+                    warn!("Ignoring unreadable bookmarks {}: {}", path.display(), e);
+                    return Self::default();
+                }
+            },
+            Err(_) => Vec::new(),
+        };
+        Self { path: Some(path), bookmarks }
+    }
+
+    /// The bookmarks, oldest first.
+    pub fn list(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    /// Adds `bookmark` and saves.
+    pub fn add(&mut self, bookmark: Bookmark) -> Result<(), String> {
+        info!("Bookmarked {} on {}", bookmark.title, bookmark.channel_title);
+        self.bookmarks.push(bookmark);
+        self.save()
+    }
+
+    /// Removes the bookmark at `index` and saves.
+    pub fn remove(&mut self, index: usize) -> Result<(), String> {
+        if index < self.bookmarks.len() {
+            self.bookmarks.remove(index);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let temp_path = path.with_extension("json.tmp");
+        serde_json::to_string_pretty(&self.bookmarks)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&temp_path, json).map_err(|e| e.to_string()))
+            .and_then(|()| fs::rename(&temp_path, path).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Failed to save bookmarks to {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bookmarks_are_kept_across_runs() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("bookmarks.json");
+        let channel = Channel::named("groovesalad", "Groove Salad");
+        let track = TrackInfo { artist: "Air".to_string(), title: "Alone in Kyoto".to_string() };
+        let time = Local.with_ymd_and_hms(2025, 3, 1, 20, 5, 0).unwrap();
+
+        let mut bookmarks = Bookmarks::at(path.clone());
+        bookmarks.add(Bookmark::new(&channel, &track, time)).unwrap();
+        let mut noted = Bookmark::new(&channel, &track, time);
+        noted.note = Some("find this mix".to_string());
+        bookmarks.add(noted.clone()).unwrap();
+        bookmarks.remove(0).unwrap();
+
+        let bookmarks = Bookmarks::at(path.clone());
+        assert_eq!(bookmarks.list(), [noted.clone()]);
+        assert_eq!(noted.to_string(), "2025-03-01 20:05  Groove Salad  Air - Alone in Kyoto  (find this mix)");
+
+        // A broken file is not overwritten
+        fs::write(&path, "not json").unwrap();
+        let mut bookmarks = Bookmarks::at(path.clone());
+        assert!(bookmarks.list().is_empty());
+        bookmarks.add(noted).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not json");
+    }
+}
@@ -0,0 +1,488 @@
+//! Casting to UPnP/DLNA media renderers.
+//!
+//! Renderers are found with an SSDP search on the local network. When a
+//! renderer is configured, the player hands it the channel's stream URL
+//! instead of playing locally, forwards pause, resume and volume changes
+//! as UPnP AVTransport / RenderingControl actions, and keeps a
+//! metadata-only connection to the stream so the TUI still shows what is
+//! playing.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use reqwest::Url;
+use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, watch};
+
+use crate::api::ApiClient;
+use crate::api::songs::SongPoller;
+use crate::audio::icy::IcyStream;
+use crate::audio::{PlaybackOptions, PlaybackStatus, PlayerCommand, PlayerUpdate};
+use crate::errors::{NetworkError, PlayerError, PlayerResult};
+use crate::models::{track_from_metadata, Channel, TrackInfo};
+
+/// How long to wait for renderers to answer a search
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Timeout for description and control requests
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const MEDIA_RENDERER: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+
+/// A UPnP media renderer able to play a stream URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renderer {
+    /// Friendly name reported by the device
+    pub name: String,
+    /// URL of the device description
+    pub location: String,
+    av_transport: Service,
+    rendering_control: Option<Service>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Service {
+    service_type: String,
+    control_url: String,
+}
+
+/// Searches the local network for media renderers.
+pub async fn discover(timeout: Duration) -> Result<Vec<Renderer>, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|e| e.to_string())?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP_ADDRESS, MEDIA_RENDERER
+    );
+    socket.send_to(search.as_bytes(), SSDP_ADDRESS).await.map_err(|e| e.to_string())?;
+
+    let mut locations = Vec::new();
+    let mut buffer = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + timeout;
+    while let Ok(Ok((len, from))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+        let response = String::from_utf8_lossy(&buffer[..len]);
+        if let Some(location) = ssdp_location(&response)
+            && !locations.contains(&location)
+        {
+            debug!("SSDP response from {}: {}", from, location);
+            locations.push(location);
+        }
+    }
+
+    let client = http_client()?;
+    let renderers = futures_util::future::join_all(
+        locations.iter().map(|location| Renderer::from_location(&client, location))
+    ).await;
+
+    Ok(renderers
+        .into_iter()
+        .filter_map(|result| result.map_err(|e| debug!("Skipping renderer: {}", e)).ok())
+        .collect())
+}
+
+/// Finds a renderer by description URL or case-insensitive part of its name.
+pub async fn find_renderer(name: &str) -> Result<Renderer, String> {
+    if name.starts_with("http://") || name.starts_with("https://") {
+        return Renderer::from_location(&http_client()?, name).await;
+    }
+
+    let wanted = name.to_lowercase();
+    let mut matches: Vec<Renderer> = discover(DISCOVERY_TIMEOUT)
+        .await?
+        .into_iter()
+        .filter(|r| r.name.to_lowercase().contains(&wanted))
+        .collect();
+
+    match matches.len() {
+        0 => Err(format!("No media renderer matching '{}' found (use `soma-player list --renderers`)", name)),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            // Prefer an exact name over a partial match
+            match matches.iter().position(|r| r.name.to_lowercase() == wanted) {
+                Some(index) => Ok(matches.remove(index)),
+                None => Err(format!(
+                    "'{}' matches several renderers: {}",
+                    name,
+                    matches.iter().map(|r| r.name.as_str()).collect::<Vec<_>>().join(", ")
+                )),
+            }
+        }
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Extracts the `LOCATION` header from an SSDP response.
+fn ssdp_location(response: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("location").then(|| value.trim().to_string())
+    })
+}
+
+/// Text of the first `<tag>` element in `xml`.
+fn tag_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(xml[start..end].trim())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl Renderer {
+    async fn from_location(client: &reqwest::Client, location: &str) -> Result<Self, String> {
+        let description = client.get(location)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("{}: {}", location, e))?
+            .text()
+            .await
+            .map_err(|e| format!("{}: {}", location, e))?;
+        Self::from_description(location, &description)
+    }
+
+    /// Builds a renderer from its device description XML.
+    fn from_description(location: &str, description: &str) -> Result<Self, String> {
+        let base = tag_text(description, "URLBase").unwrap_or(location);
+        let base = Url::parse(base).map_err(|e| format!("{}: {}", location, e))?;
+
+        let mut av_transport = None;
+        let mut rendering_control = None;
+        for block in description.split("<service>").skip(1) {
+            let (Some(service_type), Some(control_url)) = (tag_text(block, "serviceType"), tag_text(block, "controlURL")) else {
+                continue;
+            };
+            let Ok(control_url) = base.join(control_url) else {
+                continue;
+            };
+            let service = Service {
+                service_type: service_type.to_string(),
+                control_url: control_url.to_string(),
+            };
+            if service_type.contains(":AVTransport:") {
+                av_transport.get_or_insert(service);
+            } else if service_type.contains(":RenderingControl:") {
+                rendering_control.get_or_insert(service);
+            }
+        }
+
+        Ok(Self {
+            name: tag_text(description, "friendlyName").unwrap_or(location).to_string(),
+            location: location.to_string(),
+            av_transport: av_transport.ok_or_else(|| format!("{} has no AVTransport service", location))?,
+            rendering_control,
+        })
+    }
+
+    /// Loads `stream_url` on the renderer and starts playback.
+    pub async fn load(&self, stream_url: &str, title: &str) -> Result<(), String> {
+        let metadata = format!(
+            concat!(
+                r#"<DIDL-Lite xmlns="urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/" "#,
+                r#"xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:upnp="urn:schemas-upnp-org:metadata-1-0/upnp/">"#,
+                r#"<item id="0" parentID="-1" restricted="1"><dc:title>{}</dc:title>"#,
+                r#"<upnp:class>object.item.audioItem.audioBroadcast</upnp:class>"#,
+                r#"<res protocolInfo="http-get:*:audio/mpeg:*">{}</res></item></DIDL-Lite>"#
+            ),
+            escape_xml(title),
+            escape_xml(stream_url)
+        );
+        self.transport("SetAVTransportURI", &[("CurrentURI", stream_url), ("CurrentURIMetaData", &metadata)]).await?;
+        self.play().await
+    }
+
+    pub async fn play(&self) -> Result<(), String> {
+        self.transport("Play", &[("Speed", "1")]).await
+    }
+
+    pub async fn pause(&self) -> Result<(), String> {
+        self.transport("Pause", &[]).await
+    }
+
+    pub async fn stop(&self) -> Result<(), String> {
+        self.transport("Stop", &[]).await
+    }
+
+    /// Sets the renderer's master volume (0-100).
+    pub async fn set_volume(&self, volume: u8) -> Result<(), String> {
+        let service = self.rendering_control
+            .as_ref()
+            .ok_or_else(|| format!("{} does not support volume control", self.name))?;
+        let volume = volume.min(100).to_string();
+        soap(service, "SetVolume", &[("InstanceID", "0"), ("Channel", "Master"), ("DesiredVolume", &volume)]).await
+    }
+
+    async fn transport(&self, action: &str, args: &[(&str, &str)]) -> Result<(), String> {
+        let mut all_args = vec![("InstanceID", "0")];
+        all_args.extend_from_slice(args);
+        soap(&self.av_transport, action, &all_args).await
+    }
+}
+
+fn soap_envelope(service_type: &str, action: &str, args: &[(&str, &str)]) -> String {
+    let args: String = args
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, escape_xml(value)))
+        .collect();
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" "#,
+            r#"s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">"#,
+            r#"<s:Body><u:{action} xmlns:u="{service}">{args}</u:{action}></s:Body></s:Envelope>"#
+        ),
+        action = action,
+        service = service_type,
+        args = args
+    )
+}
+
+async fn soap(service: &Service, action: &str, args: &[(&str, &str)]) -> Result<(), String> {
+    debug!("UPnP {} -> {}", action, service.control_url);
+    http_client()?
+        .post(&service.control_url)
+        .header(reqwest::header::CONTENT_TYPE, r#"text/xml; charset="utf-8""#)
+        .header("SOAPACTION", format!("\"{}#{}\"", service.service_type, action))
+        .body(soap_envelope(&service.service_type, action, args))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map(|_| ())
+        .map_err(|e| format!("{} failed: {}", action, e))
+}
+
+/// Reads a stream only for its ICY metadata until dropped, or polls the
+/// API for the current song if the stream sends no metadata.
+struct MetadataWatcher {
+    stop: Arc<AtomicBool>,
+    _songs: Option<SongPoller>,
+}
+
+impl MetadataWatcher {
+    async fn start(
+        api: &ApiClient,
+        channel_id: &str,
+        stream_url: &str,
+        track_info: watch::Sender<TrackInfo>,
+        poll: Option<Duration>,
+    ) -> PlayerResult<Self> {
+        let stream = IcyStream::connect(stream_url, {
+            let track_info = track_info.clone();
+            move |title| {
+                track_info.send_replace(track_from_metadata(title));
+            }
+        })
+        .await
+        .map_err(|e| PlayerError::Network(NetworkError::StreamConnection(e.to_string())))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        if !stream.has_metadata() {
+            let songs = poll.map(|interval| SongPoller::start(api.clone(), channel_id.to_string(), interval, track_info));
+            return Ok(Self { stop, _songs: songs });
+        }
+        tokio::task::spawn_blocking({
+            let stop = Arc::clone(&stop);
+            move || {
+                if let Err(e) = stream.discard_audio(&stop) {
+                    warn!("Metadata connection failed: {}", e);
+                }
+            }
+        });
+        Ok(Self { stop, _songs: None })
+    }
+}
+
+impl Drop for MetadataWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Plays a channel on `renderer` instead of locally, handling the same
+/// commands as [`crate::audio::play_channel`] until told to quit and
+/// reporting volume and pause changes the renderer accepted to `on_update`.
+/// Of the `options`, only the volume, quality and metadata polling apply;
+/// the rest concern local output.
+pub async fn cast_channel<F>(
+    renderer: &Renderer,
+    api: &ApiClient,
+    channel: &Channel,
+    track_info: watch::Sender<TrackInfo>,
+    mut rx: mpsc::UnboundedReceiver<PlayerCommand>,
+    options: &PlaybackOptions,
+    on_update: F,
+) -> PlayerResult<bool>
+where
+    F: Fn(PlayerUpdate),
+{
+    info!("Casting {} to {}", channel.title, renderer.name);
+
+    let PlaybackOptions { quality, metadata_poll, .. } = *options;
+    let mut channel = channel.clone();
+    let mut volume = options.volume;
+    let mut paused = false;
+    let mut stopped = false;
+    let mut stream_url = api
+        .resolve_stream_url(&channel, quality)
+        .await
+        .map_err(|e| PlayerError::Network(NetworkError::ApiConnection(e.to_string())))?;
+    renderer
+        .load(&stream_url, &channel.title)
+        .await
+        .map_err(|e| PlayerError::Network(NetworkError::Renderer(e)))?;
+    if let Some(volume) = volume {
+        match renderer.set_volume(volume).await {
+            Ok(()) => on_update(PlayerUpdate::VolumeChanged(volume)),
+            Err(e) => warn!("{}", e),
+        }
+    }
+    let mut _metadata = MetadataWatcher::start(api, &channel.id, &stream_url, track_info.clone(), metadata_poll).await?;
+
+    loop {
+        let before = (paused, stopped);
+        let result = match rx.recv().await {
+            Some(PlayerCommand::Quit) | None => {
+                info!("Stopping playback on {}", renderer.name);
+                if let Err(e) = renderer.stop().await {
+                    warn!("{}", e);
+                }
+                return Ok(false);
+            }
+            Some(PlayerCommand::SetVolume(level)) => renderer.set_volume(level).await.map(|()| {
+                volume = Some(level);
+                on_update(PlayerUpdate::VolumeChanged(level));
+            }),
+            Some(PlayerCommand::Pause) => {
+                paused = true;
+                renderer.pause().await
+            }
+            Some(PlayerCommand::Resume) if stopped => {
+                (paused, stopped) = (false, false);
+                renderer.load(&stream_url, &channel.title).await
+            }
+            Some(PlayerCommand::Resume) => {
+                paused = false;
+                renderer.play().await
+            }
+            Some(PlayerCommand::Stop) => {
+                stopped = true;
+                renderer.stop().await
+            }
+            Some(PlayerCommand::JumpToLive) => {
+                (paused, stopped) = (false, false);
+                renderer.load(&stream_url, &channel.title).await
+            }
+            // The renderer keeps its own buffer, out of our reach
+            Some(PlayerCommand::SeekBack(_)) => Err("rewinding is not supported while casting".to_string()),
+            Some(PlayerCommand::SeekForward(_)) => Err("skipping ahead is not supported while casting".to_string()),
+            Some(PlayerCommand::SwitchChannel(next)) => match api.resolve_stream_url(&next, quality).await {
+                Ok(url) => {
+                    info!("Casting {} to {}", next.title, renderer.name);
+                    channel = next;
+                    stream_url = url;
+                    (paused, stopped) = (false, false);
+                    track_info.send_replace(TrackInfo::default());
+                    _metadata = MetadataWatcher::start(api, &channel.id, &stream_url, track_info.clone(), metadata_poll).await?;
+                    renderer.load(&stream_url, &channel.title).await
+                }
+                Err(e) => Err(e.to_string()),
+            },
+            Some(PlayerCommand::QueryStatus(reply)) => {
+                let _ = reply.send(PlaybackStatus::new(&channel, &stream_url, volume, paused, stopped));
+                Ok(())
+            }
+            // Ramping a remote volume in small steps would flood the renderer
+            Some(PlayerCommand::Duck(_)) => Ok(()),
+            Some(PlayerCommand::SwitchStream(playlist)) => {
+                match api.resolve_playlist_url(&playlist).await {
+                    Ok(url) => {
+                        stream_url = url;
+                        _metadata = MetadataWatcher::start(api, &channel.id, &stream_url, track_info.clone(), metadata_poll).await?;
+                        renderer.load(&stream_url, &channel.title).await
+                    }
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        };
+        match result {
+            Ok(()) if paused != before.0 => {
+                on_update(if paused { PlayerUpdate::Paused } else { PlayerUpdate::Resumed });
+            }
+            Ok(()) => {}
+            Err(e) => {
+                // The renderer did not follow, so neither does the reported state
+                (paused, stopped) = before;
+                warn!("Renderer {}: {}", renderer.name, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:MediaRenderer:1</deviceType>
+    <friendlyName>Living Room</friendlyName>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+        <controlURL>/RenderingControl/ctrl</controlURL>
+      </service>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType>
+        <controlURL>AVTransport/ctrl</controlURL>
+      </service>
+    </serviceList>
+  </device>
+</root>"#;
+
+    #[test]
+    fn test_renderer_from_description() {
+        let renderer = Renderer::from_description("http://192.168.1.20:49152/desc/device.xml", DESCRIPTION).unwrap();
+        assert_eq!(renderer.name, "Living Room");
+        assert_eq!(renderer.av_transport.control_url, "http://192.168.1.20:49152/desc/AVTransport/ctrl");
+        assert_eq!(
+            renderer.rendering_control.unwrap().control_url,
+            "http://192.168.1.20:49152/RenderingControl/ctrl"
+        );
+
+        let without_transport = DESCRIPTION.replace("AVTransport", "ConnectionManager");
+        assert!(Renderer::from_description("http://192.168.1.20/", &without_transport).is_err());
+    }
+
+    #[test]
+    fn test_ssdp_location() {
+        let response = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nLocation: http://192.168.1.20:49152/desc.xml\r\n\r\n";
+        assert_eq!(ssdp_location(response).as_deref(), Some("http://192.168.1.20:49152/desc.xml"));
+        assert_eq!(ssdp_location("HTTP/1.1 200 OK\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_soap_envelope_escapes_arguments() {
+        let envelope = soap_envelope(
+            "urn:schemas-upnp-org:service:AVTransport:1",
+            "SetAVTransportURI",
+            &[("InstanceID", "0"), ("CurrentURI", "http://ice.somafm.com/groovesalad?a=1&b=2")],
+        );
+        assert!(envelope.contains(r#"<u:SetAVTransportURI xmlns:u="urn:schemas-upnp-org:service:AVTransport:1">"#));
+        assert!(envelope.contains("<CurrentURI>http://ice.somafm.com/groovesalad?a=1&amp;b=2</CurrentURI>"));
+    }
+}
@@ -0,0 +1,39 @@
+//! Editing the config file in the user's editor (`soma-player config edit`).
+//!
+//! The editor is `$VISUAL`, then `$EDITOR`, then `vi` (`notepad` on
+//! Windows), run through the shell so values like `code --wait` work.
+//! After the editor exits the file is checked the way `soma-player doctor`
+//! checks it; a file the player couldn't read is offered for editing again,
+//! or put back the way it was.
+
+use std::env;
+use std::process::Command;
+
+/// The editor command to open files with.
+pub fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+}
+
+/// Opens `path` in `editor` and waits for it to exit.
+pub fn edit(editor: &str, path: &std::path::Path) -> Result<(), String> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(format!("{} \"{}\"", editor, path.display()));
+        command
+    } else {
+        // The path goes in as $1, so it needs no quoting
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(format!("{} \"$1\"", editor)).arg("sh").arg(path);
+        command
+    };
+
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{} exited with {}", editor, status)),
+        Err(e) => Err(format!("Failed to run {}: {}", editor, e)),
+    }
+}
@@ -1,3 +1,5 @@
+pub mod editor;
+pub mod schema;
 pub mod settings;
 
 pub use settings::*;
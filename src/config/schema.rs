@@ -0,0 +1,329 @@
+//! Every configuration key with its type, range and description.
+//!
+//! [`KEYS`] lists the keys of `config.toml` in the order they are written,
+//! top-level keys first and then each section. Defaults are not repeated
+//! here but read from [`AppConfig::default`], so they cannot drift apart.
+//! The table drives [`validate`], which reports unknown keys and values of
+//! the wrong type or out of range, the setup wizard's summary, and
+//! [`default_config_toml`], printed by `soma-player config print-default`.
+
+use std::fmt::{self, Write as _};
+use std::ops::RangeInclusive;
+
+use toml::Value;
+
+use super::AppConfig;
+
+/// Upper bound of integers that have none
+const UNBOUNDED: i64 = i64::MAX;
+
+/// Kind of value a key takes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyType {
+    Bool,
+    /// A whole number in this range
+    Integer(RangeInclusive<i64>),
+    Text,
+    /// One of these words
+    Choice(&'static [&'static str]),
+    /// A list of strings
+    List,
+    /// A list of these words
+    ChoiceList(&'static [&'static str]),
+    /// A table of names to values, checked where it is used
+    Table,
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyType::Bool => write!(f, "true or false"),
+            KeyType::Integer(range) if *range.end() == UNBOUNDED => write!(f, "{} or more", range.start()),
+            KeyType::Integer(range) => write!(f, "{} to {}", range.start(), range.end()),
+            KeyType::Text => write!(f, "text"),
+            KeyType::Choice(choices) => write!(f, "one of {}", choices.join(", ")),
+            KeyType::List => write!(f, "list of text"),
+            KeyType::ChoiceList(choices) => write!(f, "list of {}", choices.join(", ")),
+            KeyType::Table => write!(f, "table"),
+        }
+    }
+}
+
+/// A configuration key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigKey {
+    /// Dotted path, e.g. `notifications.dedup_secs`
+    pub name: &'static str,
+    pub kind: KeyType,
+    pub description: &'static str,
+}
+
+impl ConfigKey {
+    const fn new(name: &'static str, kind: KeyType, description: &'static str) -> Self {
+        Self { name, kind, description }
+    }
+
+    /// Section the key is in, `None` for top-level keys.
+    pub fn section(&self) -> Option<&'static str> {
+        self.name.split_once('.').map(|(section, _)| section)
+    }
+
+    /// Name of the key within its section.
+    pub fn short_name(&self) -> &'static str {
+        self.name.rsplit_once('.').map_or(self.name, |(_, name)| name)
+    }
+
+    /// Default value, `None` for keys that are unset by default.
+    pub fn default_value(&self) -> Option<Value> {
+        lookup(&Value::try_from(AppConfig::default()).ok()?, self.name).cloned()
+    }
+
+    /// Why `value` doesn't fit the key, if it doesn't.
+    fn check(&self, value: &Value) -> Option<String> {
+        let fits = match (&self.kind, value) {
+            (KeyType::Bool, Value::Boolean(_)) => true,
+            (KeyType::Integer(range), Value::Integer(n)) => {
+                if !range.contains(n) {
+                    return Some(format!("{} is out of range, expected {}", n, self.kind));
+                }
+                true
+            }
+            (KeyType::Text, Value::String(_)) => true,
+            (KeyType::Choice(choices), Value::String(s)) => {
+                if !choices.contains(&s.as_str()) {
+                    return Some(format!("\"{}\" is not {}", s, self.kind));
+                }
+                true
+            }
+            (KeyType::List, Value::Array(items)) => items.iter().all(Value::is_str),
+            (KeyType::ChoiceList(choices), Value::Array(items)) => {
+                if let Some(unknown) = items.iter().find(|item| !item.as_str().is_some_and(|s| choices.contains(&s))) {
+                    return Some(format!("{} is not one of {}", unknown, choices.join(", ")));
+                }
+                true
+            }
+            (KeyType::Table, Value::Table(_)) => true,
+            _ => false,
+        };
+        (!fits).then(|| format!("expected {}, found {}", self.kind, value.type_str()))
+    }
+}
+
+const QUALITIES: &[&str] = &["highest", "high", "low"];
+
+/// Every key, top-level keys first, then each section.
+pub const KEYS: &[ConfigKey] = &[
+    ConfigKey::new("last_channel_id", KeyType::Text, "ID of the last played channel, saved when switching channels"),
+    ConfigKey::new("volume", KeyType::Integer(0..=200), "Volume at startup, up to max_volume"),
+    ConfigKey::new("max_volume", KeyType::Integer(10..=200), "Highest volume the volume keys reach; above 100 amplifies through a limiter"),
+    ConfigKey::new("loud_volume", KeyType::Integer(0..=200), "Volume above which + asks for another press (0 disables)"),
+    ConfigKey::new("volume_step", KeyType::Integer(1..=25), "Volume change per + or - press"),
+    ConfigKey::new("key_repeat_ms", KeyType::Integer(0..=UNBOUNDED), "Milliseconds without a volume key press after which held-key repeats apply as one change (0 applies every press)"),
+    ConfigKey::new("confirm_quit", KeyType::Bool, "Ask for a second q before quitting while a channel plays"),
+    ConfigKey::new("auto_start", KeyType::Bool, "Play the last channel on startup, the same as start_screen = \"last_channel\""),
+    ConfigKey::new("start_screen", KeyType::Choice(&["channel_list", "last_channel"]), "What the player opens into"),
+    ConfigKey::new("balance", KeyType::Integer(-100..=100), "Left/right balance, -100 is left only and 100 right only"),
+    ConfigKey::new("mono", KeyType::Bool, "Mix both channels into one"),
+    ConfigKey::new("quality", KeyType::Choice(QUALITIES), "Preferred stream quality"),
+    ConfigKey::new("channel_quality", KeyType::Table, "Quality picked with Shift+Q, keyed by channel ID, preferred over quality"),
+    ConfigKey::new("decoder", KeyType::Choice(&["auto", "rodio", "symphonia"]), "Decoder for the streams"),
+    ConfigKey::new("theme", KeyType::Choice(&["default", "light", "monochrome"]), "Color theme"),
+    ConfigKey::new("genre_colors", KeyType::Table, "Genre badge colors keyed by genre family; names, indexes or #rrggbb"),
+    ConfigKey::new("fps", KeyType::Integer(1..=30), "Redraws per second, which every animation follows"),
+    ConfigKey::new("spectrum_bands", KeyType::Integer(4..=64), "Bars of the spectrum visualizer, changed with < and >"),
+    ConfigKey::new("replay_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds the , key jumps back"),
+    ConfigKey::new("track_format", KeyType::Text, "How tracks are shown, with {artist}, {title} and {channel}; unset keeps each place's layout"),
+    ConfigKey::new("log_to_file", KeyType::Bool, "Write log files to ~/.config/soma-player/logs"),
+    ConfigKey::new("on_device_loss", KeyType::Choice(&["pause", "switch_to_default"]), "What to do when the audio output device disappears"),
+    ConfigKey::new("channel_leveling", KeyType::Choice(&["off", "suggest", "apply"]), "Lift quiet channels by their measured level, or only say by how much"),
+    ConfigKey::new("monitor_channels", KeyType::List, "Up to three channel IDs whose current track is shown while playing"),
+    ConfigKey::new("hidden_channels", KeyType::List, "Channel IDs left out of the channel lists"),
+    ConfigKey::new("channel_refresh_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds between background refreshes of the channel list (0 disables)"),
+    ConfigKey::new("terminal_title", KeyType::Bool, "Show the current track in the terminal window title"),
+    ConfigKey::new("taskbar_progress", KeyType::Choice(&["auto", "always", "off"]), "Playing/paused badge on the taskbar"),
+    ConfigKey::new("pause_on_focus_loss", KeyType::Bool, "Pause while the terminal doesn't have the focus"),
+    ConfigKey::new("reconnect_cue", KeyType::Bool, "Play a short tone when the stream drops or comes back"),
+    ConfigKey::new("show_clock", KeyType::Bool, "Show the local time and the listening time in the header"),
+    ConfigKey::new("metadata_poll_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds between asking the API for the track of streams without metadata (0 disables)"),
+    ConfigKey::new("prefetch_art", KeyType::Bool, "Fetch every channel's logo at startup"),
+    ConfigKey::new("schedule", KeyType::Table, "Channel IDs to play at times of day, keyed by \"HH:MM-HH:MM\""),
+    ConfigKey::new("macros", KeyType::Table, "Keys running several actions, keyed by the key with a list of action names"),
+    ConfigKey::new("logging.max_file_mb", KeyType::Integer(0..=UNBOUNDED), "Size in MiB at which the log file is rotated (0 rotates daily only)"),
+    ConfigKey::new("logging.max_files", KeyType::Integer(0..=UNBOUNDED), "Rotated log files to keep"),
+    ConfigKey::new("logging.max_total_mb", KeyType::Integer(0..=UNBOUNDED), "Total size in MiB of rotated log files to keep (0 for no bound)"),
+    ConfigKey::new("logging.compress", KeyType::Bool, "Gzip rotated log files"),
+    ConfigKey::new("notifications.enabled", KeyType::Bool, "Show a desktop notification when the track changes"),
+    ConfigKey::new("notifications.hook", KeyType::Text, "Shell command run on track changes"),
+    ConfigKey::new("notifications.quiet_hours", KeyType::Text, "Daily window (\"22:00-08:00\") without notifications and hooks"),
+    ConfigKey::new("notifications.dedup_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds during which a track is not announced again (0 disables)"),
+    ConfigKey::new("alerts.artists", KeyType::List, "Patterns matched against the artist, substrings or /regex/"),
+    ConfigKey::new("alerts.titles", KeyType::List, "Patterns matched against the title, substrings or /regex/"),
+    ConfigKey::new("alerts.notify", KeyType::Bool, "Show a desktop notification when a track matches"),
+    ConfigKey::new("alerts.hook", KeyType::Text, "Shell command run when a track matches"),
+    ConfigKey::new("now_playing.file", KeyType::Text, "File to keep the now-playing string in"),
+    ConfigKey::new("now_playing.tmux", KeyType::Bool, "Set the tmux user option @soma_now_playing"),
+    ConfigKey::new("now_playing.format", KeyType::Text, "Format with {artist}, {title} and {channel}"),
+    ConfigKey::new("webhook.url", KeyType::Text, "URL to POST JSON payloads to"),
+    ConfigKey::new("webhook.secret", KeyType::Text, "Key for the X-Soma-Signature HMAC-SHA256 header"),
+    ConfigKey::new("webhook.retries", KeyType::Integer(0..=UNBOUNDED), "How often a failed request is retried"),
+    ConfigKey::new("scrobble.listenbrainz_token", KeyType::Text, "ListenBrainz user token; nothing is scrobbled without one"),
+    ConfigKey::new("scrobble.max_age_days", KeyType::Integer(0..=UNBOUNDED), "Days a listen waits in the offline queue before it is dropped"),
+    ConfigKey::new("sync.role", KeyType::Choice(&["off", "leader", "follower"]), "Role in listen-along mode"),
+    ConfigKey::new("sync.port", KeyType::Integer(1..=65535), "UDP port announcements are broadcast on"),
+    ConfigKey::new("sync.group", KeyType::Text, "Followers only follow leaders of the same group"),
+    ConfigKey::new("sync.name", KeyType::Text, "Name announced by a leader, defaults to the host name"),
+    ConfigKey::new("cast.renderer", KeyType::Text, "Part of the renderer's name, or the URL of its device description"),
+    ConfigKey::new("ducking.pipe", KeyType::Text, "Named pipe accepting duck and restore lines"),
+    ConfigKey::new("ducking.command", KeyType::Text, "Long-running shell command whose output is watched"),
+    ConfigKey::new("ducking.duck_pattern", KeyType::Text, "Regular expression for command output lines that start ducking"),
+    ConfigKey::new("ducking.restore_pattern", KeyType::Text, "Regular expression for command output lines that end ducking"),
+    ConfigKey::new("ducking.level", KeyType::Integer(0..=100), "Volume while ducked, in percent of the normal volume"),
+    ConfigKey::new("ducking.ramp_ms", KeyType::Integer(0..=UNBOUNDED), "Duration of the fade in and out of ducking, in milliseconds"),
+    ConfigKey::new("self_monitor.log_interval_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds between resource usage log lines (0 disables them)"),
+    ConfigKey::new("self_monitor.max_rss_mb", KeyType::Integer(0..=UNBOUNDED), "Memory use in MiB above which a warning is logged (0 disables it)"),
+    ConfigKey::new("self_monitor.max_buffer_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds of buffered audio beyond which the stream is reopened (0 disables)"),
+    ConfigKey::new("self_monitor.max_stream_mb", KeyType::Integer(0..=UNBOUNDED), "MiB one connection may hold in memory before it is reopened (0 disables)"),
+    ConfigKey::new("watchdog.silence_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds without audio before kiosk mode rebuilds the pipeline (0 disables)"),
+    ConfigKey::new("watchdog.max_restarts", KeyType::Integer(0..=UNBOUNDED), "Consecutive rebuilds before playback is given up"),
+    ConfigKey::new("station_ids.action", KeyType::Choice(&["off", "duck", "skip"]), "What to do while a station ID plays"),
+    ConfigKey::new("station_ids.patterns", KeyType::List, "Patterns matched against \"Artist - Title\", substrings or /regex/"),
+    ConfigKey::new("station_ids.level", KeyType::Integer(0..=100), "Volume while ducked, in percent of the normal volume"),
+    ConfigKey::new("station_ids.min_skip_buffer_secs", KeyType::Integer(0..=UNBOUNDED), "Seconds of buffered audio needed before skipping ahead"),
+    ConfigKey::new("enrichment.sources", KeyType::ChoiceList(&["musicbrainz", "genres", "lyrics"]), "Sources asked about each new track, in order"),
+];
+
+/// The key named `name`, a dotted path for keys in sections.
+pub fn key(name: &str) -> Option<&'static ConfigKey> {
+    KEYS.iter().find(|key| key.name == name)
+}
+
+/// The value at the dotted path `name` in `value`.
+fn lookup<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    name.split('.').try_fold(value, |value, part| value.get(part))
+}
+
+/// Something wrong with a key of a config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// Dotted path of the key
+    pub key: String,
+    pub problem: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.problem)
+    }
+}
+
+/// Checks the parsed contents of a config file against [`KEYS`].
+pub fn validate(config: &Value) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let Some(table) = config.as_table() else {
+        return issues;
+    };
+    for (name, value) in table {
+        match (key(name), value) {
+            (Some(key), value) => issues.extend(key.check(value).map(|problem| issue(name, problem))),
+            // A section: check each of its keys
+            (None, Value::Table(section)) if KEYS.iter().any(|key| key.section() == Some(name)) => {
+                for (short_name, value) in section {
+                    let name = format!("{}.{}", name, short_name);
+                    match key(&name) {
+                        Some(key) => issues.extend(key.check(value).map(|problem| issue(&name, problem))),
+                        None => issues.push(issue(&name, "unknown key".to_string())),
+                    }
+                }
+            }
+            (None, _) => issues.push(issue(name, "unknown key".to_string())),
+        }
+    }
+    issues
+}
+
+/// Checks the contents of a config file: an error when the player can't
+/// read it, otherwise whatever [`validate`] finds.
+pub fn check(contents: &str) -> Result<Vec<ConfigIssue>, String> {
+    toml::from_str::<AppConfig>(contents).map_err(|e| e.to_string())?;
+    let raw = toml::from_str(contents).map_err(|e| e.to_string())?;
+    Ok(validate(&raw))
+}
+
+fn issue(key: &str, problem: String) -> ConfigIssue {
+    ConfigIssue { key: key.to_string(), problem }
+}
+
+/// The default configuration as TOML, every key preceded by its
+/// description and allowed values. Keys unset by default are commented
+/// out.
+pub fn default_config_toml() -> String {
+    let mut out = String::from(
+        "# SomaFM Player configuration, ~/.config/soma-player/config.toml\n\
+         # Every key is optional; missing keys take the values shown here.\n",
+    );
+    let defaults = Value::try_from(AppConfig::default()).unwrap_or_else(|_| Value::Table(Default::default()));
+    let mut section = None;
+    for key in KEYS {
+        if key.section() != section {
+            section = key.section();
+            let _ = write!(out, "\n[{}]\n", section.unwrap_or_default());
+        }
+        let _ = write!(out, "\n# {}\n# {}\n", key.description, key.kind);
+        let _ = match lookup(&defaults, key.name) {
+            Some(value) => writeln!(out, "{} = {}", key.short_name(), value),
+            None => writeln!(out, "# {} =", key.short_name()),
+        };
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_covers_the_default_config() {
+        let defaults = Value::try_from(AppConfig::default()).unwrap();
+        for (name, value) in defaults.as_table().unwrap() {
+            let is_section = KEYS.iter().any(|key| key.section() == Some(name));
+            match value {
+                Value::Table(section) if is_section => {
+                    for short_name in section.keys() {
+                        assert!(key(&format!("{}.{}", name, short_name)).is_some(), "{}.{} missing", name, short_name);
+                    }
+                }
+                _ => assert!(key(name).is_some(), "{} missing", name),
+            }
+        }
+        assert_eq!(validate(&defaults), Vec::new());
+
+        // The printed defaults read back as the defaults
+        let printed: AppConfig = toml::from_str(&default_config_toml()).unwrap();
+        assert_eq!(toml::to_string(&printed).unwrap(), toml::to_string(&AppConfig::default()).unwrap());
+    }
+
+    #[test]
+    fn test_validate_reports_bad_keys() {
+        let config: Value = toml::from_str(
+            "fps = 120\ntheme = \"neon\"\nmono = \"yes\"\nvolumme = 50\n\
+             [sync]\nport = 0\nrol = \"leader\"\n[enrichment]\nsources = [\"lyrics\", \"discogs\"]\n",
+        )
+        .unwrap();
+        let issues: Vec<String> = validate(&config).iter().map(ToString::to_string).collect();
+        assert_eq!(check("fps = 120").unwrap().len(), 1);
+        assert!(check("fps = \"fast\"").is_err());
+        assert!(check("[sync\nport = 1").is_err());
+        assert_eq!(
+            issues,
+            [
+                "enrichment.sources: \"discogs\" is not one of musicbrainz, genres, lyrics",
+                "fps: 120 is out of range, expected 1 to 30",
+                "mono: expected true or false, found string",
+                "sync.port: 0 is out of range, expected 1 to 65535",
+                "sync.rol: unknown key",
+                "theme: \"neon\" is not one of default, light, monochrome",
+                "volumme: unknown key",
+            ]
+        );
+    }
+}
@@ -19,21 +19,472 @@
 //! ```
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::audio::{level::LevelMode, DecoderBackend, DeviceLossPolicy};
+use crate::config::schema;
+use crate::models::StreamQuality;
+use crate::ui::taskbar::TaskbarMode;
+use crate::ui::theme::Theme;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// How long the config must stay unchanged before a scheduled save is written
+pub const CONFIG_SAVE_DELAY: Duration = Duration::from_secs(2);
 
 /// Application configuration structure.
 ///  
 /// Stores user preferences and settings that persist between application runs.
 /// Configuration is automatically saved to `~/.config/soma-player/config.toml`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     /// ID of the last played channel (auto-saved when switching channels)
     pub last_channel_id: Option<String>,
-    /// Volume level (0-100), defaults to 50
+    /// Volume level (0-100, up to `max_volume`), defaults to 50
     pub volume: Option<u8>,
-    /// Whether to automatically start playing the last channel on startup
+    /// Highest volume the volume keys reach (10-200); levels above 100
+    /// amplify the stream through a limiter, levels below are a hard cap
+    pub max_volume: u8,
+    /// Volume above which `+` asks for another press (0 disables)
+    pub loud_volume: u8,
+    /// Volume change per `+`/`-` press (1-25)
+    pub volume_step: u8,
+    /// Milliseconds without a volume key press after which held-key
+    /// repeats are applied as one change (0 applies every press)
+    pub key_repeat_ms: u64,
+    /// Ask for a second `q` before quitting while a channel plays
+    pub confirm_quit: bool,
+    /// Whether to automatically start playing the last channel on startup,
+    /// the same as `start_screen = "last_channel"`
     pub auto_start: bool,
+    /// What the player opens into (`channel_list` or `last_channel`)
+    pub start_screen: StartScreen,
+    /// Left/right balance from -100 (left only) to 100 (right only)
+    pub balance: i8,
+    /// Mix both channels into one, for single speakers or one-sided hearing
+    pub mono: bool,
+    /// Preferred stream quality (`highest`, `high` or `low`)
+    pub quality: StreamQuality,
+    /// Quality last picked with Shift+Q for each channel, keyed by channel
+    /// ID, preferred over `quality` for that channel
+    pub channel_quality: BTreeMap<String, StreamQuality>,
+    /// Decoder for the streams (`auto`, `rodio` or `symphonia`)
+    pub decoder: DecoderBackend,
+    /// Color theme (`default`, `light` or `monochrome`)
+    pub theme: Theme,
+    /// Genre badge colors replacing the theme's, keyed by genre family
+    /// (e.g. `ambient = "teal"`); names, indexes or `#rrggbb`
+    pub genre_colors: BTreeMap<String, String>,
+    /// Redraws per second (1-30), which every animation follows
+    pub fps: u32,
+    /// Bars of the spectrum visualizer (4-64), changed with `<` and `>`
+    pub spectrum_bands: usize,
+    /// Seconds the `,` key jumps back to replay what was just played
+    pub replay_secs: u64,
+    /// How tracks are shown, with `{artist}`, `{title}` and `{channel}`
+    /// placeholders; unset keeps each place's own layout
+    pub track_format: Option<String>,
+    /// Write log files to `~/.config/soma-player/logs`
+    pub log_to_file: bool,
+    /// Log file rotation and retention
+    pub logging: LoggingConfig,
+    /// What to do when the audio output device disappears (`pause` or
+    /// `switch_to_default`)
+    pub on_device_loss: DeviceLossPolicy,
+    /// Measure each channel's level and lift quiet channels (`apply`),
+    /// only say how much they would be lifted (`suggest`), or neither
+    /// (`off`)
+    pub channel_leveling: LevelMode,
+    /// Up to three channel IDs whose current track is shown while playing
+    pub monitor_channels: Vec<String>,
+    /// Channel IDs left out of the channel lists; `-c` still plays them
+    pub hidden_channels: Vec<String>,
+    /// Seconds between background refreshes of the channel list (0 disables)
+    pub channel_refresh_secs: u64,
+    /// Show the current track in the terminal window title
+    pub terminal_title: bool,
+    /// Playing/paused badge on the taskbar via OSC 9;4 (`auto`, `always`
+    /// or `off`)
+    pub taskbar_progress: TaskbarMode,
+    /// Pause while the terminal doesn't have the focus and resume when it
+    /// gets it back
+    pub pause_on_focus_loss: bool,
+    /// Play a short tone when the stream drops or comes back
+    pub reconnect_cue: bool,
+    /// Show the local time and the session's listening time in the header
+    pub show_clock: bool,
+    /// Seconds between asking the API for the current track when a stream
+    /// sends no ICY metadata (0 disables)
+    pub metadata_poll_secs: u64,
+    /// Fetch every channel's logo at startup instead of when the channel
+    /// is first played; logos are only fetched for desktop notifications
+    pub prefetch_art: bool,
+    /// Desktop notification and hook settings
+    pub notifications: NotificationConfig,
+    /// Artist/title patterns to be alerted about
+    pub alerts: AlertConfig,
+    /// Now-playing export for tmux/screen status bars
+    pub now_playing: NowPlayingConfig,
+    /// Webhook posted on track and playback changes
+    pub webhook: WebhookConfig,
+    /// Submitting played tracks to ListenBrainz
+    pub scrobble: ScrobbleConfig,
+    /// Experimental listen-along mode over the LAN
+    pub sync: SyncConfig,
+    /// UPnP/DLNA renderer to play on instead of the local audio device
+    pub cast: CastConfig,
+    /// Lowering the volume while a call or other system event is active
+    pub ducking: DuckingConfig,
+    /// Resource usage logging and stream buffer bounds
+    pub self_monitor: SelfMonitorConfig,
+    /// Restarting silent playback in kiosk mode
+    pub watchdog: WatchdogConfig,
+    /// Ducking or skipping station IDs between tracks
+    pub station_ids: StationIdConfig,
+    /// Album, genre and lyrics lookups for the playing track
+    pub enrichment: EnrichmentConfig,
+    /// Channel IDs to play at times of day, keyed by `"HH:MM-HH:MM"`
+    pub schedule: BTreeMap<String, String>,
+    /// Keys running several actions at once, keyed by the key with a list
+    /// of action names
+    pub macros: BTreeMap<String, Vec<String>>,
+}
+
+/// What the player shows first on startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartScreen {
+    /// Pick a channel from the list
+    #[default]
+    ChannelList,
+    /// Play the last channel right away
+    LastChannel,
+}
+
+/// Settings for track-change notifications (`[notifications]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Show a desktop notification when the track changes
+    pub enabled: bool,
+    /// Shell command run on track changes, with `SOMA_CHANNEL_ID`,
+    /// `SOMA_CHANNEL`, `SOMA_ARTIST` and `SOMA_TITLE` set
+    pub hook: Option<String>,
+    /// Daily window (`"22:00-08:00"`) during which notifications and hooks
+    /// are suppressed
+    pub quiet_hours: Option<String>,
+    /// Seconds during which a track announced already is not announced
+    /// again, e.g. when a reconnect repeats its metadata (0 disables)
+    pub dedup_secs: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hook: None,
+            quiet_hours: None,
+            dedup_secs: 300,
+        }
+    }
+}
+
+/// Now-playing export settings (`[now_playing]` section).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NowPlayingConfig {
+    /// File to keep the now-playing string in (`~/` is expanded)
+    pub file: Option<String>,
+    /// Set the tmux user option `@soma_now_playing` when running inside tmux
+    pub tmux: bool,
+    /// Format with `{artist}`, `{title}` and `{channel}` placeholders
+    pub format: Option<String>,
+}
+
+/// Webhook settings (`[webhook]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// URL to POST JSON payloads to
+    pub url: Option<String>,
+    /// Key for the `X-Soma-Signature` HMAC-SHA256 header
+    pub secret: Option<String>,
+    /// How often a failed request is retried
+    pub retries: u32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            secret: None,
+            retries: 3,
+        }
+    }
+}
+
+/// Scrobbling settings (`[scrobble]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrobbleConfig {
+    /// ListenBrainz user token; nothing is scrobbled without one
+    pub listenbrainz_token: Option<String>,
+    /// Days a listen waits in the offline queue before it is dropped
+    pub max_age_days: u32,
+}
+
+impl Default for ScrobbleConfig {
+    fn default() -> Self {
+        Self {
+            listenbrainz_token: None,
+            max_age_days: 14,
+        }
+    }
+}
+
+/// Casting settings (`[cast]` section).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CastConfig {
+    /// Part of the renderer's name, or the URL of its device description
+    pub renderer: Option<String>,
+}
+
+/// Audio ducking settings (`[ducking]` section).
+///
+/// Ducking is triggered by lines written to `pipe`, or by lines printed by
+/// `command` that match `duck_pattern` / `restore_pattern`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuckingConfig {
+    /// Named pipe accepting `duck` and `restore` lines (created if missing)
+    pub pipe: Option<String>,
+    /// Long-running shell command whose output is watched, e.g. `dbus-monitor`
+    pub command: Option<String>,
+    /// Regular expression for `command` output lines that start ducking
+    pub duck_pattern: Option<String>,
+    /// Regular expression for `command` output lines that end ducking
+    pub restore_pattern: Option<String>,
+    /// Volume while ducked, in percent of the normal volume
+    pub level: u8,
+    /// Duration of the fade in and out of ducking, in milliseconds
+    pub ramp_ms: u64,
+}
+
+impl Default for DuckingConfig {
+    fn default() -> Self {
+        Self {
+            pipe: None,
+            command: None,
+            duck_pattern: None,
+            restore_pattern: None,
+            level: 20,
+            ramp_ms: 500,
+        }
+    }
+}
+
+/// Resource monitoring settings (`[self_monitor]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelfMonitorConfig {
+    /// Seconds between resource usage log lines (0 disables them)
+    pub log_interval_secs: u64,
+    /// Memory use in MiB above which a warning is logged (0 disables it)
+    pub max_rss_mb: u64,
+    /// Seconds of buffered audio beyond which the stream is reopened at
+    /// live (0 disables the bound)
+    pub max_buffer_secs: u64,
+    /// MiB of stream one connection may hold in memory before it is
+    /// reopened (0 disables the bound)
+    pub max_stream_mb: u64,
+}
+
+impl Default for SelfMonitorConfig {
+    fn default() -> Self {
+        Self {
+            log_interval_secs: 300,
+            max_rss_mb: 512,
+            max_buffer_secs: 1800,
+            max_stream_mb: 256,
+        }
+    }
+}
+
+/// Log file rotation settings (`[logging]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Size in MiB at which the log file is rotated (0 rotates daily only)
+    pub max_file_mb: u64,
+    /// Rotated log files to keep
+    pub max_files: usize,
+    /// Total size in MiB of rotated log files to keep (0 for no bound)
+    pub max_total_mb: u64,
+    /// Gzip rotated log files
+    pub compress: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            max_file_mb: 10,
+            max_files: 5,
+            max_total_mb: 50,
+            compress: true,
+        }
+    }
+}
+
+/// Playback watchdog settings for kiosk mode (`[watchdog]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// Seconds without decoded audio before the pipeline is rebuilt (0
+    /// disables the watchdog)
+    pub silence_secs: u64,
+    /// Consecutive rebuilds before playback is given up
+    pub max_restarts: u32,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            silence_secs: 20,
+            max_restarts: 5,
+        }
+    }
+}
+
+/// What to do while a station ID plays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StationIdAction {
+    /// Play station IDs like any other track
+    #[default]
+    Off,
+    /// Lower the volume until the next track
+    Duck,
+    /// Jump ahead to live when enough audio is buffered, duck otherwise
+    Skip,
+}
+
+/// Station ID handling (`[station_ids]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StationIdConfig {
+    pub action: StationIdAction,
+    /// Patterns matched against `Artist - Title`, substrings or `/regex/`
+    /// as in `[alerts]`
+    pub patterns: Vec<String>,
+    /// Volume while ducked, in percent of the normal volume
+    pub level: u8,
+    /// Seconds of buffered audio needed before skipping ahead
+    pub min_skip_buffer_secs: u64,
+}
+
+impl Default for StationIdConfig {
+    fn default() -> Self {
+        Self {
+            action: StationIdAction::Off,
+            patterns: vec!["/^somafm\\b/".to_string(), "station id".to_string()],
+            level: 30,
+            min_skip_buffer_secs: 20,
+        }
+    }
+}
+
+/// A source of track details for `[enrichment]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrichmentSource {
+    /// Album, release year and recording ID from MusicBrainz
+    #[serde(rename = "musicbrainz")]
+    MusicBrainz,
+    /// The artist's genres from MusicBrainz tags
+    Genres,
+    /// Lyrics from lyrics.ovh
+    Lyrics,
+}
+
+/// Track detail lookups (`[enrichment]` section).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnrichmentConfig {
+    /// Sources asked about each new track, in order; none by default
+    pub sources: Vec<EnrichmentSource>,
+}
+
+/// Role of this instance in listen-along mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncRole {
+    #[default]
+    Off,
+    /// Broadcast the playing channel to followers
+    Leader,
+    /// Play whatever the leader plays
+    Follower,
+}
+
+/// Listen-along settings (`[sync]` section).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// `off`, `leader` or `follower`
+    pub role: SyncRole,
+    /// UDP port announcements are broadcast on
+    pub port: u16,
+    /// Followers only follow leaders of the same group
+    pub group: String,
+    /// Name announced by a leader, defaults to the host name
+    pub name: Option<String>,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            role: SyncRole::Off,
+            port: crate::sync::DEFAULT_PORT,
+            group: "default".to_string(),
+            name: None,
+        }
+    }
+}
+
+/// Track alert settings (`[alerts]` section).
+///
+/// Patterns are matched case-insensitively as substrings, or as regular
+/// expressions when wrapped in slashes (`"/^boards of/"`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    /// Patterns matched against the artist
+    pub artists: Vec<String>,
+    /// Patterns matched against the title
+    pub titles: Vec<String>,
+    /// Show a desktop notification when a track matches
+    pub notify: bool,
+    /// Shell command run when a track matches, with the same environment as
+    /// the notification hook plus `SOMA_ALERT_PATTERN`
+    pub hook: Option<String>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            artists: Vec::new(),
+            titles: Vec::new(),
+            notify: true,
+            hook: None,
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -41,12 +492,62 @@ impl Default for AppConfig {
         Self {
             last_channel_id: None,
             volume: Some(50),
+            max_volume: 100,
+            loud_volume: 90,
+            volume_step: 5,
+            key_repeat_ms: 150,
+            confirm_quit: false,
             auto_start: false,
+            start_screen: StartScreen::default(),
+            balance: 0,
+            mono: false,
+            quality: StreamQuality::default(),
+            channel_quality: BTreeMap::new(),
+            decoder: DecoderBackend::default(),
+            theme: Theme::default(),
+            genre_colors: BTreeMap::new(),
+            fps: 10,
+            spectrum_bands: 12,
+            replay_secs: 15,
+            track_format: None,
+            log_to_file: true,
+            logging: LoggingConfig::default(),
+            on_device_loss: DeviceLossPolicy::default(),
+            channel_leveling: LevelMode::default(),
+            monitor_channels: Vec::new(),
+            hidden_channels: Vec::new(),
+            channel_refresh_secs: 120,
+            terminal_title: false,
+            taskbar_progress: TaskbarMode::default(),
+            pause_on_focus_loss: false,
+            reconnect_cue: false,
+            show_clock: false,
+            metadata_poll_secs: 30,
+            prefetch_art: false,
+            notifications: NotificationConfig::default(),
+            alerts: AlertConfig::default(),
+            now_playing: NowPlayingConfig::default(),
+            webhook: WebhookConfig::default(),
+            scrobble: ScrobbleConfig::default(),
+            sync: SyncConfig::default(),
+            cast: CastConfig::default(),
+            ducking: DuckingConfig::default(),
+            self_monitor: SelfMonitorConfig::default(),
+            watchdog: WatchdogConfig::default(),
+            station_ids: StationIdConfig::default(),
+            enrichment: EnrichmentConfig::default(),
+            schedule: BTreeMap::new(),
+            macros: BTreeMap::new(),
         }
     }
 }
 
 impl AppConfig {
+    /// Whether no configuration file has been written yet
+    pub fn is_first_run() -> bool {
+        Self::config_path().is_ok_and(|path| !path.exists())
+    }
+
     /// Get the configuration file path
     pub fn config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
         let home = dirs::home_dir().ok_or("Could not find home directory")?;
@@ -63,9 +564,7 @@ impl AppConfig {
         let config_path = Self::config_path()?;
         
         if config_path.exists() {
-            let contents = fs::read_to_string(config_path)?;
-            let config: AppConfig = toml::from_str(&contents)?;
-            Ok(config)
+            Self::load_from(&config_path)
         } else {
             // Create default config and save it
             let default_config = Self::default();
@@ -77,8 +576,71 @@ impl AppConfig {
     /// Save configuration to file
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
         let config_path = Self::config_path()?;
+        self.save_to(&config_path)
+    }
+
+    /// Loads the config at `path`, falling back to its backup when the
+    /// file cannot be read or parsed.
+    fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let parse = |path: &Path| -> Result<Self, Box<dyn std::error::Error>> {
+            let contents = fs::read_to_string(path)?;
+            let config = toml::from_str(&contents)?;
+            // Values serde accepts can still be out of range or misspelled
+            if let Ok(raw) = toml::from_str(&contents) {
+                for issue in schema::validate(&raw) {
+                    log::warn!("{}: {}", path.display(), issue);
+                }
+            }
+            Ok(config)
+        };
+
+        match parse(path) {
+            Ok(config) => Ok(config),
+            Err(e) => {
+                let backup_path = path.with_extension("toml.bak");
+                log::warn!("Failed to load {}: {}, trying {}", path.display(), e, backup_path.display());
+                parse(&backup_path).map_err(|_| e)
+            }
+        }
+    }
+
+    /// Writes the config to `path` atomically: the new contents go to a
+    /// temporary file that is flushed to disk and renamed over the old one,
+    /// so a crash never leaves a half-written config. The previous file is
+    /// kept as a `.bak` backup if it was valid. On failure the temporary
+    /// file is removed.
+    fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        static SAVE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
         let toml_string = toml::to_string_pretty(self)?;
-        fs::write(config_path, toml_string)?;
+        // Unique per save so concurrent saves never share a temporary file
+        let temp_path = path.with_extension(format!(
+            "toml.tmp.{}.{}",
+            std::process::id(),
+            SAVE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let replace = || -> std::io::Result<()> {
+            let mut file = fs::File::create(&temp_path)?;
+            file.write_all(toml_string.as_bytes())?;
+            // Without this a power loss can leave an empty file behind the
+            // rename
+            file.sync_all()?;
+            drop(file);
+
+            let current_is_valid = fs::read_to_string(path)
+                .ok()
+                .is_some_and(|contents| toml::from_str::<AppConfig>(&contents).is_ok());
+            if current_is_valid {
+                fs::copy(path, path.with_extension("toml.bak"))?;
+            }
+
+            fs::rename(&temp_path, path)
+        };
+        if let Err(e) = replace() {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
         Ok(())
     }
 
@@ -88,9 +650,33 @@ impl AppConfig {
         self.save()
     }
 
+    /// What to open into, with `auto_start` standing in for `last_channel`
+    pub fn initial_screen(&self) -> StartScreen {
+        if self.auto_start {
+            StartScreen::LastChannel
+        } else {
+            self.start_screen
+        }
+    }
+
+    /// Stream quality to connect to `channel_id` with
+    pub fn quality_for(&self, channel_id: &str) -> StreamQuality {
+        self.channel_quality.get(channel_id).copied().unwrap_or(self.quality)
+    }
+
+    /// Highest allowed volume, `max_volume` kept within 10-200
+    pub fn volume_limit(&self) -> u8 {
+        self.max_volume.clamp(10, 200)
+    }
+
+    /// Volume change per key press, `volume_step` kept within 1-25
+    pub fn volume_step(&self) -> u8 {
+        self.volume_step.clamp(1, 25)
+    }
+
     /// Update volume setting and save
     pub fn set_volume(&mut self, volume: u8) -> Result<(), Box<dyn std::error::Error>> {
-        self.volume = Some(volume.clamp(0, 100));
+        self.volume = Some(volume.min(self.volume_limit()));
         self.save()
     }
 
@@ -101,6 +687,73 @@ impl AppConfig {
     }
 }
 
+/// Writes configuration changes in the background.
+///
+/// Bursts of changes (such as holding down the volume key) are coalesced
+/// into a single write once no further change arrives for the save delay,
+/// so the UI never waits on the disk.
+pub struct ConfigSaver {
+    tx: mpsc::UnboundedSender<AppConfig>,
+    task: JoinHandle<()>,
+}
+
+impl ConfigSaver {
+    /// Starts the background saver. Must be called within a Tokio runtime.
+    pub fn start(delay: Duration) -> Self {
+        Self::with_writer(delay, |config| config.save().map_err(|e| e.to_string()))
+    }
+
+    fn with_writer<F>(delay: Duration, write: F) -> Self
+    where
+        F: Fn(&AppConfig) -> Result<(), String> + Send + Sync + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel::<AppConfig>();
+        let write = std::sync::Arc::new(write);
+
+        let task = tokio::spawn(async move {
+            while let Some(mut pending) = rx.recv().await {
+                // Keep taking newer versions until the config settles
+                let mut closed = false;
+                loop {
+                    match tokio::time::timeout(delay, rx.recv()).await {
+                        Ok(Some(newer)) => pending = newer,
+                        Ok(None) => {
+                            closed = true;
+                            break;
+                        }
+                        Err(_) => break,
+                    }
+                }
+
+                let write = std::sync::Arc::clone(&write);
+                let result = tokio::task::spawn_blocking(move || write(&pending)).await;
+                match result {
+                    Ok(Ok(())) => log::debug!("Configuration saved"),
+                    Ok(Err(e)) => log::error!("Failed to save config: {}", e),
+                    Err(e) => log::error!("Config save task failed: {}", e),
+                }
+
+                if closed {
+                    break;
+                }
+            }
+        });
+
+        Self { tx, task }
+    }
+
+    /// Schedules `config` to be written once changes settle.
+    pub fn schedule(&self, config: &AppConfig) {
+        let _ = self.tx.send(config.clone());
+    }
+
+    /// Writes any pending change immediately and stops the saver.
+    pub async fn flush(self) {
+        drop(self.tx);
+        let _ = self.task.await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,7 +765,7 @@ mod tests {
         
         assert_eq!(config.last_channel_id, None);
         assert_eq!(config.volume, Some(50));
-        assert_eq!(config.auto_start, false);
+        assert!(!config.auto_start);
     }
 
     #[test]
@@ -121,6 +774,7 @@ mod tests {
             last_channel_id: Some("groovesalad".to_string()),
             volume: Some(75),
             auto_start: true,
+            ..Default::default()
         };
 
         let toml_string = toml::to_string(&config).unwrap();
@@ -146,6 +800,16 @@ mod tests {
         // Test minimum value (should work with saturating_sub)
         config.set_volume(0).unwrap();
         assert_eq!(config.volume, Some(0));
+        
+        // Boosting allows up to max_volume
+        config.max_volume = 150;
+        config.set_volume(180).unwrap();
+        assert_eq!(config.volume, Some(150));
+        
+        // or caps it below 100
+        config.max_volume = 60;
+        config.set_volume(80).unwrap();
+        assert_eq!(config.volume, Some(60));
     }
 
     #[test]
@@ -161,10 +825,10 @@ mod tests {
         let mut config = AppConfig::default();
         
         config.set_auto_start(true).unwrap();
-        assert_eq!(config.auto_start, true);
+        assert!(config.auto_start);
         
         config.set_auto_start(false).unwrap();
-        assert_eq!(config.auto_start, false);
+        assert!(!config.auto_start);
     }
 
     // Integration test for save/load cycle
@@ -179,6 +843,7 @@ mod tests {
             last_channel_id: Some("spacestation".to_string()),
             volume: Some(80),
             auto_start: true,
+            ..Default::default()
         };
 
         // Write manually to test file
@@ -193,4 +858,136 @@ mod tests {
         assert_eq!(original_config.volume, loaded_config.volume);
         assert_eq!(original_config.auto_start, loaded_config.auto_start);
     }
+
+    #[test]
+    fn test_atomic_save_keeps_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        let backup_path = temp_dir.path().join("config.toml.bak");
+
+        let mut config = AppConfig {
+            volume: Some(30),
+            ..Default::default()
+        };
+        config.save_to(&config_path).unwrap();
+        assert!(!backup_path.exists());
+
+        config.volume = Some(40);
+        config.save_to(&config_path).unwrap();
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+
+        let backup: AppConfig = toml::from_str(&fs::read_to_string(&backup_path).unwrap()).unwrap();
+        assert_eq!(backup.volume, Some(30));
+        assert_eq!(AppConfig::load_from(&config_path).unwrap().volume, Some(40));
+    }
+
+    #[test]
+    fn test_failed_save_removes_temporary_file() {
+        let temp_dir = TempDir::new().unwrap();
+        // A directory in the way makes the rename fail
+        let config_path = temp_dir.path().join("config.toml");
+        fs::create_dir(&config_path).unwrap();
+        fs::write(config_path.join("keep"), "").unwrap();
+
+        assert!(AppConfig::default().save_to(&config_path).is_err());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_load_falls_back_to_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+
+        let config = AppConfig {
+            volume: Some(30),
+            ..Default::default()
+        };
+        config.save_to(&config_path).unwrap();
+        config.save_to(&config_path).unwrap();
+
+        // Simulate a crash that left the main file truncated
+        fs::write(&config_path, "volume = ").unwrap();
+        assert_eq!(AppConfig::load_from(&config_path).unwrap().volume, Some(30));
+
+        // A corrupt main file is never copied over a good backup
+        config.save_to(&config_path).unwrap();
+        fs::write(&config_path, "volume = ").unwrap();
+        assert_eq!(AppConfig::load_from(&config_path).unwrap().volume, Some(30));
+
+        fs::remove_file(temp_dir.path().join("config.toml.bak")).unwrap();
+        assert!(AppConfig::load_from(&config_path).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_config_saver_coalesces_writes() {
+        use std::sync::{Arc, Mutex};
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let saver = ConfigSaver::with_writer(Duration::from_millis(50), {
+            let written = Arc::clone(&written);
+            move |config| {
+                written.lock().unwrap().push(config.volume);
+                Ok(())
+            }
+        });
+
+        let mut config = AppConfig::default();
+        for volume in [55, 60, 65] {
+            config.volume = Some(volume);
+            saver.schedule(&config);
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(*written.lock().unwrap(), [Some(65)]);
+
+        // Flushing writes a pending change without waiting for the delay
+        config.volume = Some(70);
+        saver.schedule(&config);
+        saver.flush().await;
+        assert_eq!(*written.lock().unwrap(), [Some(65), Some(70)]);
+    }
+
+    #[test]
+    fn test_notifications_section() {
+        let config: AppConfig = toml::from_str(r#"
+volume = 40
+
+[notifications]
+enabled = true
+quiet_hours = "22:00-08:00"
+"#).unwrap();
+
+        assert_eq!(config.volume, Some(40));
+        assert!(config.notifications.enabled);
+        assert_eq!(config.notifications.quiet_hours.as_deref(), Some("22:00-08:00"));
+        assert_eq!(config.notifications.hook, None);
+
+        // Older config files without the section still load
+        let config: AppConfig = toml::from_str("auto_start = true").unwrap();
+        assert!(!config.notifications.enabled);
+    }
+
+    #[test]
+    fn test_channel_quality_overrides_quality() {
+        let config: AppConfig = toml::from_str(r#"
+quality = "low"
+
+[channel_quality]
+groovesalad = "highest"
+"#).unwrap();
+        assert_eq!(config.quality_for("groovesalad"), StreamQuality::Highest);
+        assert_eq!(config.quality_for("dronezone"), StreamQuality::Low);
+    }
+
+    #[test]
+    fn test_initial_screen() {
+        let config: AppConfig = toml::from_str(r#"start_screen = "last_channel""#).unwrap();
+        assert_eq!(config.initial_screen(), StartScreen::LastChannel);
+
+        // auto_start from older config files still plays right away
+        let config: AppConfig = toml::from_str("auto_start = true").unwrap();
+        assert_eq!(config.start_screen, StartScreen::ChannelList);
+        assert_eq!(config.initial_screen(), StartScreen::LastChannel);
+
+        assert_eq!(AppConfig::default().initial_screen(), StartScreen::ChannelList);
+    }
 }
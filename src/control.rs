@@ -0,0 +1,188 @@
+//! Remote control of a running player.
+//!
+//! While the TUI runs it listens on a Unix socket (`control.sock` in the
+//! state directory). `soma-player ctl ...` connects to it, sends one
+//! request as a line of JSON and prints the single JSON line it gets back.
+//!
+//! # Examples
+//!
+//! ```json
+//! {"channel":"drone zone"}
+//! {"ok":"Playing Drone Zone"}
+//! ```
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::state_file::get_state_directory;
+
+/// A request to the running player.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Switch to the channel best matching this ID or name
+    Channel(String),
+}
+
+/// The player's answer to a [`ControlRequest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlReply {
+    Ok(String),
+    Error(String),
+}
+
+impl From<Result<String, String>> for ControlReply {
+    fn from(result: Result<String, String>) -> Self {
+        match result {
+            Ok(message) => ControlReply::Ok(message),
+            Err(message) => ControlReply::Error(message),
+        }
+    }
+}
+
+impl From<ControlReply> for Result<String, String> {
+    fn from(reply: ControlReply) -> Self {
+        match reply {
+            ControlReply::Ok(message) => Ok(message),
+            ControlReply::Error(message) => Err(message),
+        }
+    }
+}
+
+/// Path of the control socket
+pub fn socket_path() -> Result<PathBuf, String> {
+    get_state_directory()
+        .map(|dir| dir.join("control.sock"))
+        .map_err(|e| e.to_string())
+}
+
+/// Sends `request` to the running player and returns its answer.
+#[cfg(unix)]
+pub async fn send_request(request: &ControlRequest) -> Result<String, String> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path)
+        .await
+        .map_err(|_| "No running player found".to_string())?;
+
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await.map_err(|e| e.to_string())?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).await.map_err(|e| e.to_string())?;
+    serde_json::from_str::<ControlReply>(&response)
+        .map_err(|e| format!("Invalid reply from the player: {}", e))?
+        .into()
+}
+
+#[cfg(not(unix))]
+pub async fn send_request(_request: &ControlRequest) -> Result<String, String> {
+    Err("Remote control is only supported on Unix".to_string())
+}
+
+/// Accepts control connections for the lifetime of the value and removes
+/// the socket on drop.
+pub struct ControlServer {
+    path: PathBuf,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ControlServer {
+    /// Listens on the control socket, handing every request to `handler`
+    /// together with the sender for its reply. Must be called within a
+    /// Tokio runtime.
+    #[cfg(unix)]
+    pub fn start<F>(handler: F) -> Result<Self, String>
+    where
+        F: Fn(ControlRequest, oneshot::Sender<ControlReply>) + Send + Sync + 'static,
+    {
+        use std::sync::Arc;
+        use tokio::net::UnixListener;
+
+        let path = socket_path()?;
+        if path.exists() {
+            // A socket nobody accepts on was left behind by a crashed player
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err("another player is already listening".to_string());
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        let listener = UnixListener::bind(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        log::info!("Listening for control requests on {}", path.display());
+
+        let handler = Arc::new(handler);
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = Arc::clone(&handler);
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, handler.as_ref()).await {
+                        log::debug!("Control connection failed: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { path, task })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start<F>(_handler: F) -> Result<Self, String>
+    where
+        F: Fn(ControlRequest, oneshot::Sender<ControlReply>) + Send + Sync + 'static,
+    {
+        Err("remote control is only supported on Unix".to_string())
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Answers the single request sent over `stream`.
+#[cfg(unix)]
+async fn serve<F>(stream: tokio::net::UnixStream, handler: &F) -> Result<(), String>
+where
+    F: Fn(ControlRequest, oneshot::Sender<ControlReply>),
+{
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).await.map_err(|e| e.to_string())?;
+
+    let reply = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => {
+            let (tx, rx) = oneshot::channel();
+            handler(request, tx);
+            rx.await.unwrap_or_else(|_| ControlReply::Error("The player is shutting down".to_string()))
+        }
+        Err(e) => ControlReply::Error(format!("Invalid request: {}", e)),
+    };
+
+    let mut response = serde_json::to_string(&reply).map_err(|e| e.to_string())?;
+    response.push('\n');
+    reader.into_inner().write_all(response.as_bytes()).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_format() {
+        let request = ControlRequest::Channel("drone zone".to_string());
+        assert_eq!(serde_json::to_string(&request).unwrap(), r#"{"channel":"drone zone"}"#);
+
+        let reply: ControlReply = serde_json::from_str(r#"{"error":"No channel matches 'x'"}"#).unwrap();
+        assert_eq!(Result::from(reply), Err::<String, _>("No channel matches 'x'".to_string()));
+    }
+}
@@ -0,0 +1,253 @@
+//! Startup self-test diagnostics (`soma-player doctor`).
+//!
+//! Runs a series of independent checks covering the audio output, network
+//! access to the SomaFM API and streams, configuration validity, terminal
+//! capabilities, and filesystem permissions, then prints a readable report.
+
+use std::fs;
+use std::path::Path;
+
+use crate::api::ApiClient;
+use crate::audio::device::{default_output_device, negotiate_output};
+use crate::config::{schema, AppConfig};
+use crate::logging::get_log_directory;
+
+/// Outcome of a single diagnostic check
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckStatus {
+    /// Everything works as expected
+    Ok,
+    /// Usable, but something may degrade the experience
+    Warning,
+    /// The feature is not available
+    Failed,
+}
+
+/// A single line of the diagnostic report
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self { name, status, detail: detail.into() }
+    }
+}
+
+/// Runs every diagnostic check and returns the results in report order.
+pub async fn run_diagnostics(api: &ApiClient) -> Vec<CheckResult> {
+    let mut results = vec![check_audio_output()];
+    results.extend(check_network(api).await);
+    results.push(check_config());
+    results.extend(check_terminal());
+    results.push(check_config_dir_writable());
+    results.push(check_log_dir_writable());
+    results
+}
+
+/// Prints a diagnostic report and returns whether every check passed
+/// without failures.
+pub fn print_report(results: &[CheckResult]) -> bool {
+    println!("SomaFM Player diagnostics (v{})\n", env!("CARGO_PKG_VERSION"));
+
+    let width = results.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    for result in results {
+        let marker = match result.status {
+            CheckStatus::Ok => "[ OK ]",
+            CheckStatus::Warning => "[WARN]",
+            CheckStatus::Failed => "[FAIL]",
+        };
+        println!("{} {:<width$}  {}", marker, result.name, result.detail, width = width);
+    }
+
+    let failures = results.iter().filter(|r| r.status == CheckStatus::Failed).count();
+    let warnings = results.iter().filter(|r| r.status == CheckStatus::Warning).count();
+    println!();
+    if failures == 0 && warnings == 0 {
+        println!("All checks passed.");
+    } else {
+        println!("{} failed, {} warning(s).", failures, warnings);
+    }
+
+    failures == 0
+}
+
+fn check_audio_output() -> CheckResult {
+    match rodio::OutputStream::try_default() {
+        Ok(_) => CheckResult::new("Audio output", CheckStatus::Ok, format!("default output device opened, {}", stream_format_support())),
+        Err(e) => CheckResult::new("Audio output", CheckStatus::Failed, e.to_string()),
+    }
+}
+
+/// Whether the default device plays SomaFM's 44.1 kHz stereo streams
+/// without resampling.
+fn stream_format_support() -> String {
+    use rodio::DeviceTrait;
+
+    let Some((device, _)) = default_output_device() else {
+        return "no default device".to_string();
+    };
+    let supported: Vec<_> = device.supported_output_configs().map(|configs| configs.collect()).unwrap_or_default();
+    match negotiate_output(&supported, device.default_output_config().ok(), 2, 44_100) {
+        Some(config) if config.channels() == 2 && config.sample_rate().0 == 44_100 => {
+            "44.1 kHz stereo played without resampling".to_string()
+        }
+        Some(config) => format!(
+            "44.1 kHz stereo resampled to {} channels at {} Hz",
+            config.channels(),
+            config.sample_rate().0
+        ),
+        None => "no supported output configuration".to_string(),
+    }
+}
+
+async fn check_network(api: &ApiClient) -> Vec<CheckResult> {
+    let channels = match api.fetch_channels().await {
+        Ok(channels) => channels,
+        Err(e) => {
+            return vec![
+                CheckResult::new("SomaFM API", CheckStatus::Failed, e.to_string()),
+                CheckResult::new("Stream", CheckStatus::Failed, "skipped, channel list unavailable"),
+            ];
+        }
+    };
+
+    let listing = CheckResult::new("SomaFM API", CheckStatus::Ok, format!("{} channels available", channels.len()));
+    let stream = match channels.first() {
+        Some(channel) => match api.check_channel_stream(channel).await {
+            Ok(latency) => CheckResult::new(
+                "Stream",
+                CheckStatus::Ok,
+                format!("{} responded in {} ms", channel.id, latency.as_millis()),
+            ),
+            Err(e) => CheckResult::new("Stream", CheckStatus::Failed, format!("{}: {}", channel.id, e)),
+        },
+        None => CheckResult::new("Stream", CheckStatus::Warning, "API returned no channels"),
+    };
+
+    vec![listing, stream]
+}
+
+fn check_config() -> CheckResult {
+    let path = match AppConfig::config_path() {
+        Ok(path) => path,
+        Err(e) => return CheckResult::new("Config file", CheckStatus::Failed, e.to_string()),
+    };
+
+    if !path.exists() {
+        return CheckResult::new(
+            "Config file",
+            CheckStatus::Ok,
+            format!("{} not created yet, defaults will be used", path.display()),
+        );
+    }
+
+    let issues = match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|contents| schema::check(&contents)) {
+        Ok(issues) => issues,
+        Err(e) => return CheckResult::new("Config file", CheckStatus::Failed, format!("{}: {}", path.display(), e)),
+    };
+    if issues.is_empty() {
+        CheckResult::new("Config file", CheckStatus::Ok, format!("{} is valid", path.display()))
+    } else {
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        CheckResult::new("Config file", CheckStatus::Warning, format!("{}: {}", path.display(), issues.join("; ")))
+    }
+}
+
+fn check_terminal() -> Vec<CheckResult> {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    let color = if colorterm == "truecolor" || colorterm == "24bit" {
+        CheckResult::new("Terminal colors", CheckStatus::Ok, "truecolor")
+    } else if term.contains("256color") {
+        CheckResult::new("Terminal colors", CheckStatus::Warning, "256 colors, spectrum gradient will be approximated")
+    } else if term.is_empty() || term == "dumb" {
+        CheckResult::new("Terminal colors", CheckStatus::Failed, "TERM is unset or dumb")
+    } else {
+        CheckResult::new("Terminal colors", CheckStatus::Warning, format!("basic colors only (TERM={})", term))
+    };
+
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let unicode = if locale.to_uppercase().contains("UTF-8") || locale.to_uppercase().contains("UTF8") {
+        CheckResult::new("Unicode", CheckStatus::Ok, format!("locale {}", locale))
+    } else {
+        CheckResult::new("Unicode", CheckStatus::Warning, "locale is not UTF-8, symbols may render incorrectly")
+    };
+
+    let size = match crossterm::terminal::size() {
+        Ok((cols, rows)) if cols >= 80 && rows >= 24 => {
+            CheckResult::new("Terminal size", CheckStatus::Ok, format!("{}x{}", cols, rows))
+        }
+        Ok((cols, rows)) => CheckResult::new(
+            "Terminal size",
+            CheckStatus::Warning,
+            format!("{}x{}, at least 80x24 is recommended", cols, rows),
+        ),
+        Err(e) => CheckResult::new("Terminal size", CheckStatus::Failed, e.to_string()),
+    };
+
+    vec![color, unicode, size]
+}
+
+fn check_config_dir_writable() -> CheckResult {
+    match AppConfig::config_path() {
+        Ok(path) => match path.parent() {
+            Some(dir) => check_writable("Config directory", dir),
+            None => CheckResult::new("Config directory", CheckStatus::Failed, "invalid config path"),
+        },
+        Err(e) => CheckResult::new("Config directory", CheckStatus::Failed, e.to_string()),
+    }
+}
+
+fn check_log_dir_writable() -> CheckResult {
+    match get_log_directory() {
+        Ok(dir) => check_writable("Log directory", &dir),
+        Err(e) => CheckResult::new("Log directory", CheckStatus::Failed, e.to_string()),
+    }
+}
+
+/// Verifies that a file can be created and removed inside `dir`.
+fn check_writable(name: &'static str, dir: &Path) -> CheckResult {
+    let probe = dir.join(format!(".doctor-{}", std::process::id()));
+    match fs::write(&probe, b"ok").and_then(|_| fs::remove_file(&probe)) {
+        Ok(()) => CheckResult::new(name, CheckStatus::Ok, format!("{} is writable", dir.display())),
+        Err(e) => CheckResult::new(name, CheckStatus::Failed, format!("{}: {}", dir.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_writable() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = check_writable("Temp", temp_dir.path());
+        assert_eq!(result.status, CheckStatus::Ok);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        let missing = temp_dir.path().join("missing");
+        let result = check_writable("Missing", &missing);
+        assert_eq!(result.status, CheckStatus::Failed);
+    }
+
+    #[test]
+    fn test_print_report_success() {
+        let results = vec![
+            CheckResult::new("A", CheckStatus::Ok, "fine"),
+            CheckResult::new("B", CheckStatus::Warning, "meh"),
+        ];
+        assert!(print_report(&results));
+
+        let results = vec![CheckResult::new("C", CheckStatus::Failed, "broken")];
+        assert!(!print_report(&results));
+    }
+}
@@ -0,0 +1,357 @@
+//! Lowering the volume while a call or other system event is active.
+//!
+//! Ducking is driven by two kinds of triggers from the `[ducking]` section:
+//! lines written to a named pipe (`duck` and `restore`), and lines printed
+//! by a long-running command such as `dbus-monitor` that match the
+//! configured patterns. The volume fades to the ducked level and back over
+//! `ramp_ms` instead of jumping.
+//!
+//! # Examples
+//!
+//! With `pipe = "/tmp/soma-duck"`:
+//!
+//! ```sh
+//! echo duck > /tmp/soma-duck
+//! echo restore > /tmp/soma-duck
+//! ```
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use regex::Regex;
+
+use crate::config::DuckingConfig;
+
+/// A trigger telling the player to duck or restore the volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuckSignal {
+    Duck,
+    Restore,
+}
+
+impl DuckSignal {
+    /// Parses a line written to the ducking pipe.
+    pub fn parse(line: &str) -> Option<Self> {
+        match line.trim().to_lowercase().as_str() {
+            "duck" | "on" => Some(DuckSignal::Duck),
+            "restore" | "unduck" | "off" => Some(DuckSignal::Restore),
+            _ => None,
+        }
+    }
+}
+
+/// Matches output lines of the trigger command.
+#[derive(Debug, Clone)]
+struct LineMatcher {
+    duck: Regex,
+    restore: Regex,
+}
+
+impl LineMatcher {
+    fn new(config: &DuckingConfig) -> Result<Self, String> {
+        let compile = |name: &str, pattern: &Option<String>| {
+            let pattern = pattern
+                .as_deref()
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| format!("`{}` is required with `command`", name))?;
+            Regex::new(pattern).map_err(|e| format!("Invalid {} '{}': {}", name, pattern, e))
+        };
+        Ok(Self {
+            duck: compile("duck_pattern", &config.duck_pattern)?,
+            restore: compile("restore_pattern", &config.restore_pattern)?,
+        })
+    }
+
+    fn signal(&self, line: &str) -> Option<DuckSignal> {
+        if self.duck.is_match(line) {
+            Some(DuckSignal::Duck)
+        } else if self.restore.is_match(line) {
+            Some(DuckSignal::Restore)
+        } else {
+            None
+        }
+    }
+}
+
+/// A linear fade between two volume factors.
+#[derive(Debug, Clone, Copy)]
+struct Ramp {
+    from: f32,
+    to: f32,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Ramp {
+    fn value_at(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= self.duration {
+            return self.to;
+        }
+        let progress = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        self.from + (self.to - self.from) * progress
+    }
+}
+
+/// Listens for ducking triggers and computes the current volume factor.
+pub struct Ducker {
+    level: f32,
+    ramp_duration: Duration,
+    ramp: Ramp,
+    ducked: bool,
+    signals: mpsc::Receiver<DuckSignal>,
+    command: Option<Child>,
+}
+
+impl Ducker {
+    /// Starts the configured triggers, or returns `None` when none is
+    /// configured or none could be started.
+    pub fn start(config: &DuckingConfig) -> Option<Self> {
+        let pipe = config.pipe.as_deref().filter(|p| !p.trim().is_empty());
+        let command = config.command.as_deref().filter(|c| !c.trim().is_empty());
+        if pipe.is_none() && command.is_none() {
+            return None;
+        }
+
+        let (tx, signals) = mpsc::channel();
+        let mut started = false;
+
+        if let Some(pipe) = pipe {
+            match watch_pipe(pipe, tx.clone()) {
+                Ok(()) => {
+                    info!("Ducking on messages to {}", pipe);
+                    started = true;
+                }
+                Err(e) => warn!("Ducking pipe disabled: {}", e),
+            }
+        }
+
+        let command = command.and_then(|command| {
+            match LineMatcher::new(config).and_then(|matcher| watch_command(command, matcher, tx)) {
+                Ok(child) => {
+                    info!("Ducking on output of `{}`", command);
+                    started = true;
+                    Some(child)
+                }
+                Err(e) => {
+                    warn!("Ducking command disabled: {}", e);
+                    None
+                }
+            }
+        });
+
+        started.then(|| Self::new(config, signals, command))
+    }
+
+    fn new(config: &DuckingConfig, signals: mpsc::Receiver<DuckSignal>, command: Option<Child>) -> Self {
+        Self {
+            level: config.level.min(100) as f32 / 100.0,
+            ramp_duration: Duration::from_millis(config.ramp_ms),
+            ramp: Ramp {
+                from: 1.0,
+                to: 1.0,
+                started: Instant::now(),
+                duration: Duration::ZERO,
+            },
+            ducked: false,
+            signals,
+            command,
+        }
+    }
+
+    /// Whether the volume is ducked or fading towards it
+    pub fn is_ducked(&self) -> bool {
+        self.ducked
+    }
+
+    /// Applies pending triggers and returns the factor (0.0-1.0) to scale
+    /// the volume by right now.
+    pub fn factor(&mut self) -> f32 {
+        self.factor_at(Instant::now())
+    }
+
+    fn factor_at(&mut self, now: Instant) -> f32 {
+        while let Ok(signal) = self.signals.try_recv() {
+            let ducked = signal == DuckSignal::Duck;
+            if ducked == self.ducked {
+                continue;
+            }
+            debug!("Ducking signal: {:?}", signal);
+            self.ducked = ducked;
+            // Fade from wherever a previous fade got to
+            self.ramp = Ramp {
+                from: self.ramp.value_at(now),
+                to: if ducked { self.level } else { 1.0 },
+                started: now,
+                duration: self.ramp_duration,
+            };
+        }
+        self.ramp.value_at(now)
+    }
+}
+
+impl Drop for Ducker {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.command {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Creates the named pipe if needed and reads signals from it on a
+/// background thread, reopening it after every writer closes it.
+#[cfg(unix)]
+fn watch_pipe(path: &str, tx: mpsc::Sender<DuckSignal>) -> Result<(), String> {
+    let path = std::path::PathBuf::from(path);
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+        }
+        let status = Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .map_err(|e| format!("mkfifo: {}", e))?;
+        if !status.success() {
+            return Err(format!("mkfifo {} failed", path.display()));
+        }
+    }
+
+    std::thread::spawn(move || loop {
+        // Opening blocks until a writer connects
+        let file = match std::fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Ducking pipe {}: {}", path.display(), e);
+                return;
+            }
+        };
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            match DuckSignal::parse(&line) {
+                Some(signal) => {
+                    if tx.send(signal).is_err() {
+                        return;
+                    }
+                }
+                None => debug!("Ignoring ducking message: {}", line),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn watch_pipe(_path: &str, _tx: mpsc::Sender<DuckSignal>) -> Result<(), String> {
+    Err("named pipes are only supported on Unix".to_string())
+}
+
+/// Runs `command` and turns its matching output lines into signals on a
+/// background thread.
+fn watch_command(command: &str, matcher: LineMatcher, tx: mpsc::Sender<DuckSignal>) -> Result<Child, String> {
+    let mut process = if cfg!(target_os = "windows") {
+        let mut process = Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    };
+
+    let mut child = process
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("{}: {}", command, e))?;
+    let stdout = child.stdout.take().ok_or("no output to read")?;
+
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(signal) = matcher.signal(&line)
+                && tx.send(signal).is_err()
+            {
+                return;
+            }
+        }
+        debug!("Ducking command exited");
+    });
+    Ok(child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ducker() -> (Ducker, mpsc::Sender<DuckSignal>) {
+        let (tx, rx) = mpsc::channel();
+        let config = DuckingConfig {
+            level: 20,
+            ramp_ms: 1000,
+            ..Default::default()
+        };
+        (Ducker::new(&config, rx, None), tx)
+    }
+
+    #[test]
+    fn test_parse_pipe_messages() {
+        assert_eq!(DuckSignal::parse(" Duck\n"), Some(DuckSignal::Duck));
+        assert_eq!(DuckSignal::parse("unduck"), Some(DuckSignal::Restore));
+        assert_eq!(DuckSignal::parse("restore"), Some(DuckSignal::Restore));
+        assert_eq!(DuckSignal::parse("louder"), None);
+    }
+
+    #[test]
+    fn test_command_line_matching() {
+        let config = DuckingConfig {
+            command: Some("dbus-monitor".to_string()),
+            duck_pattern: Some("CallStarted".to_string()),
+            restore_pattern: Some("CallEnded".to_string()),
+            ..Default::default()
+        };
+        let matcher = LineMatcher::new(&config).unwrap();
+        assert_eq!(matcher.signal("member=CallStarted"), Some(DuckSignal::Duck));
+        assert_eq!(matcher.signal("member=CallEnded"), Some(DuckSignal::Restore));
+        assert_eq!(matcher.signal("member=Ping"), None);
+
+        let missing = DuckingConfig { restore_pattern: None, ..config };
+        assert!(LineMatcher::new(&missing).is_err());
+    }
+
+    #[test]
+    fn test_ramps_down_and_back_up() {
+        let (mut ducker, tx) = ducker();
+        let start = Instant::now();
+        assert_eq!(ducker.factor_at(start), 1.0);
+
+        tx.send(DuckSignal::Duck).unwrap();
+        assert_eq!(ducker.factor_at(start), 1.0);
+        assert!(ducker.is_ducked());
+        let halfway = ducker.factor_at(start + Duration::from_millis(500));
+        assert!((halfway - 0.6).abs() < 0.01);
+        assert_eq!(ducker.factor_at(start + Duration::from_secs(2)), 0.2);
+
+        tx.send(DuckSignal::Restore).unwrap();
+        let later = start + Duration::from_secs(2);
+        assert_eq!(ducker.factor_at(later), 0.2);
+        assert_eq!(ducker.factor_at(later + Duration::from_secs(1)), 1.0);
+        assert!(!ducker.is_ducked());
+    }
+
+    #[test]
+    fn test_restore_mid_ramp_starts_from_current_level() {
+        let (mut ducker, tx) = ducker();
+        let start = Instant::now();
+        tx.send(DuckSignal::Duck).unwrap();
+        ducker.factor_at(start);
+
+        tx.send(DuckSignal::Restore).unwrap();
+        let reversed = ducker.factor_at(start + Duration::from_millis(500));
+        assert!((reversed - 0.6).abs() < 0.01);
+        let value = ducker.factor_at(start + Duration::from_millis(1000));
+        assert!((value - 0.8).abs() < 0.01);
+    }
+}
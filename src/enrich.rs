@@ -0,0 +1,402 @@
+//! Extra details about the playing track from other sources.
+//!
+//! Each source is a [`MetadataEnricher`]: given the artist and title it
+//! looks up whatever it knows, such as the album, genres or lyrics. The
+//! [`Enricher`] runs the sources chosen in `[enrichment]` one after another
+//! on a background task whenever the track changes, merges what they found
+//! and caches the result, so a track coming around again costs no request.
+//! Adding a source means implementing the trait and listing it in
+//! [`EnrichmentSource`]; neither the audio nor the UI code changes.
+//!
+//! Built in are [`MusicBrainz`] (album, year and recording ID),
+//! [`ArtistGenres`] (the artist's most used MusicBrainz tags) and
+//! [`Lyrics`] (lyrics.ovh).
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use log::{debug, warn};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::config::{EnrichmentConfig, EnrichmentSource};
+use crate::models::TrackInfo;
+
+/// Per-request timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tracks whose details are kept
+const CACHE_SIZE: usize = 256;
+
+/// Lowest MusicBrainz search score accepted as the same track
+const MIN_SCORE: u32 = 90;
+
+/// Genres kept per artist
+const MAX_GENRES: usize = 3;
+
+/// MusicBrainz asks clients to identify themselves
+const USER_AGENT: &str = concat!("soma-player/", env!("CARGO_PKG_VERSION"), " ( https://github.com/mpuccini/soma-play )");
+
+/// What the sources found about a track.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Enrichment {
+    pub album: Option<String>,
+    /// Year of the first release
+    pub year: Option<String>,
+    pub genres: Vec<String>,
+    /// MusicBrainz recording ID
+    pub recording_id: Option<String>,
+    pub lyrics: Option<String>,
+}
+
+impl Enrichment {
+    /// Whether nothing was found.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fills in what is still missing from `other`; genres are combined.
+    pub fn merge(&mut self, other: Enrichment) {
+        self.album = self.album.take().or(other.album);
+        self.year = self.year.take().or(other.year);
+        self.recording_id = self.recording_id.take().or(other.recording_id);
+        self.lyrics = self.lyrics.take().or(other.lyrics);
+        for genre in other.genres {
+            if !self.genres.iter().any(|known| known.eq_ignore_ascii_case(&genre)) {
+                self.genres.push(genre);
+            }
+        }
+    }
+
+    /// One line for the playing screen, e.g. `Moon Safari (1998) | trip
+    /// hop, downtempo`, or `None` without an album or genres.
+    pub fn summary(&self) -> Option<String> {
+        let album = self.album.as_ref().map(|album| match &self.year {
+            Some(year) => format!("{} ({})", album, year),
+            None => album.clone(),
+        });
+        let genres = (!self.genres.is_empty()).then(|| self.genres.join(", "));
+        let parts: Vec<String> = album.into_iter().chain(genres).collect();
+        (!parts.is_empty()).then(|| parts.join(" | "))
+    }
+}
+
+/// Future returned by [`MetadataEnricher::enrich`]
+pub type EnrichFuture<'a> = Pin<Box<dyn Future<Output = Result<Enrichment, String>> + Send + 'a>>;
+
+/// A source of details about tracks.
+pub trait MetadataEnricher: Send + Sync {
+    /// Name used in log messages
+    fn name(&self) -> &str;
+
+    /// Looks up `track`. Finding nothing is an empty [`Enrichment`], not an
+    /// error.
+    fn enrich<'a>(&'a self, client: &'a reqwest::Client, track: &'a TrackInfo) -> EnrichFuture<'a>;
+}
+
+impl EnrichmentSource {
+    fn enricher(self) -> Box<dyn MetadataEnricher> {
+        match self {
+            EnrichmentSource::MusicBrainz => Box::new(MusicBrainz),
+            EnrichmentSource::Genres => Box::new(ArtistGenres),
+            EnrichmentSource::Lyrics => Box::new(Lyrics),
+        }
+    }
+}
+
+/// Quotes `value` for a MusicBrainz (Lucene) search.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Album, first release year and recording ID from a MusicBrainz
+/// recording search.
+pub struct MusicBrainz;
+
+#[derive(Deserialize)]
+struct RecordingSearch {
+    #[serde(default)]
+    recordings: Vec<Recording>,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    id: String,
+    #[serde(default)]
+    score: u32,
+    #[serde(rename = "first-release-date")]
+    first_release_date: Option<String>,
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    title: String,
+}
+
+fn parse_recordings(json: &str) -> Result<Enrichment, String> {
+    let search: RecordingSearch = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let Some(recording) = search.recordings.into_iter().find(|r| r.score >= MIN_SCORE) else {
+        return Ok(Enrichment::default());
+    };
+    Ok(Enrichment {
+        album: recording.releases.into_iter().next().map(|release| release.title),
+        year: recording
+            .first_release_date
+            .and_then(|date| date.get(..4).map(str::to_string))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit())),
+        recording_id: Some(recording.id),
+        ..Enrichment::default()
+    })
+}
+
+impl MetadataEnricher for MusicBrainz {
+    fn name(&self) -> &str {
+        "MusicBrainz"
+    }
+
+    fn enrich<'a>(&'a self, client: &'a reqwest::Client, track: &'a TrackInfo) -> EnrichFuture<'a> {
+        Box::pin(async move {
+            let query = format!("recording:{} AND artist:{}", quoted(&track.title), quoted(&track.artist));
+            let json = client
+                .get("https://musicbrainz.org/ws/2/recording")
+                .query(&[("query", query.as_str()), ("fmt", "json"), ("limit", "3")])
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| e.to_string())?
+                .text()
+                .await
+                .map_err(|e| e.to_string())?;
+            parse_recordings(&json)
+        })
+    }
+}
+
+/// Genres from the tags MusicBrainz users gave the artist.
+pub struct ArtistGenres;
+
+#[derive(Deserialize)]
+struct ArtistSearch {
+    #[serde(default)]
+    artists: Vec<Artist>,
+}
+
+#[derive(Deserialize)]
+struct Artist {
+    #[serde(default)]
+    score: u32,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    name: String,
+    #[serde(default)]
+    count: i64,
+}
+
+fn parse_artists(json: &str) -> Result<Enrichment, String> {
+    let search: ArtistSearch = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let Some(mut artist) = search.artists.into_iter().find(|a| a.score >= MIN_SCORE) else {
+        return Ok(Enrichment::default());
+    };
+    artist.tags.sort_by_key(|tag| std::cmp::Reverse(tag.count));
+    Ok(Enrichment {
+        genres: artist.tags.into_iter().filter(|tag| tag.count > 0).take(MAX_GENRES).map(|tag| tag.name).collect(),
+        ..Enrichment::default()
+    })
+}
+
+impl MetadataEnricher for ArtistGenres {
+    fn name(&self) -> &str {
+        "MusicBrainz genres"
+    }
+
+    fn enrich<'a>(&'a self, client: &'a reqwest::Client, track: &'a TrackInfo) -> EnrichFuture<'a> {
+        Box::pin(async move {
+            let query = format!("artist:{}", quoted(&track.artist));
+            let json = client
+                .get("https://musicbrainz.org/ws/2/artist")
+                .query(&[("query", query.as_str()), ("fmt", "json"), ("limit", "1")])
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| e.to_string())?
+                .text()
+                .await
+                .map_err(|e| e.to_string())?;
+            parse_artists(&json)
+        })
+    }
+}
+
+/// Lyrics from lyrics.ovh.
+pub struct Lyrics;
+
+#[derive(Deserialize)]
+struct LyricsResponse {
+    #[serde(default)]
+    lyrics: String,
+}
+
+impl MetadataEnricher for Lyrics {
+    fn name(&self) -> &str {
+        "lyrics.ovh"
+    }
+
+    fn enrich<'a>(&'a self, client: &'a reqwest::Client, track: &'a TrackInfo) -> EnrichFuture<'a> {
+        Box::pin(async move {
+            let mut url = reqwest::Url::parse("https://api.lyrics.ovh/v1/").map_err(|e| e.to_string())?;
+            url.path_segments_mut()
+                .map_err(|_| "Invalid lyrics URL".to_string())?
+                .pop_if_empty()
+                .extend([track.artist.as_str(), track.title.as_str()]);
+            let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(Enrichment::default());
+            }
+            let response: LyricsResponse = response
+                .error_for_status()
+                .map_err(|e| e.to_string())?
+                .json()
+                .await
+                .map_err(|e| e.to_string())?;
+            let lyrics = response.lyrics.trim().replace("\r\n", "\n");
+            Ok(Enrichment {
+                lyrics: (!lyrics.is_empty()).then_some(lyrics),
+                ..Enrichment::default()
+            })
+        })
+    }
+}
+
+/// Tracks looked up recently, the oldest dropped first.
+#[derive(Default)]
+struct EnrichmentCache {
+    entries: HashMap<(String, String), Enrichment>,
+    order: VecDeque<(String, String)>,
+}
+
+impl EnrichmentCache {
+    fn key(track: &TrackInfo) -> (String, String) {
+        (track.artist.to_lowercase(), track.title.to_lowercase())
+    }
+
+    fn get(&self, track: &TrackInfo) -> Option<&Enrichment> {
+        self.entries.get(&Self::key(track))
+    }
+
+    fn insert(&mut self, track: &TrackInfo, enrichment: Enrichment) {
+        let key = Self::key(track);
+        if self.entries.insert(key.clone(), enrichment).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > CACHE_SIZE {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Looks up tracks with the configured sources on a background task.
+pub struct Enricher {
+    requests: mpsc::UnboundedSender<TrackInfo>,
+}
+
+impl Enricher {
+    /// Starts the enrichment task, handing each track with what was found
+    /// about it to `on_update`. Returns `None` when no sources are
+    /// configured. Must be called within a Tokio runtime.
+    pub fn start<F>(config: &EnrichmentConfig, on_update: F) -> Option<Self>
+    where
+        F: Fn(TrackInfo, Enrichment) + Send + 'static,
+    {
+        let mut sources = config.sources.clone();
+        sources.dedup();
+        let enrichers: Vec<Box<dyn MetadataEnricher>> = sources.into_iter().map(EnrichmentSource::enricher).collect();
+        if enrichers.is_empty() {
+            return None;
+        }
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(USER_AGENT)
+            .build()
+            .map_err(|e| warn!("Track enrichment disabled: {}", e))
+            .ok()?;
+
+        let (requests, mut rx) = mpsc::unbounded_channel::<TrackInfo>();
+        tokio::spawn(async move {
+            let mut cache = EnrichmentCache::default();
+            while let Some(mut track) = rx.recv().await {
+                // Only the latest track matters after a burst of changes
+                while let Ok(newer) = rx.try_recv() {
+                    track = newer;
+                }
+                if let Some(enrichment) = cache.get(&track) {
+                    on_update(track, enrichment.clone());
+                    continue;
+                }
+                let mut enrichment = Enrichment::default();
+                for enricher in &enrichers {
+                    match enricher.enrich(&client, &track).await {
+                        Ok(found) => enrichment.merge(found),
+                        Err(e) => debug!("{} lookup of {} - {} failed: {}", enricher.name(), track.artist, track.title, e),
+                    }
+                }
+                cache.insert(&track, enrichment.clone());
+                on_update(track, enrichment);
+            }
+        });
+
+        Some(Self { requests })
+    }
+
+    /// Asks for details about `track`. Tracks without an artist are skipped.
+    pub fn lookup(&self, track: &TrackInfo) {
+        if track.artist == TrackInfo::default().artist || track.title.trim().is_empty() {
+            return;
+        }
+        let _ = self.requests.send(track.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_merge_lookups() {
+        let recordings = r#"{"recordings": [
+            {"id": "b1a9c0e9", "score": 100, "title": "La femme d'argent", "first-release-date": "1998-01-16",
+             "releases": [{"title": "Moon Safari"}, {"title": "Moon Safari (Deluxe)"}]}
+        ]}"#;
+        let artists = r#"{"artists": [{"score": 100, "tags": [
+            {"name": "electronic", "count": 4}, {"name": "downtempo", "count": 9},
+            {"name": "french", "count": 2}, {"name": "ambient", "count": 1}, {"name": "spam", "count": -1}
+        ]}]}"#;
+
+        let mut enrichment = parse_recordings(recordings).unwrap();
+        assert_eq!(enrichment.album.as_deref(), Some("Moon Safari"));
+        assert_eq!(enrichment.year.as_deref(), Some("1998"));
+        enrichment.merge(parse_artists(artists).unwrap());
+        enrichment.merge(Enrichment { album: Some("Other".to_string()), genres: vec!["Electronic".to_string()], ..Enrichment::default() });
+
+        assert_eq!(enrichment.summary().as_deref(), Some("Moon Safari (1998) | downtempo, electronic, french"));
+        assert!(parse_recordings(r#"{"recordings": [{"id": "x", "score": 40}]}"#).unwrap().is_empty());
+        assert_eq!(quoted(r#"Say "Hi""#), r#""Say \"Hi\"""#);
+
+        let mut cache = EnrichmentCache::default();
+        let track = |n: usize| TrackInfo { artist: "Air".to_string(), title: format!("Track {}", n) };
+        for n in 0..=CACHE_SIZE {
+            cache.insert(&track(n), enrichment.clone());
+        }
+        assert!(cache.get(&track(0)).is_none());
+        assert!(cache.get(&TrackInfo { artist: "AIR".to_string(), title: "track 1".to_string() }).is_some());
+    }
+}
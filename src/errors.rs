@@ -15,6 +15,8 @@ pub enum PlayerError {
     IO(std::io::Error),
     /// Parsing errors
     Parse(String),
+    /// A panic caught on the audio thread
+    Crashed(String),
 }
 
 #[derive(Debug)]
@@ -29,6 +31,8 @@ pub enum NetworkError {
     InvalidUrl(String),
     /// Network timeout
     Timeout,
+    /// The media renderer cast to refused a request
+    Renderer(String),
 }
 
 #[derive(Debug)]
@@ -43,6 +47,8 @@ pub enum AudioError {
     DeviceUnavailable,
     /// Unsupported audio format
     UnsupportedFormat(String),
+    /// The stream kept playing silence through every restart
+    NoAudio(String),
 }
 
 #[derive(Debug)]
@@ -78,6 +84,7 @@ impl fmt::Display for PlayerError {
             PlayerError::UI(e) => write!(f, "UI error: {}", e),
             PlayerError::IO(e) => write!(f, "I/O error: {}", e),
             PlayerError::Parse(msg) => write!(f, "Parse error: {}", msg),
+            PlayerError::Crashed(msg) => write!(f, "Playback crashed: {}", msg),
         }
     }
 }
@@ -90,6 +97,7 @@ impl fmt::Display for NetworkError {
             NetworkError::StreamConnection(msg) => write!(f, "Failed to connect to stream: {}", msg),
             NetworkError::InvalidUrl(url) => write!(f, "Invalid URL: {}", url),
             NetworkError::Timeout => write!(f, "Network request timed out"),
+            NetworkError::Renderer(msg) => write!(f, "Media renderer error: {}", msg),
         }
     }
 }
@@ -102,6 +110,7 @@ impl fmt::Display for AudioError {
             AudioError::DecodingError(msg) => write!(f, "Audio decoding error: {}", msg),
             AudioError::DeviceUnavailable => write!(f, "Audio device is not available"),
             AudioError::UnsupportedFormat(format) => write!(f, "Unsupported audio format: {}", format),
+            AudioError::NoAudio(channel) => write!(f, "No audio from {}", channel),
         }
     }
 }
@@ -128,6 +137,78 @@ impl fmt::Display for UIError {
     }
 }
 
+impl PlayerError {
+    /// What the user can do about the error, shown below it.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            PlayerError::Network(e) => e.hint(),
+            PlayerError::Audio(e) => e.hint(),
+            PlayerError::Config(e) => e.hint(),
+            PlayerError::UI(e) => e.hint(),
+            PlayerError::IO(_) | PlayerError::Parse(_) => None,
+            PlayerError::Crashed(_) => Some(
+                "This is a bug. Press Enter to retry, and please report it with the log file \
+                 (soma-player --verbose shows where it is).",
+            ),
+        }
+    }
+}
+
+impl NetworkError {
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            NetworkError::ApiConnection(_) | NetworkError::Timeout => Some(
+                "Check your internet connection and press Enter to retry; soma-player doctor tests the connection to SomaFM.",
+            ),
+            NetworkError::ApiParse(_) => Some("The SomaFM API may have changed; check for a newer soma-player."),
+            NetworkError::StreamConnection(_) => Some(
+                "Check your internet connection and press Enter to retry, or press C to try another channel.",
+            ),
+            NetworkError::InvalidUrl(_) => Some("Check the stream URLs of the channel, e.g. in your --channels-file."),
+            NetworkError::Renderer(_) => Some(
+                "Check that the renderer is switched on and on this network; soma-player list --renderers shows the ones found.",
+            ),
+        }
+    }
+}
+
+impl AudioError {
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AudioError::OutputInit(_) | AudioError::SinkCreation(_) | AudioError::DeviceUnavailable => Some(
+                "Check that your audio output is connected and not held by another program; soma-player doctor tests it.",
+            ),
+            AudioError::DecodingError(_) => Some("Press Enter to retry, or press C to try another channel."),
+            AudioError::UnsupportedFormat(_) => Some(
+                "Pick an MP3 stream of the channel, or build soma-player with the symphonia-decoder feature to play AAC streams.",
+            ),
+            AudioError::NoAudio(_) => Some("The stream stays silent; press C to try another channel or Enter to retry."),
+        }
+    }
+}
+
+impl ConfigError {
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ConfigError::DirectoryCreation(_) | ConfigError::FileWrite(_) => {
+                Some("Check that ~/.config/soma-player exists and is writable.")
+            }
+            ConfigError::FileRead(_) | ConfigError::InvalidValue(_) | ConfigError::TomlParse(_) => Some(
+                "Fix or remove ~/.config/soma-player/config.toml; soma-player doctor checks it.",
+            ),
+        }
+    }
+}
+
+impl UIError {
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            UIError::TerminalInit(_) => Some("Run soma-player in an interactive terminal, or with --kiosk to play without one."),
+            UIError::RenderError(_) | UIError::EventError(_) => None,
+        }
+    }
+}
+
 impl std::error::Error for PlayerError {}
 impl std::error::Error for NetworkError {}
 impl std::error::Error for AudioError {}
@@ -209,6 +290,15 @@ mod tests {
         assert!(audio_err.to_string().contains("Audio error"));
     }
 
+    #[test]
+    fn test_error_hints() {
+        let device = PlayerError::Audio(AudioError::DeviceUnavailable);
+        assert!(device.hint().is_some_and(|hint| hint.contains("soma-player doctor")));
+        let renderer = PlayerError::Network(NetworkError::Renderer("refused".to_string()));
+        assert!(renderer.hint().is_some_and(|hint| hint.contains("list --renderers")));
+        assert_eq!(PlayerError::Parse("bad".to_string()).hint(), None);
+    }
+
     #[test]
     fn test_error_conversion() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
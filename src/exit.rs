@@ -0,0 +1,92 @@
+//! Summary of a session printed after the TUI closes.
+//!
+//! Once the alternate screen is gone, one line tells what was playing, for
+//! how long and how much stream data it took, followed by a line for each
+//! file the session left behind, such as the session transcript.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::models::TrackInfo;
+
+/// How a session ended.
+#[derive(Debug, Clone, Default)]
+pub struct ExitSummary {
+    /// Channel playing at the end, if any
+    pub channel: Option<String>,
+    /// Last track announced on it
+    pub track: Option<TrackInfo>,
+    /// Time spent playing, pauses left out
+    pub listened: Duration,
+    /// Stream bytes downloaded over the session
+    pub data_used: u64,
+    /// Lines printed below the summary, e.g. where files were saved
+    pub notes: Vec<String>,
+}
+
+impl ExitSummary {
+    /// The summary line, `None` when nothing was played.
+    pub fn line(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(channel) = &self.channel {
+            parts.push(channel.clone());
+        }
+        if let Some(track) = &self.track {
+            parts.push(format!("{} - {}", track.artist, track.title));
+        }
+        if !self.listened.is_zero() {
+            parts.push(format!("{} listened", listening_time(self.listened)));
+        }
+        if self.data_used > 0 {
+            parts.push(format!("{:.1} MB used", self.data_used as f64 / (1024.0 * 1024.0)));
+        }
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+impl fmt::Display for ExitSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line() {
+            writeln!(f, "{}", line)?;
+        }
+        for note in &self.notes {
+            writeln!(f, "{}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// `1h 05m`, `12m` or `40s`.
+fn listening_time(listened: Duration) -> String {
+    let secs = listened.as_secs();
+    match (secs / 3600, secs / 60 % 60) {
+        (0, 0) => format!("{}s", secs),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_summary_line() {
+        assert_eq!(ExitSummary::default().to_string(), "");
+
+        let summary = ExitSummary {
+            channel: Some("Groove Salad".to_string()),
+            track: Some(TrackInfo { artist: "Air".to_string(), title: "Alone in Kyoto".to_string() }),
+            listened: Duration::from_secs(3600 + 5 * 60 + 30),
+            data_used: 60 * 1024 * 1024 + 200 * 1024,
+            notes: vec!["Session transcript saved to /tmp/session.txt".to_string()],
+        };
+        assert_eq!(
+            summary.to_string(),
+            "Groove Salad · Air - Alone in Kyoto · 1h 05m listened · 60.2 MB used\n\
+             Session transcript saved to /tmp/session.txt\n"
+        );
+        assert_eq!(listening_time(Duration::from_secs(40)), "40s");
+        assert_eq!(listening_time(Duration::from_secs(12 * 60 + 59)), "12m");
+    }
+}
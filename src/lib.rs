@@ -14,6 +14,30 @@
 //! - [`models`] - Data structures and type definitions
 //! - [`logging`] - Logging configuration and management
 //! - [`errors`] - Error types and handling utilities
+//! - [`doctor`] - Self-test diagnostics
+//! - [`notifications`] - Desktop notifications and track-change hooks
+//! - [`media_keys`] - OS media key and now-playing integration
+//! - [`recording`] - Recording streams to disk
+//! - [`monitor`] - Background metadata monitoring of other channels
+//! - [`alerts`] - Wishlist alerts for artists and titles
+//! - [`state_file`] - JSON status file for external tools
+//! - [`now_playing`] - Now-playing export for tmux/screen status bars
+//! - [`webhook`] - Signed now-playing webhooks
+//! - [`scrobble`] - Scrobbling to ListenBrainz with an offline queue
+//! - [`sync`] - Experimental listen-along mode over the LAN
+//! - [`cast`] - Casting to UPnP/DLNA media renderers
+//! - [`ducking`] - Lowering the volume on system events
+//! - [`control`] - Remote control of a running player
+//! - [`schedule`] - Channels played at set times of day
+//! - [`service`] - Installing the player as a background service
+//! - [`report`] - Usage report built from the local log files
+//! - [`station_id`] - Ducking or skipping station IDs
+//! - [`session`] - Shareable transcript of a listening session
+//! - [`enrich`] - Album, genre and lyrics lookups for the playing track
+//! - [`startup`] - Summary of the setup a session starts with
+//! - [`archive`] - Playing back recordings in the TUI
+//! - [`bookmarks`] - Bookmarked moments of the stream
+//! - [`ratings`] - Star ratings of tracks
 //!
 //! ## Example
 //!
@@ -45,6 +69,32 @@ pub mod api;
 pub mod models;
 pub mod logging;
 pub mod errors;
+pub mod doctor;
+pub mod notifications;
+pub mod media_keys;
+pub mod recording;
+pub mod monitor;
+pub mod alerts;
+pub mod state_file;
+pub mod now_playing;
+pub mod webhook;
+pub mod scrobble;
+pub mod sync;
+pub mod cast;
+pub mod ducking;
+pub mod control;
+pub mod self_monitor;
+pub mod schedule;
+pub mod service;
+pub mod report;
+pub mod station_id;
+pub mod session;
+pub mod enrich;
+pub mod startup;
+pub mod exit;
+pub mod archive;
+pub mod bookmarks;
+pub mod ratings;
 
 pub use models::*;
 pub use errors::*;
@@ -1,16 +1,32 @@
-use std::fs;
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, NaiveDate};
+use flate2::{write::GzEncoder, Compression};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 use tracing_appender::non_blocking::WorkerGuard;
 
+/// Name of the log file being written to
+const LOG_FILE_NAME: &str = "soma-player.log";
+
+/// Prefix shared by the current and all rotated log files
+const LOG_FILE_PREFIX: &str = "soma-player.";
+
 /// Configuration for the logging system
 #[derive(Debug, Clone)]
 pub struct LogConfig {
     pub level: String,
     pub log_to_file: bool,
     pub log_to_console: bool,
+    /// Rotated files to keep besides the current one
     pub max_log_files: usize,
+    /// Size in bytes at which the current file is rotated (0 rotates daily
+    /// only)
     pub max_file_size: u64,
+    /// Total size in bytes of the rotated files to keep (0 for no bound)
+    pub max_total_size: u64,
+    /// Gzip rotated files
+    pub compress: bool,
 }
 
 impl Default for LogConfig {
@@ -21,6 +37,8 @@ impl Default for LogConfig {
             log_to_console: false, // Disabled by default to avoid TUI interference
             max_log_files: 5,
             max_file_size: 10 * 1024 * 1024, // 10MB
+            max_total_size: 50 * 1024 * 1024, // 50MB
+            compress: true,
         }
     }
 }
@@ -40,9 +58,9 @@ pub fn init_logging(config: LogConfig) -> Result<Option<WorkerGuard>, Box<dyn st
         let log_dir = get_log_directory()?;
         
         // Clean old log files
-        clean_old_logs(&log_dir, config.max_log_files)?;
+        clean_old_logs(&log_dir, config.max_log_files, config.max_total_size)?;
         
-        let file_appender = tracing_appender::rolling::daily(&log_dir, "soma-player.log");
+        let file_appender = RotatingFile::open(&log_dir, &config)?;
         let (non_blocking, file_guard) = tracing_appender::non_blocking(file_appender);
         guard = Some(file_guard);
 
@@ -81,7 +99,7 @@ pub fn init_logging(config: LogConfig) -> Result<Option<WorkerGuard>, Box<dyn st
 }
 
 /// Get the log directory path
-fn get_log_directory() -> Result<PathBuf, Box<dyn std::error::Error>> {
+pub fn get_log_directory() -> Result<PathBuf, Box<dyn std::error::Error>> {
     let home = dirs::home_dir().ok_or("Could not find home directory")?;
     let log_dir = home.join(".config").join("soma-player").join("logs");
     
@@ -91,29 +109,167 @@ fn get_log_directory() -> Result<PathBuf, Box<dyn std::error::Error>> {
     Ok(log_dir)
 }
 
-/// Clean old log files to maintain the specified limit
-fn clean_old_logs(log_dir: &PathBuf, max_files: usize) -> Result<(), Box<dyn std::error::Error>> {
+/// A log file that is rotated once it grows past the size limit or a new
+/// day begins. Rotated files are renamed after the time of rotation,
+/// optionally gzipped, and pruned to the configured count and total size.
+pub struct RotatingFile {
+    dir: PathBuf,
+    file: Option<File>,
+    size: u64,
+    day: NaiveDate,
+    max_file_size: u64,
+    max_files: usize,
+    max_total_size: u64,
+    compress: bool,
+}
+
+impl RotatingFile {
+    /// Opens the log file in `dir`, appending to what is already there.
+    pub fn open(dir: &Path, config: &LogConfig) -> io::Result<Self> {
+        let path = dir.join(LOG_FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        // A file left over from an earlier day is rotated on the first write
+        let day = metadata
+            .modified()
+            .map(|time| DateTime::<Local>::from(time).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file: Some(file),
+            size: metadata.len(),
+            day,
+            max_file_size: config.max_file_size,
+            max_files: config.max_log_files,
+            max_total_size: config.max_total_size,
+            compress: config.compress,
+        })
+    }
+
+    fn needs_rotation(&self, incoming: u64, now: &DateTime<Local>) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self.max_file_size > 0 && self.size + incoming > self.max_file_size;
+        too_big || now.date_naive() != self.day
+    }
+
+    /// Moves the current file aside and starts a new one.
+    fn rotate(&mut self, now: &DateTime<Local>) -> io::Result<()> {
+        self.file = None;
+        let current = self.dir.join(LOG_FILE_NAME);
+        let rotated = self.rotated_path(now);
+        fs::rename(&current, &rotated)?;
+
+        if self.compress
+            && let Err(e) = compress_file(&rotated)
+        {
+            eprintln!("Warning: Failed to compress log file {:?}: {}", rotated, e);
+        }
+        if let Err(e) = clean_old_logs(&self.dir, self.max_files, self.max_total_size) {
+            eprintln!("Warning: Failed to clean old log files: {}", e);
+        }
+
+        self.file = Some(OpenOptions::new().create(true).append(true).open(&current)?);
+        self.size = 0;
+        self.day = now.date_naive();
+        Ok(())
+    }
+
+    /// A free name for a file rotated at `now`. Names sort chronologically.
+    fn rotated_path(&self, now: &DateTime<Local>) -> PathBuf {
+        let stamp = now.format("%Y-%m-%dT%H-%M-%S");
+        let taken = |name: &str| {
+            self.dir.join(name).exists() || self.dir.join(format!("{}.gz", name)).exists()
+        };
+        let mut name = format!("{}{}.log", LOG_FILE_PREFIX, stamp);
+        let mut n = 1;
+        while taken(&name) {
+            name = format!("{}{}.{}.log", LOG_FILE_PREFIX, stamp, n);
+            n += 1;
+        }
+        self.dir.join(name)
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = Local::now();
+        if self.needs_rotation(buf.len() as u64, &now) {
+            self.rotate(&now)?;
+        }
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => {
+                // An earlier rotation failed to reopen the file
+                let file = OpenOptions::new().create(true).append(true).open(self.dir.join(LOG_FILE_NAME))?;
+                self.file.insert(file)
+            }
+        };
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().map_or(Ok(()), |file| file.flush())
+    }
+}
+
+/// Replaces `path` with a gzipped `<path>.gz`.
+fn compress_file(path: &Path) -> io::Result<()> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".gz");
+    let gz_path = PathBuf::from(gz_name);
+
+    let result = (|| {
+        let mut input = File::open(path)?;
+        let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?.sync_all()
+    })();
+    match result {
+        Ok(()) => fs::remove_file(path),
+        Err(e) => {
+            let _ = fs::remove_file(&gz_path);
+            Err(e)
+        }
+    }
+}
+
+/// Clean old log files, keeping the newest `max_files` rotated files as
+/// long as together they stay within `max_total_size` bytes (0 for no
+/// bound). The current log file is never removed.
+fn clean_old_logs(log_dir: &Path, max_files: usize, max_total_size: u64) -> Result<(), Box<dyn std::error::Error>> {
     let mut log_files: Vec<_> = fs::read_dir(log_dir)?
         .filter_map(|entry| entry.ok())
         .filter(|entry| {
-            entry.path().extension()
-                .and_then(|ext| ext.to_str()) == Some("log")
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(LOG_FILE_PREFIX) && name != LOG_FILE_NAME
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| {
+                let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+                (entry.path(), modified, metadata.len())
+            })
         })
         .collect();
 
     // Sort by modification time (newest first)
-    log_files.sort_by(|a, b| {
-        let a_time = a.metadata().and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
-        let b_time = b.metadata().and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
-        b_time.cmp(&a_time)
-    });
+    log_files.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
 
     // Remove excess files
-    if log_files.len() > max_files {
-        for file in log_files.iter().skip(max_files) {
-            if let Err(e) = fs::remove_file(file.path()) {
-                eprintln!("Warning: Failed to remove old log file {:?}: {}", file.path(), e);
-            }
+    let mut total_size = 0;
+    for (index, (path, _, size)) in log_files.iter().enumerate() {
+        total_size += size;
+        let over_size = max_total_size > 0 && total_size > max_total_size;
+        if (index >= max_files || over_size)
+            && let Err(e) = fs::remove_file(path)
+        {
+            eprintln!("Warning: Failed to remove old log file {:?}: {}", path, e);
         }
     }
 
@@ -139,16 +295,58 @@ mod tests {
         let log_dir = temp_dir.path().to_path_buf();
 
         // Create some test log files
+        fs::write(log_dir.join(LOG_FILE_NAME), "current log content").unwrap();
         for i in 0..7 {
-            let file_path = log_dir.join(format!("test-{}.log", i));
+            let file_path = log_dir.join(format!("soma-player.2024-01-0{}.log", i));
             fs::write(&file_path, "test log content").unwrap();
         }
 
         // Clean logs, keeping only 3
-        clean_old_logs(&log_dir, 3).unwrap();
+        clean_old_logs(&log_dir, 3, 0).unwrap();
 
-        // Count remaining files
+        // Count remaining files, the current one included
         let remaining = fs::read_dir(&log_dir).unwrap().count();
-        assert_eq!(remaining, 3);
+        assert_eq!(remaining, 4);
+
+        // 16 bytes each, so only two fit in 40
+        clean_old_logs(&log_dir, 3, 40).unwrap();
+        assert_eq!(fs::read_dir(&log_dir).unwrap().count(), 3);
+        assert!(log_dir.join(LOG_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_rotates_by_size_and_compresses() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = LogConfig {
+            max_log_files: 2,
+            max_file_size: 100,
+            max_total_size: 0,
+            compress: true,
+            ..LogConfig::default()
+        };
+        let mut file = RotatingFile::open(temp_dir.path(), &config).unwrap();
+
+        let line = [b'x'; 60];
+        for _ in 0..5 {
+            file.write_all(&line).unwrap();
+        }
+        file.flush().unwrap();
+
+        let mut rotated: Vec<_> = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name != LOG_FILE_NAME)
+            .collect();
+        rotated.sort();
+
+        // Four rotations, of which the newest two are kept
+        assert_eq!(rotated.len(), 2);
+        assert!(rotated.iter().all(|name| name.ends_with(".log.gz")));
+        assert_eq!(fs::metadata(temp_dir.path().join(LOG_FILE_NAME)).unwrap().len(), 60);
+
+        let mut contents = Vec::new();
+        let gz = File::open(temp_dir.path().join(&rotated[0])).unwrap();
+        io::Read::read_to_end(&mut flate2::read::GzDecoder::new(gz), &mut contents).unwrap();
+        assert_eq!(contents, line);
     }
 }
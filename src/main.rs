@@ -1,9 +1,12 @@
-use std::io;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
-use clap::Parser;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use clap::{Parser, Subcommand};
 use crossterm::{
-    event::{self, Event},
+    event::{self, DisableFocusChange, EnableFocusChange, Event, KeyEvent, KeyEventKind},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     execute,
 };
@@ -13,15 +16,54 @@ use ratatui::{
 };
 
 use soma_player::{
-    api::fetch_channels,
-    audio::{play_channel, PlayerCommand},
-    config::AppConfig,
-    models::{Channel, TrackInfo},
+    api::{
+        art::ArtCache,
+        bundle::{load_bundle, merge_bundle},
+        cache::{ChannelSource, NewChannels},
+        refresh::{merge_channels, ChannelRefresher},
+        ApiClient,
+    },
+    archive::{find_recordings, recording_path, Recording},
+    bookmarks::{Bookmark, Bookmarks},
+    ratings::Ratings,
+    audio::{isolation::panic_message, level::{ChannelLevels, LevelMeter, LevelMode}, limiter::LimiterControl, PlaybackControls, PlaybackOptions, PlayerUpdate, play_channel, play_file, stereo::StereoControl, PlayerCommand},
+    config::{editor, schema, AppConfig, ConfigSaver, StartScreen, SyncRole, CONFIG_SAVE_DELAY},
+    models::{export_channels, find_channel, Channel, StreamQuality, TrackInfo},
+    errors::{AudioError, PlayerError},
+    notifications::Notifier,
+    alerts::AlertMatcher,
+    recording::{parse_duration, record_channel, RecordOptions},
+    monitor::ChannelMonitor,
+    state_file::{PlayerStatus, StateFile},
+    now_playing::NowPlayingExporter,
+    webhook::WebhookPoster,
+    scrobble::Scrobbler,
+    sync::{FollowAction, ListenAlong},
+    cast::{cast_channel, discover, find_renderer, Renderer, DISCOVERY_TIMEOUT},
+    ducking::Ducker,
+    self_monitor::SelfMonitor,
+    schedule::{local_minute, Schedule},
+    station_id::StationIdFilter,
+    session::SessionLog,
+    enrich::Enricher,
+    startup::StartupSummary,
+    exit::ExitSummary,
+    control::{send_request, ControlRequest, ControlServer},
+    media_keys::{MediaKeyEvent, MediaKeys},
     ui::{
+        animation::{Animated, FrameClock},
         app::{AppState, UIState},
+        bookmarks::{BookmarkPopup, BookmarksPopup},
+        rating::RatingPopup,
         channel_list::{render_initial_channel_selection, render_channel_selection},
         player::render_playing_ui,
-        events::{handle_key_event, EventResult},
+        taskbar::{TaskbarProgress, TaskbarState},
+        title::{format_title, TerminalTitle},
+        wizard::{SetupWizard, WizardOutcome},
+        events::{handle_key_event, ApiEvent, AppEvent, EventResult, PlayerEvent},
+        macros::parse_macros,
+        quit::QuitGuard,
+        volume::VolumeKeys,
     },
 };
 
@@ -29,6 +71,7 @@ use soma_player::{
 #[derive(Parser)]
 #[command(author = "Marco Puccini <mpuccini@example.com>")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true)]
 #[command(about = "A terminal-based music player for SomaFM internet radio stations")]
 #[command(long_about = "SomaFM Player streams high-quality internet radio from SomaFM stations. \
 Features include a spectrum visualizer, channel browsing, persistent configuration, \
@@ -36,165 +79,1185 @@ and keyboard controls for an optimal terminal experience.")]
 #[command(after_help = "KEYBOARD CONTROLS:
     ↑/↓     Navigate channels
     Enter   Select channel  
+    h       Hide the selected channel (Shift+H lists hidden ones)
     C       Change channel (while playing)
     P       Pause/Resume playback
+    ←       Rewind 10 seconds (L jumps back to live)
+    →       Skip ahead 10 seconds (playing recordings)
+    ,/.     Replay the last 15 seconds / back to live
+    Shift+Q Choose the stream quality (while playing)
     +/-     Volume control
-    Q/Esc   Quit
+    [/]     Shift the balance left/right
+    M       Toggle mono downmix
+    </>     Fewer/more spectrum bands
+    E       Save a transcript of this session
+    b/B     Bookmark this moment / review bookmarks
+    r       Rate the playing track 1-5 stars
+    y       Show the lyrics (with [enrichment] sources)
+    ?       Show the keys of the current screen
+    q/Esc   Quit
 
 EXAMPLES:
     soma-player                      Start with channel selection
     soma-player -a                   Auto-start with last channel
     soma-player -c groovesalad       Play Groove Salad directly
     soma-player -c dronezone -v 75   Play Drone Zone at 75% volume
-    soma-player --list               List all available channels")]
+    soma-player -c \"groove salad\"    Pick a channel by name
+    soma-player list                 List all available channels
+    soma-player list --check         List channels and probe their streams
+    soma-player --channels-file extra.toml
+                                     Add your own stations to the channel list
+    soma-player list --export channels.csv
+                                     Save every channel and stream URL as CSV
+    soma-player doctor               Diagnose audio, network and terminal setup
+    soma-player config print-default > ~/.config/soma-player/config.toml
+                                     Start a config file with every key explained
+    soma-player stats                Summarize listening and errors from the logs
+    soma-player stats --rating 4     Also list the tracks rated 4 stars or more
+    soma-player --verbose            Print the startup summary for bug reports
+    soma-player --export-session set.txt
+                                     Write what was played to set.txt on exit
+    soma-player --sync follower      Play along with a leader on the LAN
+    soma-player --kiosk -c lush      Play unattended with the controls locked
+    soma-player --install-service    Play in the background at every login
+    soma-player --cast kitchen       Play on a UPnP/DLNA renderer
+    soma-player list --renderers     Find the renderers on the network
+    soma-player config edit          Edit the config file and check it when saved
+    soma-player ctl channel \"drone zone\"
+                                     Switch the running player to Drone Zone
+    soma-player record -c groovesalad --duration 1h -o out.mp3
+                                     Record a channel to disk without the TUI
+    soma-player --archive ~/Music/soma
+                                     Play back recordings with seeking")]
 struct Args {
+    #[command(flatten)]
+    play: PlayArgs,
+
+    /// List all available channels and exit
+    #[arg(short, long, hide = true)]
+    list: bool,
+
+    /// Write the channel list with all its metadata to a file and exit
+    #[arg(long, value_name = "FILE", hide = true)]
+    export_channels: Option<PathBuf>,
+
+    /// Probe each channel's stream when listing
+    #[arg(long, requires = "list", hide = true)]
+    check: bool,
+    
+    /// Run self-test diagnostics and exit
+    #[arg(long, hide = true)]
+    doctor: bool,
+    
+    /// List media renderers on the local network and exit
+    #[arg(long, hide = true)]
+    list_renderers: bool,
+    
+    /// Show config file location and exit
+    #[arg(long, hide = true)]
+    config: bool,
+    
+    /// Print the default configuration with every key documented and exit
+    #[arg(long, hide = true)]
+    print_default_config: bool,
+    
+    /// Print a usage report from the local log files and exit
+    #[arg(long, hide = true)]
+    report: bool,
+
+    /// Install a user service running the player in kiosk mode and exit
+    #[arg(long, help = "Write a systemd user unit (launchd agent on macOS) for background playback and exit")]
+    install_service: bool,
+
+    /// Check the live channel list against the fields the player reads
+    #[arg(long, hide = true)]
+    validate_api: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Args {
+    /// The subcommand to run. Without one, the flags from before there
+    /// were subcommands stand in for theirs, and the rest plays.
+    fn into_command(self) -> Command {
+        if let Some(command) = self.command {
+            return command;
+        }
+        if self.print_default_config {
+            return Command::Config { command: ConfigCommand::PrintDefault };
+        }
+        if self.config {
+            return Command::Config { command: ConfigCommand::Path };
+        }
+        if self.report {
+            return Command::Stats(StatsArgs::default());
+        }
+        if self.doctor {
+            return Command::Doctor;
+        }
+        if self.list || self.list_renderers || self.export_channels.is_some() {
+            return Command::List(ListArgs {
+                check: self.check,
+                renderers: self.list_renderers,
+                export: self.export_channels,
+                channels_file: self.play.channels_file,
+            });
+        }
+        Command::Play(self.play)
+    }
+}
+
+/// Options of the player itself, given to `play` or to `soma-player` alone.
+#[derive(clap::Args)]
+struct PlayArgs {
     /// Start playing immediately without showing channel selection
     #[arg(short, long, help = "Skip channel selection screen and start playing")]
     autostart: bool,
     
-    /// Set initial volume (0-100)
-    #[arg(short, long, value_name = "LEVEL", help = "Set volume level (0-100)")]
-    #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+    /// Set initial volume (0-100, up to max_volume)
+    #[arg(short, long, value_name = "LEVEL", help = "Set volume level (0-100, up to max_volume)")]
+    #[arg(value_parser = clap::value_parser!(u8).range(0..=200))]
     volume: Option<u8>,
     
-    /// Play specific channel by ID (e.g., 'groovesalad', 'dronezone')
-    #[arg(short, long, value_name = "CHANNEL_ID")]
-    #[arg(help = "Play specific channel by ID (use --list to see available channels)")]
+    /// Play specific channel by ID or name (e.g., 'groovesalad', 'drone zone')
+    #[arg(short, long, value_name = "CHANNEL")]
+    #[arg(help = "Play specific channel by ID or name (use `soma-player list` to see available channels)")]
     channel: Option<String>,
     
-    /// List all available channels and exit
-    #[arg(short, long, help = "Display all available SomaFM channels and exit")]
-    list: bool,
+    /// Add the stations listed in a JSON or TOML file to the channel list
+    #[arg(long, value_name = "FILE", help = "Add the stations from a JSON or TOML channel bundle to the SomaFM channels")]
+    channels_file: Option<PathBuf>,
+
+    /// Write a transcript of the session to a file when the player exits
+    #[arg(long, value_name = "FILE", help = "Write the channels, tracks, volume changes and errors of this session to FILE on exit (E saves it while playing)")]
+    export_session: Option<PathBuf>,
+
+    /// Cast to a UPnP/DLNA renderer instead of playing locally
+    #[arg(long, value_name = "NAME", help = "Play on the media renderer whose name contains NAME")]
+    cast: Option<String>,
+
+    /// Play recordings instead of the SomaFM channels
+    #[arg(long, value_name = "PATH", conflicts_with = "cast")]
+    #[arg(help = "List the recordings in PATH (a file or directory) instead of the channels, with a position bar and seeking")]
+    archive: Option<PathBuf>,
     
-    /// Show config file location and exit
-    #[arg(long, help = "Display configuration file path and exit")]
-    config: bool,
+    /// Listen-along role for this run
+    #[arg(long, value_name = "ROLE", help = "Experimental: lead or follow other players on the LAN")]
+    sync: Option<SyncRole>,
+    
+    /// Run unattended: lock the controls and keep reconnecting
+    #[arg(long, help = "Lock all controls, quit only on a signal and reconnect forever")]
+    kiosk: bool,
+    
+    /// Print the startup summary before the TUI starts
+    #[arg(long, help = "Print the version, platform, audio device, config and log paths before starting")]
+    verbose: bool,
 }
 
-async fn play_session_tui(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    channels: &[Channel],
-    selected_channel: &Channel,
-    track_info: Arc<Mutex<TrackInfo>>,
-    mut app: AppState,
-    config: &mut AppConfig,
-) -> Result<Option<usize>, String> {
-    let (tx, rx) = mpsc::unbounded_channel();
-    
-    // Only start audio if we're not in initial selection mode
-    let (audio_result_tx, mut audio_result_rx) = mpsc::unbounded_channel();
-    let audio_handle = if !matches!(app.ui_state, UIState::InitialChannelSelection) {
-        Some(tokio::task::spawn_blocking({
-            let selected_channel = selected_channel.clone();
-            let track_info = Arc::clone(&track_info);
-            let audio_result_tx = audio_result_tx.clone();
-            let volume = config.volume;
-            move || {
-                let rt = tokio::runtime::Runtime::new().unwrap();
-                let result = rt.block_on(play_channel(&selected_channel, track_info, rx, volume));
-                let _ = audio_result_tx.send(result);
-            }
-        }))
-    } else {
-        None
-    };
+#[derive(Subcommand)]
+enum Command {
+    /// Play, the same as running soma-player without a subcommand
+    Play(PlayArgs),
+    /// List the channels, or the media renderers on the network
+    List(ListArgs),
+    /// Record a channel to disk without the TUI or local audio output
+    Record(RecordArgs),
+    /// Control the running player
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+    /// Show, print or edit the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Check audio, network, config and terminal setup
+    Doctor,
+    /// Summarize listening time, reconnects and errors from the local logs
+    Stats(StatsArgs),
+}
+
+#[derive(clap::Args, Default)]
+struct StatsArgs {
+    /// Also list the tracks rated this many stars or more
+    #[arg(long, value_name = "STARS", value_parser = clap::value_parser!(u8).range(1..=5))]
+    rating: Option<u8>,
+}
+
+#[derive(clap::Args)]
+struct ListArgs {
+    /// Check that each channel's stream is reachable
+    #[arg(long, conflicts_with = "renderers")]
+    check: bool,
+
+    /// Search the local network for UPnP/DLNA media renderers instead
+    #[arg(long)]
+    renderers: bool,
+
+    /// Write all channels and their playlists to FILE as JSON, or CSV for a .csv file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["check", "renderers"])]
+    export: Option<PathBuf>,
+
+    /// Add the stations from a JSON or TOML channel bundle to the SomaFM channels
+    #[arg(long, value_name = "FILE")]
+    channels_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum CtlCommand {
+    /// Switch to the channel best matching an ID or name (e.g. "drone zone")
+    Channel {
+        #[arg(required = true, value_name = "NAME")]
+        name: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print a commented config.toml with every key at its default
+    PrintDefault,
+    /// Print the location of the config file
+    Path,
+    /// Open the config file in $VISUAL or $EDITOR and check it once saved
+    Edit,
+}
+
+#[derive(clap::Args)]
+struct RecordArgs {
+    /// Channel to record by ID or name (e.g., 'groovesalad', 'drone zone')
+    #[arg(short, long, value_name = "CHANNEL")]
+    channel: String,
     
-    let mut last_update = std::time::Instant::now();
+    /// Stop after this long (e.g. 90, 30s, 15m, 2h); records until Ctrl+C otherwise
+    #[arg(short, long, value_name = "DURATION", value_parser = parse_duration)]
+    duration: Option<Duration>,
     
+    /// Output file (the base name when splitting tracks)
+    #[arg(short, long, value_name = "FILE")]
+    output: PathBuf,
     
-    loop {
-        // Update display
-        if last_update.elapsed() >= std::time::Duration::from_millis(100) {
-            // Update spectrum visualizer based on current state
-            let is_playing = matches!(app.ui_state, UIState::Playing | UIState::SelectingChannel);
-            app.spectrum.update(is_playing, app.is_paused);
-            
-            let track = track_info.lock().await;
-            if let Err(e) = terminal.draw(|frame| {
-                match app.ui_state {
-                    UIState::InitialChannelSelection => {
-                        render_initial_channel_selection(frame, channels, app.selected_index)
-                    }
-                    UIState::Playing => {
-                        render_playing_ui(frame, selected_channel, &track, config, &app)
-                    }
-                    UIState::SelectingChannel => {
-                        render_channel_selection(frame, channels, selected_channel, &track, app.selected_index)
+    /// Start a new file for every track
+    #[arg(long)]
+    split_tracks: bool,
+    
+    /// Write ID3 tags (title, artist, channel) from the stream metadata
+    #[arg(long)]
+    tag: bool,
+}
+
+/// Integrations that live for the whole TUI run
+struct Integrations {
+    notifier: Notifier,
+    alerts: AlertMatcher,
+    media_keys: MediaKeys,
+    monitor: Option<ChannelMonitor>,
+    _channel_refresh: Option<ChannelRefresher>,
+    state_file: Option<StateFile>,
+    terminal_title: Option<TerminalTitle>,
+    taskbar: Option<TaskbarProgress>,
+    now_playing: Option<NowPlayingExporter>,
+    webhook: Option<WebhookPoster>,
+    scrobbler: Option<Scrobbler>,
+    listen_along: Option<ListenAlong>,
+    _control: Option<ControlServer>,
+    ducker: Option<Ducker>,
+    _self_monitor: Option<SelfMonitor>,
+    schedule: Schedule,
+    station_ids: Option<StationIdFilter>,
+    enricher: Option<Enricher>,
+    /// Channel logos for desktop notifications, while those are enabled
+    art: Option<ArtCache>,
+}
+
+/// How often the input reader checks whether the TUI is still running
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Terminal the TUI draws on
+type TuiTerminal = Terminal<CrosstermBackend<io::Stdout>>;
+
+/// Pause before kiosk mode restarts playback that ended or failed
+const KIOSK_RESTART_DELAY: Duration = Duration::from_secs(10);
+
+/// Audio playback of one channel running on its own thread
+struct Playback {
+    session: u64,
+    channel: Channel,
+    commands: mpsc::UnboundedSender<PlayerCommand>,
+    track_info: watch::Receiver<TrackInfo>,
+    limiter: LimiterControl,
+    /// Level of the stream, read once a second
+    level: LevelMeter,
+    /// Ducking factor last sent to the player
+    duck: f32,
+}
+
+impl Playback {
+    /// Starts playing `channel` with `options`, locally or on the `cast`
+    /// renderer, or the file of a recording listed as a channel;
+    /// download `Progress` and `Finished` for `session` are reported through
+    /// `events`.
+    fn start(
+        api: ApiClient,
+        channel: Channel,
+        session: u64,
+        options: PlaybackOptions,
+        cast: Option<Renderer>,
+        stereo: StereoControl,
+        events: mpsc::UnboundedSender<AppEvent>,
+    ) -> Self {
+        let (commands, rx) = mpsc::unbounded_channel();
+        let (track_info, track_updates) = watch::channel(TrackInfo::default());
+        let limiter = LimiterControl::default();
+        let level = LevelMeter::default();
+
+        tokio::task::spawn_blocking({
+            let channel = channel.clone();
+            let controls = PlaybackControls { stereo, limiter: limiter.clone(), level: level.clone() };
+            move || {
+                let on_update = |update| {
+                    let event = match update {
+                        PlayerUpdate::Progress(progress) => PlayerEvent::Progress { session, progress },
+                        PlayerUpdate::VolumeChanged(volume) => PlayerEvent::VolumeChanged { session, volume },
+                        PlayerUpdate::Paused => PlayerEvent::Paused { session },
+                        PlayerUpdate::Resumed => PlayerEvent::Resumed { session },
+                        PlayerUpdate::StreamInfo(info) => PlayerEvent::StreamInfo { session, info },
+                        PlayerUpdate::Position(position) => PlayerEvent::Position { session, position },
+                    };
+                    let _ = events.send(AppEvent::Player(event));
+                };
+                // A panic in the audio stack must not take the TUI down
+                let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    match (recording_path(&channel), cast) {
+                        (Some(path), _) => match Recording::open(&path) {
+                            Ok(recording) => rt.block_on(play_file(
+                                &recording, track_info, rx, options.volume, on_update, controls.stereo, controls.limiter,
+                            )),
+                            Err(e) => Err(PlayerError::Audio(AudioError::DecodingError(e))),
+                        },
+                        (None, Some(renderer)) => rt.block_on(cast_channel(
+                            &renderer, &api, &channel, track_info, rx, &options, on_update,
+                        )),
+                        (None, None) => rt.block_on(play_channel(
+                            &api, &channel, track_info, rx, &options, controls, on_update,
+                        )),
                     }
+                }));
+                let event = match outcome {
+                    Ok(result) => PlayerEvent::Finished { session, result },
+                    Err(payload) => PlayerEvent::Error { session, message: panic_message(payload.as_ref()) },
+                };
+                let _ = events.send(AppEvent::Player(event));
+            }
+        });
+
+        Self { session, channel, commands, track_info: track_updates, limiter, level, duck: 1.0 }
+    }
+
+    fn send(&self, command: PlayerCommand) {
+        let _ = self.commands.send(command);
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        self.send(PlayerCommand::Quit);
+    }
+}
+
+/// State of the running TUI, advanced one [`AppEvent`] at a time
+struct Tui {
+    channels: Vec<Channel>,
+    app: AppState,
+    playback: Option<Playback>,
+    next_session: u64,
+    last_notified: Option<(String, String)>,
+    integrations: Integrations,
+    events: mpsc::UnboundedSender<AppEvent>,
+    saver: ConfigSaver,
+    /// Renderer playback is cast to instead of the local device
+    cast: Option<Renderer>,
+    /// Balance and mono settings shared with every local playback
+    stereo: StereoControl,
+    api: ApiClient,
+    /// When kiosk mode restarts playback that ended
+    restart_at: Option<std::time::Instant>,
+    /// Channels marked as new, kept in sync with `app.new_channels`
+    new_channels: NewChannels,
+    /// Average level of each channel played, see `channel_leveling`
+    levels: ChannelLevels,
+    /// Channels, tracks, volume changes and errors so far
+    session: SessionLog,
+    /// Where the session transcript goes, `--export-session`
+    session_file: Option<PathBuf>,
+    /// Times the animations between ticks
+    clock: FrameClock,
+    /// Whether playback was paused because the terminal lost the focus
+    paused_by_focus: bool,
+    /// Moments bookmarked with `b`
+    bookmarks: Bookmarks,
+    /// Tracks rated with `r`
+    ratings: Ratings,
+}
+
+impl Tui {
+    /// Switches playback to the channel at `index`.
+    fn play(&mut self, index: usize, config: &mut AppConfig) {
+        let Some(channel) = self.channels.get(index).cloned() else {
+            return;
+        };
+        tracing::info!("Switching to channel: {}", channel.title);
+        
+        // Recordings aren't there to return to on the next start
+        if recording_path(&channel).is_none() {
+            config.last_channel_id = Some(channel.id.clone());
+            self.saver.schedule(config);
+        }
+        
+        self.app.playing_stream = channel.preferred_playlist(config.quality_for(&channel.id))
+            .and_then(|chosen| channel.playlists.iter().position(|p| p.url == chosen.url));
+        
+        self.session.channel(&channel);
+        if let Some(art) = &self.integrations.art {
+            art.fetch_in_background(&self.api, &channel);
+        }
+
+        // Dropping the previous playback stops it
+        if self.playback.take().is_some() {
+            self.levels.save();
+        }
+        self.next_session += 1;
+        // Unattended kiosk playback runs under the watchdog
+        let options = PlaybackOptions::from_config(config, &channel.id, self.app.kiosk);
+        self.playback = Some(Playback::start(
+            self.api.clone(),
+            channel,
+            self.next_session,
+            options,
+            self.cast.clone(),
+            self.stereo.clone(),
+            self.events.clone(),
+        ));
+        self.apply_leveling(config.channel_leveling);
+        self.last_notified = None;
+        if let Some(station_ids) = &mut self.integrations.station_ids {
+            station_ids.reset();
+        }
+        self.restart_at = None;
+        self.app.download = None;
+        self.app.stream_info = None;
+        self.app.enrichment = None;
+        self.app.position = None;
+        self.app.rating = None;
+        self.app.replaying = false;
+        self.paused_by_focus = false;
+        // A new player starts out playing
+        self.app.resume();
+        self.app.set_playing_mode();
+    }
+
+    /// Lifts the channel that just started by its stored leveling offset, or
+    /// only says by how much with `suggest`.
+    fn apply_leveling(&mut self, mode: LevelMode) {
+        self.app.leveling = 1.0;
+        let Some(playback) = self.playback.as_ref().filter(|_| self.cast.is_none()) else {
+            return;
+        };
+        let Some(offset) = self.levels.offset(&playback.channel.id) else {
+            return;
+        };
+        match mode {
+            LevelMode::Apply => {
+                tracing::info!("Lifting {} by {:+.1} dB", playback.channel.title, offset);
+                let factor = 10f32.powf(offset / 20.0);
+                playback.limiter.set_leveling(factor);
+                self.app.leveling = factor;
+            }
+            LevelMode::Suggest => self.app.show_toast(format!(
+                "{} plays quiet, channel_leveling = \"apply\" would lift it {:+.1} dB",
+                playback.channel.title, offset
+            )),
+            LevelMode::Off => {}
+        }
+    }
+
+    /// Pauses playback when the terminal loses the focus and resumes it when
+    /// the focus comes back, unless it was paused already.
+    fn focus_changed(&mut self, focused: bool) {
+        let Some(playback) = &self.playback else {
+            return;
+        };
+        if !focused && !self.app.is_paused {
+            tracing::info!("Terminal lost the focus, pausing");
+            playback.send(PlayerCommand::Pause);
+            self.paused_by_focus = true;
+        } else if focused && self.paused_by_focus {
+            tracing::info!("Terminal has the focus again, resuming");
+            playback.send(PlayerCommand::Resume);
+            self.paused_by_focus = false;
+        }
+    }
+
+    /// Writes the session transcript to the `--export-session` file or the
+    /// sessions directory and says where in the header.
+    fn export_session(&mut self) {
+        let path = match self.session_file.clone().map_or_else(|| self.session.default_path(), Ok) {
+            Ok(path) => path,
+            Err(e) => {
+                self.app.show_toast(format!("Session not saved: {}", e));
+                return;
+            }
+        };
+        match self.session.export(&path) {
+            Ok(()) => {
+                tracing::info!("Session transcript written to {}", path.display());
+                self.app.show_toast(format!("Session saved to {}", path.display()));
+            }
+            Err(e) => {
+                tracing::warn!("Failed to write the session transcript to {}: {}", path.display(), e);
+                self.app.show_toast(format!("Session not saved: {}", e));
+            }
+        }
+    }
+
+    /// Answers a request from `soma-player ctl`.
+    fn handle_control(&mut self, request: ControlRequest, config: &mut AppConfig) -> Result<String, String> {
+        match request {
+            ControlRequest::Channel(_) if self.app.kiosk => {
+                Err("Channel changes are disabled in kiosk mode".to_string())
+            }
+            ControlRequest::Channel(name) => {
+                let index = find_channel(&self.channels, &name)?;
+                let title = self.channels[index].title.clone();
+                if self.playback.as_ref().is_some_and(|p| p.channel.id == self.channels[index].id) {
+                    return Ok(format!("Already playing {}", title));
                 }
-            }) {
-                break Err(format!("Failed to draw terminal: {}", e));
+                self.play(index, config);
+                Ok(format!("Playing {}", title))
             }
-            last_update = std::time::Instant::now();
         }
+    }
+
+    /// Applies a key press. Returns `false` once the user quits.
+    fn handle_key(&mut self, key: KeyEvent, config: &mut AppConfig) -> bool {
+        let current_channel_index = self.playback
+            .as_ref()
+            .and_then(|p| self.channels.iter().position(|c| c.id == p.channel.id));
         
-        // Handle keyboard input
-        if let Ok(true) = event::poll(std::time::Duration::from_millis(10)) {
-            if let Ok(Event::Key(key)) = event::read() {
-                let current_channel_index = channels.iter().position(|c| c.id == selected_channel.id);
-                
-                match handle_key_event(
-                    &mut app, 
-                    key, 
-                    channels.len(), 
-                    current_channel_index,
-                    config
-                ) {
-                    EventResult::ChannelChange(new_channel_index) => {
-                        // Update config with selected channel
-                        if let Some(channel) = channels.get(new_channel_index) {                        if let Err(e) = config.set_last_channel(channel.id.clone()) {
-                            tracing::error!("Failed to save config: {}", e);
-                        }
-                        }
-                        
-                        // Send quit to current audio if playing
-                        if audio_handle.is_some() {
-                            let _ = tx.send(PlayerCommand::Quit);
-                        }
-                        break Ok(Some(new_channel_index));
+        let playing_channel = self.playback.as_ref().map(|p| &p.channel);
+        
+        match handle_key_event(&mut self.app, key, &self.channels, current_channel_index, playing_channel, config) {
+            EventResult::ChannelChange(index) => self.play(index, config),
+            EventResult::StreamChange(index) => {
+                if let Some(playback) = &self.playback
+                    && let Some(playlist) = playback.channel.playlists.get(index)
+                {
+                    tracing::info!("Switching {} to {} {} stream", playback.channel.title, playlist.format, playlist.quality);
+                    playback.send(PlayerCommand::SwitchStream(playlist.clone()));
+                    self.app.playing_stream = Some(index);
+                    // Connect to this channel with the same quality next time
+                    if let Some(quality) = StreamQuality::of_playlist(playlist) {
+                        config.channel_quality.insert(playback.channel.id.clone(), quality);
+                        self.saver.schedule(config);
                     }
-                    EventResult::PlayerCommand(cmd) => {
-                        // Send command to audio player
-                        if audio_handle.is_some() {
-                            let _ = tx.send(cmd);
-                        }
+                }
+            }
+            EventResult::StereoChange => {
+                self.stereo.set_balance(config.balance);
+                self.stereo.set_mono(config.mono);
+                self.saver.schedule(config);
+            }
+            EventResult::SettingsChange => self.saver.schedule(config),
+            EventResult::PlayerCommand(cmd) => {
+                if let Some(playback) = &self.playback {
+                    playback.send(cmd);
+                }
+            }
+            EventResult::ExportSession => self.export_session(),
+            EventResult::Bookmark => {
+                if let Some(playback) = &self.playback {
+                    let bookmark = Bookmark::new(&playback.channel, &playback.track_info.borrow(), chrono::Local::now());
+                    self.app.popups.open(Box::new(BookmarkPopup::new(bookmark)));
+                }
+            }
+            EventResult::SaveBookmark(bookmark) => {
+                let title = bookmark.title.clone();
+                match self.bookmarks.add(bookmark) {
+                    Ok(()) => self.app.show_toast(format!("Bookmarked {}, B lists bookmarks", title)),
+                    Err(e) => {
+                        tracing::warn!("{}", e);
+                        self.app.show_toast(format!("Bookmark not saved: {}", e));
                     }
-                    EventResult::Quit => {
-                        if audio_handle.is_some() {
-                            let _ = tx.send(PlayerCommand::Quit);
+                }
+            }
+            EventResult::ShowBookmarks => {
+                self.app.popups.open(Box::new(BookmarksPopup::new(self.bookmarks.list(), &self.channels)));
+            }
+            EventResult::Rate => {
+                let track = self.current_track();
+                if self.playback.is_some() && track != TrackInfo::default() {
+                    let rating = self.ratings.get(&track);
+                    self.app.popups.open(Box::new(RatingPopup::new(track, rating)));
+                }
+            }
+            EventResult::RateTrack(track, stars) => {
+                if let Some(playback) = &self.playback {
+                    match self.ratings.set(&playback.channel, &track, stars) {
+                        Ok(()) if stars == 0 => self.app.show_toast(format!("Rating of {} cleared", track.title)),
+                        Ok(()) => self.app.show_toast(format!("Rated {} {}", track.title, soma_player::ratings::stars(stars))),
+                        Err(e) => {
+                            tracing::warn!("{}", e);
+                            self.app.show_toast(format!("Rating not saved: {}", e));
                         }
-                        break Ok(None);
                     }
-                    EventResult::None => {
-                        // Do nothing, continue loop
+                    // The track may have changed while the popup was open
+                    self.app.rating = self.ratings.get(&self.current_track());
+                }
+            }
+            EventResult::DeleteBookmark(index) => {
+                if let Err(e) = self.bookmarks.remove(index) {
+                    tracing::warn!("{}", e);
+                    self.app.show_toast(e);
+                }
+            }
+            EventResult::Macro(actions) => {
+                for action in actions {
+                    match action.key() {
+                        Some(key) => {
+                            if !self.handle_key(key, config) {
+                                return false;
+                            }
+                        }
+                        None => {
+                            if let Some(playback) = &self.playback {
+                                let track = playback.track_info.borrow().clone();
+                                self.integrations.notifier.announce(&playback.channel, &track);
+                            }
+                        }
                     }
                 }
-                
-                if app.should_quit {
-                    if audio_handle.is_some() {
-                        let _ = tx.send(PlayerCommand::Quit);
+            }
+            EventResult::Quit => return false,
+            EventResult::None => {}
+        }
+        
+        !self.app.should_quit
+    }
+
+    /// Periodic housekeeping: animations, schedule, monitors, notifications,
+    /// alerts, OS media keys and the status file.
+    async fn tick(&mut self, config: &mut AppConfig) {
+        let dt = self.clock.tick(std::time::Instant::now());
+        self.app.advance(dt);
+
+        if self.restart_at.is_some_and(|at| at <= std::time::Instant::now())
+            && let Some(index) = self.playback.as_ref().and_then(|p| self.channels.iter().position(|c| c.id == p.channel.id))
+        {
+            tracing::info!("Kiosk mode: restarting playback");
+            self.play(index, config);
+        }
+        if !self.integrations.schedule.is_empty() {
+            self.follow_schedule(config);
+        }
+        
+        if let Some(playback) = &self.playback {
+            self.app.record_limiting(playback.limiter.take_reduction());
+            if let Some(volume) = self.app.volume_keys.take_settled() {
+                tracing::info!("Requesting volume {}% after key repeats", volume);
+                playback.send(PlayerCommand::SetVolume(volume));
+            }
+        }
+        
+        let playing_id = self.playback.as_ref().map(|p| p.channel.id.clone());
+        if let Some(monitor) = &self.integrations.monitor {
+            let channels = &self.channels;
+            self.app.monitored = monitor.snapshot()
+                .into_iter()
+                .filter(|m| Some(&m.channel_id) != playing_id.as_ref())
+                .filter_map(|m| channels.iter().position(|c| c.id == m.channel_id).map(|i| (i, m)))
+                .collect();
+        }
+        
+        let track = self.current_track();
+        
+        // Announce new tracks once metadata has arrived
+        if let Some(playback) = &self.playback
+            && track.title != TrackInfo::default().title
+            && self.last_notified.as_ref() != Some(&(track.artist.clone(), track.title.clone()))
+        {
+            self.integrations.notifier.track_changed(&playback.channel, &track);
+            self.integrations.media_keys.set_metadata(&playback.channel.title, &track.artist, &track.title);
+            self.last_notified = Some((track.artist.clone(), track.title.clone()));
+            self.session.track(&track);
+            self.app.enrichment = None;
+            self.app.rating = self.ratings.get(&track);
+            if let Some(enricher) = &self.integrations.enricher {
+                enricher.lookup(&track);
+            }
+            if let Some(station_ids) = &mut self.integrations.station_ids
+                && station_ids.track_changed(&track, self.app.latency())
+            {
+                playback.send(PlayerCommand::JumpToLive);
+                self.app.replaying = false;
+            }
+        }
+        
+        // Check the playing and monitored channels against the wishlist
+        if !self.integrations.alerts.is_empty() {
+            let playing = self.playback.as_ref().map(|p| (&p.channel, track.clone()));
+            let monitored = self.app.monitored
+                .iter()
+                .map(|(index, m)| (&self.channels[*index], m.track.clone()));
+            
+            let mut latest = None;
+            for (channel, channel_track) in playing.into_iter().chain(monitored) {
+                if let Some(alert) = self.integrations.alerts.check(channel, &channel_track) {
+                    if !self.integrations.notifier.is_quiet() {
+                        self.integrations.alerts.announce(channel, &alert);
                     }
-                    break Ok(None);
+                    latest = Some(alert);
                 }
             }
+            if let Some(alert) = latest {
+                self.app.show_alert(alert);
+            }
+        }
+        
+        // Handle OS media keys
+        while let Some(event) = self.integrations.media_keys.try_recv() {
+            let pause = match event {
+                MediaKeyEvent::Play => false,
+                MediaKeyEvent::Pause | MediaKeyEvent::Stop => true,
+                MediaKeyEvent::Toggle => !self.app.is_paused,
+            };
+            if self.set_paused(pause) {
+                tracing::info!("Media key: {:?}", event);
+            }
+        }
+        
+        // Fade the volume in and out of ducking, and lower it during
+        // station IDs
+        let id_factor = self.integrations.station_ids.as_ref().map_or(1.0, |ids| ids.factor());
+        let duck_factor = self.integrations.ducker.as_mut().map_or(1.0, |ducker| ducker.factor());
+        let factor = id_factor * duck_factor;
+        self.app.ducked = id_factor < 1.0 || self.integrations.ducker.as_ref().is_some_and(|ducker| ducker.is_ducked());
+        self.app.duck_factor = factor;
+        if let Some(playback) = &mut self.playback
+            && playback.duck != factor
+        {
+            playback.send(PlayerCommand::Duck(factor));
+            playback.duck = factor;
         }
         
-        // Check audio task status
-        if let Some(audio_handle) = &audio_handle {
-            if let Ok(audio_result) = audio_result_rx.try_recv() {
-                match audio_result {
-                    Ok(_) => break Ok(None),
-                    Err(e) => break Err(e),
+        // Listen-along: announce our state, or follow the leader's
+        let follow_actions = match &mut self.integrations.listen_along {
+            Some(ListenAlong::Leader(leader)) => {
+                leader.announce(playing_id.as_deref(), self.app.is_paused, self.app.latency());
+                self.app.sync_status = Some("Leading listen-along".to_string());
+                Vec::new()
+            }
+            Some(ListenAlong::Follower(follower)) => {
+                let actions = follower.poll(playing_id.as_deref(), self.app.is_paused, self.app.latency());
+                self.app.sync_status = Some(match follower.leader() {
+                    Some(leader) => format!("Following {}", leader),
+                    None => "Waiting for a leader".to_string(),
+                });
+                actions
+            }
+            None => Vec::new(),
+        };
+        for action in follow_actions {
+            self.follow_leader(action, config);
+        }
+        
+        if let Some(terminal_title) = &mut self.integrations.terminal_title
+            && let Some(playback) = &self.playback
+        {
+            terminal_title.set(&format_title(&playback.channel, &track, config.track_format.as_deref()));
+        }
+        
+        if let Some(taskbar) = &mut self.integrations.taskbar {
+            let state = match &self.playback {
+                _ if matches!(self.app.ui_state, UIState::Error) => Some(TaskbarState::Error),
+                None => None,
+                Some(_) if self.app.is_paused => Some(TaskbarState::Paused),
+                // Casting reports no download progress
+                Some(_) if self.cast.is_none() && self.app.download.is_none_or(|download| download.stalled) => {
+                    Some(TaskbarState::Buffering)
+                }
+                Some(_) => Some(TaskbarState::Playing),
+            };
+            taskbar.set(state);
+        }
+        
+        if let Some(now_playing) = &mut self.integrations.now_playing {
+            now_playing.update(self.playback.as_ref().map(|p| (&p.channel, &track)));
+        }
+        
+        let has_track = track.title != TrackInfo::default().title;
+        let status = match &self.playback {
+            Some(playback) => PlayerStatus {
+                channel_id: Some(playback.channel.id.clone()),
+                channel: Some(playback.channel.title.clone()),
+                artist: has_track.then(|| track.artist.clone()),
+                title: has_track.then(|| track.title.clone()),
+                volume: self.app.volume,
+                paused: self.app.is_paused,
+                ..PlayerStatus::idle()
+            },
+            None => PlayerStatus::idle(),
+        };
+        
+        if let Some(state_file) = &mut self.integrations.state_file {
+            state_file.update(&status);
+        }
+        
+        if let Some(webhook) = &mut self.integrations.webhook {
+            webhook.update(&status);
+        }
+        
+        if let Some(scrobbler) = &mut self.integrations.scrobbler {
+            scrobbler.update(&status);
+        }
+    }
+
+    /// Asks the player to pause or resume playback; the UI follows once the
+    /// player reports back. Returns whether a command was sent.
+    fn set_paused(&mut self, pause: bool) -> bool {
+        let Some(playback) = &self.playback else {
+            return false;
+        };
+        if pause == self.app.is_paused {
+            return false;
+        }
+        playback.send(if pause { PlayerCommand::Pause } else { PlayerCommand::Resume });
+        true
+    }
+
+    /// Switches to the scheduled channel when a scheduled range begins.
+    fn follow_schedule(&mut self, config: &mut AppConfig) {
+        let Some(slot) = self.integrations.schedule.check(local_minute()) else {
+            return;
+        };
+        let (channel_id, range) = (slot.channel_id.clone(), slot.range());
+        let Some(index) = self.channels.iter().position(|c| c.id == channel_id) else {
+            tracing::warn!("Unknown channel '{}' scheduled for {}", channel_id, range);
+            return;
+        };
+        if self.playback.as_ref().is_some_and(|p| p.channel.id == channel_id) {
+            return;
+        }
+        tracing::info!("Schedule {}: switching to {}", range, channel_id);
+        self.play(index, config);
+        self.app.show_toast(format!("⏰ {} scheduled for {}", self.channels[index].title, range));
+    }
+
+    /// Applies a change needed to follow the listen-along leader.
+    fn follow_leader(&mut self, action: FollowAction, config: &mut AppConfig) {
+        tracing::info!("Listen-along: {:?}", action);
+        match action {
+            FollowAction::SwitchChannel(channel_id) => {
+                match self.channels.iter().position(|c| c.id == channel_id) {
+                    Some(index) => self.play(index, config),
+                    None => tracing::warn!("Listen-along leader plays unknown channel '{}'", channel_id),
                 }
             }
-            
-            if audio_handle.is_finished() {
-                break Ok(None);
+            FollowAction::Pause => {
+                self.set_paused(true);
+            }
+            FollowAction::Resume => {
+                self.set_paused(false);
             }
+            FollowAction::JumpToLive => {
+                if let Some(playback) = &self.playback {
+                    playback.send(PlayerCommand::JumpToLive);
+                }
+            }
+        }
+    }
+
+    fn current_track(&self) -> TrackInfo {
+        match &self.playback {
+            Some(playback) => playback.track_info.borrow().clone(),
+            None => TrackInfo::default(),
         }
+    }
+
+    async fn draw(
+        &self,
+        terminal: &mut Option<TuiTerminal>,
+        config: &AppConfig,
+    ) -> Result<(), String> {
+        let Some(terminal) = terminal else {
+            return Ok(());
+        };
+        let track = self.current_track();
+        
+        // Prefer the refreshed entry for the playing channel's details
+        let current_channel = self.playback.as_ref().map(|p| {
+            self.channels
+                .iter()
+                .find(|c| c.id == p.channel.id)
+                .unwrap_or(&p.channel)
+        });
         
-        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        terminal.draw(|frame| {
+            match (&self.app.ui_state, current_channel) {
+                (UIState::Playing | UIState::Error, Some(channel)) => {
+                    render_playing_ui(frame, channel, &track, config, &self.app)
+                }
+                (UIState::SelectingChannel, Some(channel)) => {
+                    render_channel_selection(frame, &self.channels, channel, &track, &self.app, config)
+                }
+                _ => render_initial_channel_selection(frame, &self.channels, &self.app, config),
+            }
+            self.app.popups.render(frame, config, &self.app);
+        })
+        .map(|_| ())
+        .map_err(|e| format!("Failed to draw terminal: {}", e))
+    }
+}
+
+/// Forwards terminal input to the event channel until it closes.
+fn spawn_input_reader(events: mpsc::UnboundedSender<AppEvent>) {
+    std::thread::spawn(move || {
+        while !events.is_closed() {
+            let event = match event::poll(INPUT_POLL_INTERVAL) {
+                Ok(true) => event::read(),
+                Ok(false) => continue,
+                Err(e) => Err(e),
+            };
+            let app_event = match event {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => AppEvent::Key(key),
+                Ok(Event::Resize(columns, rows)) => AppEvent::Resize(columns, rows),
+                Ok(Event::FocusGained) => AppEvent::Focus(true),
+                Ok(Event::FocusLost) => AppEvent::Focus(false),
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::error!("Failed to read terminal input: {}", e);
+                    break;
+                }
+            };
+            if events.send(app_event).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Resolves once the process is asked to terminate: SIGINT, or SIGTERM on
+/// Unix. Keys never raise SIGINT while the terminal is in raw mode.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    if let Ok(mut terminate) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+        return;
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Runs the TUI until the user quits or playback fails. Without a
+/// `terminal` nothing is drawn and no keys are read.
+async fn run_tui(
+    terminal: &mut Option<TuiTerminal>,
+    tui: &mut Tui,
+    mut events: mpsc::UnboundedReceiver<AppEvent>,
+    config: &mut AppConfig,
+) -> Result<ExitSummary, String> {
+    if terminal.is_some() {
+        spawn_input_reader(tui.events.clone());
+    }
+    
+    tokio::spawn({
+        let events = tui.events.clone();
+        async move {
+            shutdown_signal().await;
+            let _ = events.send(AppEvent::Shutdown);
+        }
+    });
+    
+    tokio::spawn({
+        let events = tui.events.clone();
+        let interval = tui.clock.interval();
+        async move {
+            let mut ticker = tokio::time::interval(interval);
+            while events.send(AppEvent::Tick).is_ok() {
+                ticker.tick().await;
+            }
+        }
+    });
+    
+    while let Some(event) = events.recv().await {
+        match event {
+            AppEvent::Key(key) => {
+                if !tui.handle_key(key, config) {
+                    break;
+                }
+                tui.draw(terminal, config).await?;
+            }
+            AppEvent::Tick => {
+                tui.tick(config).await;
+                tui.draw(terminal, config).await?;
+            }
+            AppEvent::Shutdown => {
+                tracing::info!("Termination signal received");
+                break;
+            }
+            AppEvent::Resize(_, _) => {
+                tui.draw(terminal, config).await?;
+            }
+            AppEvent::Focus(focused) => {
+                if config.pause_on_focus_loss && !tui.app.kiosk {
+                    tui.focus_changed(focused);
+                }
+            }
+            AppEvent::Control(request, reply) => {
+                let _ = reply.send(tui.handle_control(request, config).into());
+                tui.draw(terminal, config).await?;
+            }
+            AppEvent::Api(ApiEvent::ChannelsRefreshed(fresh)) => {
+                if fresh.is_empty() {
+                    continue;
+                }
+                tui.app.channels_notice = None;
+                let added: Vec<&str> = fresh
+                    .iter()
+                    .filter(|channel| !tui.channels.iter().any(|known| known.id == channel.id))
+                    .map(|channel| channel.id.as_str())
+                    .collect();
+                tui.new_channels.add(&added);
+                tui.app.new_channels.extend(added.iter().map(|id| id.to_string()));
+                if merge_channels(&mut tui.channels, fresh) {
+                    tracing::debug!("Channel list updated");
+                    tui.api.save_channel_cache(&tui.channels);
+                }
+            }
+            AppEvent::Api(ApiEvent::Enriched(track, enrichment)) => {
+                // Lookups finishing after the track changed are dropped
+                if !enrichment.is_empty() && tui.current_track() == track {
+                    tui.app.enrichment = Some((track, enrichment));
+                }
+            }
+            AppEvent::Player(PlayerEvent::Error { session, message }) => {
+                if tui.playback.as_ref().is_some_and(|p| p.session == session) {
+                    tracing::error!("Audio playback crashed: {}", message);
+                    tui.session.error(&message);
+                    tui.app.download = None;
+                    tui.app.show_error(&PlayerError::Crashed(message));
+                    if tui.app.kiosk {
+                        tui.restart_at = Some(std::time::Instant::now() + KIOSK_RESTART_DELAY);
+                    }
+                }
+            }
+            AppEvent::Player(PlayerEvent::VolumeChanged { session, volume }) => {
+                if tui.playback.as_ref().is_some_and(|p| p.session == session) {
+                    tui.app.volume = Some(volume);
+                    tui.session.volume(volume);
+                    if config.volume != Some(volume) {
+                        config.volume = Some(volume);
+                        tui.saver.schedule(config);
+                    }
+                }
+            }
+            AppEvent::Player(PlayerEvent::Paused { session }) => {
+                if tui.playback.as_ref().is_some_and(|p| p.session == session) {
+                    tui.app.pause();
+                    tui.integrations.media_keys.set_playing(false);
+                }
+            }
+            AppEvent::Player(PlayerEvent::Resumed { session }) => {
+                if tui.playback.as_ref().is_some_and(|p| p.session == session) {
+                    tui.app.resume();
+                    tui.integrations.media_keys.set_playing(true);
+                }
+            }
+            AppEvent::Player(PlayerEvent::Progress { session, progress }) => {
+                if let Some(playback) = tui.playback.as_ref().filter(|p| p.session == session) {
+                    tui.app.update_download(progress);
+                    if config.channel_leveling != LevelMode::Off
+                        && let Some(db) = playback.level.take()
+                    {
+                        tui.levels.record(&playback.channel.id, db);
+                    }
+                }
+            }
+            AppEvent::Player(PlayerEvent::StreamInfo { session, info }) => {
+                if tui.playback.as_ref().is_some_and(|p| p.session == session) {
+                    tui.app.stream_info = Some(info).filter(|info| !info.is_empty());
+                }
+            }
+            AppEvent::Player(PlayerEvent::Position { session, position }) => {
+                if tui.playback.as_ref().is_some_and(|p| p.session == session) {
+                    tui.app.position = Some(position);
+                }
+            }
+            AppEvent::Player(PlayerEvent::Finished { session, result }) => {
+                // Ignore sessions that were replaced by a channel switch
+                if tui.playback.as_ref().is_some_and(|p| p.session == session) {
+                    if let Err(e) = &result {
+                        tui.session.error(&e.to_string());
+                    }
+                    if tui.app.kiosk {
+                        // Nobody is around to restart it
+                        match result {
+                            Ok(_) => tracing::warn!("Audio playback ended, restarting"),
+                            Err(e) => tracing::error!("Audio playback failed, restarting: {}", e),
+                        }
+                        tui.app.download = None;
+                        tui.restart_at = Some(std::time::Instant::now() + KIOSK_RESTART_DELAY);
+                        continue;
+                    }
+                    match result {
+                        Ok(_) => {
+                            // A recording that played to its end goes on to the next one
+                            let next = tui.playback.as_ref()
+                                .filter(|p| recording_path(&p.channel).is_some())
+                                .and_then(|p| tui.channels.iter().position(|c| c.id == p.channel.id))
+                                .map(|index| index + 1)
+                                .filter(|&index| index < tui.channels.len());
+                            if let Some(index) = next {
+                                tui.play(index, config);
+                                continue;
+                            }
+                            tracing::info!("Audio playback ended");
+                            break;
+                        }
+                        // Shown with what to do about it instead of quitting
+                        Err(e) => {
+                            tracing::error!("Audio playback failed: {}", e);
+                            tui.app.download = None;
+                            tui.app.show_error(&e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    let mut summary = ExitSummary {
+        channel: tui.playback.as_ref().map(|p| p.channel.title.clone()),
+        track: Some(tui.current_track()).filter(|track| *track != TrackInfo::default()),
+        listened: tui.app.listened,
+        data_used: tui.app.data_used,
+        notes: Vec::new(),
+    };
+    tui.playback = None;
+    tui.levels.save();
+    if let Some(path) = &tui.session_file {
+        match tui.session.export(path) {
+            Ok(()) => {
+                tracing::info!("Session transcript written to {}", path.display());
+                summary.notes.push(format!("Session transcript saved to {}", path.display()));
+            }
+            Err(e) => tracing::warn!("Failed to write the session transcript to {}: {}", path.display(), e),
+        }
     }
+    Ok(summary)
 }
 
 #[tokio::main]
@@ -202,72 +1265,129 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command line arguments - this automatically handles --help and --version
     let args = Args::parse();
 
+    // Loading would write a default config, so leave that to the setup
+    // wizard on the first run
+    let first_run = AppConfig::is_first_run();
+    let stored_config = if first_run {
+        AppConfig::default()
+    } else {
+        AppConfig::load().unwrap_or_default()
+    };
+
     // Initialize enhanced logging system
-    let _log_guard = soma_player::logging::init_logging(
-        soma_player::logging::LogConfig::default()
-    )?;
+    let _log_guard = soma_player::logging::init_logging(soma_player::logging::LogConfig {
+        log_to_file: stored_config.log_to_file,
+        max_log_files: stored_config.logging.max_files,
+        max_file_size: stored_config.logging.max_file_mb * 1024 * 1024,
+        max_total_size: stored_config.logging.max_total_mb * 1024 * 1024,
+        compress: stored_config.logging.compress,
+        ..soma_player::logging::LogConfig::default()
+    })?;
 
     color_eyre::install()?;
     tracing::info!("Starting SomaFM Player");
     
     // Handle special arguments that don't require the full app
-    if args.config {
-        match soma_player::config::AppConfig::config_path() {
-            Ok(path) => {
-                println!("Configuration file: {}", path.display());
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("Error getting config path: {}", e);
-                return Err(e);
-            }
+    if args.install_service {
+        let config_path = AppConfig::config_path()?;
+        let service = soma_player::service::install_service(&config_path)?;
+        println!("Wrote {}", service.path.display());
+        println!("Start it now and at every login with:");
+        println!("    {}", service.enable_command);
+        return Ok(());
+    }
+    
+    let api = ApiClient::new();
+    if args.validate_api {
+        let body = api.fetch_channels_json().await?;
+        let report = soma_player::api::check_channel_schema(&body)?;
+        println!("{} of {} channels readable", report.parsed, report.entries);
+        for (field, count) in &report.unknown_fields {
+            println!("  unknown field '{}' in {} channel(s)", field, count);
+        }
+        if report.is_clean() {
+            println!("The channel list matches the fields the player reads.");
+            return Ok(());
         }
+        return Err("The channel list has changed, see above".into());
     }
     
-    if args.list {
-        println!("Fetching SomaFM channels...");
-        match fetch_channels().await {
-            Ok(channels) => {
-                println!("\nAvailable channels:");
-                for channel in channels {
-                    println!("  {} - {}", channel.id, channel.title);
-                    if !channel.description.is_empty() {
-                        println!("    {}", channel.description);
-                    }
-                    println!();
-                }
+    match args.into_command() {
+        Command::Play(play) => run_play(api, play, first_run, stored_config).await,
+        Command::List(list) => run_list(&api, list).await,
+        Command::Record(record) => run_record(&api, record, &stored_config).await,
+        Command::Ctl { command } => run_ctl(command).await,
+        Command::Config { command } => run_config(command),
+        Command::Doctor => {
+            let results = soma_player::doctor::run_diagnostics(&api).await;
+            if soma_player::doctor::print_report(&results) {
                 return Ok(());
             }
-            Err(e) => {
-                eprintln!("Error fetching channels: {}", e);
-                return Err(e.into());
+            Err("One or more diagnostic checks failed".into())
+        }
+        Command::Stats(stats) => {
+            let report = soma_player::report::collect(&soma_player::logging::get_log_directory()?)?;
+            print!("{}", report.render());
+            if let Some(min_stars) = stats.rating {
+                print!("{}", Ratings::load().render(min_stars));
             }
+            Ok(())
         }
     }
+}
+
+/// Starts the player: `soma-player` alone or `soma-player play`.
+async fn run_play(
+    api: ApiClient,
+    args: PlayArgs,
+    first_run: bool,
+    stored_config: AppConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Stations from a bundle join the channel list
+    let bundle = match &args.channels_file {
+        Some(path) => load_bundle(path)?,
+        None => Vec::new(),
+    };
     
-    // Load configuration
-    let mut config = soma_player::config::AppConfig::load().unwrap_or_default();
+    // Load configuration, running the setup wizard on the first start
+    // Nobody is there to answer the wizard in kiosk mode
+    let mut config = if first_run && !args.kiosk && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        run_first_time_setup(&api).await?
+    } else if first_run {
+        AppConfig::load().unwrap_or_default()
+    } else {
+        stored_config
+    };
     tracing::debug!("Configuration loaded: {:?}", config);
     
     // Apply command-line overrides
-    if args.autostart {
+    if args.autostart || args.kiosk {
         config.auto_start = true;
     }
     
     if let Some(volume) = args.volume {
-        if volume <= 100 {
+        if volume <= config.volume_limit() {
             config.volume = Some(volume);
         } else {
-            eprintln!("Warning: Volume must be between 0-100, ignoring value {}", volume);
+            eprintln!(
+                "Warning: Volume must be between 0-{} (see max_volume), ignoring value {}",
+                config.volume_limit(),
+                volume
+            );
         }
     }
+    // A saved volume may be above a cap set since
+    config.volume = config.volume.map(|volume| volume.min(config.volume_limit()));
     
-    if let Some(channel_id) = args.channel {
-        config.last_channel_id = Some(channel_id);
-        config.auto_start = true; // Auto-start when specific channel is requested
+    if let Some(renderer) = &args.cast {
+        config.cast.renderer = Some(renderer.clone());
     }
     
-    let result = run_player(&mut config).await;
+    if let Some(role) = args.sync {
+        config.sync.role = role;
+    }
+    
+    let result = run_player(api, &mut config, &args, &bundle).await;
     
     if let Err(e) = &result {
         tracing::error!("Application error: {}", e);
@@ -276,90 +1396,528 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     result
 }
 
-async fn run_player(config: &mut AppConfig) -> Result<(), Box<dyn std::error::Error>> {
-    let channels = fetch_channels().await?;
-    let track_info = Arc::new(Mutex::new(TrackInfo::default()));
+/// Lists the channels, exports them or lists the media renderers.
+async fn run_list(api: &ApiClient, args: ListArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.renderers {
+        println!("Searching for media renderers...");
+        let renderers = discover(DISCOVERY_TIMEOUT).await?;
+        if renderers.is_empty() {
+            println!("No UPnP/DLNA media renderers found.");
+        }
+        for renderer in renderers {
+            println!("  {}  ({})", renderer.name, renderer.location);
+        }
+        return Ok(());
+    }
     
-    // Try to find the last used channel or default to first
-    let selected_channel_index = if let Some(ref last_id) = config.last_channel_id {
-        channels.iter().position(|c| c.id == *last_id).unwrap_or(0)
+    let bundle = match &args.channels_file {
+        Some(path) => load_bundle(path)?,
+        None => Vec::new(),
+    };
+    
+    if let Some(path) = &args.export {
+        let (mut channels, source) = api.load_channels().await?;
+        if source == ChannelSource::Cached {
+            println!("SomaFM API unavailable, exporting the cached channel list");
+        }
+        merge_bundle(&mut channels, &bundle);
+        let format = export_channels(&channels, path)?;
+        println!("Exported {} channels to {} ({:?})", channels.len(), path.display(), format);
+        return Ok(());
+    }
+    
+    println!("Fetching SomaFM channels...");
+    let mut channels = match api.fetch_channels().await {
+        Ok(channels) => channels,
+        Err(e) => {
+            eprintln!("Error fetching channels: {}", e);
+            return Err(e);
+        }
+    };
+    merge_bundle(&mut channels, &bundle);
+    let health = if args.check {
+        println!("Checking {} channel streams...", channels.len());
+        Some(futures_util::future::join_all(
+            channels.iter().map(|channel| api.check_channel_stream(channel))
+        ).await)
     } else {
-        0
+        None
     };
     
-    let mut selected_channel = &channels[selected_channel_index];
-    let mut first_run = true;
+    println!("\nAvailable channels:");
+    for (i, channel) in channels.iter().enumerate() {
+        match health.as_ref().map(|h| &h[i]) {
+            Some(Ok(latency)) => println!(
+                "  {} - {}  [OK, {} ms]", channel.id, channel.title, latency.as_millis()
+            ),
+            Some(Err(e)) => println!(
+                "  {} - {}  [UNREACHABLE: {}]", channel.id, channel.title, e
+            ),
+            None => println!("  {} - {}", channel.id, channel.title),
+        }
+        if !channel.description.is_empty() {
+            println!("    {}", channel.description);
+        }
+        println!();
+    }
     
-    loop {
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
-
-        let app = if first_run {
-            if config.auto_start {
-                // If auto_start is enabled, go directly to playing the last/first channel
-                let mut app = AppState::new();
-                app.ui_state = UIState::Playing;
-                app
-            } else {
-                // Show channel selection screen
-                AppState::new()
-            }
+    if let Some(health) = health {
+        let failed = health.iter().filter(|h| h.is_err()).count();
+        if failed == 0 {
+            println!("All {} streams reachable.", health.len());
+        } else if failed == health.len() {
+            println!("No streams reachable - check your network connection.");
         } else {
-            let mut app = AppState::new();
-            app.ui_state = UIState::Playing;
-            app
-        };
+            println!("{} of {} streams unreachable - likely an issue on SomaFM's side.", failed, health.len());
+        }
+    }
+    Ok(())
+}
 
-        let channel_selection = match play_session_tui(
-            &mut terminal, 
-            &channels, 
-            selected_channel, 
-            Arc::clone(&track_info), 
-            app,
+/// Runs the setup wizard and saves its result, or the defaults when the
+/// user skips it.
+async fn run_first_time_setup(api: &ApiClient) -> Result<AppConfig, Box<dyn std::error::Error>> {
+    let channels = api.fetch_channels().await.unwrap_or_else(|e| {
+        tracing::warn!("Setup wizard without channel list: {}", e);
+        Vec::new()
+    });
+    let config_path = AppConfig::config_path()?;
+    let mut wizard = SetupWizard::new(&channels, config_path.clone());
+    
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    
+    let outcome = loop {
+        if let Err(e) = terminal.draw(|frame| wizard.render(frame)) {
+            break Err(e);
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match wizard.handle_key(key) {
+                WizardOutcome::Continue => {}
+                outcome => break Ok(outcome),
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    
+    let config = match outcome? {
+        WizardOutcome::Finished => {
+            let config = wizard.into_config();
+            config.save()?;
+            println!("Settings saved to {}", config_path.display());
+            if !config.log_to_file {
+                println!("File logging will be off from the next start.");
+            }
             config
-        ).await {
-            Ok(result) => {
-                disable_raw_mode()?;
-                execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                terminal.show_cursor()?;
-                result
+        }
+        _ => {
+            let config = AppConfig::default();
+            config.save()?;
+            println!("Setup skipped, default settings saved to {}", config_path.display());
+            config
+        }
+    };
+    println!("Edit that file to change them later (`soma-player config edit` opens it).");
+    tracing::info!("First-run setup wrote {}", config_path.display());
+    Ok(config)
+}
+
+async fn run_ctl(command: CtlCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let request = match command {
+        CtlCommand::Channel { name } => ControlRequest::Channel(name.join(" ")),
+    };
+    println!("{}", send_request(&request).await?);
+    Ok(())
+}
+
+fn run_config(command: ConfigCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let path = AppConfig::config_path()?;
+    match command {
+        ConfigCommand::PrintDefault => print!("{}", schema::default_config_toml()),
+        ConfigCommand::Path => println!("{}", path.display()),
+        ConfigCommand::Edit => edit_config(&path)?,
+    }
+    Ok(())
+}
+
+/// Opens the config file in the user's editor until it holds a config the
+/// player can read, or puts the previous file back.
+fn edit_config(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if !path.exists() {
+        std::fs::write(path, schema::default_config_toml())?;
+    }
+    let original = std::fs::read_to_string(path)?;
+    let editor = editor::editor();
+
+    loop {
+        editor::edit(&editor, path)?;
+        let contents = std::fs::read_to_string(path)?;
+        match schema::check(&contents) {
+            Ok(issues) => {
+                for issue in &issues {
+                    println!("Warning: {}", issue);
+                }
+                println!("Saved {}", path.display());
+                return Ok(());
             }
             Err(e) => {
-                disable_raw_mode()?;
-                execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                terminal.show_cursor()?;
-                return Err(e.into());
+                eprintln!("{} can't be read: {}", path.display(), e);
+                print!("Edit it again? [Y/n] ");
+                io::Write::flush(&mut io::stdout())?;
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer)?;
+                if answer.trim().eq_ignore_ascii_case("n") {
+                    std::fs::write(path, original)?;
+                    return Err(format!("Changes discarded, {} is unchanged", path.display()).into());
+                }
             }
-        };
-        
-        match channel_selection {
-            Some(index) => {
-                if index < channels.len() {
-                    selected_channel = &channels[index];
-                    first_run = false;
-                    tracing::info!("Switching to channel: {}", selected_channel.title);
-                    
-                    // Reset track info for new channel
-                    {
-                        let mut track = track_info.lock().await;
-                        *track = TrackInfo::default();
-                    }
-                } else {
-                    continue;
+        }
+    }
+}
+
+async fn run_record(api: &ApiClient, args: RecordArgs, config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let (channels, source) = api.load_channels().await?;
+    if source == ChannelSource::Cached {
+        println!("SomaFM API unavailable, using the cached channel list");
+    }
+    let channel = &channels[find_channel(&channels, &args.channel)?];
+    
+    let options = RecordOptions {
+        output: args.output,
+        duration: args.duration,
+        split_tracks: args.split_tracks,
+        tag: args.tag,
+        quality: config.quality_for(&channel.id),
+    };
+    
+    let stop = Arc::new(AtomicBool::new(false));
+    tokio::spawn({
+        let stop = Arc::clone(&stop);
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+    
+    println!("Recording {} to {} (Ctrl+C to stop)", channel.title, options.output.display());
+    let summary = record_channel(api, channel, &options, stop).await?;
+    
+    println!(
+        "Recorded {:.1} MB in {} file(s) over {}s",
+        summary.bytes_written as f64 / (1024.0 * 1024.0),
+        summary.files.len(),
+        summary.elapsed.as_secs()
+    );
+    for file in &summary.files {
+        println!("  {}", file.display());
+    }
+    Ok(())
+}
+
+/// The channel list to start with right away when the last channel is
+/// autostarted and an earlier session saved it and the channel list: the
+/// cached list, with the last channel in it. `None` when the list has to be
+/// loaded first.
+fn fast_start_channels(api: &ApiClient, config: &AppConfig, requested_channel: Option<&str>) -> Option<Vec<Channel>> {
+    if requested_channel.is_some() || config.initial_screen() != StartScreen::LastChannel {
+        return None;
+    }
+    let last = api
+        .last_channel()
+        .filter(|last| config.last_channel_id.as_deref() == Some(last.channel.id.as_str()))?;
+    let mut channels = api.cached_channels()?;
+    let last = api.restore_last_channel(last);
+    tracing::info!("Starting {} before the channel list is loaded", last.title);
+    match channels.iter_mut().find(|channel| channel.id == last.id) {
+        Some(channel) => *channel = last,
+        None => channels.insert(0, last),
+    }
+    Some(channels)
+}
+
+/// Loads the channel list while the player already plays, and hands it to
+/// the TUI as a refresh.
+fn load_channels_in_background(api: ApiClient, events: mpsc::UnboundedSender<AppEvent>) {
+    tokio::spawn(async move {
+        match api.load_channels().await {
+            Ok((channels, ChannelSource::Live)) => {
+                let _ = events.send(AppEvent::Api(ApiEvent::ChannelsRefreshed(channels)));
+            }
+            Ok(_) => tracing::warn!("SomaFM API unavailable, keeping the cached channel list"),
+            Err(e) => tracing::warn!("Failed to load the channel list: {}", e),
+        }
+    });
+}
+
+/// Runs the TUI, starting with the channel (an ID or name) in `args` if
+/// given. In `kiosk` mode the controls are locked and playback restarts
+/// whenever it ends. Channels from `bundle` are listed after the SomaFM ones;
+/// with an `archive` the recordings found there are listed instead of any
+/// channels. The startup summary is logged, and with `verbose` printed as
+/// well. Overrides in `args` that change the config are already applied to
+/// `config`.
+async fn run_player(
+    api: ApiClient,
+    config: &mut AppConfig,
+    args: &PlayArgs,
+    bundle: &[Channel],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let requested_channel = args.channel.as_deref();
+    let archive = args.archive.as_deref();
+    let (events_tx, events_rx) = mpsc::unbounded_channel();
+    let (channels, source, new_channels) = match archive {
+        Some(path) => {
+            let recordings = find_recordings(path)?;
+            println!("Playing {} recording(s) from {}", recordings.len(), path.display());
+            (recordings.iter().map(Recording::channel).collect(), ChannelSource::Live, NewChannels::default())
+        }
+        None => {
+            // Loading replaces the cached list, which tells the channels added since
+            let previous = api.cached_channels();
+            let (mut channels, source) = match fast_start_channels(&api, config, requested_channel) {
+                Some(channels) => {
+                    load_channels_in_background(api.clone(), events_tx.clone());
+                    (channels, ChannelSource::Refreshing)
                 }
+                None => api.load_channels().await?,
+            };
+            let mut new_channels = NewChannels::load();
+            new_channels.start_session(previous.as_deref(), &channels);
+            merge_bundle(&mut channels, bundle);
+            (channels, source, new_channels)
+        }
+    };
+    if source == ChannelSource::Cached {
+        println!("SomaFM API unavailable, using the cached channel list");
+    }
+    
+    if let Some(name) = requested_channel {
+        let channel = &channels[find_channel(&channels, name)?];
+        println!("Playing {} ({})", channel.title, channel.id);
+        config.last_channel_id = Some(channel.id.clone());
+        config.auto_start = true; // Auto-start when specific channel is requested
+    }
+    
+    let cast = match config.cast.renderer.as_deref().filter(|r| !r.trim().is_empty()) {
+        Some(name) => {
+            println!("Looking for media renderer '{}'...", name);
+            let renderer = find_renderer(name).await?;
+            println!("Casting to {}", renderer.name);
+            Some(renderer)
+        }
+        None => None,
+    };
+    
+    // Refreshing would replace the recordings with the SomaFM channels
+    let channel_refresh = (config.channel_refresh_secs > 0 && archive.is_none()).then(|| {
+        let events = events_tx.clone();
+        ChannelRefresher::start(api.clone(), Duration::from_secs(config.channel_refresh_secs), move |channels| {
+            let _ = events.send(AppEvent::Api(ApiEvent::ChannelsRefreshed(channels)));
+        })
+    });
+    
+    // Logos are only shown in desktop notifications
+    let art = config.notifications.enabled.then(ArtCache::new);
+    let mut notifier = Notifier::new(&config.notifications, config.track_format.as_deref());
+    if let Some(art) = &art {
+        notifier = notifier.with_art(art.clone());
+        if config.prefetch_art {
+            art.prefetch(api.clone(), &channels);
+        }
+    }
+    
+    let mut integrations = Integrations {
+        notifier,
+        alerts: AlertMatcher::new(&config.alerts),
+        media_keys: MediaKeys::new(),
+        monitor: None,
+        _channel_refresh: channel_refresh,
+        state_file: StateFile::new()
+            .map_err(|e| tracing::warn!("Status file disabled: {}", e))
+            .ok(),
+        terminal_title: None,
+        taskbar: None,
+        now_playing: NowPlayingExporter::new(&config.now_playing, config.track_format.as_deref()),
+        webhook: WebhookPoster::new(&config.webhook),
+        // Recordings played back were heard when they were recorded
+        scrobbler: archive.is_none().then(|| Scrobbler::start(&config.scrobble)).flatten(),
+        listen_along: ListenAlong::start(&config.sync),
+        ducker: Ducker::start(&config.ducking),
+        _self_monitor: SelfMonitor::start(&config.self_monitor),
+        schedule: Schedule::from_config(&config.schedule),
+        station_ids: StationIdFilter::new(&config.station_ids),
+        enricher: Enricher::start(&config.enrichment, {
+            let events = events_tx.clone();
+            move |track, enrichment| {
+                let _ = events.send(AppEvent::Api(ApiEvent::Enriched(track, enrichment)));
             }
-            None => {
-                tracing::info!("User quit application");
-                break;
+        }),
+        _control: ControlServer::start({
+            let events = events_tx.clone();
+            move |request, reply| {
+                let _ = events.send(AppEvent::Control(request, reply));
             }
+        })
+        .map_err(|e| tracing::warn!("Remote control disabled: {}", e))
+        .ok(),
+        art,
+    };
+    
+    if !config.monitor_channels.is_empty() && archive.is_none() {
+        let monitored: Vec<Channel> = config.monitor_channels
+            .iter()
+            .filter_map(|id| {
+                let channel = channels.iter().find(|c| c.id == *id);
+                if channel.is_none() {
+                    tracing::warn!("Unknown channel '{}' in monitor_channels", id);
+                }
+                channel.cloned()
+            })
+            .collect();
+        if !monitored.is_empty() {
+            integrations.monitor = Some(ChannelMonitor::start(&api, monitored));
         }
     }
     
+    // Try to find the last used channel or default to first
+    let selected_channel_index = if let Some(ref last_id) = config.last_channel_id {
+        channels.iter().position(|c| c.id == *last_id).unwrap_or(0)
+    } else {
+        0
+    };
+    
+    let mut tui = Tui {
+        channels,
+        app: AppState { volume: config.volume, ..AppState::new() },
+        playback: None,
+        next_session: 0,
+        last_notified: None,
+        integrations,
+        events: events_tx,
+        saver: ConfigSaver::start(CONFIG_SAVE_DELAY),
+        cast,
+        stereo: StereoControl::new(config.balance, config.mono),
+        api,
+        restart_at: None,
+        new_channels,
+        levels: match config.channel_leveling {
+            LevelMode::Off => ChannelLevels::default(),
+            _ => ChannelLevels::load(),
+        },
+        session: SessionLog::default(),
+        session_file: args.export_session.clone(),
+        clock: FrameClock::new(config.fps),
+        paused_by_focus: false,
+        bookmarks: Bookmarks::load(),
+        ratings: Ratings::load(),
+    };
+    tui.app.kiosk = args.kiosk;
+    tui.app.volume_keys = VolumeKeys::new(Duration::from_millis(config.key_repeat_ms));
+    tui.app.quit_guard = QuitGuard::new(config.confirm_quit);
+    tui.app.macros = parse_macros(&config.macros);
+    tui.app.spectrum.set_band_count(config.spectrum_bands);
+    tui.app.hidden_channels = config.hidden_channels.iter().cloned().collect();
+    tui.app.select_listed(&tui.channels);
+    tui.app.new_channels = tui.new_channels.ids().map(String::from).collect();
+    tui.app.cast_target = tui.cast.as_ref().map(|r| r.name.clone());
+    if source == ChannelSource::Cached {
+        tui.app.channels_notice = Some("API unavailable, using cached list".to_string());
+    }
+    
+    // A channel scheduled for now takes precedence over the last one,
+    // but not over one asked for on the command line
+    let scheduled = tui.integrations.schedule
+        .check(local_minute())
+        .and_then(|slot| tui.channels.iter().position(|c| c.id == slot.channel_id))
+        .filter(|_| requested_channel.is_none());
+    if let Some(index) = scheduled {
+        tracing::info!("Starting scheduled channel {}", tui.channels[index].title);
+        tui.play(index, config);
+    } else if config.initial_screen() == StartScreen::LastChannel {
+        // Go directly to playing the last/first channel
+        tui.play(selected_channel_index, config);
+    }
+    
+    let summary = StartupSummary::collect(
+        config.log_to_file,
+        tui.app.cast_target.as_deref(),
+        tui.channels.len(),
+        source,
+        tui.playback.as_ref().map(|p| p.channel.title.as_str()),
+    );
+    for (label, value) in summary.lines() {
+        tracing::info!("{}: {}", label, value);
+    }
+    if args.verbose {
+        print!("{}", summary);
+    }
+    
+    // Audio panics are caught and shown on the error screen; only log them
+    // instead of printing over the TUI
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{}", info);
+        if std::thread::current().name() == Some("main") {
+            default_hook(info);
+        }
+    }));
+    
+    // Kiosk mode also runs as a background service, without a terminal
+    let mut terminal = if args.kiosk && !io::stdout().is_terminal() {
+        tracing::info!("No terminal, running kiosk mode headless");
+        None
+    } else {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        if config.pause_on_focus_loss {
+            execute!(stdout, EnableFocusChange)?;
+        }
+        let backend = CrosstermBackend::new(stdout);
+        Some(Terminal::new(backend)?)
+    };
+    
+    if config.terminal_title && terminal.is_some() {
+        tui.integrations.terminal_title = Some(TerminalTitle::new());
+    }
+    if terminal.is_some() && config.taskbar_progress.enabled(|name| std::env::var(name).ok()) {
+        tui.integrations.taskbar = Some(TaskbarProgress::default());
+    }
+    
+    let result = run_tui(&mut terminal, &mut tui, events_rx, config).await;
+    tui.saver.flush().await;
+    if let Some(webhook) = tui.integrations.webhook.take() {
+        webhook.finish().await;
+    }
+    if let Some(scrobbler) = tui.integrations.scrobbler.take() {
+        scrobbler.finish().await;
+    }
+    
+    // Restore the original window title and remove the taskbar badge
+    tui.integrations.terminal_title = None;
+    tui.integrations.taskbar = None;
+    if let Some(terminal) = &mut terminal {
+        disable_raw_mode()?;
+        if config.pause_on_focus_loss {
+            execute!(terminal.backend_mut(), DisableFocusChange)?;
+        }
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+    }
+    let summary = result?;
+    // Back on the normal screen, where it stays after the player is gone
+    if terminal.is_some() {
+        print!("{}", summary);
+    }
+    
     tracing::info!("SomaFM Player shutting down");
     Ok(())
 }
-
-
@@ -0,0 +1,111 @@
+//! `MPRemoteCommandCenter` / `MPNowPlayingInfoCenter` backend.
+//!
+//! Remote command handlers are dispatched on the main run loop, which a
+//! terminal application never runs, so [`Backend::poll`] spins it briefly
+//! from the UI loop (which runs on the main thread).
+
+use std::ffi::c_void;
+use std::sync::mpsc::Sender;
+
+use block2::RcBlock;
+use objc2::rc::Id;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send, msg_send_id};
+use objc2_foundation::{NSNumber, NSString};
+
+use super::MediaKeyEvent;
+
+#[link(name = "MediaPlayer", kind = "framework")]
+unsafe extern "C" {
+    static MPMediaItemPropertyTitle: &'static NSString;
+    static MPMediaItemPropertyArtist: &'static NSString;
+    static MPMediaItemPropertyAlbumTitle: &'static NSString;
+    static MPNowPlayingInfoPropertyIsLiveStream: &'static NSString;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    static kCFRunLoopDefaultMode: *const c_void;
+    fn CFRunLoopRunInMode(mode: *const c_void, seconds: f64, return_after_source_handled: u8) -> i32;
+}
+
+/// `MPRemoteCommandHandlerStatusSuccess`
+const HANDLER_STATUS_SUCCESS: isize = 0;
+/// `MPNowPlayingPlaybackStatePlaying` / `MPNowPlayingPlaybackStatePaused`
+const PLAYBACK_STATE_PLAYING: usize = 1;
+const PLAYBACK_STATE_PAUSED: usize = 2;
+
+pub struct Backend {
+    /// Keeps the registered handler blocks alive
+    _handlers: Vec<RcBlock<dyn Fn(*mut AnyObject) -> isize>>,
+}
+
+impl Backend {
+    pub fn new(events: Sender<MediaKeyEvent>) -> Result<Self, String> {
+        let center: *mut AnyObject = unsafe { msg_send![class!(MPRemoteCommandCenter), sharedCommandCenter] };
+        if center.is_null() {
+            return Err("MPRemoteCommandCenter is not available".to_string());
+        }
+
+        let commands: [(*mut AnyObject, MediaKeyEvent); 4] = unsafe {
+            [
+                (msg_send![center, playCommand], MediaKeyEvent::Play),
+                (msg_send![center, pauseCommand], MediaKeyEvent::Pause),
+                (msg_send![center, togglePlayPauseCommand], MediaKeyEvent::Toggle),
+                (msg_send![center, stopCommand], MediaKeyEvent::Stop),
+            ]
+        };
+
+        let mut handlers = Vec::with_capacity(commands.len());
+        for (command, event) in commands {
+            let events = events.clone();
+            let handler: RcBlock<dyn Fn(*mut AnyObject) -> isize> = RcBlock::new(move |_: *mut AnyObject| {
+                let _ = events.send(event);
+                HANDLER_STATUS_SUCCESS
+            });
+            unsafe {
+                let _: () = msg_send![command, setEnabled: true];
+                let _: *mut AnyObject = msg_send![command, addTargetWithHandler: &*handler];
+            }
+            handlers.push(handler);
+        }
+
+        Ok(Self { _handlers: handlers })
+    }
+
+    /// Services pending remote commands without blocking.
+    pub fn poll(&self) {
+        unsafe {
+            CFRunLoopRunInMode(kCFRunLoopDefaultMode, 0.0, 1);
+        }
+    }
+
+    pub fn set_metadata(&self, title: &str, artist: &str, album: &str) -> Result<(), String> {
+        unsafe {
+            let info: Id<AnyObject> = msg_send_id![class!(NSMutableDictionary), new];
+            let _: () = msg_send![&*info, setObject: &*NSString::from_str(title), forKey: MPMediaItemPropertyTitle];
+            let _: () = msg_send![&*info, setObject: &*NSString::from_str(artist), forKey: MPMediaItemPropertyArtist];
+            let _: () = msg_send![&*info, setObject: &*NSString::from_str(album), forKey: MPMediaItemPropertyAlbumTitle];
+            let _: () = msg_send![&*info, setObject: &*NSNumber::new_bool(true), forKey: MPNowPlayingInfoPropertyIsLiveStream];
+
+            let center: *mut AnyObject = msg_send![class!(MPNowPlayingInfoCenter), defaultCenter];
+            if center.is_null() {
+                return Err("MPNowPlayingInfoCenter is not available".to_string());
+            }
+            let _: () = msg_send![center, setNowPlayingInfo: &*info];
+        }
+        Ok(())
+    }
+
+    pub fn set_playing(&self, playing: bool) -> Result<(), String> {
+        let state = if playing { PLAYBACK_STATE_PLAYING } else { PLAYBACK_STATE_PAUSED };
+        unsafe {
+            let center: *mut AnyObject = msg_send![class!(MPNowPlayingInfoCenter), defaultCenter];
+            if center.is_null() {
+                return Err("MPNowPlayingInfoCenter is not available".to_string());
+            }
+            let _: () = msg_send![center, setPlaybackState: state];
+        }
+        Ok(())
+    }
+}
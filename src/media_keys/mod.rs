@@ -0,0 +1,117 @@
+//! Operating system media key integration.
+//!
+//! On Windows (System Media Transport Controls) and macOS
+//! (`MPRemoteCommandCenter` / `MPNowPlayingInfoCenter`) hardware play/pause
+//! keys control the player and the current track is shown in the OS media
+//! overlay. The platform backends are compiled only with the `media-keys`
+//! cargo feature; elsewhere [`MediaKeys`] is a no-op.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use soma_player::media_keys::{MediaKeys, MediaKeyEvent};
+//!
+//! let mut media_keys = MediaKeys::new();
+//! media_keys.set_metadata("Groove Salad", "Boards of Canada", "Roygbiv");
+//! media_keys.set_playing(true);
+//!
+//! while let Some(event) = media_keys.try_recv() {
+//!     match event {
+//!         MediaKeyEvent::Toggle => { /* pause or resume */ }
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use std::sync::mpsc::{self, Receiver};
+
+#[cfg(all(feature = "media-keys", target_os = "windows"))]
+mod windows;
+#[cfg(all(feature = "media-keys", target_os = "windows"))]
+use self::windows::Backend;
+
+#[cfg(all(feature = "media-keys", target_os = "macos"))]
+mod macos;
+#[cfg(all(feature = "media-keys", target_os = "macos"))]
+use self::macos::Backend;
+
+/// A media key press reported by the operating system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKeyEvent {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+}
+
+/// Handle to the OS media controls for the lifetime of the player.
+pub struct MediaKeys {
+    #[cfg(all(feature = "media-keys", any(target_os = "windows", target_os = "macos")))]
+    backend: Option<Backend>,
+    events: Receiver<MediaKeyEvent>,
+}
+
+impl Default for MediaKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaKeys {
+    /// Registers with the OS media controls. Failures are logged and leave
+    /// the handle disabled rather than preventing playback.
+    pub fn new() -> Self {
+        #[allow(unused_variables)]
+        let (tx, rx) = mpsc::channel();
+
+        #[cfg(all(feature = "media-keys", any(target_os = "windows", target_os = "macos")))]
+        let backend = match Backend::new(tx) {
+            Ok(backend) => {
+                log::info!("Media key integration enabled");
+                Some(backend)
+            }
+            Err(e) => {
+                log::warn!("Media key integration unavailable: {}", e);
+                None
+            }
+        };
+
+        Self {
+            #[cfg(all(feature = "media-keys", any(target_os = "windows", target_os = "macos")))]
+            backend,
+            events: rx,
+        }
+    }
+
+    /// Returns the next pending media key press, if any.
+    pub fn try_recv(&mut self) -> Option<MediaKeyEvent> {
+        #[cfg(all(feature = "media-keys", any(target_os = "windows", target_os = "macos")))]
+        if let Some(backend) = &self.backend {
+            backend.poll();
+        }
+
+        self.events.try_recv().ok()
+    }
+
+    /// Shows the current track in the OS media overlay.
+    #[allow(unused_variables)]
+    pub fn set_metadata(&mut self, channel: &str, artist: &str, title: &str) {
+        #[cfg(all(feature = "media-keys", any(target_os = "windows", target_os = "macos")))]
+        if let Some(backend) = &self.backend
+            && let Err(e) = backend.set_metadata(title, artist, channel)
+        {
+            log::debug!("Failed to update media metadata: {}", e);
+        }
+    }
+
+    /// Reports whether playback is running or paused.
+    #[allow(unused_variables)]
+    pub fn set_playing(&mut self, playing: bool) {
+        #[cfg(all(feature = "media-keys", any(target_os = "windows", target_os = "macos")))]
+        if let Some(backend) = &self.backend
+            && let Err(e) = backend.set_playing(playing)
+        {
+            log::debug!("Failed to update media playback state: {}", e);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! System Media Transport Controls backend.
+//!
+//! A console application has no window to attach SMTC to, so a
+//! `MediaPlayer` with its command manager disabled is used purely as the
+//! owner of the transport controls.
+
+use std::sync::mpsc::Sender;
+
+use windows::core::{Result, HSTRING};
+use windows::Foundation::TypedEventHandler;
+use windows::Media::Playback::MediaPlayer;
+use windows::Media::{
+    MediaPlaybackStatus, MediaPlaybackType, SystemMediaTransportControls,
+    SystemMediaTransportControlsButton, SystemMediaTransportControlsButtonPressedEventArgs,
+};
+
+use super::MediaKeyEvent;
+
+pub struct Backend {
+    _player: MediaPlayer,
+    controls: SystemMediaTransportControls,
+}
+
+impl Backend {
+    pub fn new(events: Sender<MediaKeyEvent>) -> Result<Self> {
+        let player = MediaPlayer::new()?;
+        player.CommandManager()?.SetIsEnabled(false)?;
+
+        let controls = player.SystemMediaTransportControls()?;
+        controls.SetIsEnabled(true)?;
+        controls.SetIsPlayEnabled(true)?;
+        controls.SetIsPauseEnabled(true)?;
+        controls.SetIsStopEnabled(true)?;
+        controls.ButtonPressed(&TypedEventHandler::new(
+            move |_, args: &Option<SystemMediaTransportControlsButtonPressedEventArgs>| {
+                if let Some(args) = args {
+                    let event = match args.Button()? {
+                        SystemMediaTransportControlsButton::Play => Some(MediaKeyEvent::Play),
+                        SystemMediaTransportControlsButton::Pause => Some(MediaKeyEvent::Pause),
+                        SystemMediaTransportControlsButton::Stop => Some(MediaKeyEvent::Stop),
+                        _ => None,
+                    };
+                    if let Some(event) = event {
+                        let _ = events.send(event);
+                    }
+                }
+                Ok(())
+            },
+        ))?;
+
+        Ok(Self { _player: player, controls })
+    }
+
+    /// Button events are delivered on a WinRT thread pool; nothing to pump.
+    pub fn poll(&self) {}
+
+    pub fn set_metadata(&self, title: &str, artist: &str, album: &str) -> Result<()> {
+        let updater = self.controls.DisplayUpdater()?;
+        updater.SetType(MediaPlaybackType::Music)?;
+        let properties = updater.MusicProperties()?;
+        properties.SetTitle(&HSTRING::from(title))?;
+        properties.SetArtist(&HSTRING::from(artist))?;
+        properties.SetAlbumTitle(&HSTRING::from(album))?;
+        updater.Update()
+    }
+
+    pub fn set_playing(&self, playing: bool) -> Result<()> {
+        self.controls.SetPlaybackStatus(if playing {
+            MediaPlaybackStatus::Playing
+        } else {
+            MediaPlaybackStatus::Paused
+        })
+    }
+}
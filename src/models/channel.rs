@@ -1,21 +1,287 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A SomaFM channel. Everything but the ID may be missing from the API
+/// response and falls back to empty values.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Channel {
     pub id: String,
+    #[serde(default)]
     pub title: String,
+    #[serde(default)]
     pub description: String,
+    /// Genres separated by `|`, e.g. `ambient|electronica`
+    #[serde(default)]
+    pub genre: String,
+    #[serde(default)]
     pub playlists: Vec<Playlist>,
+    /// Current listener count as reported by the API
+    #[serde(default)]
+    pub listeners: Option<String>,
+    /// Most recently played track (`Artist - Title`)
+    #[serde(default, rename = "lastPlaying")]
+    pub last_playing: Option<String>,
+    /// URL of the channel's logo
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+/// Preferred stream quality, matched against each playlist's `quality`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamQuality {
+    Highest,
+    #[default]
+    High,
+    Low,
+}
+
+impl StreamQuality {
+    pub const ALL: [StreamQuality; 3] = [StreamQuality::Highest, StreamQuality::High, StreamQuality::Low];
+
+    /// Quality a playlist is listed with, if it is one of the known ones
+    pub fn of_playlist(playlist: &Playlist) -> Option<Self> {
+        Self::ALL.into_iter().find(|quality| quality.to_string() == playlist.quality)
+    }
+
+    /// Playlist qualities to try, best match first
+    fn preference(self) -> [&'static str; 3] {
+        match self {
+            StreamQuality::Highest => ["highest", "high", "low"],
+            StreamQuality::High => ["high", "highest", "low"],
+            StreamQuality::Low => ["low", "high", "highest"],
+        }
+    }
+}
+
+impl std::fmt::Display for StreamQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StreamQuality::Highest => "highest",
+            StreamQuality::High => "high",
+            StreamQuality::Low => "low",
+        })
+    }
+}
+
+impl Channel {
+    /// Returns the playlist used for playback at the default quality.
+    pub fn primary_playlist(&self) -> Option<&Playlist> {
+        self.preferred_playlist(StreamQuality::default())
+    }
+
+    /// Returns the MP3 playlist closest to `quality`, then any MP3, then
+    /// whatever is listed first.
+    pub fn preferred_playlist(&self, quality: StreamQuality) -> Option<&Playlist> {
+        quality
+            .preference()
+            .iter()
+            .find_map(|q| self.playlists.iter().find(|p| p.format == "mp3" && p.quality == *q))
+            .or_else(|| self.playlists.iter().find(|p| p.format == "mp3"))
+            .or_else(|| self.playlists.first())
+    }
+
+    /// Parsed listener count, if the API reported a valid one
+    pub fn listener_count(&self) -> Option<u32> {
+        self.listeners.as_deref()?.trim().parse().ok()
+    }
+}
+
+#[cfg(test)]
+impl Channel {
+    /// Channel `id` titled `title` with nothing else set, for tests.
+    pub(crate) fn named(id: &str, title: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            title: title.to_string(),
+            ..Self::default()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Playlist {
     pub url: String,
+    #[serde(default)]
     pub format: String,
+    #[serde(default)]
     pub quality: String,
 }
 
+impl Playlist {
+    /// Bitrate in kbps, taken from the digits SomaFM appends to the channel
+    /// ID in playlist names (`groovesalad256.pls`); `None` when there are
+    /// none.
+    pub fn bitrate(&self, channel_id: &str) -> Option<u32> {
+        let name = self.url.rsplit('/').next()?;
+        let stem = name.split('.').next()?;
+        stem.strip_prefix(channel_id)?.parse().ok()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SomaFmResponse {
+    #[serde(default)]
     pub channels: Vec<Channel>,
 }
+
+/// Lowercase letters and digits only, so "Drone Zone" and "dronezone" compare equal
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether all characters of `needle` appear in `haystack` in order
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// Finds a channel by ID or title, tolerating case, spaces and punctuation.
+///
+/// Exact matches win over channels whose ID or title contains the query,
+/// which win over channels containing its letters in order ("grv sld").
+/// Returns the channel's index, or an error naming the candidates when the
+/// query is ambiguous.
+pub fn find_channel(channels: &[Channel], query: &str) -> Result<usize, String> {
+    let wanted = normalize(query);
+    if wanted.is_empty() {
+        return Err("No channel name given".to_string());
+    }
+
+    let keys: Vec<[String; 2]> = channels.iter().map(|c| [normalize(&c.id), normalize(&c.title)]).collect();
+    let tiers: [&dyn Fn(&str) -> bool; 3] = [
+        &|key| key == wanted,
+        &|key| key.contains(&wanted),
+        &|key| is_subsequence(&wanted, key),
+    ];
+
+    for matches_key in tiers {
+        let found: Vec<usize> = (0..channels.len())
+            .filter(|&i| keys[i].iter().any(|key| matches_key(key)))
+            .collect();
+        match found.as_slice() {
+            [] => continue,
+            [index] => return Ok(*index),
+            _ => {
+                let titles: Vec<&str> = found.iter().map(|&i| channels[i].title.as_str()).collect();
+                return Err(format!("'{}' matches several channels: {}", query, titles.join(", ")));
+            }
+        }
+    }
+
+    Err(format!("No channel matches '{}'", query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(format: &str, quality: &str) -> Playlist {
+        Playlist {
+            url: format!("https://somafm.com/test-{}-{}.pls", format, quality),
+            format: format.to_string(),
+            quality: quality.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_channel_by_name() {
+        let channels = vec![
+            Channel::named("groovesalad", "Groove Salad"),
+            Channel::named("gsclassic", "Groove Salad Classic"),
+            Channel::named("dronezone", "Drone Zone"),
+        ];
+
+        assert_eq!(find_channel(&channels, "drone zone"), Ok(2));
+        assert_eq!(find_channel(&channels, "DRONE"), Ok(2));
+        // An exact title wins over the longer title containing it
+        assert_eq!(find_channel(&channels, "groove salad"), Ok(0));
+        assert_eq!(find_channel(&channels, "salad classic"), Ok(1));
+        assert_eq!(find_channel(&channels, "drn zn"), Ok(2));
+
+        let ambiguous = find_channel(&channels, "groove").unwrap_err();
+        assert!(ambiguous.contains("Groove Salad, Groove Salad Classic"));
+        assert!(find_channel(&channels, "metal").is_err());
+        assert!(find_channel(&channels, "  ").is_err());
+    }
+
+    #[test]
+    fn test_primary_playlist_prefers_high_quality_mp3() {
+        let channel = Channel {
+            playlists: vec![playlist("aac", "high"), playlist("mp3", "low"), playlist("mp3", "high")],
+            ..Channel::named("test", "Test")
+        };
+
+        let primary = channel.primary_playlist().unwrap();
+        assert_eq!(primary.format, "mp3");
+        assert_eq!(primary.quality, "high");
+    }
+
+    #[test]
+    fn test_primary_playlist_falls_back() {
+        let mut channel = Channel {
+            playlists: vec![playlist("aac", "high"), playlist("mp3", "low")],
+            ..Channel::named("test", "Test")
+        };
+        assert_eq!(channel.primary_playlist().unwrap().format, "mp3");
+
+        channel.playlists = vec![playlist("aac", "high")];
+        assert_eq!(channel.primary_playlist().unwrap().format, "aac");
+
+        channel.playlists.clear();
+        assert!(channel.primary_playlist().is_none());
+    }
+
+    #[test]
+    fn test_preferred_playlist_follows_quality() {
+        let channel = Channel {
+            playlists: vec![playlist("aac", "highest"), playlist("mp3", "highest"), playlist("mp3", "high")],
+            ..Channel::named("test", "Test")
+        };
+
+        assert_eq!(channel.preferred_playlist(StreamQuality::Highest).unwrap().quality, "highest");
+        assert_eq!(channel.preferred_playlist(StreamQuality::Highest).unwrap().format, "mp3");
+        assert_eq!(channel.preferred_playlist(StreamQuality::High).unwrap().quality, "high");
+        // No low MP3 stream: the next best is used
+        assert_eq!(channel.preferred_playlist(StreamQuality::Low).unwrap().quality, "high");
+    }
+
+    #[test]
+    fn test_playlist_bitrate() {
+        let mut playlist = playlist("mp3", "highest");
+        playlist.url = "https://api.somafm.com/groovesalad256.pls".to_string();
+        assert_eq!(playlist.bitrate("groovesalad"), Some(256));
+
+        playlist.url = "https://api.somafm.com/groovesalad.pls".to_string();
+        assert_eq!(playlist.bitrate("groovesalad"), None);
+
+        // Digits in the channel ID are not a bitrate
+        playlist.url = "https://api.somafm.com/sf1033.pls".to_string();
+        assert_eq!(playlist.bitrate("sf1033"), None);
+        playlist.url = "https://api.somafm.com/sf1033130.pls".to_string();
+        assert_eq!(playlist.bitrate("sf1033"), Some(130));
+    }
+
+    #[test]
+    fn test_api_fields() {
+        let channel: Channel = serde_json::from_str(r#"{
+            "id": "groovesalad",
+            "title": "Groove Salad",
+            "description": "A nicely chilled plate of ambient beats",
+            "playlists": [],
+            "listeners": "1234",
+            "lastPlaying": "Thievery Corporation - Lebanese Blonde"
+        }"#).unwrap();
+        assert_eq!(channel.listener_count(), Some(1234));
+        assert_eq!(channel.last_playing.as_deref(), Some("Thievery Corporation - Lebanese Blonde"));
+
+        // Both fields are optional
+        let channel: Channel = serde_json::from_str(
+            r#"{"id": "x", "title": "X", "description": "", "playlists": []}"#
+        ).unwrap();
+        assert_eq!(channel.listener_count(), None);
+        assert_eq!(channel.last_playing, None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Writing the channel list to a file for other players and scripts.
+//!
+//! JSON output has the same shape as the SomaFM API response, so anything
+//! that reads `channels.json` from SomaFM reads the export too. CSV output
+//! has one row per playlist, repeating the channel columns.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::models::Channel;
+
+/// File format of a channel export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// Picks the format from the file extension, JSON unless it is `.csv`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+}
+
+/// Same shape as [`SomaFmResponse`](crate::models::SomaFmResponse), without
+/// copying the channels.
+#[derive(Serialize)]
+struct Export<'a> {
+    channels: &'a [Channel],
+}
+
+/// Renders `channels` in `format`.
+pub fn format_channels(channels: &[Channel], format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&Export { channels })
+            .map(|json| json + "\n")
+            .map_err(|e| format!("Failed to serialize channels: {}", e)),
+        ExportFormat::Csv => Ok(channels_csv(channels)),
+    }
+}
+
+/// Writes `channels` to `path` in the format its extension asks for.
+pub fn export_channels(channels: &[Channel], path: &Path) -> Result<ExportFormat, Box<dyn std::error::Error>> {
+    let format = ExportFormat::from_path(path);
+    fs::write(path, format_channels(channels, format)?)?;
+    Ok(format)
+}
+
+fn channels_csv(channels: &[Channel]) -> String {
+    let mut csv = String::from("id,title,description,listeners,last_playing,playlist_url,format,quality\n");
+    for channel in channels {
+        let columns = [
+            channel.id.as_str(),
+            &channel.title,
+            &channel.description,
+            channel.listeners.as_deref().unwrap_or(""),
+            channel.last_playing.as_deref().unwrap_or(""),
+        ];
+        let mut push_row = |playlist: [&str; 3]| {
+            let row: Vec<String> = columns.iter().chain(playlist.iter()).map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        };
+        if channel.playlists.is_empty() {
+            push_row(["", "", ""]);
+        }
+        for playlist in &channel.playlists {
+            push_row([&playlist.url, &playlist.format, &playlist.quality]);
+        }
+    }
+    csv
+}
+
+/// Quotes a field when it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Playlist, SomaFmResponse};
+
+    #[test]
+    fn test_export_formats() {
+        let channels = vec![Channel {
+            id: "groovesalad".to_string(),
+            title: "Groove Salad".to_string(),
+            description: "A nicely chilled plate of \"ambient\", downtempo beats".to_string(),
+            playlists: vec![
+                Playlist {
+                    url: "https://api.somafm.com/groovesalad256.pls".to_string(),
+                    format: "mp3".to_string(),
+                    quality: "highest".to_string(),
+                },
+                Playlist {
+                    url: "https://api.somafm.com/groovesalad64.pls".to_string(),
+                    format: "aacp".to_string(),
+                    quality: "low".to_string(),
+                },
+            ],
+            listeners: Some("1234".to_string()),
+            ..Channel::default()
+        }];
+
+        let csv = format_channels(&channels, ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "groovesalad,Groove Salad,\"A nicely chilled plate of \"\"ambient\"\", downtempo beats\",1234,,https://api.somafm.com/groovesalad256.pls,mp3,highest"
+        );
+
+        // The JSON export reads back like an API response
+        let json = format_channels(&channels, ExportFormat::Json).unwrap();
+        let parsed: SomaFmResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.channels[0].playlists.len(), 2);
+
+        assert_eq!(ExportFormat::from_path(Path::new("channels.CSV")), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_path(Path::new("channels")), ExportFormat::Json);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod channel;
 pub mod track;
 pub mod spectrum;
+pub mod export;
+pub mod stream_info;
 
 pub use channel::*;
 pub use track::*;
 pub use spectrum::*;
+pub use export::*;
+pub use stream_info::*;
@@ -1,14 +1,23 @@
 //! Audio spectrum visualization data structures and simulation.
+//!
+//! The spectrum is moved forward by the frame clock, see
+//! [`crate::ui::animation`]; all rates below are per second, so it looks
+//! the same at any frame rate.
 
-use std::time::Instant;
+/// Fewest and most bands a spectrum can have
+pub const BAND_RANGE: (usize, usize) = (4, 64);
 
 /// Represents audio frequency spectrum data for visualization
 #[derive(Debug, Clone)]
 pub struct AudioSpectrum {
     /// Frequency bands (typically 8-16 bands for visualization)
     pub bands: Vec<f32>,
-    /// Last update timestamp
-    pub last_update: Instant,
+    /// Seconds animated so far, driving the simulated beat
+    pub elapsed: f32,
+    /// Whether music is playing, as last set with [`Self::set_playback`]
+    playing: bool,
+    /// Whether playback is paused
+    paused: bool,
     /// Spectrum animation state
     animation_state: SpectrumAnimationState,
 }
@@ -19,8 +28,6 @@ struct SpectrumAnimationState {
     targets: Vec<f32>,
     /// Decay rate for falling bars
     decay_rate: f32,
-    /// Update frequency in Hz
-    update_hz: f32,
     /// Random number generator seed state
     rng_state: u64,
 }
@@ -36,11 +43,12 @@ impl AudioSpectrum {
     pub fn new(num_bands: usize) -> Self {
         let mut spectrum = Self {
             bands: vec![0.0; num_bands],
-            last_update: Instant::now(),
+            elapsed: 0.0,
+            playing: false,
+            paused: false,
             animation_state: SpectrumAnimationState {
                 targets: vec![0.0; num_bands],
                 decay_rate: 0.95, // How fast bars fall
-                update_hz: 30.0,  // 30 FPS updates
                 rng_state: 42,    // Seed for deterministic randomness
             },
         };
@@ -57,33 +65,35 @@ impl AudioSpectrum {
         spectrum
     }
 
-    /// Update the spectrum with simulated audio data
-    /// This creates a realistic-looking spectrum that reacts to "music"
-    pub fn update(&mut self, is_playing: bool, is_paused: bool) {
-        let now = Instant::now();
-        let delta_time = now.duration_since(self.last_update).as_secs_f32();
-        
-        // Only update at target framerate
-        if delta_time < 1.0 / self.animation_state.update_hz {
-            return;
-        }
-        
-        self.last_update = now;
+    /// Sets whether music is playing and whether it is paused, which the
+    /// next steps follow.
+    pub fn set_playback(&mut self, is_playing: bool, is_paused: bool) {
+        self.playing = is_playing;
+        self.paused = is_paused;
+    }
 
-        if is_paused {
+    /// Advances the spectrum by `delta_time` seconds of simulated audio
+    /// data. This creates a realistic-looking spectrum that reacts to
+    /// "music".
+    pub fn step(&mut self, delta_time: f32) {
+        self.elapsed += delta_time;
+        // Factors below are per tenth of a second
+        let frames = delta_time * 10.0;
+
+        if self.paused {
             // When paused, just decay existing values
             for (band, target) in self.bands.iter_mut().zip(self.animation_state.targets.iter_mut()) {
-                *target *= 0.8; // Faster decay when paused
-                *band = (*band * 0.9).max(*target);
+                *target *= 0.8f32.powf(frames); // Faster decay when paused
+                *band = (*band * 0.9f32.powf(frames)).max(*target);
             }
             return;
         }
 
-        if !is_playing {
+        if !self.playing {
             // When not playing, gradually reduce to zero
             for (band, target) in self.bands.iter_mut().zip(self.animation_state.targets.iter_mut()) {
                 *target = 0.0;
-                *band *= 0.95;
+                *band *= 0.95f32.powf(frames);
             }
             return;
         }
@@ -118,11 +128,12 @@ impl AudioSpectrum {
             let prev_influence = if i > 0 { prev_target * 0.3 } else { 0.0 };
             
             // Simulate musical "beats" and variation
-            let beat_pattern = ((self.last_update.elapsed().as_secs_f32() * 2.0).sin() + 1.0) / 2.0;
+            let beat_pattern = ((self.elapsed * 2.0).sin() + 1.0) / 2.0;
             let variation = random_factor * band_multiplier + prev_influence + beat_pattern * 0.2;
             
             // Set new target with some smoothing
-            *target = (*target * 0.7 + variation.clamp(0.0, 1.0) * 0.3).min(1.0);
+            let keep = 0.7f32.powf(delta_time * 10.0);
+            *target = (*target * keep + variation.clamp(0.0, 1.0) * (1.0 - keep)).min(1.0);
             
             // Store current target for next iteration
             prev_target = *target;
@@ -150,15 +161,28 @@ impl AudioSpectrum {
         self.bands.len()
     }
 
+    /// Changes the number of bands, kept within [`BAND_RANGE`]. The bands
+    /// start over while the playback state carries on.
+    pub fn set_band_count(&mut self, num_bands: usize) {
+        let num_bands = num_bands.clamp(BAND_RANGE.0, BAND_RANGE.1);
+        if num_bands == self.bands.len() {
+            return;
+        }
+        let resized = Self {
+            elapsed: self.elapsed,
+            playing: self.playing,
+            paused: self.paused,
+            ..Self::new(num_bands)
+        };
+        let decay_rate = self.animation_state.decay_rate;
+        *self = resized;
+        self.animation_state.decay_rate = decay_rate;
+    }
+
     /// Set the decay rate (how fast bars fall)
     pub fn set_decay_rate(&mut self, decay_rate: f32) {
         self.animation_state.decay_rate = decay_rate.clamp(0.1, 0.99);
     }
-
-    /// Set the update frequency
-    pub fn set_update_hz(&mut self, hz: f32) {
-        self.animation_state.update_hz = hz.clamp(10.0, 60.0);
-    }
 }
 
 #[cfg(test)]
@@ -172,6 +196,21 @@ mod tests {
         assert_eq!(spectrum.get_bands().len(), 8);
     }
 
+    #[test]
+    fn test_set_band_count() {
+        let mut spectrum = AudioSpectrum::new(12);
+        spectrum.set_playback(true, true);
+        spectrum.set_band_count(32);
+        assert_eq!(spectrum.band_count(), 32);
+        assert_eq!(spectrum.animation_state.targets.len(), 32);
+        assert!(spectrum.paused);
+
+        spectrum.set_band_count(1);
+        assert_eq!(spectrum.band_count(), BAND_RANGE.0);
+        spectrum.set_band_count(1000);
+        assert_eq!(spectrum.band_count(), BAND_RANGE.1);
+    }
+
     #[test]
     fn test_spectrum_update_when_not_playing() {
         let mut spectrum = AudioSpectrum::new(4);
@@ -181,7 +220,8 @@ mod tests {
         spectrum.bands = initial_values.clone();
         
         // Update when not playing
-        spectrum.update(false, false);
+        spectrum.set_playback(false, false);
+        spectrum.step(0.1);
         
         // Values should decay - each should be less than or equal to initial value
         for (i, &band) in spectrum.get_bands().iter().enumerate() {
@@ -197,7 +237,8 @@ mod tests {
         spectrum.bands = vec![0.8, 0.6, 0.4, 0.2];
         
         // Update when paused
-        spectrum.update(true, true);
+        spectrum.set_playback(true, true);
+        spectrum.step(0.1);
         
         // Values should still be positive but decaying
         for &band in spectrum.get_bands() {
@@ -211,12 +252,13 @@ mod tests {
         let mut spectrum = AudioSpectrum::new(6);
         
         // Force update multiple times
+        spectrum.set_playback(true, false);
         for _ in 0..100 {
-            spectrum.update(true, false);
+            spectrum.step(0.1);
             
             // All values should be within bounds
             for &band in spectrum.get_bands() {
-                assert!(band >= 0.0 && band <= 1.0, "Band value {} out of bounds", band);
+                assert!((0.0..=1.0).contains(&band), "Band value {} out of bounds", band);
             }
         }
     }
@@ -226,17 +268,13 @@ mod tests {
         let mut spectrum = AudioSpectrum::new(4);
         
         spectrum.set_decay_rate(0.8);
-        spectrum.set_update_hz(25.0);
         
         // Should accept valid values
         assert!(spectrum.animation_state.decay_rate >= 0.1);
-        assert!(spectrum.animation_state.update_hz >= 10.0);
         
         // Test boundary conditions
         spectrum.set_decay_rate(2.0); // Should be clamped
-        spectrum.set_update_hz(5.0);  // Should be clamped
         
         assert!(spectrum.animation_state.decay_rate <= 0.99);
-        assert!(spectrum.animation_state.update_hz >= 10.0);
     }
 }
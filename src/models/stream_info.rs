@@ -0,0 +1,86 @@
+//! Station details a stream announces in its ICY headers.
+//!
+//! Servers send `icy-name`, `icy-genre`, `icy-description` and `icy-url`
+//! along with the audio. For SomaFM channels the API already knows all of
+//! this, but for stations from a channel bundle or any other URL these
+//! headers are the only description there is.
+
+/// The `icy-*` headers of a connected stream.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamInfo {
+    /// Station name (`icy-name`)
+    pub name: Option<String>,
+    /// Genre (`icy-genre`)
+    pub genre: Option<String>,
+    /// Description (`icy-description`)
+    pub description: Option<String>,
+    /// Station website (`icy-url`)
+    pub url: Option<String>,
+    /// Bitrate in kbps (`icy-br`)
+    pub bitrate: Option<u32>,
+}
+
+impl StreamInfo {
+    /// Builds the info from raw header values. Blank values and the
+    /// placeholders some servers send, such as `Unspecified`, are dropped.
+    pub fn new(
+        name: Option<&str>,
+        genre: Option<&str>,
+        description: Option<&str>,
+        url: Option<&str>,
+        bitrate: Option<u32>,
+    ) -> Self {
+        Self {
+            name: clean(name),
+            genre: clean(genre),
+            description: clean(description),
+            url: clean(url),
+            bitrate,
+        }
+    }
+
+    /// Whether the stream announced nothing worth showing.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.genre.is_none() && self.description.is_none() && self.url.is_none()
+    }
+
+    /// One line with every announced detail, e.g. `Groove Salad | Ambient |
+    /// https://somafm.com`, or `None` when there are none.
+    pub fn summary(&self) -> Option<String> {
+        let parts: Vec<&str> = [&self.name, &self.genre, &self.description, &self.url]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" | "))
+    }
+}
+
+fn clean(value: Option<&str>) -> Option<String> {
+    let value = value?.trim();
+    let placeholder = ["unspecified", "unknown", "n/a", "none"].iter().any(|p| value.eq_ignore_ascii_case(p));
+    (!value.is_empty() && !placeholder).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_info_summary() {
+        let info = StreamInfo::new(
+            Some(" My Radio "),
+            Some("Unspecified"),
+            Some(""),
+            Some("https://radio.example.com"),
+            Some(128),
+        );
+        assert_eq!(info.name.as_deref(), Some("My Radio"));
+        assert_eq!(info.genre, None);
+        assert_eq!(info.summary().as_deref(), Some("My Radio | https://radio.example.com"));
+
+        let empty = StreamInfo::new(None, None, Some("N/A"), None, Some(128));
+        assert!(empty.is_empty());
+        assert_eq!(empty.summary(), None);
+    }
+}
@@ -3,11 +3,13 @@
 //! This module provides data structures and functions for handling
 //! track metadata from ICY streams.
 
+use unicode_normalization::UnicodeNormalization;
+
 /// Represents information about the currently playing track.
 ///
 /// This information is typically extracted from ICY metadata
 /// sent by the audio stream.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TrackInfo {
     /// The artist name
     pub artist: String,
@@ -73,6 +75,60 @@ pub fn parse_track_info(stream_title: &str) -> TrackInfo {
     }
 }
 
+/// Cleans up raw ICY metadata.
+///
+/// Stations fill the stream title from whatever their playout software
+/// holds, so it may come HTML escaped, padded, split over several lines,
+/// NUL terminated or in decomposed Unicode. This unescapes HTML entities,
+/// normalizes to NFC, turns control characters into spaces, drops
+/// zero-width spaces and byte order marks, and collapses runs of
+/// whitespace into single spaces.
+///
+/// # Examples
+///
+/// ```rust
+/// use soma_player::models::sanitize_metadata;
+///
+/// assert_eq!(sanitize_metadata("Simon &amp; Garfunkel -  Mrs. Robinson\0"), "Simon & Garfunkel - Mrs. Robinson");
+/// ```
+pub fn sanitize_metadata(raw: &str) -> String {
+    let decoded = html_escape::decode_html_entities(raw);
+    let cleaned: String = decoded
+        .nfc()
+        .filter(|c| !matches!(c, '\u{200B}' | '\u{2060}' | '\u{FEFF}'))
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Track information from a raw ICY stream title: the title is
+/// sanitized with [`sanitize_metadata`], then parsed with
+/// [`parse_track_info`].
+///
+/// Everything that reads ICY metadata goes through this, so the UI,
+/// notifications and recordings only ever see clean text.
+pub fn track_from_metadata(stream_title: &str) -> TrackInfo {
+    parse_track_info(&sanitize_metadata(stream_title))
+}
+
+/// Fills in `{artist}`, `{title}` and `{channel}` in a `track_format`
+/// style template.
+///
+/// # Examples
+///
+/// ```rust
+/// use soma_player::models::{format_track, TrackInfo};
+///
+/// let track = TrackInfo { artist: "Air".to_string(), title: "Alone in Kyoto".to_string() };
+/// assert_eq!(format_track("{title} — {artist} [{channel}]", "Groove Salad", &track), "Alone in Kyoto — Air [Groove Salad]");
+/// ```
+pub fn format_track(format: &str, channel: &str, track: &TrackInfo) -> String {
+    format
+        .replace("{artist}", &track.artist)
+        .replace("{title}", &track.title)
+        .replace("{channel}", channel)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,4 +202,72 @@ mod tests {
         assert_eq!(track.artist, "Björk");
         assert_eq!(track.title, "Jóga");
     }
+
+    #[test]
+    fn test_sanitize_metadata_samples() {
+        let samples = [
+            ("Beyonc&eacute; - Halo", "Beyoncé - Halo"),
+            ("&#39;Til Tuesday - Voices Carry", "'Til Tuesday - Voices Carry"),
+            ("AC&#x2F;DC - T.N.T.", "AC/DC - T.N.T."),
+            ("Artist\r\n - Title\0\0\0", "Artist - Title"),
+            ("  Boards  of\tCanada -\u{a0} Roygbiv ", "Boards of Canada - Roygbiv"),
+            ("\u{feff}Air - La Femme d\u{2019}Argent", "Air - La Femme d\u{2019}Argent"),
+            ("Zero\u{200b}7 - In the Waiting Line", "Zero7 - In the Waiting Line"),
+            ("Red\u{1b}[0m - Alert", "Red [0m - Alert"),
+            // Unknown or unterminated entities are left as they are
+            ("Tom &amp Jerry - &bogus; &lt;3", "Tom &amp Jerry - &bogus; <3"),
+        ];
+        for (raw, clean) in samples {
+            assert_eq!(sanitize_metadata(raw), clean, "sanitizing {:?}", raw);
+        }
+        // Decomposed accents are composed, so equal names compare equal
+        assert_eq!(sanitize_metadata("Bjo\u{308}rk"), "Bj\u{f6}rk");
+
+        let track = track_from_metadata("Simon &amp; Garfunkel\n -\tMrs. Robinson\0");
+        assert_eq!(track.artist, "Simon & Garfunkel");
+        assert_eq!(track.title, "Mrs. Robinson");
+    }
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// A name as found in stream metadata: any text without the
+        /// separator, not blank and not padded
+        fn name() -> impl Strategy<Value = String> {
+            "\\PC{1,30}".prop_filter("a name", |s| !s.contains(" - ") && !s.trim().is_empty() && s.trim() == s)
+        }
+
+        proptest! {
+            #[test]
+            fn never_panics(title in "\\PC*") {
+                let track = parse_track_info(&title);
+                prop_assert!(!track.artist.is_empty());
+            }
+
+            #[test]
+            fn splits_at_the_first_separator(
+                artist in name(),
+                title in name(),
+                rest in proptest::option::of(name()),
+                before in "[ \t]{0,3}",
+                after in "[ \t]{0,3}",
+            ) {
+                let title = match rest {
+                    Some(rest) => format!("{} - {}", title, rest),
+                    None => title,
+                };
+                let track = parse_track_info(&format!("{}{} - {}{}", before, artist, title, after));
+                prop_assert_eq!(track.artist, artist);
+                prop_assert_eq!(track.title, title);
+            }
+
+            #[test]
+            fn keeps_titles_without_separator(title in "\\PC*") {
+                prop_assume!(!title.contains(" - "));
+                let track = parse_track_info(&title);
+                prop_assert_eq!(track.artist, "Unknown");
+                prop_assert_eq!(track.title, title);
+            }
+        }
+    }
 }
@@ -0,0 +1,134 @@
+//! Background monitoring of what other channels are playing.
+//!
+//! Each monitored channel gets its own lightweight stream connection whose
+//! audio is read and discarded without decoding, so only the interleaved
+//! ICY metadata is kept. The latest track per channel can be read at any
+//! time with [`ChannelMonitor::snapshot`].
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+
+use crate::api::ApiClient;
+use crate::audio::icy::IcyStream;
+use crate::models::{track_from_metadata, Channel, TrackInfo};
+
+/// Maximum number of channels monitored at once
+pub const MAX_MONITORED_CHANNELS: usize = 3;
+
+/// Delay before reconnecting a monitor whose stream failed
+const RECONNECT_DELAY: Duration = Duration::from_secs(15);
+
+/// The latest known track on a monitored channel
+#[derive(Debug, Clone)]
+pub struct MonitoredTrack {
+    pub channel_id: String,
+    pub channel_title: String,
+    pub track: TrackInfo,
+}
+
+/// Keeps metadata-only connections open to a few channels.
+pub struct ChannelMonitor {
+    channels: Vec<Channel>,
+    tracks: Arc<Mutex<HashMap<String, TrackInfo>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ChannelMonitor {
+    /// Starts monitoring the given channels (at most
+    /// [`MAX_MONITORED_CHANNELS`]), resolving their playlists through `api`.
+    /// Must be called within a Tokio runtime.
+    pub fn start(api: &ApiClient, mut channels: Vec<Channel>) -> Self {
+        if channels.len() > MAX_MONITORED_CHANNELS {
+            warn!("Monitoring only the first {} of {} channels", MAX_MONITORED_CHANNELS, channels.len());
+            channels.truncate(MAX_MONITORED_CHANNELS);
+        }
+
+        let tracks = Arc::new(Mutex::new(HashMap::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        for channel in &channels {
+            info!("Monitoring channel {}", channel.id);
+            tokio::spawn(monitor_channel(api.clone(), channel.clone(), Arc::clone(&tracks), Arc::clone(&stop)));
+        }
+
+        Self { channels, tracks, stop }
+    }
+
+    /// Returns the latest track for every monitored channel, in the order
+    /// the channels were given. Channels without metadata yet show the
+    /// default "Loading..." track.
+    pub fn snapshot(&self) -> Vec<MonitoredTrack> {
+        let tracks = self.tracks.lock().unwrap_or_else(|e| e.into_inner());
+        self.channels
+            .iter()
+            .map(|channel| MonitoredTrack {
+                channel_id: channel.id.clone(),
+                channel_title: channel.title.clone(),
+                track: tracks.get(&channel.id).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+impl Drop for ChannelMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Keeps one channel's metadata connection alive until `stop` is set.
+async fn monitor_channel(
+    api: ApiClient,
+    channel: Channel,
+    tracks: Arc<Mutex<HashMap<String, TrackInfo>>>,
+    stop: Arc<AtomicBool>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        if let Err(e) = read_metadata(&api, &channel, &tracks, &stop).await {
+            warn!("Monitor for {} failed: {}", channel.id, e);
+        }
+        if !stop.load(Ordering::Relaxed) {
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+    debug!("Stopped monitoring {}", channel.id);
+}
+
+/// Connects to the channel's lowest-bandwidth stream and records every
+/// stream title until the connection ends or `stop` is set.
+async fn read_metadata(
+    api: &ApiClient,
+    channel: &Channel,
+    tracks: &Arc<Mutex<HashMap<String, TrackInfo>>>,
+    stop: &Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let playlist = channel.playlists
+        .iter()
+        .find(|p| p.quality == "low")
+        .or_else(|| channel.primary_playlist())
+        .ok_or("No stream URL for this channel")?;
+
+    let stream_url = api.resolve_playlist_url(playlist).await.map_err(|e| e.to_string())?;
+
+    let channel_id = channel.id.clone();
+    let tracks = Arc::clone(tracks);
+    let stream = IcyStream::connect(&stream_url, move |stream_title| {
+        debug!("Monitor {}: {}", channel_id, stream_title);
+        tracks.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(channel_id.clone(), track_from_metadata(stream_title));
+    })
+    .await?;
+    if !stream.has_metadata() {
+        return Err("Stream does not provide ICY metadata".into());
+    }
+
+    let stop = Arc::clone(stop);
+    tokio::task::spawn_blocking(move || stream.discard_audio(&stop)).await??;
+
+    Ok(())
+}
@@ -0,0 +1,337 @@
+//! Desktop notifications and track-change hooks.
+//!
+//! When enabled, the player announces track changes with a desktop
+//! notification (`notify-send` on Linux, `osascript` on macOS) and can run a
+//! user-provided hook command. Both are suppressed during the configured
+//! quiet hours while playback continues normally, and for a track that was
+//! announced a moment ago, as reconnects and flapping metadata repeat the
+//! same artist and title.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use soma_player::notifications::QuietHours;
+//! use chrono::NaiveTime;
+//!
+//! let quiet = QuietHours::parse("22:00-08:00").unwrap();
+//! assert!(quiet.contains(NaiveTime::from_hms_opt(23, 30, 0).unwrap()));
+//! assert!(!quiet.contains(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+use log::{debug, warn};
+
+use crate::api::art::ArtCache;
+use crate::config::NotificationConfig;
+use crate::models::{format_track, Channel, TrackInfo};
+
+/// A daily time window, possibly wrapping past midnight (e.g. `22:00-08:00`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Parses a `HH:MM-HH:MM` range.
+    pub fn parse(range: &str) -> Result<Self, String> {
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| format!("Invalid quiet hours '{}', expected HH:MM-HH:MM", range))?;
+
+        let parse_time = |s: &str| {
+            NaiveTime::parse_from_str(s.trim(), "%H:%M")
+                .map_err(|e| format!("Invalid time '{}' in quiet hours: {}", s.trim(), e))
+        };
+
+        Ok(Self {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    }
+
+    /// Returns whether `time` falls inside the window. The start is
+    /// inclusive and the end exclusive; an empty window never matches.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Sends desktop notifications and runs hooks for track changes.
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    enabled: bool,
+    hook: Option<String>,
+    quiet_hours: Option<QuietHours>,
+    /// `track_format` for the notification text
+    format: Option<String>,
+    /// How long an announced track is not announced again
+    dedup_window: Duration,
+    /// When each track was last announced, keyed by lowercased artist and
+    /// title
+    announced: HashMap<(String, String), Instant>,
+    /// Channel logos shown as the notification icon
+    art: Option<ArtCache>,
+}
+
+impl Notifier {
+    /// Builds a notifier from configuration. Invalid quiet hours are logged
+    /// and ignored rather than disabling notifications altogether. Tracks
+    /// are shown as `Artist - Title` unless `track_format` is set.
+    pub fn new(config: &NotificationConfig, track_format: Option<&str>) -> Self {
+        let quiet_hours = config.quiet_hours.as_deref().and_then(|range| {
+            QuietHours::parse(range)
+                .map_err(|e| warn!("{}", e))
+                .ok()
+        });
+
+        Self {
+            enabled: config.enabled,
+            hook: config.hook.clone().filter(|h| !h.trim().is_empty()),
+            quiet_hours,
+            format: track_format.map(str::to_string),
+            dedup_window: Duration::from_secs(config.dedup_secs),
+            announced: HashMap::new(),
+            art: None,
+        }
+    }
+
+    /// Shows the cached channel logos as the notification icon.
+    pub fn with_art(mut self, art: ArtCache) -> Self {
+        self.art = Some(art);
+        self
+    }
+
+    fn icon(&self, channel: &Channel) -> Option<std::path::PathBuf> {
+        self.art.as_ref()?.cached(channel)
+    }
+
+    /// Returns whether `track` was announced less than the dedup window
+    /// before `now`, and otherwise remembers it as announced at `now`.
+    fn is_repeat(&mut self, track: &TrackInfo, now: Instant) -> bool {
+        if self.dedup_window.is_zero() {
+            return false;
+        }
+        let window = self.dedup_window;
+        self.announced.retain(|_, at| now.duration_since(*at) < window);
+        let key = (track.artist.trim().to_lowercase(), track.title.trim().to_lowercase());
+        if self.announced.contains_key(&key) {
+            return true;
+        }
+        self.announced.insert(key, now);
+        false
+    }
+
+    /// Notification text for `track`.
+    fn body(&self, channel: &Channel, track: &TrackInfo) -> String {
+        match &self.format {
+            Some(format) => format_track(format, &channel.title, track),
+            None => format!("{} - {}", track.artist, track.title),
+        }
+    }
+
+    /// Returns whether notifications and hooks are currently suppressed.
+    pub fn is_quiet(&self) -> bool {
+        self.quiet_hours
+            .is_some_and(|quiet| quiet.contains(Local::now().time()))
+    }
+
+    /// Announces a new track on `channel`.
+    pub fn track_changed(&mut self, channel: &Channel, track: &TrackInfo) {
+        if !self.enabled && self.hook.is_none() {
+            return;
+        }
+
+        if self.is_quiet() {
+            debug!("Quiet hours active, suppressing notification for {} - {}", track.artist, track.title);
+            return;
+        }
+
+        if self.is_repeat(track, Instant::now()) {
+            debug!("{} - {} was announced recently, suppressing notification", track.artist, track.title);
+            return;
+        }
+
+        if self.enabled {
+            send_desktop_notification(&channel.title, &self.body(channel, track), self.icon(channel).as_deref());
+        }
+
+        if let Some(hook) = &self.hook {
+            run_hook(hook, channel, track, &[]);
+        }
+    }
+
+    /// Announces the playing track on request, with a desktop notification
+    /// and the hook, whether or not track changes are announced.
+    pub fn announce(&self, channel: &Channel, track: &TrackInfo) {
+        send_desktop_notification(&channel.title, &self.body(channel, track), self.icon(channel).as_deref());
+        if let Some(hook) = &self.hook {
+            run_hook(hook, channel, track, &[]);
+        }
+    }
+}
+
+/// Shows a desktop notification using the platform's notification tool,
+/// with `icon` where the tool supports one.
+pub(crate) fn send_desktop_notification(summary: &str, body: &str, icon: Option<&Path>) {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            applescript_escape(body),
+            applescript_escape(summary)
+        );
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(script);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg("--app-name=SomaFM Player");
+        if let Some(icon) = icon {
+            command.arg(format!("--icon={}", icon.display()));
+        }
+        command.arg(summary).arg(body);
+        command
+    };
+
+    match command.stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+        Ok(child) => reap(child),
+        Err(e) => debug!("Failed to send desktop notification: {}", e),
+    }
+}
+
+/// Escapes `text` for a double-quoted AppleScript string.
+fn applescript_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Waits for `child` on a background thread, so it doesn't linger as a
+/// zombie once it exits.
+fn reap(mut child: Child) {
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+}
+
+/// Runs the user's hook command through the shell with track details and
+/// `extra_env` in the environment.
+pub(crate) fn run_hook(hook: &str, channel: &Channel, track: &TrackInfo, extra_env: &[(&str, &str)]) {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(hook);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(hook);
+        command
+    };
+
+    let result = command
+        .env("SOMA_CHANNEL_ID", &channel.id)
+        .env("SOMA_CHANNEL", &channel.title)
+        .env("SOMA_ARTIST", &track.artist)
+        .env("SOMA_TITLE", &track.title)
+        .envs(extra_env.iter().copied())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    match result {
+        Ok(child) => reap(child),
+        Err(e) => warn!("Failed to run notification hook '{}': {}", hook, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours_parse() {
+        let quiet = QuietHours::parse("22:00-08:00").unwrap();
+        assert_eq!(quiet.start, time(22, 0));
+        assert_eq!(quiet.end, time(8, 0));
+
+        let quiet = QuietHours::parse(" 13:30 - 14:15 ").unwrap();
+        assert_eq!(quiet.start, time(13, 30));
+        assert_eq!(quiet.end, time(14, 15));
+
+        assert!(QuietHours::parse("22:00").is_err());
+        assert!(QuietHours::parse("25:00-08:00").is_err());
+        assert!(QuietHours::parse("late-early").is_err());
+    }
+
+    #[test]
+    fn test_quiet_hours_wrapping_midnight() {
+        let quiet = QuietHours::parse("22:00-08:00").unwrap();
+        assert!(quiet.contains(time(22, 0)));
+        assert!(quiet.contains(time(23, 59)));
+        assert!(quiet.contains(time(0, 0)));
+        assert!(quiet.contains(time(7, 59)));
+        assert!(!quiet.contains(time(8, 0)));
+        assert!(!quiet.contains(time(12, 0)));
+    }
+
+    #[test]
+    fn test_quiet_hours_same_day() {
+        let quiet = QuietHours::parse("12:00-13:00").unwrap();
+        assert!(quiet.contains(time(12, 30)));
+        assert!(!quiet.contains(time(13, 0)));
+        assert!(!quiet.contains(time(11, 59)));
+
+        let empty = QuietHours::parse("12:00-12:00").unwrap();
+        assert!(!empty.contains(time(12, 0)));
+    }
+
+    #[test]
+    fn test_applescript_escape() {
+        assert_eq!(applescript_escape(r#"Say "Hi" \"#), r#"Say \"Hi\" \\"#);
+    }
+
+    #[test]
+    fn test_notifier_ignores_invalid_quiet_hours() {
+        let config = NotificationConfig {
+            enabled: true,
+            hook: Some("  ".to_string()),
+            quiet_hours: Some("whenever".to_string()),
+            ..NotificationConfig::default()
+        };
+        let notifier = Notifier::new(&config, None);
+        assert!(notifier.quiet_hours.is_none());
+        assert!(notifier.hook.is_none());
+        assert!(!notifier.is_quiet());
+    }
+
+    #[test]
+    fn test_recently_announced_tracks_are_not_repeated() {
+        let mut notifier = Notifier::new(&NotificationConfig::default(), None);
+        let track = |artist: &str, title: &str| TrackInfo {
+            artist: artist.to_string(),
+            title: title.to_string(),
+        };
+        let start = Instant::now();
+        assert!(!notifier.is_repeat(&track("Boards of Canada", "Dayvan Cowboy"), start));
+        assert!(!notifier.is_repeat(&track("Tycho", "Awake"), start + Duration::from_secs(5)));
+        // Flapping back after a reconnect
+        assert!(notifier.is_repeat(&track("boards of canada ", "Dayvan Cowboy"), start + Duration::from_secs(10)));
+        assert!(!notifier.is_repeat(&track("Boards of Canada", "Dayvan Cowboy"), start + Duration::from_secs(300)));
+
+        let config = NotificationConfig { dedup_secs: 0, ..NotificationConfig::default() };
+        let mut notifier = Notifier::new(&config, None);
+        assert!(!notifier.is_repeat(&track("Tycho", "Awake"), start));
+        assert!(!notifier.is_repeat(&track("Tycho", "Awake"), start));
+    }
+}
@@ -0,0 +1,172 @@
+//! Now-playing export for tmux and screen status bars.
+//!
+//! Configured in the `[now_playing]` section, the player writes a short
+//! now-playing string to a file and/or the tmux user option
+//! `@soma_now_playing` whenever it changes, so a status bar can embed it:
+//!
+//! ```text
+//! set -g status-right '#{@soma_now_playing}'
+//! ```
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use log::{debug, warn};
+
+use crate::config::NowPlayingConfig;
+use crate::models::{format_track, Channel, TrackInfo};
+
+/// tmux user option holding the now-playing string
+const TMUX_OPTION: &str = "@soma_now_playing";
+
+/// Default format for the exported string
+pub const DEFAULT_FORMAT: &str = "{artist} - {title}";
+
+/// Expands a leading `~/` to the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Publishes the now-playing string to the configured targets.
+#[derive(Debug)]
+pub struct NowPlayingExporter {
+    file: Option<PathBuf>,
+    tmux: bool,
+    format: String,
+    current: Option<String>,
+}
+
+impl NowPlayingExporter {
+    /// Returns an exporter, or `None` when no target is configured. The
+    /// section's own `format` wins over the general `track_format`.
+    pub fn new(config: &NowPlayingConfig, track_format: Option<&str>) -> Option<Self> {
+        let file = config.file.as_deref().filter(|f| !f.trim().is_empty()).map(expand_home);
+        // Only talk to tmux when running inside it
+        let tmux = config.tmux && std::env::var_os("TMUX").is_some();
+
+        if file.is_none() && !tmux {
+            return None;
+        }
+
+        Some(Self {
+            file,
+            tmux,
+            format: config.format.as_deref().or(track_format).unwrap_or(DEFAULT_FORMAT).to_string(),
+            current: None,
+        })
+    }
+
+    /// Publishes the current track, or clears the string when nothing is
+    /// playing.
+    pub fn update(&mut self, playing: Option<(&Channel, &TrackInfo)>) {
+        let text = match playing {
+            Some((channel, track)) if track.title != TrackInfo::default().title => {
+                format_track(&self.format, &channel.title, track)
+            }
+            _ => String::new(),
+        };
+        if self.current.as_deref() == Some(text.as_str()) {
+            return;
+        }
+
+        if let Some(file) = &self.file {
+            let temp_path = file.with_extension("tmp");
+            if let Err(e) = fs::write(&temp_path, &text).and_then(|_| fs::rename(&temp_path, file)) {
+                warn!("Failed to write now-playing file {}: {}", file.display(), e);
+            }
+        }
+
+        if self.tmux {
+            run_tmux(&["set-option", "-g", TMUX_OPTION, &text]);
+        }
+
+        debug!("Now playing: {}", text);
+        self.current = Some(text);
+    }
+}
+
+impl Drop for NowPlayingExporter {
+    fn drop(&mut self) {
+        if let Some(file) = &self.file {
+            let _ = fs::write(file, "");
+        }
+        if self.tmux {
+            run_tmux(&["set-option", "-gu", TMUX_OPTION]);
+        }
+    }
+}
+
+fn run_tmux(args: &[&str]) {
+    let result = Command::new("tmux")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if let Err(e) = result {
+        debug!("Failed to run tmux: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn track() -> TrackInfo {
+        TrackInfo {
+            artist: "Stars of the Lid".to_string(),
+            title: "Requiem for Dying Mothers".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format_now_playing() {
+        assert_eq!(
+            format_track(DEFAULT_FORMAT, "Drone Zone", &track()),
+            "Stars of the Lid - Requiem for Dying Mothers"
+        );
+        assert_eq!(
+            format_track("♪ {title} [{channel}]", "Drone Zone", &track()),
+            "♪ Requiem for Dying Mothers [Drone Zone]"
+        );
+    }
+
+    #[test]
+    fn test_exporter_writes_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("now-playing");
+        let config = NowPlayingConfig {
+            file: Some(path.to_string_lossy().into_owned()),
+            tmux: false,
+            format: None,
+        };
+
+        let channel = Channel::named("dronezone", "Drone Zone");
+        let mut exporter = NowPlayingExporter::new(&config, Some("{title}")).unwrap();
+        exporter.update(Some((&channel, &track())));
+        // Without a format of its own the general track_format is used
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Requiem for Dying Mothers");
+
+        exporter.update(None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        exporter.update(Some((&channel, &track())));
+        drop(exporter);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_exporter_disabled_without_targets() {
+        let config = NowPlayingConfig {
+            file: Some("  ".to_string()),
+            tmux: false,
+            format: None,
+        };
+        assert!(NowPlayingExporter::new(&config, None).is_none());
+    }
+}
@@ -0,0 +1,168 @@
+//! Star ratings of tracks (`r` while playing, `soma-player stats --rating`).
+//!
+//! A track is rated 1 to 5 stars from the rating popup; rating it again
+//! replaces the earlier rating, and 0 removes it. Ratings are kept in
+//! `~/.config/soma-player/ratings.json` next to the config file, keyed by
+//! artist and title, so the same track rated on two channels counts once.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::models::{Channel, TrackInfo};
+
+/// Most stars a track can get
+pub const MAX_STARS: u8 = 5;
+
+/// A rated track.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub artist: String,
+    pub title: String,
+    /// Channel the track was rated on
+    pub channel_title: String,
+    /// 1 to [`MAX_STARS`]
+    pub stars: u8,
+    /// Unix timestamp (seconds) of the last change
+    pub rated_at: i64,
+}
+
+impl Rating {
+    fn is_track(&self, track: &TrackInfo) -> bool {
+        self.artist.eq_ignore_ascii_case(&track.artist) && self.title.eq_ignore_ascii_case(&track.title)
+    }
+}
+
+/// `★★★☆☆` for 3 stars.
+pub fn stars(stars: u8) -> String {
+    let stars = stars.min(MAX_STARS);
+    format!("{}{}", "★".repeat(stars.into()), "☆".repeat((MAX_STARS - stars).into()))
+}
+
+/// Every rating given so far.
+#[derive(Debug, Clone, Default)]
+pub struct Ratings {
+    /// `None` keeps the ratings in memory only
+    path: Option<PathBuf>,
+    ratings: Vec<Rating>,
+}
+
+impl Ratings {
+    /// Loads the ratings from the config directory.
+    pub fn load() -> Self {
+        match AppConfig::config_path() {
+            Ok(path) => Self::at(path.with_file_name("ratings.json")),
+            Err(e) => {
+                warn!("Ratings not saved: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Loads the ratings from the file at `path`. An unreadable file is
+    /// left alone rather than replaced.
+    pub fn at(path: PathBuf) -> Self {
+        let ratings = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(ratings) => ratings,
+                Err(e) => {
+                    warn!("Ignoring unreadable ratings {}: {}", path.display(), e);
+                    return Self::default();
+                }
+            },
+            Err(_) => Vec::new(),
+        };
+        Self { path: Some(path), ratings }
+    }
+
+    /// Stars given to `track`, if it was rated.
+    pub fn get(&self, track: &TrackInfo) -> Option<u8> {
+        self.ratings.iter().find(|rating| rating.is_track(track)).map(|rating| rating.stars)
+    }
+
+    /// Rates `track` playing on `channel` with `stars`, 0 removing its
+    /// rating, and saves.
+    pub fn set(&mut self, channel: &Channel, track: &TrackInfo, stars: u8) -> Result<(), String> {
+        self.ratings.retain(|rating| !rating.is_track(track));
+        if stars > 0 {
+            info!("Rated {} - {} {} stars", track.artist, track.title, stars.min(MAX_STARS));
+            self.ratings.push(Rating {
+                artist: track.artist.clone(),
+                title: track.title.clone(),
+                channel_title: channel.title.clone(),
+                stars: stars.min(MAX_STARS),
+                rated_at: Utc::now().timestamp(),
+            });
+        }
+        self.save()
+    }
+
+    /// Tracks rated `min_stars` or more, best first, then most recently
+    /// rated first.
+    pub fn at_least(&self, min_stars: u8) -> Vec<&Rating> {
+        let mut rated: Vec<&Rating> = self.ratings.iter().filter(|rating| rating.stars >= min_stars).collect();
+        rated.sort_by(|a, b| b.stars.cmp(&a.stars).then(b.rated_at.cmp(&a.rated_at)));
+        rated
+    }
+
+    /// The tracks rated `min_stars` or more, as `soma-player stats
+    /// --rating` prints them.
+    pub fn render(&self, min_stars: u8) -> String {
+        let rated = self.at_least(min_stars);
+        let mut out = format!("\nTracks rated {} or more\n", stars(min_stars));
+        if rated.is_empty() {
+            out.push_str("  none yet, press r while a track plays to rate it\n");
+        }
+        for rating in rated {
+            let _ = writeln!(out, "  {}  {} - {}  ({})", stars(rating.stars), rating.artist, rating.title, rating.channel_title);
+        }
+        out
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let temp_path = path.with_extension("json.tmp");
+        serde_json::to_string_pretty(&self.ratings)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&temp_path, json).map_err(|e| e.to_string()))
+            .and_then(|()| fs::rename(&temp_path, path).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Failed to save ratings to {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ratings_replace_and_filter() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("ratings.json");
+        let channel = Channel::named("groovesalad", "Groove Salad");
+        let track = |artist: &str, title: &str| TrackInfo { artist: artist.to_string(), title: title.to_string() };
+
+        let mut ratings = Ratings::at(path.clone());
+        ratings.set(&channel, &track("Air", "Alone in Kyoto"), 3).unwrap();
+        ratings.set(&channel, &track("AIR", "alone in kyoto"), 5).unwrap();
+        ratings.set(&channel, &track("Boards of Canada", "Roygbiv"), 2).unwrap();
+        ratings.set(&channel, &track("Bonobo", "Kiara"), 4).unwrap();
+        ratings.set(&channel, &track("Bonobo", "Kiara"), 0).unwrap();
+
+        let ratings = Ratings::at(path);
+        assert_eq!(ratings.get(&track("Air", "Alone in Kyoto")), Some(5));
+        assert_eq!(ratings.get(&track("Bonobo", "Kiara")), None);
+        assert_eq!(
+            ratings.render(2),
+            "\nTracks rated ★★☆☆☆ or more\n  ★★★★★  AIR - alone in kyoto  (Groove Salad)\n  ★★☆☆☆  Boards of Canada - Roygbiv  (Groove Salad)\n"
+        );
+        assert!(ratings.render(3).ends_with("★★★★★  AIR - alone in kyoto  (Groove Salad)\n"));
+    }
+}
@@ -0,0 +1,434 @@
+//! Recording SomaFM streams to disk.
+//!
+//! The raw stream (MP3 or AAC frames, with ICY metadata stripped) is written
+//! straight to disk without decoding, so no audio output is required.
+//! Recordings can optionally be split at track changes and tagged with
+//! ID3v2 metadata from the stream titles.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use soma_player::recording::parse_duration;
+//! use std::time::Duration;
+//!
+//! assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+//! assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+
+use crate::api::ApiClient;
+use crate::audio::icy::IcyStream;
+use crate::models::{track_from_metadata, Channel, StreamQuality, TrackInfo};
+
+/// Most audio held back while waiting for the first stream title, about a
+/// minute at 128 kbps; a stream without titles is then recorded untagged
+const MAX_PENDING: usize = 1024 * 1024;
+
+/// Options for a recording session
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    /// Output file; with `split_tracks` this is the base name for each track
+    pub output: PathBuf,
+    /// Stop after this long, or record until interrupted
+    pub duration: Option<Duration>,
+    /// Start a new file whenever the stream title changes
+    pub split_tracks: bool,
+    /// Prefix each file with an ID3v2 tag built from the stream metadata
+    pub tag: bool,
+    /// Stream quality to record
+    pub quality: StreamQuality,
+}
+
+/// What a finished recording produced
+#[derive(Debug, Clone, Default)]
+pub struct RecordingSummary {
+    pub files: Vec<PathBuf>,
+    pub bytes_written: u64,
+    pub elapsed: Duration,
+}
+
+/// Parses durations such as `90`, `30s`, `15m` or `2h` (bare numbers are seconds).
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let (number, multiplier) = match input.char_indices().last() {
+        Some((i, 's')) => (&input[..i], 1),
+        Some((i, 'm')) => (&input[..i], 60),
+        Some((i, 'h')) => (&input[..i], 3600),
+        _ => (input, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("Invalid duration '{}', expected e.g. 90, 30s, 15m or 2h", input))
+}
+
+/// Records `channel` to disk until the duration elapses, the stream ends,
+/// or `stop` is set.
+pub async fn record_channel(
+    api: &ApiClient,
+    channel: &Channel,
+    options: &RecordOptions,
+    stop: Arc<AtomicBool>,
+) -> Result<RecordingSummary, String> {
+    info!("Recording channel {} to {}", channel.title, options.output.display());
+
+    let stream_url = api.resolve_stream_url(channel, options.quality).await.map_err(|e| e.to_string())?;
+    let (title_tx, title_rx) = mpsc::channel();
+    // Read straight off the connection: the player's seekable buffer would
+    // hold the whole recording in memory as well
+    let mut reader = IcyStream::connect(&stream_url, move |title| {
+        let _ = title_tx.send(title.to_string());
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let channel = channel.clone();
+    let options = options.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut recorder = Recorder::new(&channel, &options);
+        let started = Instant::now();
+        let mut buf = [0u8; 16 * 1024];
+
+        while !stop.load(Ordering::Relaxed) && options.duration.is_none_or(|d| started.elapsed() < d) {
+            let read = match reader.read(&mut buf) {
+                Ok(read) => read,
+                // How the stream reader reports the end of the stream
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => 0,
+                Err(e) => return Err(format!("Failed to read stream: {}", e)),
+            };
+            if read == 0 {
+                info!("Stream ended");
+                break;
+            }
+
+            while let Ok(title) = title_rx.try_recv() {
+                recorder.track_changed(track_from_metadata(&title))?;
+            }
+            recorder.write(&buf[..read])?;
+        }
+
+        let mut summary = recorder.finish()?;
+        summary.elapsed = started.elapsed();
+        info!("Recording finished: {} bytes in {} file(s)", summary.bytes_written, summary.files.len());
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| format!("Recording task failed: {}", e))?
+}
+
+/// Writes stream data to one or more files.
+struct Recorder {
+    options: RecordOptions,
+    album: String,
+    current: Option<BufWriter<File>>,
+    current_track: Option<TrackInfo>,
+    /// Audio received before the first stream title, when the file is
+    /// named or tagged after it
+    pending: Vec<u8>,
+    track_number: usize,
+    summary: RecordingSummary,
+}
+
+impl Recorder {
+    fn new(channel: &Channel, options: &RecordOptions) -> Self {
+        Self {
+            options: options.clone(),
+            album: format!("SomaFM {}", channel.title),
+            current: None,
+            current_track: None,
+            pending: Vec::new(),
+            track_number: 0,
+            summary: RecordingSummary::default(),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        if self.current.is_none() {
+            // The stream's first chunks arrive before its first title
+            let needs_title = self.options.tag || self.options.split_tracks;
+            if needs_title && self.current_track.is_none() && self.pending.len() < MAX_PENDING {
+                self.pending.extend_from_slice(data);
+                return Ok(());
+            }
+            self.open_next()?;
+            let pending = std::mem::take(&mut self.pending);
+            self.write_current(&pending)?;
+        }
+        self.write_current(data)
+    }
+
+    fn write_current(&mut self, data: &[u8]) -> Result<(), String> {
+        if let Some(file) = self.current.as_mut() {
+            file.write_all(data).map_err(|e| format!("Failed to write recording: {}", e))?;
+            self.summary.bytes_written += data.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn track_changed(&mut self, track: TrackInfo) -> Result<(), String> {
+        let unchanged = self.current_track.as_ref()
+            .is_some_and(|t| t.artist == track.artist && t.title == track.title);
+        if unchanged {
+            return Ok(());
+        }
+
+        println!("▶ {} - {}", track.artist, track.title);
+        self.current_track = Some(track);
+
+        // The next write opens a file named after the new track. Without
+        // splitting, the single file keeps the tag of the first track heard,
+        // as audio is held back until that title arrives.
+        if self.options.split_tracks {
+            self.close_current()?;
+        }
+        Ok(())
+    }
+
+    fn open_next(&mut self) -> Result<(), String> {
+        let path = if self.options.split_tracks {
+            self.track_number += 1;
+            track_file_path(&self.options.output, self.track_number, self.current_track.as_ref())
+        } else {
+            self.options.output.clone()
+        };
+
+        debug!("Opening recording file {}", path.display());
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+
+        if self.options.tag
+            && let Some(track) = &self.current_track
+        {
+            writer.write_all(&id3v2_tag(&track.title, &track.artist, &self.album))
+                .map_err(|e| format!("Failed to write tag: {}", e))?;
+        }
+
+        self.summary.files.push(path);
+        self.current = Some(writer);
+        Ok(())
+    }
+
+    fn close_current(&mut self) -> Result<(), String> {
+        if let Some(mut file) = self.current.take() {
+            file.flush().map_err(|e| format!("Failed to flush recording: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<RecordingSummary, String> {
+        // The stream ended before any title
+        if !self.pending.is_empty() {
+            self.open_next()?;
+            let pending = std::mem::take(&mut self.pending);
+            self.write_current(&pending)?;
+        }
+        self.close_current()?;
+        Ok(self.summary)
+    }
+}
+
+/// Builds the file name for the `number`th track of a split recording,
+/// e.g. `out-003 Artist - Title.mp3`.
+fn track_file_path(base: &Path, number: usize, track: Option<&TrackInfo>) -> PathBuf {
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+    let extension = base.extension().and_then(|s| s.to_str()).unwrap_or("mp3");
+    let name = match track {
+        Some(track) => format!(
+            "{}-{:03} {}.{}",
+            stem,
+            number,
+            sanitize_file_name(&format!("{} - {}", track.artist, track.title)),
+            extension
+        ),
+        None => format!("{}-{:03}.{}", stem, number, extension),
+    };
+    base.with_file_name(name)
+}
+
+/// Replaces characters that are not allowed in file names on common platforms.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Builds an ID3v2.4 tag with UTF-8 title, artist and album frames.
+pub(crate) fn id3v2_tag(title: &str, artist: &str, album: &str) -> Vec<u8> {
+    let mut frames = Vec::new();
+    for (id, text) in [(b"TIT2", title), (b"TPE1", artist), (b"TALB", album)] {
+        if text.is_empty() {
+            continue;
+        }
+        frames.extend_from_slice(id);
+        frames.extend_from_slice(&synchsafe(text.len() as u32 + 1));
+        frames.extend_from_slice(&[0, 0]); // flags
+        frames.push(3); // UTF-8
+        frames.extend_from_slice(text.as_bytes());
+    }
+
+    let mut tag = Vec::with_capacity(10 + frames.len());
+    tag.extend_from_slice(b"ID3");
+    tag.extend_from_slice(&[4, 0, 0]); // version 2.4.0, no flags
+    tag.extend_from_slice(&synchsafe(frames.len() as u32));
+    tag.extend_from_slice(&frames);
+    tag
+}
+
+/// Encodes a size as an ID3v2 synchsafe integer (7 bits per byte).
+fn synchsafe(size: u32) -> [u8; 4] {
+    [
+        ((size >> 21) & 0x7f) as u8,
+        ((size >> 14) & 0x7f) as u8,
+        ((size >> 7) & 0x7f) as u8,
+        (size & 0x7f) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn track(artist: &str, title: &str) -> TrackInfo {
+        TrackInfo { artist: artist.to_string(), title: title.to_string() }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration("99999999999999999h").is_err());
+    }
+
+    #[test]
+    fn test_synchsafe() {
+        assert_eq!(synchsafe(0), [0, 0, 0, 0]);
+        assert_eq!(synchsafe(127), [0, 0, 0, 127]);
+        assert_eq!(synchsafe(128), [0, 0, 1, 0]);
+        assert_eq!(synchsafe(255), [0, 0, 1, 127]);
+    }
+
+    #[test]
+    fn test_id3v2_tag() {
+        let tag = id3v2_tag("Title", "Artist", "");
+        assert_eq!(&tag[..6], b"ID3\x04\x00\x00");
+        // Two frames of 10 header bytes plus encoding byte and text
+        assert_eq!(tag.len(), 10 + (10 + 6) + (10 + 7));
+        assert_eq!(&tag[10..14], b"TIT2");
+        assert_eq!(&tag[21..26], b"Title");
+        assert_eq!(&tag[26..30], b"TPE1");
+    }
+
+    #[test]
+    fn test_track_file_path() {
+        let base = Path::new("/tmp/out.mp3");
+        assert_eq!(
+            track_file_path(base, 3, Some(&track("AC/DC", "What?"))),
+            PathBuf::from("/tmp/out-003 AC_DC - What_.mp3")
+        );
+        assert_eq!(track_file_path(base, 1, None), PathBuf::from("/tmp/out-001.mp3"));
+    }
+
+    #[test]
+    fn test_recorder_single_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("out.mp3");
+        let options = RecordOptions {
+            output: output.clone(),
+            duration: None,
+            split_tracks: false,
+            tag: false,
+            quality: StreamQuality::default(),
+        };
+
+        let mut recorder = Recorder::new(&Channel::named("groovesalad", "Groove Salad"), &options);
+        recorder.write(b"abc").unwrap();
+        recorder.track_changed(track("A", "One")).unwrap();
+        recorder.write(b"def").unwrap();
+        recorder.track_changed(track("B", "Two")).unwrap();
+        recorder.write(b"ghi").unwrap();
+        let summary = recorder.finish().unwrap();
+
+        assert_eq!(summary.files, vec![output.clone()]);
+        assert_eq!(summary.bytes_written, 9);
+        assert_eq!(std::fs::read(output).unwrap(), b"abcdefghi");
+    }
+
+    #[test]
+    fn test_recorder_split_tracks_with_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("out.mp3");
+        let options = RecordOptions {
+            output,
+            duration: None,
+            split_tracks: true,
+            tag: true,
+            quality: StreamQuality::default(),
+        };
+
+        // Audio read before the first title goes into that title's file
+        let mut recorder = Recorder::new(&Channel::named("groovesalad", "Groove Salad"), &options);
+        recorder.write(b"000").unwrap();
+        recorder.track_changed(track("A", "One")).unwrap();
+        recorder.write(b"111").unwrap();
+        recorder.track_changed(track("A", "One")).unwrap();
+        recorder.write(b"111").unwrap();
+        recorder.track_changed(track("B", "Two")).unwrap();
+        recorder.write(b"222").unwrap();
+        let summary = recorder.finish().unwrap();
+
+        assert_eq!(summary.files.len(), 2);
+        assert!(summary.files[0].ends_with("out-001 A - One.mp3"));
+        assert!(summary.files[1].ends_with("out-002 B - Two.mp3"));
+
+        let first = std::fs::read(&summary.files[0]).unwrap();
+        assert!(first.starts_with(b"ID3"));
+        assert!(first.ends_with(b"000111111"));
+        let second = std::fs::read(&summary.files[1]).unwrap();
+        assert!(second.ends_with(b"222"));
+        assert_eq!(summary.bytes_written, 12);
+
+        // A single file is tagged with the first title too
+        let output = temp_dir.path().join("single.mp3");
+        let options = RecordOptions {
+            output: output.clone(),
+            duration: None,
+            split_tracks: false,
+            tag: true,
+            quality: StreamQuality::default(),
+        };
+        let mut recorder = Recorder::new(&Channel::named("groovesalad", "Groove Salad"), &options);
+        recorder.write(b"abc").unwrap();
+        recorder.track_changed(track("A", "One")).unwrap();
+        recorder.write(b"def").unwrap();
+        recorder.finish().unwrap();
+        let single = std::fs::read(&output).unwrap();
+        assert_eq!(&single[..single.len() - 6], id3v2_tag("One", "A", "SomaFM Groove Salad"));
+        assert!(single.ends_with(b"abcdef"));
+    }
+}
@@ -0,0 +1,301 @@
+//! Local usage report (`soma-player stats`).
+//!
+//! Reads the player's own log files, rotated and gzipped ones included, and
+//! summarizes how long each channel was played, how often the stream had to
+//! reconnect, crashes and the most frequent warnings and errors. Everything
+//! is computed from what is already on disk; nothing is sent anywhere.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+use flate2::read::GzDecoder;
+
+/// Messages logged when the player reconnects to the stream
+const RECONNECT_MESSAGES: &[&str] = &[
+    "No stream data for",
+    "System resumed after",
+    "Stream buffer out of bounds",
+    "Audio stream ended unexpectedly",
+    "Watchdog: rebuilding",
+];
+
+/// Messages logged when playback ends
+const STOP_MESSAGES: &[&str] = &[
+    "Playback stopped",
+    "Audio playback stopped",
+    "Audio playback ended",
+    "Audio playback crashed",
+    "Audio playback failed",
+    "SomaFM Player shutting down",
+];
+
+/// Number of channels and problems listed in the report
+const TOP_ENTRIES: usize = 10;
+
+/// A parsed line of the log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LogLine<'a> {
+    time: DateTime<Utc>,
+    level: &'a str,
+    message: &'a str,
+}
+
+/// Splits a line written by the file logger, e.g.
+/// `2025-03-01T14:05:09.123456Z  INFO ThreadId(02) soma_player::audio::player: src/audio/player.rs:267: Starting playback`.
+fn parse_line(line: &str) -> Option<LogLine<'_>> {
+    let (time, rest) = line.split_once(char::is_whitespace)?;
+    let time = DateTime::parse_from_rfc3339(time).ok()?.with_timezone(&Utc);
+    let rest = rest.trim_start();
+    let (level, rest) = rest.split_once(char::is_whitespace)?;
+    if !matches!(level, "TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR") {
+        return None;
+    }
+    let mut rest = rest.trim_start();
+    if rest.starts_with("ThreadId(") {
+        rest = rest.split_once(char::is_whitespace)?.1.trim_start();
+    }
+    // Target, then the source location if there is one
+    let (_, mut message) = rest.split_once(": ")?;
+    if let Some((location, after)) = message.split_once(": ")
+        && !location.contains(char::is_whitespace)
+        && location.rsplit_once(':').is_some_and(|(_, line)| line.parse::<u32>().is_ok())
+    {
+        message = after;
+    }
+    Some(LogLine { time, level, message })
+}
+
+/// Usage figures gathered from the log files.
+#[derive(Debug, Default)]
+pub struct UsageReport {
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Times the player was started
+    pub runs: usize,
+    /// Playing time per channel title
+    pub listening: HashMap<String, Duration>,
+    pub reconnects: usize,
+    /// Panics, in the audio pipeline or elsewhere
+    pub crashes: usize,
+    /// Warnings and errors by level and message, numbers masked
+    pub problems: HashMap<(String, String), usize>,
+    /// Channel playing since the given time, unless paused
+    playing: Option<(String, DateTime<Utc>)>,
+    /// Channel of the current playback while paused
+    paused: Option<String>,
+}
+
+impl UsageReport {
+    /// Accounts for one line of a log file. Lines must come in order.
+    pub fn add_line(&mut self, line: &str) {
+        let Some(LogLine { time, level, message }) = parse_line(line) else {
+            return;
+        };
+
+        if message == "Starting SomaFM Player" {
+            // A run that ended without saying so stopped at its last line
+            if let Some(last) = self.last_seen {
+                self.stop(last);
+            }
+            self.runs += 1;
+        }
+        self.first_seen.get_or_insert(time);
+        self.last_seen = Some(time);
+
+        let started = message
+            .strip_prefix("Starting playback for channel: ")
+            .or_else(|| message.strip_prefix("Switching to channel: "))
+            .or_else(|| message.strip_prefix("Switching to channel "))
+            .or_else(|| message.strip_prefix("Casting ").and_then(|rest| rest.rsplit_once(" to ")).map(|(title, _)| title));
+        if let Some(title) = started {
+            self.stop(time);
+            self.playing = Some((title.to_string(), time));
+        } else if message == "Playback paused" {
+            if let Some((title, _)) = self.playing.as_ref() {
+                self.paused = Some(title.clone());
+            }
+            self.close_segment(time);
+        } else if message == "Playback resumed" {
+            if let Some(title) = self.paused.take() {
+                self.playing = Some((title, time));
+            }
+        } else if STOP_MESSAGES.iter().any(|stop| message.starts_with(stop)) {
+            self.stop(time);
+        }
+
+        if RECONNECT_MESSAGES.iter().any(|reconnect| message.starts_with(reconnect)) {
+            self.reconnects += 1;
+        }
+        if message.starts_with("panicked at") {
+            self.crashes += 1;
+        }
+        if matches!(level, "WARN" | "ERROR") {
+            *self.problems.entry((level.to_string(), mask_numbers(message))).or_default() += 1;
+        }
+    }
+
+    /// Ends a playback still open after the last line.
+    pub fn finish(&mut self) {
+        if let Some(last) = self.last_seen {
+            self.stop(last);
+        }
+    }
+
+    fn stop(&mut self, time: DateTime<Utc>) {
+        self.close_segment(time);
+        self.paused = None;
+    }
+
+    fn close_segment(&mut self, time: DateTime<Utc>) {
+        if let Some((title, since)) = self.playing.take()
+            && time > since
+        {
+            *self.listening.entry(title).or_insert_with(Duration::zero) += time - since;
+        }
+    }
+
+    /// Total playing time over all channels.
+    pub fn total_listening(&self) -> Duration {
+        self.listening.values().fold(Duration::zero(), |total, time| total + *time)
+    }
+
+    /// The report as printed by `soma-player stats`.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "SomaFM Player usage report (v{})\n", env!("CARGO_PKG_VERSION"));
+        let (Some(first), Some(last)) = (self.first_seen, self.last_seen) else {
+            out.push_str("No log entries found. Is log_to_file enabled?\n");
+            return out;
+        };
+        let _ = writeln!(
+            out,
+            "From local log files covering {} to {}; nothing is sent anywhere.\n",
+            first.format("%Y-%m-%d"),
+            last.format("%Y-%m-%d")
+        );
+        let _ = writeln!(out, "Player runs     {}", self.runs);
+        let _ = writeln!(out, "Listening time  {}", format_duration(self.total_listening()));
+        let _ = writeln!(out, "Reconnects      {}", self.reconnects);
+        let _ = writeln!(out, "Crashes         {}", self.crashes);
+
+        if !self.listening.is_empty() {
+            let mut channels: Vec<_> = self.listening.iter().collect();
+            channels.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            let width = channels.iter().take(TOP_ENTRIES).map(|(title, _)| title.chars().count()).max().unwrap_or(0);
+            out.push_str("\nTop channels\n");
+            for (title, time) in channels.iter().take(TOP_ENTRIES) {
+                let _ = writeln!(out, "  {:<width$}  {}", title, format_duration(**time), width = width);
+            }
+        }
+
+        if !self.problems.is_empty() {
+            let mut problems: Vec<_> = self.problems.iter().collect();
+            problems.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            out.push_str("\nMost frequent warnings and errors\n");
+            for ((level, message), count) in problems.iter().take(TOP_ENTRIES) {
+                let _ = writeln!(out, "  {:>5}  {:<5}  {}", count, level, message);
+            }
+        }
+        out
+    }
+}
+
+/// Replaces runs of digits with `#` so messages differing only in numbers
+/// are counted together, and shortens long messages.
+fn mask_numbers(message: &str) -> String {
+    let mut masked = String::new();
+    for c in message.chars() {
+        if c.is_ascii_digit() {
+            if !masked.ends_with('#') {
+                masked.push('#');
+            }
+        } else {
+            masked.push(c);
+        }
+    }
+    if masked.chars().count() > 100 {
+        masked = masked.chars().take(99).chain(['…']).collect();
+    }
+    masked
+}
+
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// Builds the report from every log file in `log_dir`, oldest first.
+pub fn collect(log_dir: &Path) -> Result<UsageReport, Box<dyn std::error::Error>> {
+    let mut files: Vec<_> = fs::read_dir(log_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("soma-player."))
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    files.sort();
+
+    let mut report = UsageReport::default();
+    for (_, path) in files {
+        let file = File::open(&path)?;
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        // Lines that are not valid UTF-8 are skipped rather than ending the file
+        for line in BufReader::new(reader).split(b'\n') {
+            match line {
+                Ok(line) => report.add_line(&String::from_utf8_lossy(&line)),
+                Err(e) => {
+                    eprintln!("Warning: Stopped reading {}: {}", path.display(), e);
+                    break;
+                }
+            }
+        }
+    }
+    report.finish();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_from_log_lines() {
+        let log = "\
+2025-03-01T10:00:00.000000Z  INFO ThreadId(01) soma_player: src/main.rs:830: Starting SomaFM Player
+2025-03-01T10:00:05.000000Z  INFO ThreadId(07) soma_player::audio::player: src/audio/player.rs:267: Starting playback for channel: Groove Salad
+2025-03-01T10:30:05.000000Z  INFO ThreadId(07) soma_player::audio::player: src/audio/player.rs:367: Playback paused
+2025-03-01T10:40:05.000000Z  INFO ThreadId(07) soma_player::audio::player: src/audio/player.rs:376: Playback resumed
+2025-03-01T10:50:05.000000Z  WARN ThreadId(07) soma_player::audio::player: src/audio/player.rs:436: No stream data for 12.5s, reconnecting to Groove Salad
+2025-03-01T11:10:05.000000Z  INFO ThreadId(07) soma_player::audio::player: src/audio/player.rs:623: Switching to channel Drone Zone
+2025-03-01T11:25:05.000000Z  WARN ThreadId(07) soma_player::audio::player: src/audio/player.rs:436: No stream data for 3.1s, reconnecting to Drone Zone
+not a log line
+2025-03-02T09:00:00.000000Z  INFO ThreadId(01) soma_player: src/main.rs:830: Starting SomaFM Player
+2025-03-02T09:00:01.000000Z ERROR ThreadId(01) soma_player: src/main.rs:1205: panicked at src/ui/mod.rs:10:5:
+";
+        let mut report = UsageReport::default();
+        log.lines().for_each(|line| report.add_line(line));
+        report.finish();
+
+        assert_eq!(report.runs, 2);
+        assert_eq!(report.listening["Groove Salad"], Duration::minutes(60));
+        // Drone Zone played until the last line of the first run
+        assert_eq!(report.listening["Drone Zone"], Duration::minutes(15));
+        assert_eq!(report.reconnects, 2);
+        assert_eq!(report.crashes, 1);
+        let key = ("WARN".to_string(), "No stream data for #.#s, reconnecting to Groove Salad".to_string());
+        assert_eq!(report.problems[&key], 1);
+
+        let text = report.render();
+        assert!(text.contains("Listening time  1h 15m"));
+        assert!(text.contains("  Groove Salad  1h 00m\n  Drone Zone    0h 15m\n"));
+    }
+}
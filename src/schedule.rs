@@ -0,0 +1,170 @@
+//! Playing different channels at different times of day.
+//!
+//! The `[schedule]` section maps daily time ranges to channel IDs. Ranges
+//! may cross midnight; where they overlap, the one that sorts first wins. A
+//! channel scheduled for the current time is started at launch, and when a
+//! range begins while the player is running it switches over. Between
+//! ranges, or after picking another channel by hand, playback is left
+//! alone until the next range begins.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::collections::BTreeMap;
+//! use soma_player::schedule::Schedule;
+//!
+//! let entries = BTreeMap::from([
+//!     ("09:00-12:00".to_string(), "groovesalad".to_string()),
+//!     ("22:00-02:00".to_string(), "dronezone".to_string()),
+//! ]);
+//! let mut schedule = Schedule::from_config(&entries);
+//!
+//! assert_eq!(schedule.check(9 * 60).map(|slot| slot.channel_id.as_str()), Some("groovesalad"));
+//! // Still in the same range, nothing to switch
+//! assert!(schedule.check(10 * 60).is_none());
+//! assert_eq!(schedule.check(60).map(|slot| slot.channel_id.as_str()), Some("dronezone"));
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::Timelike;
+use log::warn;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// A daily time range assigned to a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleSlot {
+    /// Minute of the day the range begins, inclusive
+    pub start: u16,
+    /// Minute of the day the range ends, exclusive
+    pub end: u16,
+    pub channel_id: String,
+}
+
+impl ScheduleSlot {
+    /// Parses a `HH:MM-HH:MM` range.
+    pub fn parse(range: &str, channel_id: &str) -> Result<Self, String> {
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| format!("Invalid schedule range '{}', expected HH:MM-HH:MM", range))?;
+        let start = parse_time(start).ok_or_else(|| format!("Invalid start time in schedule range '{}'", range))?;
+        let end = parse_time(end).ok_or_else(|| format!("Invalid end time in schedule range '{}'", range))?;
+        if start == end {
+            return Err(format!("Schedule range '{}' is empty", range));
+        }
+        if channel_id.trim().is_empty() {
+            return Err(format!("No channel for schedule range '{}'", range));
+        }
+        Ok(Self { start, end, channel_id: channel_id.trim().to_string() })
+    }
+
+    /// Whether `minute` of the day falls within the range.
+    pub fn contains(&self, minute: u16) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            // Crosses midnight
+            minute >= self.start || minute < self.end
+        }
+    }
+
+    /// The range as written in the configuration.
+    pub fn range(&self) -> String {
+        format!(
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+/// Parses `HH:MM` into a minute of the day.
+fn parse_time(time: &str) -> Option<u16> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u16>().ok()?, minutes.parse::<u16>().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// The configured ranges and which of them was last active.
+#[derive(Debug, Default)]
+pub struct Schedule {
+    slots: Vec<ScheduleSlot>,
+    active: Option<usize>,
+}
+
+impl Schedule {
+    /// Builds the schedule from the `[schedule]` section, skipping invalid
+    /// ranges with a warning.
+    pub fn from_config(entries: &BTreeMap<String, String>) -> Self {
+        let slots = entries
+            .iter()
+            .filter_map(|(range, channel_id)| {
+                ScheduleSlot::parse(range, channel_id)
+                    .map_err(|e| warn!("{}", e))
+                    .ok()
+            })
+            .collect();
+        Self { slots, active: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Records the time as `minute` of the day and returns the slot to switch
+    /// to when a range began since the previous check.
+    pub fn check(&mut self, minute: u16) -> Option<&ScheduleSlot> {
+        let minute = minute % MINUTES_PER_DAY;
+        let active = self.slots.iter().position(|slot| slot.contains(minute));
+        if std::mem::replace(&mut self.active, active) == active {
+            return None;
+        }
+        active.map(|index| &self.slots[index])
+    }
+}
+
+/// The current local time as a minute of the day.
+pub fn local_minute() -> u16 {
+    let now = chrono::Local::now();
+    (now.hour() * 60 + now.minute()) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges() {
+        let slot = ScheduleSlot::parse("22:30 - 02:00", "dronezone").unwrap();
+        assert_eq!((slot.start, slot.end), (22 * 60 + 30, 120));
+        assert_eq!(slot.range(), "22:30-02:00");
+        assert!(slot.contains(23 * 60) && slot.contains(0) && !slot.contains(120));
+
+        assert!(ScheduleSlot::parse("9-12", "groovesalad").is_err());
+        assert!(ScheduleSlot::parse("24:00-02:00", "groovesalad").is_err());
+        assert!(ScheduleSlot::parse("09:00-09:00", "groovesalad").is_err());
+        assert!(ScheduleSlot::parse("09:00-12:00", " ").is_err());
+    }
+
+    #[test]
+    fn test_switches_only_when_a_range_begins() {
+        let entries = BTreeMap::from([
+            ("09:00-12:00".to_string(), "groovesalad".to_string()),
+            ("11:00-13:00".to_string(), "lush".to_string()),
+            ("bogus".to_string(), "dronezone".to_string()),
+        ]);
+        let mut schedule = Schedule::from_config(&entries);
+        let channel = |slot: Option<&ScheduleSlot>| slot.map(|slot| slot.channel_id.clone());
+
+        assert_eq!(channel(schedule.check(8 * 60)), None);
+        assert_eq!(channel(schedule.check(9 * 60)), Some("groovesalad".to_string()));
+        // The earlier range wins the overlap
+        assert_eq!(channel(schedule.check(11 * 60)), None);
+        assert_eq!(channel(schedule.check(12 * 60)), Some("lush".to_string()));
+        assert_eq!(channel(schedule.check(13 * 60)), None);
+        assert_eq!(channel(schedule.check(9 * 60)), Some("groovesalad".to_string()));
+    }
+}
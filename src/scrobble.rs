@@ -0,0 +1,525 @@
+//! Scrobbling listens to ListenBrainz, through a durable queue.
+//!
+//! With a `[scrobble]` token set, [`Scrobbler`] follows the player status
+//! and turns every track heard for at least [`MIN_PLAYED`] into a
+//! [`Listen`]. Listens are appended to a queue persisted as JSON in the
+//! state directory and only removed once a [`ScrobbleBackend`] accepted
+//! them, so listens recorded while offline are submitted when connectivity
+//! returns. Duplicate listens are ignored and listens older than the
+//! queue's maximum age are dropped, since scrobbling services reject stale
+//! submissions.
+
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::config::ScrobbleConfig;
+use crate::state_file::{get_state_directory, PlayerStatus};
+
+/// Listens older than this are dropped (the limit used by Last.fm)
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Most listens submitted in a single request
+pub const BATCH_SIZE: usize = 50;
+
+/// Tracks heard for less than this are not scrobbled, e.g. while zapping
+/// through channels
+pub const MIN_PLAYED: Duration = Duration::from_secs(30);
+
+/// Base URL of the public ListenBrainz API
+pub const LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org";
+
+/// How often queued listens are submitted again while the backend fails
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Per-request timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the last submission may hold up exiting; whatever is left stays
+/// queued for the next run
+const FINISH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A track that was listened to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Listen {
+    pub artist: String,
+    pub title: String,
+    pub channel_id: String,
+    /// Unix timestamp (seconds) when the track started
+    pub listened_at: i64,
+}
+
+/// A service listens can be submitted to.
+pub trait ScrobbleBackend {
+    /// Name used in log messages
+    fn name(&self) -> &str;
+
+    /// Submits a batch of listens. On error the whole batch is retried later.
+    fn submit(&self, listens: &[Listen]) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+/// Listens waiting for submission, persisted to disk on every change.
+#[derive(Debug)]
+pub struct ScrobbleQueue {
+    path: PathBuf,
+    max_age: Duration,
+    listens: Vec<Listen>,
+}
+
+impl ScrobbleQueue {
+    /// Path of the queue file in the state directory
+    pub fn default_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(get_state_directory()?.join("scrobble-queue.json"))
+    }
+
+    /// Opens the queue at `path`, loading listens left from earlier runs.
+    /// An unreadable queue file is logged and replaced by an empty queue.
+    pub fn open(path: PathBuf, max_age: Duration) -> Self {
+        let listens = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Discarding unreadable scrobble queue {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        let mut queue = Self { path, max_age, listens };
+        if queue.prune() > 0 {
+            queue.persist();
+        }
+        debug!("Scrobble queue has {} pending listens", queue.len());
+        queue
+    }
+
+    /// Number of pending listens
+    pub fn len(&self) -> usize {
+        self.listens.len()
+    }
+
+    /// Whether no listens are pending
+    pub fn is_empty(&self) -> bool {
+        self.listens.is_empty()
+    }
+
+    /// Queues a listen. Returns `false` if it was a duplicate or too old.
+    pub fn push(&mut self, listen: Listen) -> bool {
+        if self.is_expired(&listen) || self.listens.contains(&listen) {
+            return false;
+        }
+        self.listens.push(listen);
+        self.persist();
+        true
+    }
+
+    /// Drops listens older than the maximum age, returning how many.
+    pub fn prune(&mut self) -> usize {
+        let before = self.listens.len();
+        let max_age = self.max_age;
+        self.listens.retain(|listen| !expired(listen, max_age));
+        let dropped = before - self.listens.len();
+        if dropped > 0 {
+            info!("Dropped {} listens older than {:?} from the scrobble queue", dropped, max_age);
+        }
+        dropped
+    }
+
+    /// Submits pending listens in batches, oldest first. Stops at the first
+    /// failed batch, leaving it queued. Returns how many were submitted.
+    pub async fn flush<B: ScrobbleBackend>(&mut self, backend: &B) -> Result<usize, String> {
+        if self.prune() > 0 {
+            self.persist();
+        }
+
+        let mut submitted = 0;
+        while !self.listens.is_empty() {
+            let batch_len = self.listens.len().min(BATCH_SIZE);
+            if let Err(e) = backend.submit(&self.listens[..batch_len]).await {
+                warn!("{} rejected {} listens: {}", backend.name(), batch_len, e);
+                return Err(e);
+            }
+            self.listens.drain(..batch_len);
+            self.persist();
+            submitted += batch_len;
+        }
+
+        if submitted > 0 {
+            info!("Submitted {} listens to {}", submitted, backend.name());
+        }
+        Ok(submitted)
+    }
+
+    fn is_expired(&self, listen: &Listen) -> bool {
+        expired(listen, self.max_age)
+    }
+
+    /// Writes the queue atomically; failures are logged since the in-memory
+    /// queue stays usable.
+    fn persist(&self) {
+        if let Err(e) = write_atomically(&self.path, &self.listens) {
+            warn!("Failed to save scrobble queue {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Submits listens to ListenBrainz with a user token.
+#[derive(Debug)]
+pub struct ListenBrainz {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+impl ListenBrainz {
+    pub fn new(token: String) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+        Ok(Self {
+            client,
+            url: LISTENBRAINZ_URL.to_string(),
+            token,
+        })
+    }
+
+    /// Body of a `submit-listens` request for `listens`.
+    fn submission(listens: &[Listen]) -> serde_json::Value {
+        let payload: Vec<_> = listens
+            .iter()
+            .map(|listen| {
+                json!({
+                    "listened_at": listen.listened_at,
+                    "track_metadata": {
+                        "artist_name": listen.artist,
+                        "track_name": listen.title,
+                        "additional_info": {
+                            "media_player": "soma-player",
+                            "music_service_name": "SomaFM",
+                            "origin_url": format!("https://somafm.com/{}/", listen.channel_id),
+                        },
+                    },
+                })
+            })
+            .collect();
+        json!({
+            "listen_type": if listens.len() == 1 { "single" } else { "import" },
+            "payload": payload,
+        })
+    }
+}
+
+impl ScrobbleBackend for ListenBrainz {
+    fn name(&self) -> &str {
+        "ListenBrainz"
+    }
+
+    async fn submit(&self, listens: &[Listen]) -> Result<(), String> {
+        self.client
+            .post(format!("{}/1/submit-listens", self.url))
+            .header(reqwest::header::AUTHORIZATION, format!("Token {}", self.token))
+            .json(&Self::submission(listens))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// The track playing and how long it has been heard, paused time left out.
+#[derive(Debug)]
+struct Playing {
+    listen: Listen,
+    played: Duration,
+    since: Instant,
+    paused: bool,
+}
+
+/// Follows the player status and reports each track once it ends.
+#[derive(Debug, Default)]
+pub struct ListenTracker {
+    playing: Option<Playing>,
+}
+
+impl ListenTracker {
+    /// Takes in the status at `now`. Returns the previous track as a listen
+    /// when it just ended and was heard for at least [`MIN_PLAYED`].
+    pub fn update(&mut self, status: &PlayerStatus, now: Instant) -> Option<Listen> {
+        if let Some(playing) = &mut self.playing {
+            if !playing.paused {
+                playing.played += now.saturating_duration_since(playing.since);
+            }
+            playing.since = now;
+            playing.paused = status.paused;
+
+            let listen = &playing.listen;
+            if status.channel_id.as_deref() == Some(listen.channel_id.as_str())
+                && status.artist.as_deref() == Some(listen.artist.as_str())
+                && status.title.as_deref() == Some(listen.title.as_str())
+            {
+                return None;
+            }
+        }
+
+        let ended = self.playing.take().filter(|playing| playing.played >= MIN_PLAYED);
+        if let (Some(channel_id), Some(artist), Some(title)) = (&status.channel_id, &status.artist, &status.title) {
+            self.playing = Some(Playing {
+                listen: Listen {
+                    artist: artist.clone(),
+                    title: title.clone(),
+                    channel_id: channel_id.clone(),
+                    listened_at: Utc::now().timestamp(),
+                },
+                played: Duration::ZERO,
+                since: now,
+                paused: status.paused,
+            });
+        }
+        ended.map(|playing| playing.listen)
+    }
+}
+
+/// Scrobbles the tracks played to ListenBrainz in the background.
+#[derive(Debug)]
+pub struct Scrobbler {
+    tracker: ListenTracker,
+    listens: mpsc::UnboundedSender<Listen>,
+    task: JoinHandle<()>,
+}
+
+impl Scrobbler {
+    /// Starts submitting listens, or returns `None` when no token is
+    /// configured. Must be called within a Tokio runtime.
+    pub fn start(config: &ScrobbleConfig) -> Option<Self> {
+        let token = config.listenbrainz_token.clone().filter(|t| !t.trim().is_empty())?;
+        let backend = ListenBrainz::new(token)
+            .map_err(|e| warn!("Scrobbling disabled: {}", e))
+            .ok()?;
+        let path = ScrobbleQueue::default_path()
+            .map_err(|e| warn!("Scrobbling disabled: {}", e))
+            .ok()?;
+        let max_age = Duration::from_secs(u64::from(config.max_age_days) * 24 * 60 * 60);
+        let queue = ScrobbleQueue::open(path, max_age);
+
+        let (listens, received) = mpsc::unbounded_channel();
+        let task = tokio::spawn(submit_listens(queue, backend, received));
+        Some(Self {
+            tracker: ListenTracker::default(),
+            listens,
+            task,
+        })
+    }
+
+    /// Queues the track that just ended, if `status` ends one.
+    pub fn update(&mut self, status: &PlayerStatus) {
+        if let Some(listen) = self.tracker.update(status, Instant::now()) {
+            debug!("Scrobbling {} - {}", listen.artist, listen.title);
+            let _ = self.listens.send(listen);
+        }
+    }
+
+    /// Queues the playing track and waits a moment for the queue to be
+    /// submitted.
+    pub async fn finish(mut self) {
+        self.update(&PlayerStatus::idle());
+        drop(self.listens);
+        if tokio::time::timeout(FINISH_TIMEOUT, self.task).await.is_err() {
+            debug!("Listens left in the scrobble queue for the next run");
+        }
+    }
+}
+
+/// Queues incoming listens and submits the queue whenever a listen comes
+/// in, and every [`RETRY_INTERVAL`] while listens are left over.
+async fn submit_listens(
+    mut queue: ScrobbleQueue,
+    backend: ListenBrainz,
+    mut listens: mpsc::UnboundedReceiver<Listen>,
+) {
+    let mut retry = tokio::time::interval(RETRY_INTERVAL);
+    retry.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        let closed = tokio::select! {
+            listen = listens.recv() => match listen {
+                Some(listen) => {
+                    queue.push(listen);
+                    false
+                }
+                None => true,
+            },
+            _ = retry.tick() => false,
+        };
+        if !queue.is_empty() {
+            // Failures are logged by the queue and retried on the next tick
+            let _ = queue.flush(&backend).await;
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+fn expired(listen: &Listen, max_age: Duration) -> bool {
+    Utc::now().timestamp() - listen.listened_at > max_age.as_secs() as i64
+}
+
+fn write_atomically(path: &Path, listens: &[Listen]) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string(listens)?)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    fn listen(title: &str, age_secs: i64) -> Listen {
+        Listen {
+            artist: "Artist".to_string(),
+            title: title.to_string(),
+            channel_id: "groovesalad".to_string(),
+            listened_at: Utc::now().timestamp() - age_secs,
+        }
+    }
+
+    /// Backend that fails a set number of times before accepting
+    struct MockBackend {
+        failures: Mutex<usize>,
+        accepted: Mutex<Vec<Listen>>,
+    }
+
+    impl ScrobbleBackend for MockBackend {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        async fn submit(&self, listens: &[Listen]) -> Result<(), String> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err("offline".to_string());
+            }
+            self.accepted.lock().unwrap().extend_from_slice(listens);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_queue_persists_and_dedups() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("queue.json");
+
+        let mut queue = ScrobbleQueue::open(path.clone(), DEFAULT_MAX_AGE);
+        let first = listen("One", 60);
+        assert!(queue.push(first.clone()));
+        assert!(!queue.push(first));
+        assert!(queue.push(listen("Two", 30)));
+
+        let reopened = ScrobbleQueue::open(path, DEFAULT_MAX_AGE);
+        assert_eq!(reopened.len(), 2);
+    }
+
+    #[test]
+    fn test_queue_drops_expired_listens() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("queue.json");
+        let max_age = Duration::from_secs(3600);
+
+        let mut queue = ScrobbleQueue::open(path.clone(), DEFAULT_MAX_AGE);
+        queue.push(listen("Old", 7200));
+        queue.push(listen("New", 60));
+
+        let mut queue = ScrobbleQueue::open(path, max_age);
+        assert_eq!(queue.len(), 1);
+        assert!(!queue.push(listen("Older", 7200)));
+    }
+
+    #[test]
+    fn test_queue_ignores_corrupt_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("queue.json");
+        fs::write(&path, "[{").unwrap();
+
+        assert!(ScrobbleQueue::open(path, DEFAULT_MAX_AGE).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_flush_retries_after_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("queue.json");
+        let backend = MockBackend {
+            failures: Mutex::new(1),
+            accepted: Mutex::new(Vec::new()),
+        };
+
+        let mut queue = ScrobbleQueue::open(path.clone(), DEFAULT_MAX_AGE);
+        for i in 0..(BATCH_SIZE + 5) {
+            queue.push(listen(&format!("Track {}", i), 600));
+        }
+
+        assert!(queue.flush(&backend).await.is_err());
+        assert_eq!(queue.len(), BATCH_SIZE + 5);
+
+        assert_eq!(queue.flush(&backend).await.unwrap(), BATCH_SIZE + 5);
+        assert!(queue.is_empty());
+        assert!(ScrobbleQueue::open(path, DEFAULT_MAX_AGE).is_empty());
+        assert_eq!(backend.accepted.lock().unwrap()[0].title, "Track 0");
+    }
+
+    fn status(title: &str, paused: bool) -> PlayerStatus {
+        PlayerStatus {
+            channel_id: Some("groovesalad".to_string()),
+            channel: Some("Groove Salad".to_string()),
+            artist: Some("Artist".to_string()),
+            title: Some(title.to_string()),
+            paused,
+            ..PlayerStatus::idle()
+        }
+    }
+
+    #[test]
+    fn test_tracker_reports_tracks_heard_long_enough() {
+        let mut tracker = ListenTracker::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(tracker.update(&status("One", false), at(0)), None);
+        assert_eq!(tracker.update(&status("One", false), at(20)), None);
+        let listen = tracker.update(&status("Two", false), at(40)).unwrap();
+        assert_eq!((listen.title.as_str(), listen.channel_id.as_str()), ("One", "groovesalad"));
+
+        // Time spent paused doesn't count
+        tracker.update(&status("Two", true), at(50));
+        tracker.update(&status("Two", false), at(100));
+        assert_eq!(tracker.update(&status("Three", false), at(110)), None);
+
+        // Stopping ends the track too
+        let listen = tracker.update(&PlayerStatus::idle(), at(200)).unwrap();
+        assert_eq!(listen.title, "Three");
+        assert_eq!(tracker.update(&PlayerStatus::idle(), at(300)), None);
+    }
+
+    #[test]
+    fn test_listenbrainz_submission() {
+        let single = ListenBrainz::submission(&[listen("One", 60)]);
+        assert_eq!(single["listen_type"], "single");
+        assert_eq!(single["payload"][0]["track_metadata"]["track_name"], "One");
+        assert_eq!(single["payload"][0]["track_metadata"]["artist_name"], "Artist");
+        assert_eq!(
+            single["payload"][0]["track_metadata"]["additional_info"]["origin_url"],
+            "https://somafm.com/groovesalad/"
+        );
+
+        let batch = ListenBrainz::submission(&[listen("One", 120), listen("Two", 60)]);
+        assert_eq!(batch["listen_type"], "import");
+        assert_eq!(batch["payload"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,108 @@
+//! Periodic logging of the player's own resource usage.
+//!
+//! Long sessions occasionally end in crashes that are hard to reproduce.
+//! [`SelfMonitor`] logs the resident memory and the Tokio task counts at an
+//! interval and warns once memory grows past a configured bound, so the
+//! logs show what was growing before things went wrong. Trimming oversized
+//! stream buffers happens in the player, see
+//! [`BufferLimits`](crate::audio::drift::BufferLimits).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::config::SelfMonitorConfig;
+
+/// Logs resource usage in the background for as long as it is alive.
+pub struct SelfMonitor {
+    stop: Arc<AtomicBool>,
+}
+
+impl SelfMonitor {
+    /// Starts logging at the configured interval, or returns `None` when
+    /// logging is disabled. Must be called within a Tokio runtime.
+    pub fn start(config: &SelfMonitorConfig) -> Option<Self> {
+        if config.log_interval_secs == 0 {
+            return None;
+        }
+        let interval = Duration::from_secs(config.log_interval_secs);
+        let max_rss = (config.max_rss_mb > 0).then_some(config.max_rss_mb * 1024 * 1024);
+        let stop = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn({
+            let stop = Arc::clone(&stop);
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                let mut warned = false;
+                loop {
+                    ticker.tick().await;
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let rss = resident_memory();
+                    log_usage(rss);
+                    if let (Some(rss), Some(max_rss)) = (rss, max_rss)
+                        && rss > max_rss
+                        && !std::mem::replace(&mut warned, true)
+                    {
+                        warn!("Memory use of {} MiB exceeds max_rss_mb", rss / 1024 / 1024);
+                    }
+                }
+            }
+        });
+
+        Some(Self { stop })
+    }
+}
+
+impl Drop for SelfMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn log_usage(rss: Option<u64>) {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let memory = rss.map_or_else(|| "unknown".to_string(), |rss| format!("{} MiB", rss / 1024 / 1024));
+    info!(
+        "Resource usage: memory {}, {} tasks alive, {} queued",
+        memory,
+        metrics.num_alive_tasks(),
+        metrics.global_queue_depth()
+    );
+}
+
+/// Resident memory of this process in bytes, where the platform reports it.
+pub fn resident_memory() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        parse_vm_rss(&status)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Reads the `VmRSS` line of `/proc/self/status`, given in KiB.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_rss() {
+        let status = "Name:\tsoma-player\nVmPeak:\t  204800 kB\nVmRSS:\t   51200 kB\nThreads:\t8\n";
+        assert_eq!(parse_vm_rss(status), Some(50 * 1024 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tsoma-player\n"), None);
+    }
+}
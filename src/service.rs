@@ -0,0 +1,139 @@
+//! Installing the player as a background service.
+//!
+//! `soma-player --install-service` writes a user-level systemd unit on
+//! Linux or a launchd agent on macOS that runs the player in kiosk mode.
+//! Without a terminal, kiosk mode plays headless and restarts playback on
+//! its own, so this gives always-on background playback from the current
+//! configuration. Enabling the service is left to the printed command.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the systemd unit
+pub const SYSTEMD_UNIT: &str = "soma-player.service";
+
+/// Label of the launchd agent
+pub const LAUNCHD_LABEL: &str = "com.somafm.soma-player";
+
+/// A written service definition and how to start it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledService {
+    pub path: PathBuf,
+    /// Command that enables and starts the service
+    pub enable_command: String,
+}
+
+/// Contents of a systemd user unit running `exe` in kiosk mode.
+pub fn systemd_unit(exe: &Path, config_path: &Path) -> String {
+    format!(
+        "[Unit]
+Description=SomaFM Player
+Wants=network-online.target
+After=network-online.target sound.target
+
+[Service]
+# Plays the channels set up in {}
+ExecStart=\"{}\" --kiosk
+Restart=on-failure
+RestartSec=10
+
+[Install]
+WantedBy=default.target
+",
+        config_path.display(),
+        // systemd expands specifiers starting with %
+        exe.display().to_string().replace('%', "%%")
+    )
+}
+
+/// Contents of a launchd agent running `exe` in kiosk mode.
+pub fn launchd_plist(exe: &Path, config_path: &Path) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<!-- Plays the channels set up in {} -->
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>--kiosk</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        xml_comment(&config_path.display().to_string()),
+        LAUNCHD_LABEL,
+        xml_escape(&exe.display().to_string())
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// `text` made safe to put in an XML comment, which may not contain `--`.
+fn xml_comment(text: &str) -> String {
+    let mut text = text.to_string();
+    while text.contains("--") {
+        text = text.replace("--", "- -");
+    }
+    text
+}
+
+/// Writes the service definition for this platform, replacing an existing
+/// one, and returns where it went.
+pub fn install_service(config_path: &Path) -> Result<InstalledService, Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+
+    let (path, contents, enable_command) = if cfg!(target_os = "linux") {
+        (
+            home.join(".config/systemd/user").join(SYSTEMD_UNIT),
+            systemd_unit(&exe, config_path),
+            format!("systemctl --user daemon-reload && systemctl --user enable --now {}", SYSTEMD_UNIT),
+        )
+    } else if cfg!(target_os = "macos") {
+        let path = home.join("Library/LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL));
+        let enable_command = format!("launchctl load -w \"{}\"", path.display());
+        (path, launchd_plist(&exe, config_path), enable_command)
+    } else {
+        return Err("Installing a service is only supported on Linux (systemd) and macOS (launchd)".into());
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, contents)?;
+    Ok(InstalledService { path, enable_command })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_definitions_run_kiosk_mode() {
+        let exe = Path::new("/opt/soma & co/soma-player");
+        let config = Path::new("/home/me/.config/soma-player/config.toml");
+
+        let unit = systemd_unit(exe, config);
+        assert!(unit.contains("ExecStart=\"/opt/soma & co/soma-player\" --kiosk\n"));
+        assert!(unit.contains("# Plays the channels set up in /home/me/.config/soma-player/config.toml"));
+        assert!(unit.contains("WantedBy=default.target"));
+
+        let plist = launchd_plist(exe, config);
+        assert!(plist.contains("<string>/opt/soma &amp; co/soma-player</string>\n        <string>--kiosk</string>"));
+        assert!(plist.contains(LAUNCHD_LABEL));
+
+        let plist = launchd_plist(exe, Path::new("/home/me/my---config/config.toml"));
+        assert!(plist.contains("<!-- Plays the channels set up in /home/me/my- - -config/config.toml -->"));
+        assert_eq!(plist.matches("--").count(), 3);
+    }
+}
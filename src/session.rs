@@ -0,0 +1,173 @@
+//! Transcript of a listening session (`--export-session` and the `E` key).
+//!
+//! The player notes which channels were played, every track with the time
+//! it came on, volume changes and errors, and writes them as plain text
+//! that reads well when shared, e.g. to answer "what was that set?".
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::models::{Channel, TrackInfo};
+
+/// Something that happened during the session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEntry {
+    /// Playback of this channel title started
+    Channel(String),
+    Track(TrackInfo),
+    Volume(u8),
+    Error(String),
+}
+
+/// Everything noted since the player started.
+#[derive(Debug, Clone)]
+pub struct SessionLog {
+    started: DateTime<Local>,
+    entries: Vec<(DateTime<Local>, SessionEntry)>,
+}
+
+impl Default for SessionLog {
+    fn default() -> Self {
+        Self::new(Local::now())
+    }
+}
+
+impl SessionLog {
+    pub fn new(started: DateTime<Local>) -> Self {
+        Self { started, entries: Vec::new() }
+    }
+
+    /// Notes `entry` as happening at `time`. A volume equal to the last one
+    /// noted is skipped, as players confirm their volume on every start.
+    pub fn record(&mut self, time: DateTime<Local>, entry: SessionEntry) {
+        if let SessionEntry::Volume(volume) = entry
+            && self.last_volume() == Some(volume)
+        {
+            return;
+        }
+        self.entries.push((time, entry));
+    }
+
+    fn last_volume(&self) -> Option<u8> {
+        self.entries.iter().rev().find_map(|(_, entry)| match entry {
+            SessionEntry::Volume(volume) => Some(*volume),
+            _ => None,
+        })
+    }
+
+    /// Notes that `channel` started playing now.
+    pub fn channel(&mut self, channel: &Channel) {
+        self.record(Local::now(), SessionEntry::Channel(channel.title.clone()));
+    }
+
+    /// Notes that `track` came on now.
+    pub fn track(&mut self, track: &TrackInfo) {
+        self.record(Local::now(), SessionEntry::Track(track.clone()));
+    }
+
+    /// Notes the volume the player now uses.
+    pub fn volume(&mut self, volume: u8) {
+        self.record(Local::now(), SessionEntry::Volume(volume));
+    }
+
+    /// Notes an error that stopped playback.
+    pub fn error(&mut self, message: &str) {
+        self.record(Local::now(), SessionEntry::Error(message.to_string()));
+    }
+
+    /// The transcript up to `now`.
+    pub fn render(&self, now: DateTime<Local>) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "SomaFM Player session, {} to {}",
+            self.started.format("%Y-%m-%d %H:%M"),
+            now.format(if now.date_naive() == self.started.date_naive() { "%H:%M" } else { "%Y-%m-%d %H:%M" })
+        );
+
+        let mut channels: Vec<&str> = Vec::new();
+        for (_, entry) in &self.entries {
+            if let SessionEntry::Channel(title) = entry
+                && !channels.contains(&title.as_str())
+            {
+                channels.push(title);
+            }
+        }
+        let tracks = self.entries.iter().filter(|(_, entry)| matches!(entry, SessionEntry::Track(_))).count();
+        let _ = writeln!(out, "Channels: {}", if channels.is_empty() { "none".to_string() } else { channels.join(", ") });
+        let _ = writeln!(out, "Tracks: {}\n", tracks);
+
+        for (time, entry) in &self.entries {
+            let time = time.format("%H:%M");
+            let _ = match entry {
+                SessionEntry::Channel(title) => writeln!(out, "{}  == {} ==", time, title),
+                SessionEntry::Track(track) => writeln!(out, "{}  {} - {}", time, track.artist, track.title),
+                SessionEntry::Volume(volume) => writeln!(out, "{}  (volume {}%)", time, volume),
+                SessionEntry::Error(message) => writeln!(out, "{}  (error: {})", time, message),
+            };
+        }
+        out
+    }
+
+    /// Writes the transcript to `path`.
+    pub fn export(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.render(Local::now()))?;
+        Ok(())
+    }
+
+    /// Where the transcript goes when no file was given:
+    /// `~/.config/soma-player/sessions/session-<start time>.txt`.
+    pub fn default_path(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let home = dirs::home_dir().ok_or("Could not find home directory")?;
+        let name = format!("session-{}.txt", self.started.format("%Y-%m-%d-%H%M%S"));
+        Ok(home.join(".config").join("soma-player").join("sessions").join(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_session_transcript() {
+        let at = |hour, minute| Local.with_ymd_and_hms(2025, 3, 1, hour, minute, 0).unwrap();
+        let track = |artist: &str, title: &str| TrackInfo { artist: artist.to_string(), title: title.to_string() };
+
+        let mut log = SessionLog::new(at(20, 0));
+        log.record(at(20, 0), SessionEntry::Channel("Groove Salad".to_string()));
+        log.record(at(20, 0), SessionEntry::Volume(50));
+        log.record(at(20, 1), SessionEntry::Track(track("Boards of Canada", "Roygbiv")));
+        log.record(at(20, 5), SessionEntry::Volume(55));
+        log.record(at(20, 6), SessionEntry::Channel("Drone Zone".to_string()));
+        // Confirmed again by the new player
+        log.record(at(20, 6), SessionEntry::Volume(55));
+        log.record(at(20, 7), SessionEntry::Track(track("Stars of the Lid", "Requiem for Dying Mothers")));
+        log.record(at(20, 9), SessionEntry::Error("No audio from Drone Zone".to_string()));
+        log.record(at(20, 9), SessionEntry::Channel("Groove Salad".to_string()));
+
+        assert_eq!(
+            log.render(at(21, 30)),
+            "\
+SomaFM Player session, 2025-03-01 20:00 to 21:30
+Channels: Groove Salad, Drone Zone
+Tracks: 2
+
+20:00  == Groove Salad ==
+20:00  (volume 50%)
+20:01  Boards of Canada - Roygbiv
+20:05  (volume 55%)
+20:06  == Drone Zone ==
+20:07  Stars of the Lid - Requiem for Dying Mothers
+20:09  (error: No audio from Drone Zone)
+20:09  == Groove Salad ==
+"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Summary of the setup a session starts with.
+//!
+//! Logged at every start and printed before the TUI with `--verbose`, so
+//! the top of a log file attached to a bug report already tells the
+//! version, platform, audio device and files involved.
+
+use std::fmt;
+
+use crate::api::cache::ChannelSource;
+
+/// What a session starts with.
+#[derive(Debug, Clone)]
+pub struct StartupSummary {
+    pub version: &'static str,
+    /// Operating system and architecture, e.g. `linux x86_64`
+    pub platform: String,
+    /// Audio host and output device, or the renderer cast to
+    pub audio: String,
+    pub config_path: String,
+    /// Log directory, or why nothing is logged to files
+    pub log_path: String,
+    /// Number of channels and whether they came from the cache
+    pub channels: String,
+    /// Channel playing at start, if any
+    pub start: String,
+}
+
+impl StartupSummary {
+    /// Collects the summary; `cast_target` is the renderer played on instead
+    /// of the local device and `playing` the channel started right away.
+    pub fn collect(
+        log_to_file: bool,
+        cast_target: Option<&str>,
+        channel_count: usize,
+        source: ChannelSource,
+        playing: Option<&str>,
+    ) -> Self {
+        let config_path = crate::config::AppConfig::config_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|e| format!("unavailable ({})", e));
+        let log_path = if log_to_file {
+            crate::logging::get_log_directory()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|e| format!("unavailable ({})", e))
+        } else {
+            "file logging off".to_string()
+        };
+        let source = match source {
+            ChannelSource::Live => "from the API",
+            ChannelSource::Cached => "from the cache, API unavailable",
+            ChannelSource::Refreshing => "from the cache, refreshing",
+        };
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            platform: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            audio: match cast_target {
+                Some(renderer) => format!("casting to {}", renderer),
+                None => local_audio(),
+            },
+            config_path,
+            log_path,
+            channels: format!("{} {}", channel_count, source),
+            start: playing.map_or_else(|| "channel list".to_string(), |channel| format!("playing {}", channel)),
+        }
+    }
+
+    /// Label and value of each line.
+    pub fn lines(&self) -> [(&'static str, &str); 7] {
+        [
+            ("Version", self.version),
+            ("Platform", &self.platform),
+            ("Audio", &self.audio),
+            ("Config", &self.config_path),
+            ("Logs", &self.log_path),
+            ("Channels", &self.channels),
+            ("Start", &self.start),
+        ]
+    }
+}
+
+impl fmt::Display for StartupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "SomaFM Player")?;
+        for (label, value) in self.lines() {
+            writeln!(f, "  {:<9} {}", format!("{}:", label), value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Audio host and default output device.
+fn local_audio() -> String {
+    let host = rodio::cpal::default_host().id().name();
+    match crate::audio::device::default_output_device() {
+        Some((_, name)) => format!("{}, {}", host, name),
+        None => format!("{}, no output device", host),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_summary_lines() {
+        let summary = StartupSummary::collect(false, Some("Kitchen"), 42, ChannelSource::Cached, Some("Groove Salad"));
+        assert_eq!(summary.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(summary.audio, "casting to Kitchen");
+        assert_eq!(summary.log_path, "file logging off");
+        assert_eq!(summary.channels, "42 from the cache, API unavailable");
+
+        let text = summary.to_string();
+        assert!(text.contains("  Start:    playing Groove Salad\n"));
+        assert_eq!(text.lines().count(), 8);
+    }
+}
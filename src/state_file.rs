@@ -0,0 +1,159 @@
+//! Player status file for external tools.
+//!
+//! While the TUI runs, the current status is kept in a small JSON file
+//! (`~/.local/state/soma-player/status.json` on Linux) so scripts and status
+//! bars can read it without talking to the player. The file is replaced
+//! atomically on every change and removed when the player exits.
+//!
+//! # Examples
+//!
+//! ```json
+//! {
+//!   "pid": 4242,
+//!   "channel_id": "groovesalad",
+//!   "channel": "Groove Salad",
+//!   "artist": "Thievery Corporation",
+//!   "title": "Lebanese Blonde",
+//!   "volume": 75,
+//!   "paused": false
+//! }
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+/// Snapshot of what the player is doing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStatus {
+    pub pid: u32,
+    pub channel_id: Option<String>,
+    pub channel: Option<String>,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub volume: Option<u8>,
+    pub paused: bool,
+}
+
+impl PlayerStatus {
+    /// Status of this process with nothing playing.
+    pub fn idle() -> Self {
+        Self {
+            pid: std::process::id(),
+            channel_id: None,
+            channel: None,
+            artist: None,
+            title: None,
+            volume: None,
+            paused: false,
+        }
+    }
+}
+
+/// Returns the directory holding runtime state, creating it if needed.
+pub fn get_state_directory() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let base = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .ok_or("Could not find a state directory")?;
+    let state_dir = base.join("soma-player");
+    fs::create_dir_all(&state_dir)?;
+    Ok(state_dir)
+}
+
+/// Keeps the status file up to date and removes it on drop.
+#[derive(Debug)]
+pub struct StateFile {
+    path: PathBuf,
+    last_written: Option<PlayerStatus>,
+}
+
+impl StateFile {
+    /// Uses `status.json` in the state directory.
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::at(get_state_directory()?.join("status.json")))
+    }
+
+    /// Uses the file at `path`.
+    pub fn at(path: PathBuf) -> Self {
+        Self { path, last_written: None }
+    }
+
+    /// Path of the status file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `status` if it differs from what was last written.
+    pub fn update(&mut self, status: &PlayerStatus) {
+        if self.last_written.as_ref() == Some(status) {
+            return;
+        }
+
+        match write_atomically(&self.path, status) {
+            Ok(()) => {
+                debug!("Wrote status file {}", self.path.display());
+                self.last_written = Some(status.clone());
+            }
+            Err(e) => warn!("Failed to write status file {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+impl Drop for StateFile {
+    fn drop(&mut self) {
+        if self.last_written.is_some()
+            && let Err(e) = fs::remove_file(&self.path)
+        {
+            debug!("Failed to remove status file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Writes to a temporary file next to `path` and renames it into place so
+/// readers never see a partial file.
+fn write_atomically(path: &Path, status: &PlayerStatus) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_path = path.with_extension(format!("json.tmp.{}", std::process::id()));
+    fs::write(&temp_path, serde_json::to_string_pretty(status)?)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_status_file_lifecycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("status.json");
+
+        let mut status = PlayerStatus {
+            channel_id: Some("groovesalad".to_string()),
+            channel: Some("Groove Salad".to_string()),
+            artist: Some("Artist".to_string()),
+            title: Some("Title".to_string()),
+            volume: Some(75),
+            ..PlayerStatus::idle()
+        };
+
+        let mut state_file = StateFile::at(path.clone());
+        state_file.update(&status);
+        let written: PlayerStatus = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, status);
+        assert_eq!(written.pid, std::process::id());
+
+        status.paused = true;
+        state_file.update(&status);
+        let written: PlayerStatus = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(written.paused);
+
+        // Only the status file remains; temporary files are renamed away
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        drop(state_file);
+        assert!(!path.exists());
+    }
+}
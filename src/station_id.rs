@@ -0,0 +1,121 @@
+//! Ducking or skipping SomaFM station IDs.
+//!
+//! Between tracks SomaFM plays short station IDs and promos, which show up
+//! in the metadata like a track (e.g. `SomaFM - Station ID`). With the
+//! `[station_ids]` action set, [`StationIdFilter`] recognizes them by the
+//! configured patterns and either lowers the volume until the next track
+//! or skips ahead. Where an ID ends within the buffer is not known, so
+//! skipping drops the buffered audio and reconnects at live; it is only
+//! done when at least `min_skip_buffer_secs` are buffered, and the rest of
+//! an ID still playing at live is ducked.
+
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::alerts::AlertPattern;
+use crate::config::{StationIdAction, StationIdConfig};
+use crate::models::TrackInfo;
+
+/// Recognizes station IDs in the playing channel's metadata.
+#[derive(Debug)]
+pub struct StationIdFilter {
+    patterns: Vec<AlertPattern>,
+    skip: bool,
+    level: f32,
+    min_skip_buffer: Duration,
+    /// Whether an ID is playing right now
+    in_break: bool,
+}
+
+impl StationIdFilter {
+    /// Builds the filter, or returns `None` when station IDs are played as
+    /// they are. Invalid patterns are logged and skipped.
+    pub fn new(config: &StationIdConfig) -> Option<Self> {
+        if config.action == StationIdAction::Off {
+            return None;
+        }
+        let patterns: Vec<AlertPattern> = config
+            .patterns
+            .iter()
+            .filter_map(|p| AlertPattern::parse(p).map_err(|e| warn!("{}", e)).ok())
+            .collect();
+        if patterns.is_empty() {
+            warn!("No station ID patterns, station IDs are played as they are");
+            return None;
+        }
+        Some(Self {
+            patterns,
+            skip: config.action == StationIdAction::Skip,
+            level: f32::from(config.level.min(100)) / 100.0,
+            min_skip_buffer: Duration::from_secs(config.min_skip_buffer_secs),
+            in_break: false,
+        })
+    }
+
+    /// Whether `track` is a station ID.
+    pub fn is_station_id(&self, track: &TrackInfo) -> bool {
+        let text = format!("{} - {}", track.artist, track.title);
+        self.patterns.iter().any(|pattern| pattern.is_match(&text))
+    }
+
+    /// Records a track change with `buffered` audio behind live and returns
+    /// whether to skip ahead to live.
+    pub fn track_changed(&mut self, track: &TrackInfo, buffered: Option<Duration>) -> bool {
+        let is_station_id = self.is_station_id(track);
+        let was_in_break = std::mem::replace(&mut self.in_break, is_station_id);
+        if !self.in_break || was_in_break {
+            return false;
+        }
+        let skip = self.skip && buffered.is_some_and(|buffered| buffered >= self.min_skip_buffer);
+        if skip {
+            info!("Station ID '{} - {}', skipping ahead to live", track.artist, track.title);
+        } else {
+            info!("Station ID '{} - {}', ducking", track.artist, track.title);
+        }
+        skip
+    }
+
+    /// Factor (0.0-1.0) to scale the volume by right now.
+    pub fn factor(&self) -> f32 {
+        if self.in_break { self.level } else { 1.0 }
+    }
+
+    /// Forgets the current break, e.g. when another channel starts.
+    pub fn reset(&mut self) {
+        self.in_break = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(artist: &str, title: &str) -> TrackInfo {
+        TrackInfo { artist: artist.to_string(), title: title.to_string() }
+    }
+
+    #[test]
+    fn test_skips_or_ducks_station_ids() {
+        let config = StationIdConfig { action: StationIdAction::Skip, ..StationIdConfig::default() };
+        let mut filter = StationIdFilter::new(&config).unwrap();
+        let buffered = |secs| Some(Duration::from_secs(secs));
+
+        assert!(!filter.track_changed(&track("Boards of Canada", "Roygbiv"), buffered(60)));
+        assert_eq!(filter.factor(), 1.0);
+
+        assert!(filter.track_changed(&track("SomaFM", "Station ID"), buffered(60)));
+        assert_eq!(filter.factor(), 0.3);
+        // The same break continuing at live is not skipped again
+        assert!(!filter.track_changed(&track("SomaFM", "Station ID"), buffered(0)));
+
+        assert!(!filter.track_changed(&track("Aphex Twin", "Xtal"), buffered(0)));
+        assert_eq!(filter.factor(), 1.0);
+
+        // Too little buffered to skip, so only ducked
+        assert!(!filter.track_changed(&track("Groove Salad", "Station ID"), buffered(5)));
+        assert_eq!(filter.factor(), 0.3);
+
+        assert!(StationIdFilter::new(&StationIdConfig::default()).is_none());
+    }
+}
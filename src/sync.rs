@@ -0,0 +1,364 @@
+//! Experimental listen-along mode over the LAN.
+//!
+//! With `[sync] role = "leader"`, the player broadcasts its channel, pause
+//! state and distance behind live as a small JSON datagram once a second.
+//! Instances with `role = "follower"` and the same `group` switch to the
+//! leader's channel and pause state whenever the leader changes them, and
+//! jump to live when they fall noticeably further behind than the leader.
+//! Both sides stream from SomaFM themselves, so playback is only roughly
+//! in sync (usually within a few seconds).
+//!
+//! Followers only react to changes on the leader, so switching channel
+//! locally keeps working until the leader switches again.
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{SyncConfig, SyncRole};
+
+/// UDP port used when none is configured
+pub const DEFAULT_PORT: u16 = 47474;
+
+/// Bumped whenever [`SyncMessage`] changes incompatibly
+const PROTOCOL_VERSION: u8 = 1;
+
+/// How often the leader repeats an unchanged announcement
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How much further behind live than the leader a follower may drift
+/// before it jumps to live
+const SYNC_TOLERANCE: Duration = Duration::from_secs(3);
+
+/// Minimum time between two jumps to live, so a slow connection does not
+/// reconnect over and over
+const RESYNC_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// A leader not heard from for this long is considered gone
+const LEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// State announced by the leader.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncMessage {
+    pub version: u8,
+    pub group: String,
+    /// Name of the leading instance
+    pub leader: String,
+    pub channel_id: Option<String>,
+    pub paused: bool,
+    /// How far the leader is behind the live stream
+    pub latency_ms: Option<u64>,
+}
+
+/// What a follower has to do to match the leader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FollowAction {
+    SwitchChannel(String),
+    Pause,
+    Resume,
+    JumpToLive,
+}
+
+/// Name announced by this instance: the configured one, else the host name.
+fn instance_name(config: &SyncConfig) -> String {
+    config.name
+        .clone()
+        .filter(|n| !n.trim().is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok().filter(|n| !n.is_empty()))
+        .or_else(|| std::env::var("COMPUTERNAME").ok().filter(|n| !n.is_empty()))
+        .unwrap_or_else(|| format!("soma-player-{}", std::process::id()))
+}
+
+/// Broadcasts this instance's playback state.
+#[derive(Debug)]
+pub struct SyncLeader {
+    socket: UdpSocket,
+    target: SocketAddr,
+    group: String,
+    name: String,
+    last_sent: Option<(SyncMessage, Instant)>,
+}
+
+impl SyncLeader {
+    /// Opens a socket broadcasting on the configured port.
+    pub fn bind(config: &SyncConfig) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        let target = SocketAddr::from((Ipv4Addr::BROADCAST, config.port));
+        Self::with_socket(socket, target, config)
+    }
+
+    fn with_socket(socket: UdpSocket, target: SocketAddr, config: &SyncConfig) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        let name = instance_name(config);
+        info!("Listen-along leader '{}' announcing to {} (group '{}')", name, target, config.group);
+        Ok(Self {
+            socket,
+            target,
+            group: config.group.clone(),
+            name,
+            last_sent: None,
+        })
+    }
+
+    /// Announces the current state when it changed, or when the last
+    /// announcement is older than a second.
+    pub fn announce(&mut self, channel_id: Option<&str>, paused: bool, latency: Option<Duration>) {
+        let message = SyncMessage {
+            version: PROTOCOL_VERSION,
+            group: self.group.clone(),
+            leader: self.name.clone(),
+            channel_id: channel_id.map(str::to_string),
+            paused,
+            latency_ms: latency.map(|l| l.as_millis() as u64),
+        };
+
+        let due = match &self.last_sent {
+            Some((last, sent)) => {
+                last.channel_id != message.channel_id
+                    || last.paused != message.paused
+                    || sent.elapsed() >= ANNOUNCE_INTERVAL
+            }
+            None => true,
+        };
+        if !due {
+            return;
+        }
+
+        match serde_json::to_vec(&message) {
+            Ok(datagram) => {
+                if let Err(e) = self.socket.send_to(&datagram, self.target) {
+                    debug!("Failed to send listen-along announcement: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to encode listen-along announcement: {}", e),
+        }
+        self.last_sent = Some((message, Instant::now()));
+    }
+}
+
+/// Follows the announcements of a leader in the same group.
+#[derive(Debug)]
+pub struct SyncFollower {
+    socket: UdpSocket,
+    group: String,
+    leader: Option<(String, Instant)>,
+    /// Last leader channel and pause state acted upon
+    followed_channel: Option<String>,
+    followed_paused: Option<bool>,
+    last_resync: Option<Instant>,
+}
+
+impl SyncFollower {
+    /// Listens for announcements on the configured port.
+    pub fn bind(config: &SyncConfig) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, config.port))?;
+        socket.set_nonblocking(true)?;
+        info!("Listen-along follower waiting on port {} (group '{}')", config.port, config.group);
+        Ok(Self {
+            socket,
+            group: config.group.clone(),
+            leader: None,
+            followed_channel: None,
+            followed_paused: None,
+            last_resync: None,
+        })
+    }
+
+    /// Name of the leader, while it keeps announcing
+    pub fn leader(&self) -> Option<&str> {
+        self.leader
+            .as_ref()
+            .filter(|(_, seen)| seen.elapsed() < LEADER_TIMEOUT)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Reads pending announcements and returns what to change to follow the
+    /// latest one, given what this instance is playing.
+    pub fn poll(&mut self, playing: Option<&str>, paused: bool, latency: Option<Duration>) -> Vec<FollowAction> {
+        let mut latest = None;
+        let mut buffer = [0u8; 2048];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, from)) => match serde_json::from_slice::<SyncMessage>(&buffer[..len]) {
+                    Ok(message) => latest = Some(message),
+                    Err(e) => debug!("Ignoring malformed listen-along datagram from {}: {}", from, e),
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    debug!("Failed to receive listen-along announcement: {}", e);
+                    break;
+                }
+            }
+        }
+
+        match latest {
+            Some(message) => self.follow(&message, playing, paused, latency),
+            None => Vec::new(),
+        }
+    }
+
+    fn follow(
+        &mut self,
+        message: &SyncMessage,
+        playing: Option<&str>,
+        paused: bool,
+        latency: Option<Duration>,
+    ) -> Vec<FollowAction> {
+        if message.version != PROTOCOL_VERSION || message.group != self.group {
+            return Vec::new();
+        }
+        if self.leader().is_none_or(|name| name != message.leader) {
+            info!("Following listen-along leader '{}'", message.leader);
+        }
+        self.leader = Some((message.leader.clone(), Instant::now()));
+
+        let mut actions = Vec::new();
+
+        if message.channel_id != self.followed_channel {
+            self.followed_channel = message.channel_id.clone();
+            if let Some(channel_id) = &message.channel_id
+                && playing != Some(channel_id.as_str())
+            {
+                actions.push(FollowAction::SwitchChannel(channel_id.clone()));
+            }
+        }
+        let switching = !actions.is_empty();
+
+        if self.followed_paused != Some(message.paused) {
+            self.followed_paused = Some(message.paused);
+            // Switching channel starts playback, so re-apply a pause
+            if message.paused != paused || (switching && message.paused) {
+                actions.push(if message.paused { FollowAction::Pause } else { FollowAction::Resume });
+            }
+        }
+
+        // Catch up when clearly further behind live than the leader
+        let same_channel = playing.is_some() && playing == message.channel_id.as_deref();
+        if same_channel
+            && !switching
+            && !paused
+            && let (Some(ours), Some(theirs)) = (latency, message.latency_ms)
+            && ours > Duration::from_millis(theirs) + SYNC_TOLERANCE
+            && self.last_resync.is_none_or(|at| at.elapsed() >= RESYNC_COOLDOWN)
+        {
+            info!("{:?} behind live while the leader is {}ms behind, jumping to live", ours, theirs);
+            self.last_resync = Some(Instant::now());
+            actions.push(FollowAction::JumpToLive);
+        }
+
+        actions
+    }
+}
+
+/// Leader or follower, as configured.
+#[derive(Debug)]
+pub enum ListenAlong {
+    Leader(SyncLeader),
+    Follower(SyncFollower),
+}
+
+impl ListenAlong {
+    /// Starts the configured role, or returns `None` when sync is off or the
+    /// socket cannot be opened.
+    pub fn start(config: &SyncConfig) -> Option<Self> {
+        let result = match config.role {
+            SyncRole::Off => return None,
+            SyncRole::Leader => SyncLeader::bind(config).map(ListenAlong::Leader),
+            SyncRole::Follower => SyncFollower::bind(config).map(ListenAlong::Follower),
+        };
+        result
+            .map_err(|e| warn!("Listen-along disabled, port {} unavailable: {}", config.port, e))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SyncConfig {
+        SyncConfig {
+            role: SyncRole::Follower,
+            port: 0,
+            group: "home".to_string(),
+            name: Some("living-room".to_string()),
+        }
+    }
+
+    fn message(channel_id: &str, paused: bool, latency_ms: u64) -> SyncMessage {
+        SyncMessage {
+            version: PROTOCOL_VERSION,
+            group: "home".to_string(),
+            leader: "living-room".to_string(),
+            channel_id: Some(channel_id.to_string()),
+            paused,
+            latency_ms: Some(latency_ms),
+        }
+    }
+
+    #[test]
+    fn test_follower_reacts_to_leader_changes() {
+        let mut follower = SyncFollower::bind(&config()).unwrap();
+
+        let actions = follower.follow(&message("groovesalad", false, 2000), None, false, None);
+        assert_eq!(actions, [FollowAction::SwitchChannel("groovesalad".to_string())]);
+        assert_eq!(follower.leader(), Some("living-room"));
+
+        // A local channel change sticks until the leader changes again
+        assert!(follower.follow(&message("groovesalad", false, 2000), Some("dronezone"), false, None).is_empty());
+        let actions = follower.follow(&message("lush", false, 2000), Some("dronezone"), false, None);
+        assert_eq!(actions, [FollowAction::SwitchChannel("lush".to_string())]);
+
+        let actions = follower.follow(&message("lush", true, 2000), Some("lush"), false, None);
+        assert_eq!(actions, [FollowAction::Pause]);
+    }
+
+    #[test]
+    fn test_follower_catches_up_with_cooldown() {
+        let mut follower = SyncFollower::bind(&config()).unwrap();
+        follower.follow(&message("groovesalad", false, 2000), Some("groovesalad"), false, None);
+
+        let behind = Some(Duration::from_secs(9));
+        let actions = follower.follow(&message("groovesalad", false, 2000), Some("groovesalad"), false, behind);
+        assert_eq!(actions, [FollowAction::JumpToLive]);
+        assert!(follower.follow(&message("groovesalad", false, 2000), Some("groovesalad"), false, behind).is_empty());
+
+        let mut follower = SyncFollower::bind(&config()).unwrap();
+        follower.follow(&message("groovesalad", false, 2000), Some("groovesalad"), false, None);
+        let close = Some(Duration::from_secs(4));
+        assert!(follower.follow(&message("groovesalad", false, 2000), Some("groovesalad"), false, close).is_empty());
+    }
+
+    #[test]
+    fn test_follower_ignores_other_groups() {
+        let mut follower = SyncFollower::bind(&config()).unwrap();
+        let mut other = message("groovesalad", false, 0);
+        other.group = "office".to_string();
+        assert!(follower.follow(&other, None, false, None).is_empty());
+        assert_eq!(follower.leader(), None);
+    }
+
+    #[test]
+    fn test_announcement_round_trip() {
+        let mut follower = SyncFollower::bind(&config()).unwrap();
+        let target = SocketAddr::from((Ipv4Addr::LOCALHOST, follower.socket.local_addr().unwrap().port()));
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut leader = SyncLeader::with_socket(socket, target, &config()).unwrap();
+
+        leader.announce(Some("groovesalad"), false, Some(Duration::from_secs(2)));
+
+        let mut actions = Vec::new();
+        for _ in 0..50 {
+            actions = follower.poll(None, false, None);
+            if !actions.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(actions, [FollowAction::SwitchChannel("groovesalad".to_string())]);
+    }
+}
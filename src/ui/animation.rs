@@ -0,0 +1,118 @@
+//! Timing of everything that moves on screen.
+//!
+//! The main loop's tick is the only clock: each tick, [`FrameClock`]
+//! measures the time since the previous one and that delta is passed to
+//! every [`Animated`] component, which advances by exactly that much. Nothing
+//! animated reads the time itself, so the frame rate (`fps` in the
+//! configuration) is set in one place and a stalled frame doesn't make
+//! anything jump.
+
+use std::time::{Duration, Instant};
+
+use crate::models::AudioSpectrum;
+
+/// Frame rates `fps` is kept within
+const FPS_RANGE: (u32, u32) = (1, 30);
+
+/// Longest step an animation takes at once, e.g. after the process was
+/// suspended
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Something drawn differently as time passes.
+pub trait Animated {
+    /// Moves the animation `dt` forward.
+    fn advance(&mut self, dt: Duration);
+}
+
+/// Source of the frame deltas, driven by the main loop's tick.
+#[derive(Debug, Clone)]
+pub struct FrameClock {
+    interval: Duration,
+    last: Instant,
+}
+
+impl FrameClock {
+    /// A clock ticking `fps` times a second, kept within 1-30.
+    pub fn new(fps: u32) -> Self {
+        let fps = fps.clamp(FPS_RANGE.0, FPS_RANGE.1);
+        Self {
+            interval: Duration::from_secs(1) / fps,
+            last: Instant::now(),
+        }
+    }
+
+    /// Time between two ticks.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Notes a tick at `now` and returns the time since the previous one,
+    /// at most a quarter of a second.
+    pub fn tick(&mut self, now: Instant) -> Duration {
+        let dt = now.saturating_duration_since(self.last);
+        self.last = now;
+        dt.min(MAX_FRAME_TIME)
+    }
+}
+
+/// A value shown for a limited time, such as a toast.
+#[derive(Debug, Clone)]
+pub struct Timed<T> {
+    value: T,
+    left: Duration,
+}
+
+impl<T> Timed<T> {
+    /// Shows `value` for `duration`.
+    pub fn new(value: T, duration: Duration) -> Self {
+        Self { value, left: duration }
+    }
+
+    /// The value while it should still be shown.
+    pub fn get(&self) -> Option<&T> {
+        (!self.left.is_zero()).then_some(&self.value)
+    }
+}
+
+impl<T> Animated for Timed<T> {
+    fn advance(&mut self, dt: Duration) {
+        self.left = self.left.saturating_sub(dt);
+    }
+}
+
+impl<T: Animated> Animated for Option<T> {
+    fn advance(&mut self, dt: Duration) {
+        if let Some(animated) = self {
+            animated.advance(dt);
+        }
+    }
+}
+
+impl Animated for AudioSpectrum {
+    fn advance(&mut self, dt: Duration) {
+        self.step(dt.as_secs_f32());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_clock_and_timed_values() {
+        let mut clock = FrameClock::new(100);
+        assert_eq!(clock.interval(), Duration::from_secs(1) / 30);
+        assert_eq!(FrameClock::new(0).interval(), Duration::from_secs(1));
+
+        let start = clock.last;
+        assert_eq!(clock.tick(start + Duration::from_millis(40)), Duration::from_millis(40));
+        // A long stall counts as one short step
+        assert_eq!(clock.tick(start + Duration::from_secs(10)), MAX_FRAME_TIME);
+
+        let mut toast = Some(Timed::new("Saved", Duration::from_millis(100)));
+        toast.advance(Duration::from_millis(60));
+        assert_eq!(toast.as_ref().and_then(Timed::get), Some(&"Saved"));
+        toast.advance(Duration::from_millis(60));
+        assert_eq!(toast.as_ref().and_then(Timed::get), None);
+    }
+}
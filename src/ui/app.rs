@@ -1,18 +1,111 @@
-use crate::models::AudioSpectrum;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::alerts::TrackAlert;
+use crate::audio::progress::DownloadProgress;
+use crate::audio::PlaybackPosition;
+use crate::enrich::Enrichment;
+use crate::errors::PlayerError;
+use crate::models::{AudioSpectrum, Channel, StreamInfo, TrackInfo};
+use crate::monitor::MonitoredTrack;
+use crate::ui::animation::{Animated, Timed};
+use crate::ui::confirm::ConfirmCheck;
+use crate::ui::loudness::LoudnessGuard;
+use crate::ui::macros::MacroAction;
+use crate::ui::popup::PopupStack;
+use crate::ui::quit::{QuitGuard, CONFIRM_WINDOW};
+use crate::ui::volume::VolumeKeys;
+
+/// How long a track alert stays on screen
+const ALERT_DISPLAY_TIME: Duration = Duration::from_secs(30);
+
+/// How long a toast stays in the header
+const TOAST_DISPLAY_TIME: Duration = Duration::from_secs(8);
+
+/// How long the limiter indicator stays on after the limiter last engaged,
+/// and how long its deepest gain reduction is held on the meter
+const LIMITER_HOLD_TIME: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone)]
 pub enum UIState {
     InitialChannelSelection,
     Playing,
     SelectingChannel,
+    /// Playback failed unexpectedly, see [`AppState::error`]
+    Error,
 }
 
 pub struct AppState {
     pub ui_state: UIState,
     pub selected_index: usize,
     pub should_quit: bool,
+    /// Pause state as last reported by the player
     pub is_paused: bool,
     pub spectrum: AudioSpectrum,
+    /// Latest tracks on monitored channels with their index in the channel
+    /// list, refreshed by the main loop
+    pub monitored: Vec<(usize, MonitoredTrack)>,
+    /// Most recent track alert, while it is shown
+    pub alert: Option<Timed<TrackAlert>>,
+    /// Latest download progress of the playing stream, when known
+    pub download: Option<DownloadProgress>,
+    /// Details the playing stream announced in its ICY headers
+    pub stream_info: Option<StreamInfo>,
+    /// Position in the playing recording, `None` for live streams
+    pub position: Option<PlaybackPosition>,
+    /// Stars given to the playing track, if it was rated
+    pub rating: Option<u8>,
+    /// What the enrichment sources found about the playing track
+    pub enrichment: Option<(TrackInfo, Enrichment)>,
+    /// Index of the playing stream in the channel's playlists
+    pub playing_stream: Option<usize>,
+    /// Listen-along status shown in the status bar
+    pub sync_status: Option<String>,
+    /// Name of the renderer playback is cast to
+    pub cast_target: Option<String>,
+    /// Whether the volume is currently ducked
+    pub ducked: bool,
+    /// Factor the ducker currently scales the volume by, fading included
+    pub duck_factor: f32,
+    /// Gain reduction the limiter last applied to a boosted stream, as a
+    /// factor, while it is held on the meter
+    pub limiting: Option<Timed<f32>>,
+    /// Problem with the channel list shown above it, such as a cached list
+    pub channels_notice: Option<String>,
+    /// Why playback failed, shown on the error screen
+    pub error: Option<String>,
+    /// What the user can do about [`Self::error`]
+    pub error_hint: Option<&'static str>,
+    /// Volume as last confirmed by the player, `None` for the system volume
+    pub volume: Option<u8>,
+    /// Short announcement shown in the header
+    pub toast: Option<Timed<String>>,
+    /// Running unattended with every control locked
+    pub kiosk: bool,
+    /// IDs of channels SomaFM added recently, marked in the channel lists
+    pub new_channels: HashSet<String>,
+    /// Actions of the macro keys available while playing
+    pub macros: HashMap<char, Vec<MacroAction>>,
+    /// Popups open over the screen, the focused one on top
+    pub popups: PopupStack,
+    /// Warning shown before the volume keys go past `loud_volume`
+    pub loudness: LoudnessGuard,
+    /// Volume key repeats waiting to be applied
+    pub volume_keys: VolumeKeys,
+    /// Toast asking for a second `q` before the music stops
+    pub quit_guard: QuitGuard,
+    /// IDs of the channels left out of the channel lists
+    pub hidden_channels: HashSet<String>,
+    /// List hidden channels anyway, marked, so they can be unhidden
+    pub show_hidden: bool,
+    /// Whether playback was rewound and hasn't returned to live since
+    pub replaying: bool,
+    /// Time spent playing this session, pauses left out
+    pub listened: Duration,
+    /// Stream bytes downloaded this session, over every connection
+    pub data_used: u64,
+    /// Factor the playing channel is lifted by, see `channel_leveling`
+    pub leveling: f32,
 }
 
 impl Default for AppState {
@@ -29,23 +122,114 @@ impl AppState {
             should_quit: false,
             is_paused: false,
             spectrum: AudioSpectrum::default(),
+            monitored: Vec::new(),
+            alert: None,
+            download: None,
+            stream_info: None,
+            position: None,
+            rating: None,
+            enrichment: None,
+            playing_stream: None,
+            sync_status: None,
+            cast_target: None,
+            ducked: false,
+            duck_factor: 1.0,
+            limiting: None,
+            channels_notice: None,
+            error: None,
+            error_hint: None,
+            volume: None,
+            toast: None,
+            kiosk: false,
+            new_channels: HashSet::new(),
+            macros: HashMap::new(),
+            popups: PopupStack::default(),
+            loudness: LoudnessGuard::default(),
+            volume_keys: VolumeKeys::default(),
+            quit_guard: QuitGuard::default(),
+            hidden_channels: HashSet::new(),
+            show_hidden: false,
+            replaying: false,
+            listened: Duration::ZERO,
+            data_used: 0,
+            leveling: 1.0,
+        }
+    }
+
+    /// How far playback is behind the live stream, when known.
+    pub fn latency(&self) -> Option<Duration> {
+        self.download.map(|download| download.buffered)
+    }
+
+    /// Whether the limiter engaged recently enough to be shown.
+    pub fn is_limiting(&self) -> bool {
+        self.limiter_reduction().is_some()
+    }
+
+    /// The limiter's recent gain reduction as a factor, while it is shown.
+    pub fn limiter_reduction(&self) -> Option<f32> {
+        self.limiting.as_ref().and_then(Timed::get).copied()
+    }
+
+    /// Records the limiter's gain reduction since the last call. The
+    /// deepest reduction is held for a moment so the meter stays readable.
+    pub fn record_limiting(&mut self, factor: f32) {
+        if factor < 1.0 && self.limiter_reduction().is_none_or(|held| factor <= held) {
+            self.limiting = Some(Timed::new(factor, LIMITER_HOLD_TIME));
+        }
+    }
+
+    /// Shows `alert` in the header for a while.
+    pub fn show_alert(&mut self, alert: TrackAlert) {
+        self.alert = Some(Timed::new(alert, ALERT_DISPLAY_TIME));
+    }
+
+    /// Returns the current alert while it should still be displayed.
+    pub fn active_alert(&self) -> Option<&TrackAlert> {
+        self.alert.as_ref().and_then(Timed::get)
+    }
+
+    /// Shows `message` in the header for a few seconds.
+    pub fn show_toast(&mut self, message: String) {
+        self.toast = Some(Timed::new(message, TOAST_DISPLAY_TIME));
+    }
+
+    /// Returns the current toast while it should still be displayed.
+    pub fn active_toast(&self) -> Option<&str> {
+        self.toast.as_ref().and_then(Timed::get).map(String::as_str)
+    }
+
+    /// Whether `channel` shows up in the channel lists.
+    pub fn is_listed(&self, channel: &Channel) -> bool {
+        self.show_hidden || !self.hidden_channels.contains(&channel.id)
+    }
+
+    /// Indices of the channels shown in the channel lists.
+    pub fn listed_channels(&self, channels: &[Channel]) -> Vec<usize> {
+        (0..channels.len()).filter(|&i| self.is_listed(&channels[i])).collect()
+    }
+
+    /// Selects the next listed channel, wrapping around.
+    pub fn next_channel(&mut self, channels: &[Channel]) {
+        let listed = self.listed_channels(channels);
+        if let Some(&next) = listed.iter().find(|&&i| i > self.selected_index).or(listed.first()) {
+            self.selected_index = next;
         }
     }
 
-    pub fn next_channel(&mut self, max_channels: usize) {
-        self.selected_index = if self.selected_index < max_channels - 1 { 
-            self.selected_index + 1 
-        } else { 
-            0 
-        };
+    /// Selects the previous listed channel, wrapping around.
+    pub fn previous_channel(&mut self, channels: &[Channel]) {
+        let listed = self.listed_channels(channels);
+        if let Some(&previous) = listed.iter().rev().find(|&&i| i < self.selected_index).or(listed.last()) {
+            self.selected_index = previous;
+        }
     }
 
-    pub fn previous_channel(&mut self, max_channels: usize) {
-        self.selected_index = if self.selected_index > 0 { 
-            self.selected_index - 1 
-        } else { 
-            max_channels - 1 
-        };
+    /// Moves the selection off a channel that is not listed.
+    pub fn select_listed(&mut self, channels: &[Channel]) {
+        if channels.get(self.selected_index).is_some_and(|channel| !self.is_listed(channel)) {
+            self.next_channel(channels);
+        }
     }
 
     pub fn set_channel_selection_mode(&mut self, current_channel_index: Option<usize>) {
@@ -57,14 +241,52 @@ impl AppState {
 
     pub fn set_playing_mode(&mut self) {
         self.ui_state = UIState::Playing;
+        self.error = None;
+        self.error_hint = None;
+    }
+
+    /// Shows the error screen with `error` and its hint.
+    pub fn show_error(&mut self, error: &PlayerError) {
+        self.popups.clear();
+        self.ui_state = UIState::Error;
+        self.error = Some(error.to_string());
+        self.error_hint = error.hint();
+    }
+
+    /// Takes the latest download progress of the playing stream, counting
+    /// what was fetched since the previous one towards [`Self::data_used`].
+    pub fn update_download(&mut self, progress: Option<DownloadProgress>) {
+        if let Some(progress) = progress {
+            // A count below the previous one is from a new connection
+            let previous = self.download
+                .map(|download| download.bytes_fetched)
+                .filter(|&fetched| fetched <= progress.bytes_fetched)
+                .unwrap_or(0);
+            self.data_used += progress.bytes_fetched - previous;
+        }
+        self.download = progress;
     }
 
     pub fn quit(&mut self) {
         self.should_quit = true;
     }
 
-    pub fn toggle_pause(&mut self) {
-        self.is_paused = !self.is_paused;
+    /// Quits on a press of the quit key, unless `confirm_quit` asks for
+    /// another press first because a channel is playing. Returns whether
+    /// it quit.
+    pub fn request_quit(&mut self) -> bool {
+        let playing = matches!(self.ui_state, UIState::Playing | UIState::SelectingChannel);
+        match if playing { self.quit_guard.check() } else { ConfirmCheck::Proceed } {
+            ConfirmCheck::Proceed => {
+                self.quit();
+                true
+            }
+            ConfirmCheck::Ask => {
+                self.show_toast(format!("Press q again within {}s to quit", CONFIRM_WINDOW.as_secs()));
+                false
+            }
+            ConfirmCheck::Hold => false,
+        }
     }
 
     pub fn pause(&mut self) {
@@ -75,3 +297,20 @@ impl AppState {
         self.is_paused = false;
     }
 }
+
+impl Animated for AppState {
+    fn advance(&mut self, dt: Duration) {
+        let is_playing = matches!(self.ui_state, UIState::Playing | UIState::SelectingChannel);
+        self.spectrum.set_playback(is_playing, self.is_paused);
+        if is_playing && !self.is_paused {
+            self.listened += dt;
+        }
+        self.spectrum.advance(dt);
+        self.alert.advance(dt);
+        self.limiting.advance(dt);
+        self.toast.advance(dt);
+        self.loudness.advance(dt);
+        self.volume_keys.advance(dt);
+        self.quit_guard.advance(dt);
+    }
+}
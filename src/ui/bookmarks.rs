@@ -0,0 +1,362 @@
+//! Popups for bookmarking the playing moment (`b`) and reviewing the
+//! bookmarks (`B`).
+
+use std::ops::RangeInclusive;
+
+use chrono::{Days, Local, NaiveDate};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::bookmarks::Bookmark;
+use crate::config::AppConfig;
+use crate::models::Channel;
+use crate::ui::app::AppState;
+use crate::ui::events::{quit, EventResult};
+use crate::ui::popup::{centered_rect, Popup, PopupAction};
+
+/// Longest note accepted, in characters
+const MAX_NOTE_LEN: usize = 200;
+
+/// Asks for an optional note before saving a bookmark.
+pub struct BookmarkPopup {
+    bookmark: Bookmark,
+    note: String,
+}
+
+impl BookmarkPopup {
+    pub fn new(bookmark: Bookmark) -> Self {
+        Self { bookmark, note: String::new() }
+    }
+}
+
+impl Popup for BookmarkPopup {
+    fn handle_key(&mut self, key: KeyEvent, _app: &mut AppState) -> PopupAction {
+        // Every character goes into the note, so `q` doesn't quit here
+        match key.code {
+            KeyCode::Enter => {
+                let mut bookmark = self.bookmark.clone();
+                bookmark.note = Some(self.note.trim().to_string()).filter(|note| !note.is_empty());
+                PopupAction::Close(EventResult::SaveBookmark(bookmark))
+            }
+            KeyCode::Esc => PopupAction::Close(EventResult::None),
+            KeyCode::Backspace => {
+                self.note.pop();
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Char(c) if self.note.chars().count() < MAX_NOTE_LEN => {
+                self.note.push(c);
+                PopupAction::Stay(EventResult::None)
+            }
+            _ => PopupAction::Stay(EventResult::None),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame, config: &AppConfig, _app: &AppState) {
+        let area = centered_rect(frame.area(), 60, 7);
+        let lines = vec![
+            Line::from(Span::styled(self.bookmark.channel_title.as_str(), Style::default().fg(Color::Yellow))),
+            Line::from(format!("{} - {}", self.bookmark.artist, self.bookmark.title)),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Note: ", Style::default().fg(Color::DarkGray)),
+                Span::raw(self.note.as_str()),
+                Span::styled("▏", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            ]),
+        ];
+        let popup = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Bookmark (Enter to save, Esc to cancel)")
+                .title_style(config.theme.accent()))
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+}
+
+/// Days a search word limits the bookmarks to: `today`, `7d` for the last
+/// seven days, `2025-03-01` for that day, or `2025-03-01..2025-03-15`, either
+/// end of which may be left open.
+fn parse_days(word: &str, today: NaiveDate) -> Option<RangeInclusive<NaiveDate>> {
+    let date = |text: &str| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok();
+    if word.eq_ignore_ascii_case("today") {
+        return Some(today..=today);
+    }
+    if let Some(days) = word.strip_suffix('d').and_then(|days| days.parse::<u64>().ok()) {
+        let first = today.checked_sub_days(Days::new(days.saturating_sub(1)))?;
+        return Some(first..=today);
+    }
+    if let Some((from, to)) = word.split_once("..") {
+        let from = if from.is_empty() { NaiveDate::MIN } else { date(from)? };
+        let to = if to.is_empty() { NaiveDate::MAX } else { date(to)? };
+        return Some(from..=to);
+    }
+    date(word).map(|day| day..=day)
+}
+
+/// Whether `bookmark` matches the search `query` on `today`: its date
+/// words (see [`parse_days`]) by the day it was saved, the other words,
+/// ignoring case, by artist, title or channel.
+fn matches(bookmark: &Bookmark, query: &str, today: NaiveDate) -> bool {
+    let mut text = Vec::new();
+    for word in query.split_whitespace() {
+        match parse_days(word, today) {
+            Some(days) => {
+                if !bookmark.time().is_some_and(|time| days.contains(&time.date_naive())) {
+                    return false;
+                }
+            }
+            None => text.push(word),
+        }
+    }
+    let text = text.join(" ").to_lowercase();
+    [&bookmark.artist, &bookmark.title, &bookmark.channel_title]
+        .iter()
+        .any(|field| field.to_lowercase().contains(&text))
+}
+
+/// The saved bookmarks, newest on top. Enter plays the bookmark's channel,
+/// `d` deletes the bookmark and `/` searches them, by text and by date.
+pub struct BookmarksPopup {
+    /// The bookmarks oldest first, as stored, each with the index of its
+    /// channel in the channel list
+    bookmarks: Vec<(Bookmark, Option<usize>)>,
+    /// Index of the highlighted bookmark in `bookmarks`
+    index: usize,
+    /// Only bookmarks matching this are listed
+    query: String,
+    /// Whether keys are typed into `query`
+    searching: bool,
+}
+
+impl BookmarksPopup {
+    pub fn new(bookmarks: &[Bookmark], channels: &[Channel]) -> Self {
+        let bookmarks: Vec<_> = bookmarks
+            .iter()
+            .map(|bookmark| {
+                let channel = channels.iter().position(|channel| channel.id == bookmark.channel_id);
+                (bookmark.clone(), channel)
+            })
+            .collect();
+        let index = bookmarks.len().saturating_sub(1);
+        Self { bookmarks, index, query: String::new(), searching: false }
+    }
+
+    /// Indices in `bookmarks` of the bookmarks matching the search, oldest
+    /// first.
+    fn visible(&self) -> Vec<usize> {
+        let today = Local::now().date_naive();
+        (0..self.bookmarks.len()).filter(|&i| matches(&self.bookmarks[i].0, &self.query, today)).collect()
+    }
+
+    /// Moves the highlight to the newest matching bookmark at or before
+    /// `index`, or the oldest one after it.
+    fn highlight_near(&mut self, index: usize) {
+        let visible = self.visible();
+        if let Some(&i) = visible.iter().rev().find(|&&i| i <= index).or(visible.first()) {
+            self.index = i;
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.query.clear();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+            }
+            KeyCode::Char(c) => self.query.push(c),
+            _ => return PopupAction::Stay(EventResult::None),
+        }
+        self.highlight_near(self.bookmarks.len().saturating_sub(1));
+        PopupAction::Stay(EventResult::None)
+    }
+}
+
+impl Popup for BookmarksPopup {
+    fn handle_key(&mut self, key: KeyEvent, app: &mut AppState) -> PopupAction {
+        if self.searching && !matches!(key.code, KeyCode::Up | KeyCode::Down) {
+            return self.handle_search_key(key);
+        }
+        let visible = self.visible();
+        // Listed newest first, so up goes to later bookmarks
+        match key.code {
+            KeyCode::Up => {
+                if let Some(&i) = visible.iter().find(|&&i| i > self.index) {
+                    self.index = i;
+                }
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Down => {
+                if let Some(&i) = visible.iter().rev().find(|&&i| i < self.index) {
+                    self.index = i;
+                }
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Char('/') => {
+                self.searching = true;
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Enter if !visible.is_empty() && !visible.contains(&self.index) => PopupAction::Stay(EventResult::None),
+            KeyCode::Enter => match self.bookmarks.get(self.index) {
+                Some((_, Some(channel))) => PopupAction::Close(EventResult::ChannelChange(*channel)),
+                Some((bookmark, None)) => {
+                    app.show_toast(format!("{} is not in the channel list", bookmark.channel_title));
+                    PopupAction::Stay(EventResult::None)
+                }
+                None => PopupAction::Close(EventResult::None),
+            },
+            KeyCode::Char('d') | KeyCode::Delete if visible.contains(&self.index) => {
+                self.bookmarks.remove(self.index);
+                let deleted = self.index;
+                self.highlight_near(deleted.saturating_sub(1));
+                PopupAction::Stay(EventResult::DeleteBookmark(deleted))
+            }
+            KeyCode::Esc if !self.query.is_empty() => {
+                self.query.clear();
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Esc | KeyCode::Char('B') => PopupAction::Close(EventResult::None),
+            KeyCode::Char('q') => PopupAction::Stay(quit(app)),
+            _ => PopupAction::Stay(EventResult::None),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame, config: &AppConfig, _app: &AppState) {
+        let visible = self.visible();
+        let height = (visible.len() as u16 + 2).clamp(3, frame.area().height.saturating_sub(4));
+        let area = centered_rect(frame.area(), 90, height);
+        let title = if self.searching || !self.query.is_empty() {
+            format!("Bookmarks matching /{}{}", self.query, if self.searching { "▏" } else { "" })
+        } else {
+            "Bookmarks (Enter to play, d to delete, / to search by text or date, Esc to close)".to_string()
+        };
+
+        if visible.is_empty() {
+            let message = if self.bookmarks.is_empty() { "No bookmarks yet, press b while playing" } else { "No matching bookmarks" };
+            let popup = Paragraph::new(message)
+                .block(Block::default().borders(Borders::ALL).title(title).title_style(config.theme.accent()));
+            frame.render_widget(Clear, area);
+            frame.render_widget(popup, area);
+            return;
+        }
+
+        let items: Vec<ListItem> = visible
+            .iter()
+            .rev()
+            .map(|&i| {
+                let (bookmark, channel) = &self.bookmarks[i];
+                let style = if channel.is_some() { Style::default() } else { Style::default().fg(Color::DarkGray) };
+                ListItem::new(bookmark.to_string()).style(style)
+            })
+            .collect();
+        let mut list_state = ListState::default();
+        list_state.select(visible.iter().rev().position(|&i| i == self.index));
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title).title_style(config.theme.accent()))
+            .highlight_style(config.theme.highlight())
+            .highlight_symbol("► ");
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut list_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crossterm::event::KeyModifiers;
+
+    #[test]
+    fn test_bookmarks_popup_plays_and_deletes() {
+        let bookmark = |channel_id: &str| Bookmark {
+            channel_id: channel_id.to_string(),
+            channel_title: channel_id.to_string(),
+            artist: "Air".to_string(),
+            title: "Alone in Kyoto".to_string(),
+            saved_at: 0,
+            note: None,
+        };
+        let channel = Channel::named("lush", "Lush");
+        let mut app = AppState::new();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut popup = BookmarksPopup::new(&[bookmark("lush"), bookmark("gone")], &[channel]);
+
+        // The newest bookmark is highlighted, and its channel no longer exists
+        assert!(matches!(popup.handle_key(key(KeyCode::Enter), &mut app), PopupAction::Stay(EventResult::None)));
+        assert!(matches!(
+            popup.handle_key(key(KeyCode::Char('d')), &mut app),
+            PopupAction::Stay(EventResult::DeleteBookmark(1))
+        ));
+        assert!(matches!(
+            popup.handle_key(key(KeyCode::Enter), &mut app),
+            PopupAction::Close(EventResult::ChannelChange(0))
+        ));
+    }
+
+    #[test]
+    fn test_search_matches_text_and_days() {
+        let bookmark = Bookmark {
+            channel_id: "lush".to_string(),
+            channel_title: "Lush".to_string(),
+            artist: "Air".to_string(),
+            title: "Alone in Kyoto".to_string(),
+            saved_at: Local.with_ymd_and_hms(2025, 3, 1, 20, 5, 0).unwrap().timestamp(),
+            note: None,
+        };
+        let today = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let found = |query| matches(&bookmark, query, today);
+
+        assert!(found("lush") && found("kyoto") && found("AIR") && found("alone in"));
+        assert!(!found("bonobo"));
+        assert!(found("3d") && found("7d kyoto") && found("2025-03-01"));
+        assert!(!found("2d") && !found("today") && !found("7d bonobo"));
+        assert!(found("2025-02-01..2025-03-01") && found("2025-03-01..") && found("..2025-03-31"));
+        assert!(!found("2025-03-02..") && !found("..2025-02-28"));
+    }
+
+    #[test]
+    fn test_bookmarks_popup_search() {
+        let bookmark = |artist: &str, channel_title: &str| Bookmark {
+            channel_id: channel_title.to_lowercase(),
+            channel_title: channel_title.to_string(),
+            artist: artist.to_string(),
+            title: "Untitled".to_string(),
+            saved_at: 0,
+            note: None,
+        };
+        let mut app = AppState::new();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let bookmarks = [bookmark("Air", "Lush"), bookmark("Bonobo", "Groove Salad"), bookmark("Moby", "Lush")];
+        let mut popup = BookmarksPopup::new(&bookmarks, &[]);
+
+        for c in "/LUSH".chars() {
+            popup.handle_key(key(KeyCode::Char(c)), &mut app);
+        }
+        popup.handle_key(key(KeyCode::Enter), &mut app);
+        assert_eq!(popup.visible(), [0, 2]);
+
+        // Down skips the Groove Salad bookmark, and `d` deletes rather than types
+        popup.handle_key(key(KeyCode::Down), &mut app);
+        assert!(matches!(
+            popup.handle_key(key(KeyCode::Char('d')), &mut app),
+            PopupAction::Stay(EventResult::DeleteBookmark(0))
+        ));
+        assert_eq!(popup.index, 1);
+
+        // Esc clears the search before closing
+        assert!(matches!(popup.handle_key(key(KeyCode::Esc), &mut app), PopupAction::Stay(EventResult::None)));
+        assert_eq!(popup.visible(), [0, 1]);
+        assert!(matches!(popup.handle_key(key(KeyCode::Esc), &mut app), PopupAction::Close(EventResult::None)));
+    }
+}
@@ -6,14 +6,127 @@ use ratatui::{
     Frame,
 };
 
-use crate::models::{Channel, TrackInfo};
+use crate::config::AppConfig;
+use crate::models::{Channel, StreamQuality, TrackInfo};
+use crate::ui::app::AppState;
+use crate::ui::genre::genre_badge;
+use crate::ui::text::{pad, truncate, visual, width};
+
+/// Widest the title column of the channel lists gets
+const TITLE_COLUMNS: usize = 28;
+
+/// Columns the list's borders and highlight symbol take
+const LIST_CHROME: u16 = 4;
+
+/// Summarizes a channel's streams as one badge per format, e.g.
+/// `[mp3 256/high*] [aacp 64/32]`. Variants show their bitrate, or their
+/// quality when the bitrate is unknown; `*` marks the stream `quality`
+/// would pick.
+pub fn quality_badges(channel: &Channel, quality: StreamQuality) -> String {
+    let chosen = channel.preferred_playlist(quality).map(|p| p.url.as_str());
+    let mut badges: Vec<(&str, Vec<String>)> = Vec::new();
+
+    for playlist in &channel.playlists {
+        let mut label = match playlist.bitrate(&channel.id) {
+            Some(bitrate) => bitrate.to_string(),
+            None => playlist.quality.clone(),
+        };
+        if chosen == Some(playlist.url.as_str()) {
+            label.push('*');
+        }
+        match badges.iter_mut().find(|(format, _)| *format == playlist.format) {
+            Some((_, labels)) => labels.push(label),
+            None => badges.push((&playlist.format, vec![label])),
+        }
+    }
+
+    badges
+        .iter()
+        .map(|(format, labels)| format!("[{} {}]", format, labels.join("/")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Marker for channels SomaFM added recently and for hidden channels,
+/// empty for the others.
+fn channel_badge(channel: &Channel, app: &AppState) -> Span<'static> {
+    if app.hidden_channels.contains(&channel.id) {
+        Span::styled(" HIDDEN", Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD))
+    } else if app.new_channels.contains(&channel.id) {
+        Span::styled(" NEW", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+    } else {
+        Span::raw("")
+    }
+}
+
+/// The channel's title padded to `columns` together with its badge, so the
+/// text after it lines up.
+fn title_column(channel: &Channel, app: &AppState, columns: usize) -> [Span<'static>; 2] {
+    let badge = channel_badge(channel, app);
+    let title = pad(&channel.title, columns.saturating_sub(badge.width()).max(1));
+    let (title, padding) = title.split_at(title.trim_end().len());
+    [Span::raw(format!(" {}", title)), Span::styled(format!("{}{}", badge.content, padding), badge.style)]
+}
+
+/// Width of the title column for `listed`: the widest title with its
+/// badge, up to [`TITLE_COLUMNS`].
+fn title_columns(listed: &[(usize, &Channel)], app: &AppState) -> usize {
+    listed
+        .iter()
+        .map(|(_, channel)| width(&channel.title) + channel_badge(channel, app).width())
+        .max()
+        .unwrap_or(0)
+        .min(TITLE_COLUMNS)
+}
+
+/// Truncates the span at `flexible` so all of `spans` fit in `columns`.
+fn fit_spans(mut spans: Vec<Span<'static>>, flexible: usize, columns: usize) -> Vec<Span<'static>> {
+    let fixed: usize = spans
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != flexible)
+        .map(|(_, span)| span.width())
+        .sum();
+    let content = truncate(&spans[flexible].content, columns.saturating_sub(fixed)).into_owned();
+    spans[flexible].content = content.into();
+    spans
+}
+
+/// Border of the channel list, titled with `notice` when there is one and
+/// with a placeholder message while the list is empty. The number of hidden
+/// channels is noted while they are left out.
+fn list_block(channels: &[Channel], app: &AppState) -> Block<'static> {
+    let block = Block::default().borders(Borders::ALL);
+    let hidden = channels.iter().filter(|channel| app.hidden_channels.contains(&channel.id)).count();
+    match (app.channels_notice.as_deref(), channels.is_empty()) {
+        (Some(notice), _) => block
+            .title(format!("Channels - {}", notice))
+            .title_style(Style::default().fg(Color::Yellow)),
+        (None, true) => block.title("Channels - none available yet"),
+        (None, false) if hidden > 0 && !app.show_hidden => {
+            block.title(format!("Channels - {} hidden (Shift+H to show)", hidden))
+        }
+        (None, false) => block.title("Channels"),
+    }
+}
+
+/// The listed channels with their index in `channels`, and the position of
+/// the selected one among them.
+fn listed<'a>(channels: &'a [Channel], app: &AppState) -> (Vec<(usize, &'a Channel)>, Option<usize>) {
+    let listed: Vec<(usize, &Channel)> = app.listed_channels(channels).into_iter().map(|i| (i, &channels[i])).collect();
+    let selected = listed.iter().position(|(i, _)| *i == app.selected_index);
+    (listed, selected)
+}
 
 /// Renders the initial channel selection UI
 pub fn render_initial_channel_selection(
     frame: &mut Frame,
     channels: &[Channel],
-    selected_index: usize
+    app: &AppState,
+    config: &AppConfig,
 ) {
+    let theme = config.theme;
+    let selected_index = app.selected_index;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
@@ -26,33 +139,59 @@ pub fn render_initial_channel_selection(
 
     // Header
     let header = ratatui::widgets::Paragraph::new("🎵 Welcome to SomaFM Player - Select a Channel 🎵")
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .style(theme.accent())
         .block(Block::default().borders(Borders::ALL))
         .wrap(ratatui::widgets::Wrap { trim: true });
     frame.render_widget(header, chunks[0]);
 
     // Channel list
-    let items: Vec<ListItem> = channels
+    let (listed, selected) = listed(channels, app);
+    let columns = usize::from(chunks[1].width.saturating_sub(LIST_CHROME));
+    let title_width = title_columns(&listed, app);
+    let items: Vec<ListItem> = listed
         .iter()
         .enumerate()
-        .map(|(i, channel)| {
+        .map(|(position, &(i, channel))| {
             let style = if i == selected_index {
-                Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD)
+                theme.highlight()
             } else {
                 Style::default()
             };
             
-            let content = format!("{:>3}. {} - {}", i + 1, channel.title, channel.description);
-            ListItem::new(content).style(style)
+            let details = match channel.listener_count() {
+                Some(listeners) => format!(" ({} listening) - {}", listeners, channel.description),
+                None => format!(" - {}", channel.description),
+            };
+            let [title, badge] = title_column(channel, app, title_width);
+            let spans = vec![
+                Span::raw(format!("{:>3}. ", position + 1)),
+                genre_badge(channel, config),
+                title,
+                badge,
+                Span::raw(details),
+                Span::styled(
+                    format!("  {}", quality_badges(channel, config.quality_for(&channel.id))),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ];
+            let mut lines = vec![Line::from(fit_spans(spans, 4, columns))];
+            // What's on right now, kept fresh by the background channel refresh
+            if let Some(last_playing) = channel.last_playing.as_deref().filter(|s| !s.trim().is_empty()) {
+                lines.push(Line::from(Span::styled(
+                    format!("     ♪ {}", truncate(&visual(last_playing), columns.saturating_sub(7))),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            ListItem::new(lines).style(style)
         })
         .collect();
 
     let mut list_state = ListState::default();
-    list_state.select(Some(selected_index));
+    list_state.select(selected);
 
     let channels_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Channels"))
-        .highlight_style(Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD))
+        .block(list_block(channels, app))
+        .highlight_style(theme.highlight())
         .highlight_symbol("► ");
     
     frame.render_stateful_widget(channels_list, chunks[1], &mut list_state);
@@ -81,8 +220,11 @@ pub fn render_channel_selection(
     channels: &[Channel],
     current_channel: &Channel,
     track_info: &TrackInfo,
-    selected_index: usize
+    app: &AppState,
+    config: &AppConfig,
 ) {
+    let theme = config.theme;
+    let selected_index = app.selected_index;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
@@ -96,28 +238,32 @@ pub fn render_channel_selection(
 
     // Header
     let header = ratatui::widgets::Paragraph::new("🎵 Select New Channel (Music Still Playing) 🎵")
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .style(theme.accent())
         .block(Block::default().borders(Borders::ALL))
         .wrap(ratatui::widgets::Wrap { trim: true });
     frame.render_widget(header, chunks[0]);
 
     // Current playing info
-    let current_info = format!("🔊 Currently: {} - {} - {}", current_channel.title, track_info.artist, track_info.title);
+    let current_info = format!("🔊 Currently: {} - {} - {}", current_channel.title, visual(&track_info.artist), visual(&track_info.title));
+    let current_info = truncate(&current_info, usize::from(chunks[1].width.saturating_sub(2))).into_owned();
     let current_widget = ratatui::widgets::Paragraph::new(current_info)
-        .style(Style::default().fg(Color::Green))
+        .style(theme.playing())
         .block(Block::default().borders(Borders::ALL))
         .wrap(ratatui::widgets::Wrap { trim: true });
     frame.render_widget(current_widget, chunks[1]);
 
     // Channel list
-    let items: Vec<ListItem> = channels
+    let (listed, selected) = listed(channels, app);
+    let columns = usize::from(chunks[2].width.saturating_sub(LIST_CHROME));
+    let title_width = title_columns(&listed, app);
+    let items: Vec<ListItem> = listed
         .iter()
         .enumerate()
-        .map(|(i, channel)| {
+        .map(|(position, &(i, channel))| {
             let style = if i == selected_index {
-                Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD)
+                theme.highlight()
             } else if channel.id == current_channel.id {
-                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+                theme.playing()
             } else {
                 Style::default()
             };
@@ -128,17 +274,31 @@ pub fn render_channel_selection(
                 "  "
             };
 
-            let content = format!("{}{:>3}. {}", prefix, i + 1, channel.title);
-            ListItem::new(content).style(style)
+            let last_playing = channel.last_playing.as_ref().map_or_else(String::new, |s| format!(" - {}", visual(s)));
+            let badges = Span::styled(
+                format!("  {}", quality_badges(channel, config.quality_for(&channel.id))),
+                Style::default().fg(Color::DarkGray),
+            );
+            let [title, badge] = title_column(channel, app, title_width);
+            let spans = vec![
+                Span::raw(format!("{}{:>3}. ", prefix, position + 1)),
+                genre_badge(channel, config),
+                title,
+                badge,
+                Span::raw(last_playing),
+                badges,
+            ];
+            ListItem::new(Line::from(fit_spans(spans, 4, columns)))
+            .style(style)
         })
         .collect();
 
     let mut list_state = ListState::default();
-    list_state.select(Some(selected_index));
+    list_state.select(selected);
 
     let channels_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Channels"))
-        .highlight_style(Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD))
+        .block(list_block(channels, app))
+        .highlight_style(theme.highlight())
         .highlight_symbol("► ");
     
     frame.render_stateful_widget(channels_list, chunks[2], &mut list_state);
@@ -162,3 +322,79 @@ pub fn render_channel_selection(
         .wrap(ratatui::widgets::Wrap { trim: true });
     frame.render_widget(controls_widget, chunks[3]);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Playlist;
+
+    fn playlist(name: &str, format: &str, quality: &str) -> Playlist {
+        Playlist {
+            url: format!("https://api.somafm.com/{}.pls", name),
+            format: format.to_string(),
+            quality: quality.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_quality_badges() {
+        let channel = Channel {
+            playlists: vec![
+                playlist("groovesalad256", "mp3", "highest"),
+                playlist("groovesalad", "mp3", "high"),
+                playlist("groovesalad64", "aacp", "high"),
+                playlist("groovesalad32", "aacp", "low"),
+            ],
+            ..Channel::named("groovesalad", "Groove Salad")
+        };
+
+        assert_eq!(quality_badges(&channel, StreamQuality::High), "[mp3 256/high*] [aacp 64/32]");
+        assert_eq!(quality_badges(&channel, StreamQuality::Highest), "[mp3 256*/high] [aacp 64/32]");
+    }
+
+    #[test]
+    fn test_hidden_channels_are_skipped() {
+        let channel = |id: &str| Channel {
+            playlists: vec![playlist(id, "mp3", "high")],
+            ..Channel::named(id, id)
+        };
+        let channels = vec![channel("groovesalad"), channel("dronezone"), channel("lush")];
+        let mut app = AppState::new();
+        app.hidden_channels.insert("dronezone".to_string());
+
+        app.next_channel(&channels);
+        assert_eq!(app.selected_index, 2);
+        app.next_channel(&channels);
+        assert_eq!(app.selected_index, 0);
+        app.previous_channel(&channels);
+        assert_eq!(app.selected_index, 2);
+        let (listed_channels, selected) = listed(&channels, &app);
+        assert_eq!(listed_channels.len(), 2);
+        assert_eq!(selected, Some(1));
+
+        // Listed again, marked, while hidden channels are shown
+        app.show_hidden = true;
+        app.previous_channel(&channels);
+        assert_eq!(app.selected_index, 1);
+        assert_eq!(channel_badge(&channels[1], &app).content, " HIDDEN");
+    }
+
+    #[test]
+    fn test_title_column_lines_up_wide_titles() {
+        let channels = vec![Channel::named("tokyo", "東京ラウンジ"), Channel::named("lush", "Lush")];
+        let mut app = AppState::new();
+        app.new_channels.insert("lush".to_string());
+        let listed: Vec<(usize, &Channel)> = channels.iter().enumerate().collect();
+        let columns = title_columns(&listed, &app);
+        assert_eq!(columns, 12);
+        for channel in &channels {
+            let [title, badge] = title_column(channel, &app, columns);
+            assert_eq!(title.width() + badge.width(), columns + 1);
+        }
+
+        let spans = vec![Span::raw("  1. "), Span::raw(" - 夜のドライブ"), Span::raw("  [mp3]")];
+        let line = Line::from(fit_spans(spans, 1, 20));
+        assert_eq!(line.width(), 20);
+        assert_eq!(line.spans[1].content, " - 夜の…");
+    }
+}
@@ -0,0 +1,78 @@
+//! Confirming an action with a second press of its key.
+//!
+//! The first press only asks for confirmation; the action goes ahead once
+//! the key is pressed again within the confirm window. Presses that follow
+//! each other quickly, as a held key's repeats do, don't count as that
+//! second press, so holding the key never confirms.
+
+use std::time::Duration;
+
+use crate::ui::animation::Animated;
+
+/// Pause after a press before the next one counts as deliberate, longer
+/// than the gap between a held key's repeats
+const REPEAT_GAP: Duration = Duration::from_millis(400);
+
+/// What to do with a press of a key that needs confirming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmCheck {
+    /// Go ahead
+    Proceed,
+    /// Ask for another press
+    Ask,
+    /// Still waiting for a deliberate press; stay quiet
+    Hold,
+}
+
+/// Tracks the request for confirmation shown for the last press.
+#[derive(Debug, Clone)]
+pub struct ConfirmPress {
+    /// How long a request waits for the confirming press
+    confirm_window: Duration,
+    /// Time left to confirm, zero without a pending request
+    window: Duration,
+    /// Time until a press counts as a new one
+    repeat: Duration,
+}
+
+impl ConfirmPress {
+    pub fn new(confirm_window: Duration) -> Self {
+        Self { confirm_window, window: Duration::ZERO, repeat: Duration::ZERO }
+    }
+
+    /// Checks a press of the key.
+    pub fn press(&mut self) -> ConfirmCheck {
+        if self.window.is_zero() {
+            self.window = self.confirm_window;
+            self.repeat = REPEAT_GAP;
+            return ConfirmCheck::Ask;
+        }
+        if !self.repeat.is_zero() {
+            self.repeat = REPEAT_GAP;
+            return ConfirmCheck::Hold;
+        }
+        self.window = Duration::ZERO;
+        ConfirmCheck::Proceed
+    }
+}
+
+impl Animated for ConfirmPress {
+    fn advance(&mut self, dt: Duration) {
+        self.window = self.window.saturating_sub(dt);
+        self.repeat = self.repeat.saturating_sub(dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirming_starts_over_afterwards() {
+        let mut confirm = ConfirmPress::new(Duration::from_secs(2));
+        assert_eq!(confirm.press(), ConfirmCheck::Ask);
+        confirm.advance(Duration::from_secs(1));
+        assert_eq!(confirm.press(), ConfirmCheck::Proceed);
+        assert_eq!(confirm.press(), ConfirmCheck::Ask);
+    }
+}
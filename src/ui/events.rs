@@ -1,43 +1,195 @@
 use crossterm::event::{KeyCode, KeyEvent};
 use crate::ui::app::{AppState, UIState};
+use crate::ui::help::HelpPopup;
+use crate::ui::confirm::ConfirmCheck;
+use crate::ui::lyrics::LyricsPopup;
+use crate::ui::macros::MacroAction;
+use crate::ui::player::StreamPopup;
+use crate::ui::popup::handle_popup_key;
 use crate::config::AppConfig;
-use crate::audio::PlayerCommand;
-use log::{error, info};
+use crate::audio::{PlaybackPosition, PlayerCommand};
+use crate::bookmarks::Bookmark;
+use crate::audio::progress::DownloadProgress;
+use crate::enrich::Enrichment;
+use crate::errors::PlayerResult;
+use crate::models::{Channel, StreamInfo, TrackInfo};
+use crate::control::{ControlReply, ControlRequest};
+use tokio::sync::oneshot;
+use log::info;
+
+/// Everything that can drive the TUI forward, delivered through a single
+/// channel so the main loop never blocks on any one source.
+#[derive(Debug)]
+pub enum AppEvent {
+    /// A key was pressed
+    Key(KeyEvent),
+    /// Periodic redraw and housekeeping
+    Tick,
+    /// The audio player reported something
+    Player(PlayerEvent),
+    /// A background API request completed
+    Api(ApiEvent),
+    /// The terminal was resized to the given columns and rows
+    Resize(u16, u16),
+    /// The terminal gained (`true`) or lost the focus
+    Focus(bool),
+    /// A request arrived on the control socket
+    Control(ControlRequest, oneshot::Sender<ControlReply>),
+    /// The process was asked to terminate
+    Shutdown,
+}
+
+/// Events from the audio playback task.
+#[derive(Debug)]
+pub enum PlayerEvent {
+    /// Playback session `session` ended, normally or with an error
+    Finished {
+        session: u64,
+        result: PlayerResult<bool>,
+    },
+    /// The audio task of session `session` panicked
+    Error {
+        session: u64,
+        message: String,
+    },
+    /// The player of session `session` applied this volume
+    VolumeChanged {
+        session: u64,
+        volume: u8,
+    },
+    /// The player of session `session` paused playback
+    Paused {
+        session: u64,
+    },
+    /// The player of session `session` resumed playback
+    Resumed {
+        session: u64,
+    },
+    /// Download progress of session `session`, `None` while it connects
+    Progress {
+        session: u64,
+        progress: Option<DownloadProgress>,
+    },
+    /// The stream of session `session` announced these details
+    StreamInfo {
+        session: u64,
+        info: StreamInfo,
+    },
+    /// Position of session `session` in the recording it plays
+    Position {
+        session: u64,
+        position: PlaybackPosition,
+    },
+}
+
+/// Results of background API requests.
+#[derive(Debug)]
+pub enum ApiEvent {
+    /// A fresh channel list was fetched
+    ChannelsRefreshed(Vec<Channel>),
+    /// The enrichment sources finished looking up a track
+    Enriched(TrackInfo, Enrichment),
+}
 
 pub enum EventResult {
     ChannelChange(usize),
+    /// Switch to the playlist at this index of the playing channel
+    StreamChange(usize),
+    /// `balance` or `mono` in the config changed
+    StereoChange,
+    /// A setting without other effects, such as `hidden_channels`, changed
+    /// and the config needs saving
+    SettingsChange,
     PlayerCommand(PlayerCommand),
+    /// Write the session transcript
+    ExportSession,
+    /// Bookmark the playing moment, asking for a note first
+    Bookmark,
+    /// Save this bookmark
+    SaveBookmark(Bookmark),
+    /// Open the saved bookmarks
+    ShowBookmarks,
+    /// Delete the bookmark at this index of the saved ones
+    DeleteBookmark(usize),
+    /// Rate the playing track, asking for the stars first
+    Rate,
+    /// Give this track so many stars, 0 removing its rating
+    RateTrack(TrackInfo, u8),
+    /// A macro key was pressed; its actions are carried out in order
+    Macro(Vec<MacroAction>),
     Quit,
     None,
 }
 
+/// Balance change per key press
+const BALANCE_STEP: i8 = 10;
+
+/// Seconds rewound per key press, or skipped ahead in a recording
+const REWIND_STEP: u64 = 10;
+
+/// Spectrum bands added or removed by `>` and `<`
+const SPECTRUM_BAND_STEP: usize = 4;
+
 pub fn handle_key_event(
     app: &mut AppState,
     key: KeyEvent,
-    channels_len: usize,
+    channels: &[Channel],
     current_channel_index: Option<usize>,
+    playing_channel: Option<&Channel>,
     config: &mut AppConfig
 ) -> EventResult {
+    if app.kiosk {
+        return EventResult::None;
+    }
+    if let Some(result) = handle_popup_key(app, key) {
+        return result;
+    }
     match (&app.ui_state, key.code) {
+        (UIState::InitialChannelSelection | UIState::Playing | UIState::SelectingChannel, KeyCode::Char('?')) => {
+            app.popups.open(Box::new(HelpPopup));
+            EventResult::None
+        }
+        (UIState::InitialChannelSelection | UIState::SelectingChannel, KeyCode::Char('h')) => {
+            // Hide the selected channel, or unhide it while hidden ones are shown
+            let Some(channel) = channels.get(app.selected_index) else {
+                return EventResult::None;
+            };
+            if app.hidden_channels.remove(&channel.id) {
+                info!("Unhiding channel {}", channel.id);
+            } else {
+                info!("Hiding channel {}", channel.id);
+                app.hidden_channels.insert(channel.id.clone());
+                app.select_listed(channels);
+            }
+            // Saved in the background by the caller
+            config.hidden_channels = app.hidden_channels.iter().cloned().collect();
+            config.hidden_channels.sort();
+            EventResult::SettingsChange
+        }
+        (UIState::InitialChannelSelection | UIState::SelectingChannel, KeyCode::Char('H')) => {
+            app.show_hidden = !app.show_hidden;
+            app.select_listed(channels);
+            EventResult::None
+        }
+
         // Initial channel selection
         (UIState::InitialChannelSelection, KeyCode::Up) => {
-            app.previous_channel(channels_len);
+            app.previous_channel(channels);
             EventResult::None
         }
         (UIState::InitialChannelSelection, KeyCode::Down) => {
-            app.next_channel(channels_len);
+            app.next_channel(channels);
             EventResult::None
         }
         (UIState::InitialChannelSelection, KeyCode::Enter) => {
-            if app.selected_index < channels_len {
+            if app.selected_index < channels.len() {
                 EventResult::ChannelChange(app.selected_index)
             } else {
                 EventResult::None
             }
         }
         (UIState::InitialChannelSelection, KeyCode::Char('q') | KeyCode::Char('Q')) => {
-            app.quit();
-            EventResult::Quit
+            quit(app)
         }
 
         // Playing mode
@@ -47,72 +199,203 @@ pub fn handle_key_event(
         }
         (UIState::Playing, KeyCode::Char('+') | KeyCode::Char('=')) => {
             // Increase volume
-            if let Some(current_vol) = config.volume {
-                let new_vol = (current_vol + 5).min(100);
-                if let Err(e) = config.set_volume(new_vol) {
-                    error!("Failed to save volume: {}", e);
-                } else {
-                    info!("Volume increased to {}%", new_vol);
-                    return EventResult::PlayerCommand(PlayerCommand::SetVolume(new_vol));
+            // The player confirms the change, which updates the display
+            // and the config
+            if let Some(current_vol) = app.volume {
+                // Repeats step from where the earlier ones are heading
+                let current_vol = app.volume_keys.target().unwrap_or(current_vol);
+                let mut new_vol = current_vol.saturating_add(config.volume_step()).min(config.volume_limit());
+                let threshold = config.loud_volume;
+                match app.loudness.check(current_vol, new_vol, threshold) {
+                    ConfirmCheck::Proceed => {}
+                    check => {
+                        if check == ConfirmCheck::Ask {
+                            info!("Volume above {}% needs another press", threshold);
+                            app.show_toast(format!("🔊 Above {}% is loud on headphones, press + again to go louder", threshold));
+                        }
+                        // Stop at the threshold until confirmed
+                        if current_vol >= threshold {
+                            return EventResult::None;
+                        }
+                        new_vol = threshold;
+                    }
                 }
+                return request_volume(app, new_vol);
             }
             EventResult::None
         }
         (UIState::Playing, KeyCode::Char('-') | KeyCode::Char('_')) => {
             // Decrease volume
-            if let Some(current_vol) = config.volume {
-                let new_vol = current_vol.saturating_sub(5);
-                if let Err(e) = config.set_volume(new_vol) {
-                    error!("Failed to save volume: {}", e);
-                } else {
-                    info!("Volume decreased to {}%", new_vol);
-                    return EventResult::PlayerCommand(PlayerCommand::SetVolume(new_vol));
-                }
+            if let Some(current_vol) = app.volume {
+                let current_vol = app.volume_keys.target().unwrap_or(current_vol);
+                return request_volume(app, current_vol.saturating_sub(config.volume_step()));
             }
             EventResult::None
         }
         (UIState::Playing, KeyCode::Char('p') | KeyCode::Char('P')) => {
-            // Toggle pause/resume
+            // Toggle pause/resume; the player reports back once it did
             if app.is_paused {
-                app.resume();
-                info!("Playback resumed");
+                info!("Requesting resume");
                 EventResult::PlayerCommand(PlayerCommand::Resume)
             } else {
-                app.pause();
-                info!("Playback paused");
+                info!("Requesting pause");
                 EventResult::PlayerCommand(PlayerCommand::Pause)
             }
         }
-        (UIState::Playing, KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc) => {
-            app.quit();
-            EventResult::Quit
+        (UIState::Playing, KeyCode::Char('[') | KeyCode::Char(']')) => {
+            // Shift the balance left or right
+            let step = if key.code == KeyCode::Char('[') { -BALANCE_STEP } else { BALANCE_STEP };
+            // Saved in the background by the caller
+            config.balance = config.balance.saturating_add(step).clamp(-100, 100);
+            info!("Balance set to {}", config.balance);
+            EventResult::StereoChange
+        }
+        (UIState::Playing, KeyCode::Char('m') | KeyCode::Char('M')) => {
+            config.mono = !config.mono;
+            info!("Mono downmix {}", if config.mono { "on" } else { "off" });
+            EventResult::StereoChange
+        }
+        (UIState::Playing, KeyCode::Char('l') | KeyCode::Char('L') | KeyCode::Char('.')) => {
+            info!("Jumping to live");
+            app.replaying = false;
+            EventResult::PlayerCommand(PlayerCommand::JumpToLive)
+        }
+        (UIState::Playing, KeyCode::Left) => {
+            info!("Rewinding {}s", REWIND_STEP);
+            // A recording shows where it is instead of how far behind live
+            app.replaying = app.position.is_none();
+            EventResult::PlayerCommand(PlayerCommand::SeekBack(REWIND_STEP))
+        }
+        (UIState::Playing, KeyCode::Right) if app.position.is_some() => {
+            info!("Skipping ahead {}s", REWIND_STEP);
+            EventResult::PlayerCommand(PlayerCommand::SeekForward(REWIND_STEP))
+        }
+        (UIState::Playing, KeyCode::Char(',')) => {
+            // Replay what was just played, e.g. to catch a lyric again
+            let secs = config.replay_secs.max(1);
+            info!("Replaying the last {}s", secs);
+            app.replaying = true;
+            EventResult::PlayerCommand(PlayerCommand::SeekBack(secs))
+        }
+        (UIState::Playing, KeyCode::Char('e') | KeyCode::Char('E')) => {
+            EventResult::ExportSession
+        }
+        (UIState::Playing, KeyCode::Char('b')) => EventResult::Bookmark,
+        (UIState::Playing, KeyCode::Char('r')) => EventResult::Rate,
+        (UIState::InitialChannelSelection | UIState::Playing | UIState::SelectingChannel, KeyCode::Char('B')) => {
+            EventResult::ShowBookmarks
+        }
+        (UIState::Playing, KeyCode::Char('y') | KeyCode::Char('Y')) => {
+            // Lyrics of the playing track, when a source found them
+            let found = app.enrichment.as_ref().and_then(|(track, enrichment)| {
+                enrichment.lyrics.as_deref().map(|lyrics| LyricsPopup::new(track, lyrics))
+            });
+            match found {
+                Some(popup) => app.popups.open(Box::new(popup)),
+                None => app.show_toast("No lyrics found for this track".to_string()),
+            }
+            EventResult::None
+        }
+        (UIState::Playing, KeyCode::Char(c @ ('<' | '>'))) => {
+            // More bands for wide terminals, fewer for narrow ones
+            let bands = if c == '>' {
+                app.spectrum.band_count() + SPECTRUM_BAND_STEP
+            } else {
+                app.spectrum.band_count().saturating_sub(SPECTRUM_BAND_STEP)
+            };
+            app.spectrum.set_band_count(bands);
+            config.spectrum_bands = app.spectrum.band_count();
+            app.show_toast(format!("Spectrum: {} bands", config.spectrum_bands));
+            EventResult::SettingsChange
+        }
+        (UIState::Playing, KeyCode::Char(c @ '1'..='3')) => {
+            // Jump to a monitored channel
+            let slot = c as usize - '1' as usize;
+            match app.monitored.get(slot) {
+                Some((index, _)) if *index < channels.len() => EventResult::ChannelChange(*index),
+                _ => EventResult::None,
+            }
+        }
+        (UIState::Playing, KeyCode::Char('Q')) => {
+            // Shift+Q picks a stream of the playing channel
+            if let Some(channel) = playing_channel.filter(|channel| !channel.playlists.is_empty()) {
+                app.popups.open(Box::new(StreamPopup::new(channel, app.playing_stream)));
+            }
+            EventResult::None
+        }
+        (UIState::Playing, KeyCode::Char('q') | KeyCode::Esc) => {
+            quit(app)
+        }
+        (UIState::Playing, KeyCode::Char(c)) if app.macros.contains_key(&c) => {
+            info!("Running macro '{}'", c);
+            EventResult::Macro(app.macros[&c].clone())
+        }
+
+        // Playback failed
+        (UIState::Error, KeyCode::Enter | KeyCode::Char('r') | KeyCode::Char('R')) => {
+            match current_channel_index {
+                Some(index) => EventResult::ChannelChange(index),
+                None => EventResult::None,
+            }
+        }
+        (UIState::Error, KeyCode::Char('c') | KeyCode::Char('C')) => {
+            app.set_channel_selection_mode(current_channel_index);
+            EventResult::None
+        }
+        (UIState::Error, KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc) => {
+            quit(app)
         }
 
         // Channel selection while playing
         (UIState::SelectingChannel, KeyCode::Up) => {
-            app.previous_channel(channels_len);
+            app.previous_channel(channels);
             EventResult::None
         }
         (UIState::SelectingChannel, KeyCode::Down) => {
-            app.next_channel(channels_len);
+            app.next_channel(channels);
             EventResult::None
         }
         (UIState::SelectingChannel, KeyCode::Enter) => {
-            if app.selected_index < channels_len {
+            if app.selected_index < channels.len() {
                 EventResult::ChannelChange(app.selected_index)
             } else {
                 EventResult::None
             }
         }
         (UIState::SelectingChannel, KeyCode::Esc) => {
-            app.set_playing_mode();
+            // Back to the error screen if playback failed
+            if app.error.is_some() {
+                app.ui_state = UIState::Error;
+            } else {
+                app.set_playing_mode();
+            }
             EventResult::None
         }
         (UIState::SelectingChannel, KeyCode::Char('q') | KeyCode::Char('Q')) => {
-            app.quit();
-            EventResult::Quit
+            quit(app)
         }
 
         _ => EventResult::None,
     }
 }
+
+/// Quits, or asks for another press first, see [`AppState::request_quit`].
+pub fn quit(app: &mut AppState) -> EventResult {
+    if app.request_quit() {
+        EventResult::Quit
+    } else {
+        EventResult::None
+    }
+}
+
+/// Asks the player for `volume`, unless the press is a key repeat, whose
+/// volume is applied once the repeats settle.
+fn request_volume(app: &mut AppState, volume: u8) -> EventResult {
+    match app.volume_keys.press(volume) {
+        Some(volume) => {
+            info!("Requesting volume {}%", volume);
+            EventResult::PlayerCommand(PlayerCommand::SetVolume(volume))
+        }
+        None => EventResult::None,
+    }
+}
@@ -0,0 +1,170 @@
+//! Colored two-letter genre badges standing in for channel icons.
+//!
+//! SomaFM lists each channel's genres, e.g. `ambient|electronica`. The
+//! first genre that belongs to a known family picks the badge, so channels
+//! of the same kind share a color in the lists and the playing screen.
+//! Colors follow the theme and can be changed per family in
+//! `[genre_colors]`.
+
+use std::str::FromStr;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+
+use crate::config::AppConfig;
+use crate::models::Channel;
+use crate::ui::text::{clip, pad};
+use crate::ui::theme::Theme;
+
+/// A group of related genres sharing one badge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenreFamily {
+    Ambient,
+    Beats,
+    Electronic,
+    Lounge,
+    Jazz,
+    Rock,
+    Folk,
+    World,
+    Retro,
+    Holiday,
+    Spoken,
+}
+
+impl GenreFamily {
+    pub const ALL: [GenreFamily; 11] = [
+        GenreFamily::Ambient,
+        GenreFamily::Beats,
+        GenreFamily::Electronic,
+        GenreFamily::Lounge,
+        GenreFamily::Jazz,
+        GenreFamily::Rock,
+        GenreFamily::Folk,
+        GenreFamily::World,
+        GenreFamily::Retro,
+        GenreFamily::Holiday,
+        GenreFamily::Spoken,
+    ];
+
+    /// Name used as the key in `[genre_colors]`
+    pub fn name(self) -> &'static str {
+        match self {
+            GenreFamily::Ambient => "ambient",
+            GenreFamily::Beats => "beats",
+            GenreFamily::Electronic => "electronic",
+            GenreFamily::Lounge => "lounge",
+            GenreFamily::Jazz => "jazz",
+            GenreFamily::Rock => "rock",
+            GenreFamily::Folk => "folk",
+            GenreFamily::World => "world",
+            GenreFamily::Retro => "retro",
+            GenreFamily::Holiday => "holiday",
+            GenreFamily::Spoken => "spoken",
+        }
+    }
+
+    /// Text of the badge
+    pub fn label(self) -> &'static str {
+        match self {
+            GenreFamily::Ambient => "AM",
+            GenreFamily::Beats => "BT",
+            GenreFamily::Electronic => "EL",
+            GenreFamily::Lounge => "LO",
+            GenreFamily::Jazz => "JZ",
+            GenreFamily::Rock => "RK",
+            GenreFamily::Folk => "FK",
+            GenreFamily::World => "WD",
+            GenreFamily::Retro => "RE",
+            GenreFamily::Holiday => "XM",
+            GenreFamily::Spoken => "SP",
+        }
+    }
+
+    /// Words in a genre that put it in this family
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            GenreFamily::Ambient => &["ambient", "drone", "space", "chill"],
+            GenreFamily::Beats => &["beats", "downtempo", "trip", "breaks", "dubstep", "hip"],
+            GenreFamily::Electronic => &["electronic", "electronica", "house", "techno", "idm", "trance", "dance"],
+            GenreFamily::Lounge => &["lounge", "exotica", "bossa", "easy"],
+            GenreFamily::Jazz => &["jazz", "soul", "blues", "funk"],
+            GenreFamily::Rock => &["rock", "alternative", "metal", "punk", "indie", "pop"],
+            GenreFamily::Folk => &["folk", "americana", "country", "singer", "acoustic"],
+            GenreFamily::World => &["world", "reggae", "dub", "celtic", "latin"],
+            GenreFamily::Retro => &["70s", "80s", "90s", "oldies", "retro", "synthpop"],
+            GenreFamily::Holiday => &["holiday", "christmas", "xmas"],
+            GenreFamily::Spoken => &["spoken", "talk", "news", "live"],
+        }
+    }
+
+    /// Finds the family of a SomaFM genre list such as `ambient|electronica`;
+    /// the first genre with a known family wins.
+    pub fn of(genres: &str) -> Option<Self> {
+        genres.split('|').find_map(|genre| {
+            let genre = genre.trim().to_lowercase();
+            let words: Vec<&str> = genre.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+            Self::ALL
+                .into_iter()
+                .find(|family| family.keywords().iter().any(|keyword| words.contains(keyword)))
+        })
+    }
+
+    /// Badge color in `theme`
+    fn color(self, theme: Theme) -> Color {
+        let light = theme == Theme::Light;
+        match self {
+            GenreFamily::Ambient => if light { Color::Rgb(0, 110, 110) } else { Color::Cyan },
+            GenreFamily::Beats => if light { Color::Rgb(110, 40, 150) } else { Color::Magenta },
+            GenreFamily::Electronic => if light { Color::Blue } else { Color::LightBlue },
+            GenreFamily::Lounge => if light { Color::Rgb(150, 100, 0) } else { Color::Yellow },
+            GenreFamily::Jazz => if light { Color::Rgb(150, 70, 0) } else { Color::LightYellow },
+            GenreFamily::Rock => if light { Color::Red } else { Color::LightRed },
+            GenreFamily::Folk => if light { Color::Rgb(0, 110, 0) } else { Color::Green },
+            GenreFamily::World => if light { Color::Rgb(60, 120, 0) } else { Color::LightGreen },
+            GenreFamily::Retro => if light { Color::Rgb(170, 0, 110) } else { Color::LightMagenta },
+            GenreFamily::Holiday => if light { Color::Rgb(170, 0, 0) } else { Color::Red },
+            GenreFamily::Spoken => if light { Color::DarkGray } else { Color::Gray },
+        }
+    }
+}
+
+/// The channel's two-letter genre badge, e.g. `AM`, styled by the theme
+/// and `[genre_colors]`. Channels of unknown genres get the first two
+/// letters of their genre in gray, channels without one blank space.
+pub fn genre_badge(channel: &Channel, config: &AppConfig) -> Span<'static> {
+    let theme = config.theme;
+    let Some(family) = GenreFamily::of(&channel.genre) else {
+        let label: String = channel.genre.chars().filter(|c| c.is_alphanumeric()).take(2).collect();
+        return Span::styled(pad(clip(&label.to_uppercase(), 2), 2), Style::default().fg(Color::DarkGray));
+    };
+    let color = config
+        .genre_colors
+        .get(family.name())
+        .and_then(|name| Color::from_str(name).ok())
+        .unwrap_or_else(|| family.color(theme));
+    let style = match theme {
+        Theme::Monochrome => Style::default().add_modifier(Modifier::REVERSED),
+        Theme::Light => Style::default().fg(Color::White).bg(color),
+        Theme::Default => Style::default().fg(Color::Black).bg(color),
+    };
+    Span::styled(family.label(), style.add_modifier(Modifier::BOLD))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genre_families() {
+        assert_eq!(GenreFamily::of("ambient|electronica"), Some(GenreFamily::Ambient));
+        assert_eq!(GenreFamily::of("eclectic|Trip-Hop"), Some(GenreFamily::Beats));
+        assert_eq!(GenreFamily::of("Folk|singer/songwriter"), Some(GenreFamily::Folk));
+        assert_eq!(GenreFamily::of("80s|new wave"), Some(GenreFamily::Retro));
+        assert_eq!(GenreFamily::of("eclectic"), None);
+        assert_eq!(GenreFamily::of(""), None);
+        assert!(GenreFamily::ALL.iter().all(|family| family.label().len() == 2));
+    }
+}
@@ -0,0 +1,107 @@
+//! Popup listing the keys of the current screen (`?`).
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear},
+    Frame,
+};
+
+use crate::config::AppConfig;
+use crate::ui::app::{AppState, UIState};
+use crate::ui::events::{quit, EventResult};
+use crate::ui::popup::{centered_rect, Popup, PopupAction};
+use crate::ui::text::pad_left;
+
+/// Keys of the channel lists
+const CHANNEL_LIST_KEYS: &[(&str, &str)] = &[
+    ("↑/↓", "Navigate channels"),
+    ("Enter", "Play the selected channel"),
+    ("h", "Hide or unhide the selected channel"),
+    ("Shift+H", "List hidden channels too"),
+    ("Shift+B", "Review bookmarks"),
+    ("Esc", "Back to the playing channel, if any"),
+    ("q", "Quit"),
+];
+
+/// Keys of the playing screen
+const PLAYING_KEYS: &[(&str, &str)] = &[
+    ("C", "Change channel"),
+    ("P", "Pause/Resume"),
+    ("L", "Jump to live"),
+    ("←", "Rewind 10 seconds"),
+    ("→", "Skip ahead 10 seconds in a recording"),
+    (",", "Replay the last seconds (replay_secs)"),
+    (".", "Back to live"),
+    ("+/-", "Volume"),
+    ("[/]", "Shift the balance"),
+    ("M", "Toggle mono downmix"),
+    ("</>", "Fewer/more spectrum bands"),
+    ("1-3", "Jump to a monitored channel"),
+    ("Shift+Q", "Pick a stream"),
+    ("E", "Save a session transcript"),
+    ("b", "Bookmark this moment, with a note"),
+    ("r", "Rate the track 1-5 stars"),
+    ("Shift+B", "Review bookmarks"),
+    ("y", "Show the lyrics, with [enrichment]"),
+    ("q/Esc", "Quit"),
+];
+
+/// Help for the screen below it, including the macro keys.
+pub struct HelpPopup;
+
+impl HelpPopup {
+    fn lines(app: &AppState) -> Vec<Line<'static>> {
+        let key_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+        let keys = match app.ui_state {
+            UIState::InitialChannelSelection | UIState::SelectingChannel => CHANNEL_LIST_KEYS,
+            _ => PLAYING_KEYS,
+        };
+        let mut lines: Vec<Line> = keys
+            .iter()
+            .map(|(key, action)| {
+                Line::from(vec![Span::styled(format!("{}  ", pad_left(key, 8)), key_style), Span::raw(*action)])
+            })
+            .collect();
+
+        if matches!(app.ui_state, UIState::Playing) && !app.macros.is_empty() {
+            let mut macros: Vec<_> = app.macros.iter().collect();
+            macros.sort_by_key(|(key, _)| **key);
+            lines.push(Line::from(""));
+            for (key, actions) in macros {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{}  ", pad_left(&key.to_string(), 8)), key_style),
+                    Span::raw(actions.iter().map(|action| action.name()).collect::<Vec<_>>().join(", ")),
+                ]));
+            }
+        }
+        lines
+    }
+}
+
+impl Popup for HelpPopup {
+    fn handle_key(&mut self, key: KeyEvent, app: &mut AppState) -> PopupAction {
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('?') => PopupAction::Close(EventResult::None),
+            KeyCode::Char('q') | KeyCode::Char('Q') => {
+                PopupAction::Stay(quit(app))
+            }
+            _ => PopupAction::Stay(EventResult::None),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame, config: &AppConfig, app: &AppState) {
+        let lines = Self::lines(app);
+        let area = centered_rect(frame.area(), 50, lines.len() as u16 + 2);
+        let popup = ratatui::widgets::Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Keys (Esc to close)")
+                .title_style(config.theme.accent()))
+            .wrap(ratatui::widgets::Wrap { trim: false });
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+}
@@ -0,0 +1,72 @@
+//! Confirmation before the volume keys go past a loud level.
+//!
+//! Raising the volume across `loud_volume` with `+` only shows a warning
+//! toast; the volume goes up once `+` is pressed again. Presses that follow
+//! each other quickly, as a held key's repeats do, don't count as that
+//! second press, so holding `+` stops at the threshold.
+
+use std::time::Duration;
+
+use crate::ui::animation::Animated;
+use crate::ui::confirm::{ConfirmCheck, ConfirmPress};
+
+/// How long the warning waits for the confirming press
+const CONFIRM_WINDOW: Duration = Duration::from_secs(5);
+
+/// Tracks the warning shown for the last increase past the threshold.
+#[derive(Debug, Clone)]
+pub struct LoudnessGuard(ConfirmPress);
+
+impl Default for LoudnessGuard {
+    fn default() -> Self {
+        Self(ConfirmPress::new(CONFIRM_WINDOW))
+    }
+}
+
+impl LoudnessGuard {
+    /// Checks raising the volume from `current` to `requested` against
+    /// `threshold` (0 disables the check). Asking means showing the
+    /// warning and staying below the threshold.
+    pub fn check(&mut self, current: u8, requested: u8, threshold: u8) -> ConfirmCheck {
+        if threshold == 0 || current > threshold || requested <= threshold {
+            return ConfirmCheck::Proceed;
+        }
+        self.0.press()
+    }
+}
+
+impl Animated for LoudnessGuard {
+    fn advance(&mut self, dt: Duration) {
+        self.0.advance(dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loud_volume_needs_a_deliberate_press() {
+        let mut guard = LoudnessGuard::default();
+        let step = Duration::from_millis(30);
+        assert_eq!(guard.check(80, 85, 90), ConfirmCheck::Proceed);
+
+        // A held key stops at the threshold
+        assert_eq!(guard.check(90, 95, 90), ConfirmCheck::Ask);
+        for _ in 0..20 {
+            guard.advance(step);
+            assert_eq!(guard.check(90, 95, 90), ConfirmCheck::Hold);
+        }
+
+        // Pressing again after letting go confirms
+        guard.advance(Duration::from_secs(1));
+        assert_eq!(guard.check(90, 95, 90), ConfirmCheck::Proceed);
+        assert_eq!(guard.check(95, 100, 90), ConfirmCheck::Proceed);
+
+        // The warning expires
+        assert_eq!(guard.check(90, 95, 90), ConfirmCheck::Ask);
+        guard.advance(CONFIRM_WINDOW);
+        assert_eq!(guard.check(90, 95, 90), ConfirmCheck::Ask);
+        assert_eq!(guard.check(90, 95, 0), ConfirmCheck::Proceed);
+    }
+}
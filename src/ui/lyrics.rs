@@ -0,0 +1,67 @@
+//! Popup with the lyrics of the playing track (`y`).
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::config::AppConfig;
+use crate::models::TrackInfo;
+use crate::ui::app::AppState;
+use crate::ui::events::{quit, EventResult};
+use crate::ui::popup::{centered_rect, Popup, PopupAction};
+
+/// Lyrics found by the enrichment sources, scrollable with the arrow keys.
+pub struct LyricsPopup {
+    title: String,
+    lyrics: String,
+    scroll: u16,
+}
+
+impl LyricsPopup {
+    pub fn new(track: &TrackInfo, lyrics: &str) -> Self {
+        Self {
+            title: format!("{} - {} (Esc to close)", track.artist, track.title),
+            lyrics: lyrics.to_string(),
+            scroll: 0,
+        }
+    }
+}
+
+impl Popup for LyricsPopup {
+    fn handle_key(&mut self, key: KeyEvent, app: &mut AppState) -> PopupAction {
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('y') => PopupAction::Close(EventResult::None),
+            KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Down => {
+                let lines = self.lyrics.lines().count() as u16;
+                self.scroll = (self.scroll + 1).min(lines.saturating_sub(1));
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Char('q') | KeyCode::Char('Q') => {
+                PopupAction::Stay(quit(app))
+            }
+            _ => PopupAction::Stay(EventResult::None),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame, config: &AppConfig, _app: &AppState) {
+        let area = centered_rect(frame.area(), 70, frame.area().height.saturating_sub(4));
+        let lines: Vec<Line> = self.lyrics.lines().map(Line::from).collect();
+        let popup = Paragraph::new(lines)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(self.title.as_str())
+                .title_style(config.theme.accent()))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+}
@@ -0,0 +1,155 @@
+//! Keys running several actions at once (`[macros]` section).
+//!
+//! Each entry binds a key that is free while playing to a list of action
+//! names, e.g. `n = ["notify", "jump_to_live"]`. Most actions stand for one
+//! of the playing screen's keys and are carried out as if it was pressed;
+//! `notify` announces the current track like a track change does.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use log::warn;
+
+/// Keys the playing screen already uses
+const RESERVED_KEYS: &str = "cC+=-_pP[]mMlLeEyYbBr123qQ?,.<>";
+
+/// Something a macro key does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroAction {
+    VolumeUp,
+    VolumeDown,
+    TogglePause,
+    ToggleMono,
+    BalanceLeft,
+    BalanceRight,
+    JumpToLive,
+    Rewind,
+    ChannelList,
+    StreamList,
+    /// Jump to the monitored channel in this slot (0-2)
+    Monitor(u8),
+    ExportSession,
+    Quit,
+    /// Desktop notification and hook for the current track
+    Notify,
+}
+
+impl MacroAction {
+    /// Parses an action name such as `volume_up` or `monitor_2`.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let action = match name.trim() {
+            "volume_up" => MacroAction::VolumeUp,
+            "volume_down" => MacroAction::VolumeDown,
+            "toggle_pause" => MacroAction::TogglePause,
+            "toggle_mono" => MacroAction::ToggleMono,
+            "balance_left" => MacroAction::BalanceLeft,
+            "balance_right" => MacroAction::BalanceRight,
+            "jump_to_live" => MacroAction::JumpToLive,
+            "rewind" => MacroAction::Rewind,
+            "channel_list" => MacroAction::ChannelList,
+            "stream_list" => MacroAction::StreamList,
+            "monitor_1" => MacroAction::Monitor(0),
+            "monitor_2" => MacroAction::Monitor(1),
+            "monitor_3" => MacroAction::Monitor(2),
+            "export_session" => MacroAction::ExportSession,
+            "quit" => MacroAction::Quit,
+            "notify" => MacroAction::Notify,
+            other => return Err(format!("Unknown macro action '{}'", other)),
+        };
+        Ok(action)
+    }
+
+    /// The name `parse` accepts for the action.
+    pub fn name(self) -> &'static str {
+        match self {
+            MacroAction::VolumeUp => "volume_up",
+            MacroAction::VolumeDown => "volume_down",
+            MacroAction::TogglePause => "toggle_pause",
+            MacroAction::ToggleMono => "toggle_mono",
+            MacroAction::BalanceLeft => "balance_left",
+            MacroAction::BalanceRight => "balance_right",
+            MacroAction::JumpToLive => "jump_to_live",
+            MacroAction::Rewind => "rewind",
+            MacroAction::ChannelList => "channel_list",
+            MacroAction::StreamList => "stream_list",
+            MacroAction::Monitor(0) => "monitor_1",
+            MacroAction::Monitor(1) => "monitor_2",
+            MacroAction::Monitor(_) => "monitor_3",
+            MacroAction::ExportSession => "export_session",
+            MacroAction::Quit => "quit",
+            MacroAction::Notify => "notify",
+        }
+    }
+
+    /// The key of the playing screen doing the same, `None` for actions
+    /// without one.
+    pub fn key(self) -> Option<KeyEvent> {
+        let code = match self {
+            MacroAction::VolumeUp => KeyCode::Char('+'),
+            MacroAction::VolumeDown => KeyCode::Char('-'),
+            MacroAction::TogglePause => KeyCode::Char('p'),
+            MacroAction::ToggleMono => KeyCode::Char('m'),
+            MacroAction::BalanceLeft => KeyCode::Char('['),
+            MacroAction::BalanceRight => KeyCode::Char(']'),
+            MacroAction::JumpToLive => KeyCode::Char('l'),
+            MacroAction::Rewind => KeyCode::Left,
+            MacroAction::ChannelList => KeyCode::Char('c'),
+            MacroAction::StreamList => KeyCode::Char('Q'),
+            MacroAction::Monitor(slot) => KeyCode::Char((b'1' + slot) as char),
+            MacroAction::ExportSession => KeyCode::Char('e'),
+            MacroAction::Quit => KeyCode::Char('q'),
+            MacroAction::Notify => return None,
+        };
+        Some(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+}
+
+/// Parses the `[macros]` section. Keys that are not a single free
+/// character and unknown actions are logged and left out.
+pub fn parse_macros(config: &BTreeMap<String, Vec<String>>) -> HashMap<char, Vec<MacroAction>> {
+    let mut macros = HashMap::new();
+    for (key, names) in config {
+        let mut chars = key.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            warn!("Ignoring macro '{}': the key must be a single character", key);
+            continue;
+        };
+        if RESERVED_KEYS.contains(c) {
+            warn!("Ignoring macro '{}': the key is already in use", key);
+            continue;
+        }
+        let actions: Vec<MacroAction> = names
+            .iter()
+            .filter_map(|name| MacroAction::parse(name).map_err(|e| warn!("Macro '{}': {}", key, e)).ok())
+            .collect();
+        if !actions.is_empty() {
+            macros.insert(c, actions);
+        }
+    }
+    macros
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_macros() {
+        let config: BTreeMap<String, Vec<String>> = toml::from_str(
+            r#"
+n = ["notify", "jump_to_live", "louder"]
+p = ["toggle_pause"]
+ab = ["quit"]
+x = ["monitor_2", "volume_up"]
+"#,
+        )
+        .unwrap();
+        let macros = parse_macros(&config);
+
+        assert_eq!(macros.len(), 2);
+        assert_eq!(macros[&'n'], [MacroAction::Notify, MacroAction::JumpToLive]);
+        assert_eq!(macros[&'x'][0].key(), Some(KeyEvent::new(KeyCode::Char('2'), KeyModifiers::NONE)));
+        assert_eq!(MacroAction::Notify.key(), None);
+        assert_eq!(MacroAction::parse(macros[&'x'][0].name()), Ok(MacroAction::Monitor(1)));
+    }
+}
@@ -1,8 +1,25 @@
+pub mod animation;
 pub mod app;
+pub mod bookmarks;
+pub mod confirm;
 pub mod events;
+pub mod genre;
+pub mod help;
+pub mod loudness;
+pub mod lyrics;
+pub mod macros;
 pub mod channel_list;
 pub mod player;
+pub mod popup;
+pub mod quit;
+pub mod rating;
 pub mod spectrum;
+pub mod taskbar;
+pub mod text;
+pub mod theme;
+pub mod title;
+pub mod volume;
+pub mod wizard;
 
 pub use app::*;
 pub use events::*;
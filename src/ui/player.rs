@@ -1,37 +1,145 @@
+use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
     Frame,
 };
 
-use crate::models::{Channel, TrackInfo};
+use crate::audio::PlaybackPosition;
+use crate::models::{format_track, Channel, TrackInfo};
+use crate::ratings::stars;
 use crate::config::AppConfig;
-use crate::ui::app::AppState;
+use crate::ui::app::{AppState, UIState};
+use crate::ui::events::{quit, EventResult};
+use crate::ui::help::HelpPopup;
+use crate::ui::genre::genre_badge;
+use crate::ui::popup::{centered_rect, Popup, PopupAction};
 use crate::ui::spectrum::SpectrumWidget;
+use crate::ui::text::{truncate, visual, width};
+use std::time::Duration;
+
+/// Level in decibels of a linear gain factor.
+fn decibels(factor: f32) -> f32 {
+    20.0 * factor.max(1e-4).log10()
+}
+
+/// Summarizes the stages that make the output louder or quieter than the
+/// volume setting alone, e.g. `Gain: boost +3.5 dB | limiter -2.1 dB | 112%
+/// heard`, or `None` while none of them changes the level.
+pub fn gain_meters(app: &AppState) -> Option<String> {
+    // Boost, limiting and ducking only happen on the local output
+    if app.cast_target.is_some() {
+        return None;
+    }
+    let volume = app.volume.map(f32::from);
+    let boost = volume.map_or(1.0, |volume| (volume / 100.0).max(1.0));
+    let limiter = app.limiter_reduction().unwrap_or(1.0);
+    let duck = app.duck_factor;
+    let leveling = app.leveling;
+
+    let mut stages = Vec::new();
+    if boost > 1.0 {
+        stages.push(format!("boost {:+.1} dB", decibels(boost)));
+    }
+    if leveling != 1.0 {
+        stages.push(format!("leveling {:+.1} dB", decibels(leveling)));
+    }
+    if limiter < 1.0 {
+        stages.push(format!("limiter {:+.1} dB", decibels(limiter)));
+    }
+    if duck < 1.0 {
+        stages.push(format!("ducked {:+.1} dB", decibels(duck)));
+    }
+    if stages.is_empty() {
+        return None;
+    }
+    if let Some(volume) = volume {
+        stages.push(format!("{:.0}% heard", volume * duck * limiter * leveling));
+    }
+    Some(format!("Gain: {}", stages.join(" | ")))
+}
+
+/// Local time and listening time for the header, e.g. `🕒 14:05  ⏱ 1:07`.
+pub fn clock_text(now: chrono::NaiveTime, listened: Duration) -> String {
+    let minutes = listened.as_secs() / 60;
+    format!("🕒 {}  ⏱ {}:{:02}", now.format("%H:%M"), minutes / 60, minutes % 60)
+}
+
+/// Width in cells of the bar in [`position_text`]
+const POSITION_BAR_WIDTH: usize = 20;
+
+/// Where a recording is, e.g. `1:05 / 4:20 ━━━━━───────────────`, or
+/// only the time played when its length is unknown.
+pub fn position_text(position: &PlaybackPosition) -> String {
+    let time = |d: Duration| format!("{}:{:02}", d.as_secs() / 60, d.as_secs() % 60);
+    let Some(duration) = position.duration.filter(|d| !d.is_zero()) else {
+        return time(position.position);
+    };
+    let played = (position.position.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0);
+    let filled = (played * POSITION_BAR_WIDTH as f64).round() as usize;
+    format!(
+        "{} / {} {}{}",
+        time(position.position.min(duration)),
+        time(duration),
+        "━".repeat(filled),
+        "─".repeat(POSITION_BAR_WIDTH - filled)
+    )
+}
 
 /// Renders the playing UI
 pub fn render_playing_ui(frame: &mut Frame, channel: &Channel, track_info: &TrackInfo, config: &AppConfig, app: &AppState) {
+    let meters = gain_meters(app);
+    let stream_summary = app.stream_info.as_ref().and_then(|info| info.summary());
+    let album_summary = app.enrichment
+        .as_ref()
+        .filter(|(track, _)| track == track_info)
+        .and_then(|(_, enrichment)| {
+            let label = if enrichment.album.is_some() { "💿 Album:  " } else { "💿 Genre:  " };
+            enrichment.summary().map(|summary| (label, summary))
+        });
+    // A `track_format` puts artist and title on one line
+    let track_lines = if config.track_format.is_some() { 3 } else { 4 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
         .constraints([
             Constraint::Length(3), // Header
             Constraint::Length(6), // Channel info with spectrum
-            Constraint::Length(4), // Track info
-            Constraint::Length(2), // Status
+            Constraint::Length(track_lines + u16::from(stream_summary.is_some()) + u16::from(album_summary.is_some())), // Track info
+            Constraint::Length(3), // Status
+            Constraint::Length(u16::from(meters.is_some())), // Gain meters
             Constraint::Min(0),    // Spacer
-            Constraint::Length(3), // Controls
+            Constraint::Length(4), // Controls
         ])
         .split(frame.area());
 
-    // Header
-    let header = ratatui::widgets::Paragraph::new("🎵 SomaFM Player 🎵")
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
-        .block(Block::default().borders(Borders::ALL))
-        .wrap(ratatui::widgets::Wrap { trim: true });
-    frame.render_widget(header, chunks[0]);
+    // Header, replaced by the track alert or a toast while one is active
+    let mut header_block = Block::default().borders(Borders::ALL);
+    if config.show_clock {
+        let clock = clock_text(chrono::Local::now().time(), app.listened);
+        header_block = header_block.title(Line::from(format!(" {} ", clock)).right_aligned());
+    }
+    let header = if let Some(alert) = app.active_alert() {
+        ratatui::widgets::Paragraph::new(format!(
+            "🔔 {} - {} on {}",
+            visual(&alert.track.artist),
+            visual(&alert.track.title),
+            visual(&alert.channel_title)
+        ))
+        .style(Style::default().fg(Color::Black).bg(Color::Magenta).add_modifier(Modifier::BOLD))
+        .block(header_block.title("Alert"))
+    } else if let Some(toast) = app.active_toast() {
+        ratatui::widgets::Paragraph::new(toast.to_string())
+            .style(config.theme.accent())
+            .block(header_block)
+    } else {
+        ratatui::widgets::Paragraph::new("🎵 SomaFM Player 🎵")
+            .style(config.theme.accent())
+            .block(header_block)
+    };
+    frame.render_widget(header.wrap(ratatui::widgets::Wrap { trim: true }), chunks[0]);
 
     // Channel info section with spectrum visualizer
     let channel_chunks = Layout::default()
@@ -43,9 +151,15 @@ pub fn render_playing_ui(frame: &mut Frame, channel: &Channel, track_info: &Trac
         .split(chunks[1]);
 
     // Channel name
-    let channel_text = format!("📻 Channel:\n{}", channel.title);
+    let mut channel_text = vec![
+        Line::from("📻 Channel:"),
+        Line::from(vec![genre_badge(channel, config), Span::raw(format!(" {}", channel.title))]),
+    ];
+    if let Some(listeners) = channel.listener_count() {
+        channel_text.push(Line::from(format!("👥 {} listening", listeners)));
+    }
     let channel_widget = ratatui::widgets::Paragraph::new(channel_text)
-        .style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        .style(config.theme.playing())
         .block(Block::default().borders(Borders::ALL))
         .wrap(ratatui::widgets::Wrap { trim: true });
     frame.render_widget(channel_widget, channel_chunks[0]);
@@ -73,16 +187,48 @@ pub fn render_playing_ui(frame: &mut Frame, channel: &Channel, track_info: &Trac
         Style::default().fg(Color::Gray)
     };
 
-    let track_text = vec![
-        Line::from(vec![
-            Span::styled("🎤 Artist: ", Style::default().fg(Color::Yellow)),
-            Span::styled(&track_info.artist, artist_style),
-        ]),
-        Line::from(vec![
-            Span::styled("🎵 Title:  ", Style::default().fg(Color::Yellow)),
-            Span::styled(&track_info.title, title_style),
-        ]),
-    ];
+    // Artist and title reordered separately, so a right-to-left artist
+    // doesn't swap them around
+    let shown = TrackInfo {
+        artist: visual(&track_info.artist).into_owned(),
+        title: visual(&track_info.title).into_owned(),
+    };
+    let mut track_text = match &config.track_format {
+        Some(format) => vec![Line::from(vec![
+            Span::styled("🎵 ", Style::default().fg(Color::Yellow)),
+            Span::styled(format_track(format, &channel.title, &shown), title_style),
+        ])],
+        None => vec![
+            Line::from(vec![
+                Span::styled("🎤 Artist: ", Style::default().fg(Color::Yellow)),
+                Span::styled(shown.artist, artist_style),
+            ]),
+            Line::from(vec![
+                Span::styled("🎵 Title:  ", Style::default().fg(Color::Yellow)),
+                Span::styled(shown.title, title_style),
+            ]),
+        ],
+    };
+    if let Some(rating) = app.rating {
+        track_text.push(Line::from(vec![
+            Span::styled("⭐ Rating: ", Style::default().fg(Color::Yellow)),
+            Span::styled(stars(rating), Style::default().fg(Color::Yellow)),
+        ]));
+    }
+    // Album and genres found by the enrichment sources
+    if let Some((label, summary)) = album_summary {
+        track_text.push(Line::from(vec![
+            Span::styled(label, Style::default().fg(Color::Yellow)),
+            Span::styled(summary, Style::default().fg(Color::Gray)),
+        ]));
+    }
+    // What the stream says about itself, for stations the API doesn't know
+    if let Some(summary) = stream_summary {
+        track_text.push(Line::from(vec![
+            Span::styled("📡 Stream: ", Style::default().fg(Color::Yellow)),
+            Span::styled(summary, Style::default().fg(Color::Gray)),
+        ]));
+    }
 
     let track_widget = ratatui::widgets::Paragraph::new(track_text)
         .block(Block::default().borders(Borders::ALL).title("Now Playing"))
@@ -90,17 +236,58 @@ pub fn render_playing_ui(frame: &mut Frame, channel: &Channel, track_info: &Trac
     frame.render_widget(track_widget, chunks[2]);
 
     // Status with volume and pause state
-    let volume_text = if let Some(vol) = config.volume {
-        format!(" | 🔊 {}%", vol)
+    let volume_text = if let Some(vol) = app.volume_keys.target().or(app.volume) {
+        let ducked = if app.ducked { " (ducked)" } else { "" };
+        format!(" | 🔊 {}%{}", vol, ducked)
     } else {
         "".to_string()
     };
     
+    let mut stereo_text = String::new();
+    if config.balance != 0 {
+        let side = if config.balance < 0 { "L" } else { "R" };
+        stereo_text.push_str(&format!(" | ⚖️ {}{}", side, config.balance.unsigned_abs()));
+    }
+    if config.mono {
+        stereo_text.push_str(" | Mono");
+    }
+    if app.is_limiting() {
+        stereo_text.push_str(" | 🚧 Limiting");
+    }
+    
+    let mut latency_text = match (app.position, app.download) {
+        // A recording has no live edge to be behind
+        (Some(position), _) => format!(" | ⏩ {}", position_text(&position)),
+        (None, Some(download)) if download.stalled => " | ⚠️ Buffering".to_string(),
+        (None, Some(download)) if app.replaying => format!(
+            " | ⏪ Replaying {:.1}s behind live (. for live) | 📶 {} kbps, buffer {:.0}%",
+            download.buffered.as_secs_f32(),
+            download.kbps(),
+            download.prefetch_fill * 100.0
+        ),
+        (None, Some(download)) => format!(
+            " | ⏱️ {:.1}s behind live | 📶 {} kbps, buffer {:.0}%",
+            download.buffered.as_secs_f32(),
+            download.kbps(),
+            download.prefetch_fill * 100.0
+        ),
+        (None, None) => String::new(),
+    };
+    if let Some(download) = app.download.filter(|d| d.underruns > 0) {
+        latency_text.push_str(&format!(" | {} underruns", download.underruns));
+    }
+    if let Some(cast_target) = &app.cast_target {
+        latency_text.push_str(&format!(" | 📡 {}", cast_target));
+    }
+    if let Some(sync_status) = &app.sync_status {
+        latency_text.push_str(&format!(" | 🔗 {}", sync_status));
+    }
+    
     let (status_text, status_color) = if track_info.title != "Loading..." {
         if app.is_paused {
-            (format!("⏸️ Paused{}", volume_text), Color::Yellow)
+            (format!("⏸️ Paused{}{}{}", volume_text, stereo_text, latency_text), Color::Yellow)
         } else {
-            (format!("🔊 Playing{}", volume_text), Color::Green)
+            (format!("🔊 Playing{}{}{}", volume_text, stereo_text, latency_text), Color::Green)
         }
     } else {
         (format!("⏳ Connecting to {}...{}", channel.title, volume_text), Color::Yellow)
@@ -112,22 +299,238 @@ pub fn render_playing_ui(frame: &mut Frame, channel: &Channel, track_info: &Trac
         .wrap(ratatui::widgets::Wrap { trim: true });
     frame.render_widget(status_widget, chunks[3]);
 
+    // Why the output is louder or quieter than the volume says
+    if let Some(meters) = meters {
+        let meters_widget = ratatui::widgets::Paragraph::new(format!(" {}", meters))
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(meters_widget, chunks[4]);
+    }
+
+    // Monitored channels
+    if !app.monitored.is_empty() && chunks[5].height >= 3 {
+        let columns = usize::from(chunks[5].width.saturating_sub(2));
+        let monitor_lines: Vec<Line> = app.monitored
+            .iter()
+            .enumerate()
+            .map(|(slot, (_, monitored))| {
+                let channel = format!("{}: ", monitored.channel_title);
+                let track = format!("{} - {}", visual(&monitored.track.artist), visual(&monitored.track.title));
+                let track = truncate(&track, columns.saturating_sub(2 + width(&channel))).into_owned();
+                Line::from(vec![
+                    Span::styled(format!("{} ", slot + 1), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                    Span::styled(channel, Style::default().fg(Color::Yellow)),
+                    Span::raw(track),
+                ])
+            })
+            .collect();
+
+        let monitor_height = (monitor_lines.len() as u16 + 2).min(chunks[5].height);
+        let monitor_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(monitor_height), Constraint::Min(0)])
+            .split(chunks[5])[0];
+
+        let monitor_widget = ratatui::widgets::Paragraph::new(monitor_lines)
+            .block(Block::default().borders(Borders::ALL).title("Also Playing (1-3 to switch)"));
+        frame.render_widget(monitor_widget, monitor_area);
+    }
+
     // Controls
-    let controls_text = vec![
+    let controls_text = if app.kiosk {
+        vec![Line::from("Kiosk mode - controls are locked")]
+    } else {
+        vec![
+            Line::from(vec![
+                Span::styled("C", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                Span::raw(" - Change channel  |  "),
+                Span::styled("P", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+                Span::raw(" - Pause/Resume  |  "),
+                if app.position.is_some() {
+                    Span::styled("←/→", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD))
+                } else {
+                    Span::styled("L", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD))
+                },
+                Span::raw(if app.position.is_some() { " - Seek  |  " } else { " - Jump to live  |  " }),
+                Span::styled("+/-", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::raw(" - Volume  |  "),
+                Span::styled("[/]", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::raw(" - Balance  |  "),
+                Span::styled("M", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::raw(" - Mono  |  "),
+                Span::styled("Shift+Q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::raw(" - Stream  |  "),
+                Span::styled("q", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                Span::raw(" - Quit"),
+            ]),
+        ]
+    };
+
+    let controls_widget = ratatui::widgets::Paragraph::new(controls_text)
+        .block(Block::default().borders(Borders::ALL).title("Controls"))
+        .wrap(ratatui::widgets::Wrap { trim: true });
+    frame.render_widget(controls_widget, chunks[6]);
+
+    if let (UIState::Error, Some(error)) = (&app.ui_state, &app.error) {
+        render_error(frame, channel, error, app.error_hint, config, app.kiosk);
+    }
+}
+
+/// Renders the popup explaining that playback failed, and what to do
+/// about it when known
+fn render_error(frame: &mut Frame, channel: &Channel, error: &str, hint: Option<&str>, config: &AppConfig, kiosk: bool) {
+    let actions = if kiosk {
+        Line::from("Retrying shortly...")
+    } else {
         Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::raw(" - Retry  |  "),
             Span::styled("C", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
             Span::raw(" - Change channel  |  "),
-            Span::styled("P", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
-            Span::raw(" - Pause/Resume  |  "),
-            Span::styled("+/-", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-            Span::raw(" - Volume  |  "),
-            Span::styled("Q", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled("q", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
             Span::raw(" - Quit"),
-        ]),
+        ])
+    };
+    let mut text = vec![
+        Line::from(format!("Playback of {} stopped unexpectedly:", channel.title)),
+        Line::from(""),
+        Line::from(Span::styled(error.to_string(), Style::default().fg(Color::Red))),
+        Line::from(""),
     ];
+    if let Some(hint) = hint {
+        text.push(Line::from(Span::styled(format!("💡 {}", hint), config.theme.accent())));
+        text.push(Line::from(""));
+    }
+    text.push(actions);
 
-    let controls_widget = ratatui::widgets::Paragraph::new(controls_text)
-        .block(Block::default().borders(Borders::ALL).title("Controls"))
+    let height = if hint.is_some() { 12 } else { 9 };
+    let area = centered_rect(frame.area(), 60, height);
+    let popup = ratatui::widgets::Paragraph::new(text)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Playback Error")
+            .title_style(config.theme.accent()))
         .wrap(ratatui::widgets::Wrap { trim: true });
-    frame.render_widget(controls_widget, chunks[5]);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+/// Popup listing the playing channel's streams (Shift+Q).
+pub struct StreamPopup {
+    /// One line per playlist of the channel
+    streams: Vec<String>,
+    /// Index of the highlighted stream
+    index: usize,
+}
+
+impl StreamPopup {
+    /// Lists the streams of `channel`, highlighting the one playing.
+    pub fn new(channel: &Channel, playing: Option<usize>) -> Self {
+        let streams = channel.playlists
+            .iter()
+            .map(|playlist| match playlist.bitrate(&channel.id) {
+                Some(bitrate) => format!("{} {} kbps ({})", playlist.format, bitrate, playlist.quality),
+                None => format!("{} ({})", playlist.format, playlist.quality),
+            })
+            .collect();
+        Self { streams, index: playing.unwrap_or(0) }
+    }
+}
+
+impl Popup for StreamPopup {
+    fn handle_key(&mut self, key: KeyEvent, app: &mut AppState) -> PopupAction {
+        let count = self.streams.len();
+        match key.code {
+            KeyCode::Up => {
+                self.index = self.index.checked_sub(1).unwrap_or(count.saturating_sub(1));
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Down => {
+                self.index = if self.index + 1 < count { self.index + 1 } else { 0 };
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Enter if self.index < count && Some(self.index) != app.playing_stream => {
+                PopupAction::Close(EventResult::StreamChange(self.index))
+            }
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('Q') => PopupAction::Close(EventResult::None),
+            KeyCode::Char('?') => PopupAction::Open(Box::new(HelpPopup)),
+            KeyCode::Char('q') => {
+                PopupAction::Stay(quit(app))
+            }
+            _ => PopupAction::Stay(EventResult::None),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame, config: &AppConfig, app: &AppState) {
+        let items: Vec<ListItem> = self.streams
+            .iter()
+            .enumerate()
+            .map(|(i, stream)| {
+                let prefix = if app.playing_stream == Some(i) { "♪ " } else { "  " };
+                ListItem::new(format!("{}{}", prefix, stream))
+            })
+            .collect();
+
+        let area = centered_rect(frame.area(), 44, items.len() as u16 + 2);
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.index));
+
+        let list = List::new(items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Stream (Enter to switch, Esc to cancel)")
+                .title_style(config.theme.accent()))
+            .highlight_style(config.theme.highlight())
+            .highlight_symbol("► ");
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut list_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::animation::Timed;
+
+    #[test]
+    fn test_gain_meters() {
+        let mut app = AppState { volume: Some(80), ..AppState::new() };
+        assert_eq!(gain_meters(&app), None);
+
+        app.volume = Some(200);
+        app.limiting = Some(Timed::new(0.5, Duration::from_secs(1)));
+        assert_eq!(
+            gain_meters(&app).as_deref(),
+            Some("Gain: boost +6.0 dB | limiter -6.0 dB | 100% heard")
+        );
+
+        app.volume = Some(50);
+        app.limiting = None;
+        app.duck_factor = 0.2;
+        assert_eq!(gain_meters(&app).as_deref(), Some("Gain: ducked -14.0 dB | 10% heard"));
+
+        app.duck_factor = 1.0;
+        app.leveling = 2.0;
+        assert_eq!(gain_meters(&app).as_deref(), Some("Gain: leveling +6.0 dB | 100% heard"));
+
+        app.cast_target = Some("Kitchen".to_string());
+        assert_eq!(gain_meters(&app), None);
+    }
+
+    #[test]
+    fn test_clock_text() {
+        let now = chrono::NaiveTime::from_hms_opt(9, 5, 59).unwrap();
+        assert_eq!(clock_text(now, Duration::from_secs(59)), "🕒 09:05  ⏱ 0:00");
+        assert_eq!(clock_text(now, Duration::from_secs(2 * 3600 + 7 * 60 + 30)), "🕒 09:05  ⏱ 2:07");
+    }
+
+    #[test]
+    fn test_position_text() {
+        let position = PlaybackPosition { position: Duration::from_secs(65), duration: Some(Duration::from_secs(260)) };
+        assert_eq!(position_text(&position), "1:05 / 4:20 ━━━━━───────────────");
+
+        let position = PlaybackPosition { position: Duration::from_secs(65), duration: None };
+        assert_eq!(position_text(&position), "1:05");
+    }
 }
@@ -0,0 +1,142 @@
+//! Popups drawn over a screen, such as the stream picker and the help.
+//!
+//! Open popups form a stack kept in [`AppState::popups`]. The popup on top
+//! has the focus: it gets every key first and decides whether to stay open,
+//! close and hand the focus back to the one below, open another popup on
+//! top of itself, or leave the key to the screen underneath. Popups are
+//! drawn after the screen, bottom to top. A new popup only needs to
+//! implement [`Popup`] instead of adding a [`UIState`](crate::ui::UIState)
+//! variant and arms to the key handling.
+
+use crossterm::event::KeyEvent;
+use ratatui::{layout::Rect, Frame};
+
+use crate::config::AppConfig;
+use crate::ui::app::AppState;
+use crate::ui::events::EventResult;
+
+/// What the focused popup did with a key.
+pub enum PopupAction {
+    /// Stay open and carry out the result
+    Stay(EventResult),
+    /// Close, giving the focus back, and carry out the result
+    Close(EventResult),
+    /// Open another popup on top, which takes the focus
+    Open(Box<dyn Popup>),
+    /// Not a key for this popup; the screen below handles it
+    Ignore,
+}
+
+/// A popup that can be stacked over a screen.
+pub trait Popup {
+    /// Handles a key while the popup has the focus.
+    fn handle_key(&mut self, key: KeyEvent, app: &mut AppState) -> PopupAction;
+
+    /// Draws the popup over what is already on the frame.
+    fn render(&self, frame: &mut Frame, config: &AppConfig, app: &AppState);
+}
+
+/// The open popups, the focused one last.
+#[derive(Default)]
+pub struct PopupStack {
+    popups: Vec<Box<dyn Popup>>,
+}
+
+impl PopupStack {
+    /// Opens `popup` on top, giving it the focus.
+    pub fn open(&mut self, popup: Box<dyn Popup>) {
+        self.popups.push(popup);
+    }
+
+    /// Whether any popup is open.
+    pub fn is_open(&self) -> bool {
+        !self.popups.is_empty()
+    }
+
+    /// Closes every popup.
+    pub fn clear(&mut self) {
+        self.popups.clear();
+    }
+
+    /// Draws the popups bottom to top.
+    pub fn render(&self, frame: &mut Frame, config: &AppConfig, app: &AppState) {
+        for popup in &self.popups {
+            popup.render(frame, config, app);
+        }
+    }
+}
+
+/// Passes `key` to the focused popup. Returns `None` when no popup is open
+/// or the popup left the key to the screen.
+pub fn handle_popup_key(app: &mut AppState, key: KeyEvent) -> Option<EventResult> {
+    let mut popup = app.popups.popups.pop()?;
+    match popup.handle_key(key, app) {
+        PopupAction::Stay(result) => {
+            app.popups.open(popup);
+            Some(result)
+        }
+        PopupAction::Close(result) => Some(result),
+        PopupAction::Open(next) => {
+            app.popups.open(popup);
+            app.popups.open(next);
+            Some(EventResult::None)
+        }
+        PopupAction::Ignore => {
+            app.popups.open(popup);
+            None
+        }
+    }
+}
+
+/// A `width` x `height` area centered in `area`, shrunk to fit
+pub fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Channel, Playlist};
+    use crate::ui::events::handle_key_event;
+    use crate::ui::UIState;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn test_stacked_popups_take_the_focus() {
+        let playlist = |format: &str| Playlist {
+            url: format!("https://api.somafm.com/groovesalad-{}.pls", format),
+            format: format.to_string(),
+            quality: "high".to_string(),
+        };
+        let channel = Channel {
+            playlists: vec![playlist("mp3"), playlist("aacp")],
+            ..Channel::named("groovesalad", "Groove Salad")
+        };
+        let mut config = AppConfig::default();
+        let mut app = AppState { ui_state: UIState::Playing, playing_stream: Some(0), ..AppState::new() };
+        let mut press = |app: &mut AppState, code| {
+            handle_key_event(app, KeyEvent::new(code, KeyModifiers::NONE), std::slice::from_ref(&channel), Some(0), Some(&channel), &mut config)
+        };
+
+        press(&mut app, KeyCode::Char('Q'));
+        press(&mut app, KeyCode::Down);
+        // Help opens over the stream picker and takes the keys
+        press(&mut app, KeyCode::Char('?'));
+        assert_eq!(app.popups.popups.len(), 2);
+        assert!(matches!(press(&mut app, KeyCode::Enter), EventResult::None));
+        assert_eq!(app.popups.popups.len(), 1);
+
+        // The stream picker has the focus again, still on the second stream
+        assert!(matches!(press(&mut app, KeyCode::Enter), EventResult::StreamChange(1)));
+        assert!(!app.popups.is_open());
+        // Keys reach the playing screen once every popup is closed
+        assert!(matches!(press(&mut app, KeyCode::Char('l')), EventResult::PlayerCommand(_)));
+    }
+}
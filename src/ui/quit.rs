@@ -0,0 +1,69 @@
+//! Confirmation before `q` stops the music.
+//!
+//! With `confirm_quit`, pressing `q` while a channel plays only shows a
+//! toast; the player quits once `q` is pressed again within
+//! [`CONFIRM_WINDOW`], see [`ConfirmPress`].
+
+use std::time::Duration;
+
+use crate::ui::animation::Animated;
+use crate::ui::confirm::{ConfirmCheck, ConfirmPress};
+
+/// How long the toast waits for the confirming press
+pub const CONFIRM_WINDOW: Duration = Duration::from_secs(2);
+
+/// Tracks the toast shown for the last quit key press.
+#[derive(Debug, Clone)]
+pub struct QuitGuard {
+    /// Whether quitting needs a second press at all
+    enabled: bool,
+    press: ConfirmPress,
+}
+
+impl Default for QuitGuard {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl QuitGuard {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, press: ConfirmPress::new(CONFIRM_WINDOW) }
+    }
+
+    /// Checks a press of the quit key.
+    pub fn check(&mut self) -> ConfirmCheck {
+        if !self.enabled {
+            return ConfirmCheck::Proceed;
+        }
+        self.press.press()
+    }
+}
+
+impl Animated for QuitGuard {
+    fn advance(&mut self, dt: Duration) {
+        self.press.advance(dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quit_needs_a_second_press_in_time() {
+        assert_eq!(QuitGuard::new(false).check(), ConfirmCheck::Proceed);
+
+        let mut guard = QuitGuard::new(true);
+        assert_eq!(guard.check(), ConfirmCheck::Ask);
+        // A held key doesn't confirm
+        guard.advance(Duration::from_millis(30));
+        assert_eq!(guard.check(), ConfirmCheck::Hold);
+
+        // The toast expires
+        guard.advance(CONFIRM_WINDOW);
+        assert_eq!(guard.check(), ConfirmCheck::Ask);
+        guard.advance(Duration::from_secs(1));
+        assert_eq!(guard.check(), ConfirmCheck::Proceed);
+    }
+}
@@ -0,0 +1,74 @@
+//! Popup rating the playing track (`r`).
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::config::AppConfig;
+use crate::models::TrackInfo;
+use crate::ratings::{stars, MAX_STARS};
+use crate::ui::app::AppState;
+use crate::ui::events::{quit, EventResult};
+use crate::ui::popup::{centered_rect, Popup, PopupAction};
+
+/// Stars for the track that was playing when the popup opened: a number
+/// key rates it right away, ←/→ and Enter pick the stars first.
+pub struct RatingPopup {
+    track: TrackInfo,
+    stars: u8,
+}
+
+impl RatingPopup {
+    /// Rates `track`, starting from its current rating.
+    pub fn new(track: TrackInfo, rating: Option<u8>) -> Self {
+        Self { track, stars: rating.unwrap_or(0) }
+    }
+}
+
+impl Popup for RatingPopup {
+    fn handle_key(&mut self, key: KeyEvent, app: &mut AppState) -> PopupAction {
+        match key.code {
+            KeyCode::Char(c @ '0'..='5') => {
+                let stars = c as u8 - b'0';
+                PopupAction::Close(EventResult::RateTrack(self.track.clone(), stars))
+            }
+            KeyCode::Left => {
+                self.stars = self.stars.saturating_sub(1);
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Right => {
+                self.stars = (self.stars + 1).min(MAX_STARS);
+                PopupAction::Stay(EventResult::None)
+            }
+            KeyCode::Enter => PopupAction::Close(EventResult::RateTrack(self.track.clone(), self.stars)),
+            KeyCode::Esc | KeyCode::Char('r') => PopupAction::Close(EventResult::None),
+            KeyCode::Char('q') | KeyCode::Char('Q') => PopupAction::Stay(quit(app)),
+            _ => PopupAction::Stay(EventResult::None),
+        }
+    }
+
+    fn render(&self, frame: &mut Frame, config: &AppConfig, _app: &AppState) {
+        let area = centered_rect(frame.area(), 50, 6);
+        let lines = vec![
+            Line::from(format!("{} - {}", self.track.artist, self.track.title)),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled(stars(self.stars), Style::default().fg(Color::Yellow)),
+                Span::styled("  1-5 to rate, 0 to clear", Style::default().fg(Color::DarkGray)),
+            ]),
+        ];
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Rate (Esc to cancel)")
+                .title_style(config.theme.accent()),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+}
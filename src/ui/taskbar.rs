@@ -0,0 +1,111 @@
+//! Playing/paused badge on the taskbar through OSC 9;4 progress reports.
+//!
+//! Windows Terminal, ConEmu and a few others show the progress a program
+//! reports with `ESC ] 9 ; 4 ; state ; percent BEL` on the taskbar button
+//! or tab. The player reports a full bar while playing, a paused (yellow)
+//! one while paused, an indeterminate one while buffering and an error (red)
+//! one when playback failed. Other terminals may print the sequence, so it is
+//! only sent where `taskbar_progress` allows.
+
+use std::io::{self, Write};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+/// When to report progress, the `taskbar_progress` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskbarMode {
+    /// Only in terminals known to support it
+    #[default]
+    Auto,
+    /// In any terminal
+    Always,
+    Off,
+}
+
+impl TaskbarMode {
+    /// Whether to report progress, given the value of an environment
+    /// variable by name.
+    pub fn enabled(self, env: impl Fn(&str) -> Option<String>) -> bool {
+        match self {
+            TaskbarMode::Always => true,
+            TaskbarMode::Off => false,
+            TaskbarMode::Auto => {
+                env("WT_SESSION").is_some()
+                    || env("ConEmuANSI").is_some_and(|value| value == "ON")
+                    || env("TERM_PROGRAM").is_some_and(|program| program == "ghostty")
+            }
+        }
+    }
+}
+
+/// What the badge shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskbarState {
+    Playing,
+    Paused,
+    Buffering,
+    Error,
+}
+
+/// The OSC 9;4 sequence for `state`, or the one removing the badge.
+pub fn progress_sequence(state: Option<TaskbarState>) -> &'static str {
+    match state {
+        Some(TaskbarState::Playing) => "\x1b]9;4;1;100\x07",
+        Some(TaskbarState::Paused) => "\x1b]9;4;4;100\x07",
+        Some(TaskbarState::Buffering) => "\x1b]9;4;3;0\x07",
+        Some(TaskbarState::Error) => "\x1b]9;4;2;100\x07",
+        None => "\x1b]9;4;0;0\x07",
+    }
+}
+
+/// Reports the playback state as taskbar progress, removing the badge on
+/// drop.
+#[derive(Default)]
+pub struct TaskbarProgress {
+    current: Option<Option<TaskbarState>>,
+}
+
+impl TaskbarProgress {
+    /// Shows `state`, or no badge, if it changed since the last call.
+    pub fn set(&mut self, state: Option<TaskbarState>) {
+        if self.current == Some(state) {
+            return;
+        }
+        let mut stdout = io::stdout();
+        if let Err(e) = stdout.write_all(progress_sequence(state).as_bytes()).and_then(|_| stdout.flush()) {
+            debug!("Failed to report taskbar progress: {}", e);
+        }
+        self.current = Some(state);
+    }
+}
+
+impl Drop for TaskbarProgress {
+    fn drop(&mut self) {
+        if self.current.is_some_and(|state| state.is_some()) {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(progress_sequence(None).as_bytes()).and_then(|_| stdout.flush());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taskbar_detection_and_sequences() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        };
+        assert!(TaskbarMode::Auto.enabled(env(&[("WT_SESSION", "0f1e")])));
+        assert!(TaskbarMode::Auto.enabled(env(&[("ConEmuANSI", "ON")])));
+        assert!(!TaskbarMode::Auto.enabled(env(&[("ConEmuANSI", "OFF"), ("TERM_PROGRAM", "iTerm.app")])));
+        assert!(TaskbarMode::Always.enabled(env(&[])));
+        assert!(!TaskbarMode::Off.enabled(env(&[("WT_SESSION", "0f1e")])));
+
+        assert_eq!(progress_sequence(Some(TaskbarState::Paused)), "\x1b]9;4;4;100\x07");
+        assert_eq!(progress_sequence(None), "\x1b]9;4;0;0\x07");
+    }
+}
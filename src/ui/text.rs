@@ -0,0 +1,120 @@
+//! Truncating and padding text by the columns it takes on screen.
+//!
+//! CJK characters take two columns and combining marks none, so counting
+//! bytes or chars misaligns lists as soon as a title is not plain ASCII.
+//! Widths here are display columns as measured by `unicode-width`, the
+//! same measure ratatui lays text out by.
+//!
+//! Terminals draw every line left to right, so Arabic or Hebrew in a title
+//! comes out backwards; [`visual`] puts such text in display order first.
+
+use std::borrow::Cow;
+
+use unicode_bidi::BidiInfo;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Marks text that was cut short
+const ELLIPSIS: char = '…';
+
+/// Columns `text` takes on screen.
+pub fn width(text: &str) -> usize {
+    text.width()
+}
+
+/// The longest start of `text` that fits in `max` columns.
+pub fn clip(text: &str, max: usize) -> &str {
+    let mut used = 0;
+    for (index, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > max {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+/// `text` cut to `max` columns, ending in `…` when anything was cut.
+pub fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+    if width(text) <= max {
+        return Cow::Borrowed(text);
+    }
+    if max == 0 {
+        return Cow::Borrowed("");
+    }
+    let mut truncated = clip(text, max - 1).trim_end().to_string();
+    truncated.push(ELLIPSIS);
+    Cow::Owned(truncated)
+}
+
+/// `text` truncated to `columns` and padded with spaces on the right to
+/// fill them.
+pub fn pad(text: &str, columns: usize) -> String {
+    let text = truncate(text, columns);
+    format!("{}{}", text, " ".repeat(columns - width(&text)))
+}
+
+/// `text` truncated to `columns` and padded with spaces on the left, for
+/// right-aligned columns.
+pub fn pad_left(text: &str, columns: usize) -> String {
+    let text = truncate(text, columns);
+    format!("{}{}", " ".repeat(columns - width(&text)), text)
+}
+
+/// Direction marks, embeddings and isolates: once text is in display
+/// order they have done their job, and most terminals draw them as boxes.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// `text` in the order its characters appear on screen, with right-to-left
+/// runs reversed as the Unicode bidirectional algorithm lays them out.
+/// Left-to-right text is returned as it is. Mirrored brackets are not
+/// swapped.
+pub fn visual(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let bidi = BidiInfo::new(text, None);
+    if !bidi.has_rtl() && !text.contains(is_bidi_control) {
+        return Cow::Borrowed(text);
+    }
+    let mut ordered = String::with_capacity(text.len());
+    for paragraph in &bidi.paragraphs {
+        ordered.push_str(&bidi.reorder_line(paragraph, paragraph.range.clone()));
+    }
+    ordered.retain(|c| !is_bidi_control(c));
+    Cow::Owned(ordered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_and_pad_by_columns() {
+        assert_eq!(width("東京 Lounge"), 11);
+        assert_eq!(truncate("Groove Salad", 20), "Groove Salad");
+        assert_eq!(truncate("Groove Salad", 8), "Groove…");
+        // A wide character that would straddle the edge is left out
+        assert_eq!(truncate("東京ナイト", 6), "東京…");
+        assert_eq!(width(&truncate("東京ナイト", 6)), 5);
+        assert_eq!(truncate("🎵🎵🎵", 0), "");
+        assert_eq!(clip("ab東", 3), "ab");
+
+        assert_eq!(pad("東京", 6), "東京  ");
+        assert_eq!(pad_left("Enter", 8), "   Enter");
+        assert_eq!(pad("Groove Salad", 8), "Groove… ");
+        assert_eq!(width(&pad("Café del Mar ☕", 10)), 10);
+    }
+
+    #[test]
+    fn test_visual_order_of_mixed_direction_text() {
+        assert!(matches!(visual("Groove Salad"), Cow::Borrowed(_)));
+        assert!(matches!(visual("東京ナイト"), Cow::Borrowed(_)));
+        assert_eq!(visual("שלום"), "םולש");
+        // Latin runs keep their order inside a right-to-left title
+        assert_eq!(visual("שלום DJ"), "DJ םולש");
+        assert_eq!(visual("Live في القاهرة"), "Live ةرهاقلا يف");
+        assert_eq!(visual("\u{2067}שלום\u{2069} mix"), "םולש mix");
+    }
+}
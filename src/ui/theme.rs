@@ -0,0 +1,59 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+/// Color theme for headers, selections and the playing channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Bright colors for dark terminal backgrounds
+    #[default]
+    Default,
+    /// Darker colors that stay readable on light backgrounds
+    Light,
+    /// No colors, only bold and reversed text
+    Monochrome,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Default, Theme::Light, Theme::Monochrome];
+
+    /// Screen titles and headers
+    pub fn accent(self) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        match self {
+            Theme::Default => style.fg(Color::Cyan),
+            Theme::Light => style.fg(Color::Blue),
+            Theme::Monochrome => style,
+        }
+    }
+
+    /// The selected entry of a list
+    pub fn highlight(self) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        match self {
+            Theme::Default => style.fg(Color::Black).bg(Color::White),
+            Theme::Light => style.fg(Color::White).bg(Color::Blue),
+            Theme::Monochrome => style.add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    /// The channel that is currently playing
+    pub fn playing(self) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        match self {
+            Theme::Default => style.fg(Color::Green),
+            Theme::Light => style.fg(Color::Magenta),
+            Theme::Monochrome => style,
+        }
+    }
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Theme::Default => "default",
+            Theme::Light => "light",
+            Theme::Monochrome => "monochrome",
+        })
+    }
+}
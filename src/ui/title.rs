@@ -0,0 +1,92 @@
+//! The current track in the terminal window title.
+//!
+//! [`format_title`] builds the title from the channel and track, and
+//! [`TerminalTitle`] sets it, saving the title the terminal had before and
+//! putting it back when the player exits.
+
+use std::io::{self, Write};
+
+use crossterm::{execute, terminal::SetTitle};
+use log::debug;
+
+use crate::models::{format_track, Channel, TrackInfo};
+
+/// xterm control sequences saving and restoring the window title
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// Formats the window title for the current track, with `track_format`
+/// if set. The channel is appended unless the format places it.
+pub fn format_title(channel: &Channel, track: &TrackInfo, track_format: Option<&str>) -> String {
+    if track.title == TrackInfo::default().title {
+        return channel.title.clone();
+    }
+    match track_format {
+        Some(format) if format.contains("{channel}") => format_track(format, &channel.title, track),
+        Some(format) => format!("{} | {}", format_track(format, &channel.title, track), channel.title),
+        None => format!("{} – {} | {}", track.artist, track.title, channel.title),
+    }
+}
+
+/// Shows the current track in the terminal window title.
+///
+/// The original title is saved on the terminal's title stack when created
+/// and restored on drop. Terminals without a title stack simply keep the
+/// last title set.
+pub struct TerminalTitle {
+    current: Option<String>,
+}
+
+impl TerminalTitle {
+    pub fn new() -> Self {
+        let mut stdout = io::stdout();
+        if let Err(e) = stdout.write_all(PUSH_TITLE.as_bytes()).and_then(|_| stdout.flush()) {
+            debug!("Failed to save terminal title: {}", e);
+        }
+        Self { current: None }
+    }
+
+    /// Sets the title if it changed since the last call.
+    pub fn set(&mut self, title: &str) {
+        if self.current.as_deref() == Some(title) {
+            return;
+        }
+        if let Err(e) = execute!(io::stdout(), SetTitle(title)) {
+            debug!("Failed to set terminal title: {}", e);
+        }
+        self.current = Some(title.to_string());
+    }
+}
+
+impl Default for TerminalTitle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TerminalTitle {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(POP_TITLE.as_bytes()).and_then(|_| stdout.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_title() {
+        let channel = Channel::named("groovesalad", "Groove Salad");
+
+        assert_eq!(format_title(&channel, &TrackInfo::default(), None), "Groove Salad");
+
+        let track = TrackInfo {
+            artist: "Thievery Corporation".to_string(),
+            title: "Lebanese Blonde".to_string(),
+        };
+        assert_eq!(format_title(&channel, &track, None), "Thievery Corporation – Lebanese Blonde | Groove Salad");
+        assert_eq!(format_title(&channel, &track, Some("{title} / {artist}")), "Lebanese Blonde / Thievery Corporation | Groove Salad");
+        assert_eq!(format_title(&channel, &track, Some("{channel}: {title}")), "Groove Salad: Lebanese Blonde");
+    }
+}
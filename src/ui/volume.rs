@@ -0,0 +1,100 @@
+//! Coalescing volume key repeats.
+//!
+//! Holding `+` or `-` sends a key press per repeat, far faster than the
+//! player confirms each level. The first press after a pause is applied
+//! right away; the repeats that follow only move the target, which is
+//! applied once no press came for `key_repeat_ms`. Each press steps from
+//! the target, not from the last confirmed volume, so no repeat is lost
+//! while the player catches up.
+
+use std::time::Duration;
+
+use crate::ui::animation::Animated;
+
+/// Target volume of a burst of volume key presses.
+#[derive(Debug, Clone, Default)]
+pub struct VolumeKeys {
+    /// How long after the last press the burst ends, zero applying every
+    /// press right away
+    settle: Duration,
+    /// Time left until the burst ends, zero without one
+    quiet: Duration,
+    /// Volume the burst asked for last
+    target: Option<u8>,
+    /// Target not sent to the player yet
+    pending: Option<u8>,
+}
+
+impl VolumeKeys {
+    pub fn new(settle: Duration) -> Self {
+        Self { settle, ..Self::default() }
+    }
+
+    /// Volume the burst is heading for, while one is going on
+    pub fn target(&self) -> Option<u8> {
+        self.target
+    }
+
+    /// Records a press asking for `volume` and returns the volume to apply
+    /// right away, if any.
+    pub fn press(&mut self, volume: u8) -> Option<u8> {
+        let repeat = !self.quiet.is_zero();
+        self.quiet = self.settle;
+        if self.settle.is_zero() {
+            return Some(volume);
+        }
+        self.target = Some(volume);
+        if repeat {
+            self.pending = Some(volume);
+            None
+        } else {
+            Some(volume)
+        }
+    }
+
+    /// Ends a burst that has settled and returns its final volume, unless
+    /// it was applied already.
+    pub fn take_settled(&mut self) -> Option<u8> {
+        if !self.quiet.is_zero() {
+            return None;
+        }
+        self.target = None;
+        self.pending.take()
+    }
+}
+
+impl Animated for VolumeKeys {
+    fn advance(&mut self, dt: Duration) {
+        self.quiet = self.quiet.saturating_sub(dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeats_apply_the_final_volume() {
+        let mut keys = VolumeKeys::new(Duration::from_millis(150));
+        assert_eq!(keys.press(55), Some(55));
+        for volume in [60, 65, 70] {
+            keys.advance(Duration::from_millis(30));
+            assert_eq!(keys.press(volume), None);
+        }
+        assert_eq!(keys.target(), Some(70));
+        keys.advance(Duration::from_millis(100));
+        assert_eq!(keys.take_settled(), None);
+        keys.advance(Duration::from_millis(50));
+        assert_eq!(keys.take_settled(), Some(70));
+        assert_eq!((keys.take_settled(), keys.target()), (None, None));
+
+        // A single press is applied right away and nothing is left over
+        assert_eq!(keys.press(65), Some(65));
+        keys.advance(Duration::from_secs(1));
+        assert_eq!(keys.take_settled(), None);
+
+        let mut keys = VolumeKeys::new(Duration::ZERO);
+        assert_eq!((keys.press(50), keys.press(45)), (Some(50), Some(45)));
+        assert_eq!(keys.target(), None);
+    }
+}
@@ -0,0 +1,431 @@
+//! First-run setup wizard.
+//!
+//! Shown when no configuration file exists yet. It walks through the default
+//! channel, volume, stream quality, theme, auto-start and file logging, then
+//! summarizes the choices and where they will be saved.
+
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::config::{schema, AppConfig};
+use crate::models::{Channel, StreamQuality};
+use crate::ui::text::pad;
+use crate::ui::theme::Theme;
+
+/// Volume change per key press on the volume step
+const VOLUME_STEP: u8 = 5;
+
+/// A page of the wizard, in the order they are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardStep {
+    Channel,
+    Volume,
+    Quality,
+    Theme,
+    AutoStart,
+    Logging,
+    Summary,
+}
+
+impl WizardStep {
+    const ORDER: [WizardStep; 7] = [
+        WizardStep::Channel,
+        WizardStep::Volume,
+        WizardStep::Quality,
+        WizardStep::Theme,
+        WizardStep::AutoStart,
+        WizardStep::Logging,
+        WizardStep::Summary,
+    ];
+
+    fn position(self) -> usize {
+        Self::ORDER.iter().position(|s| *s == self).unwrap_or(0)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            WizardStep::Channel => "Default Channel",
+            WizardStep::Volume => "Volume",
+            WizardStep::Quality => "Stream Quality",
+            WizardStep::Theme => "Theme",
+            WizardStep::AutoStart => "Auto-start",
+            WizardStep::Logging => "File Logging",
+            WizardStep::Summary => "Summary",
+        }
+    }
+
+    fn prompt(self) -> &'static str {
+        match self {
+            WizardStep::Channel => "Which channel should be selected when the player starts?",
+            WizardStep::Volume => "How loud should playback start?",
+            WizardStep::Quality => "Which stream quality do you prefer?",
+            WizardStep::Theme => "Which colors suit your terminal?",
+            WizardStep::AutoStart => "Start playing the default channel right away?",
+            WizardStep::Logging => "Write log files? They help when reporting problems.",
+            WizardStep::Summary => "Ready to save these settings:",
+        }
+    }
+}
+
+/// What the caller should do after a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardOutcome {
+    /// Keep showing the wizard
+    Continue,
+    /// The user confirmed the summary; take the config with `into_config`
+    Finished,
+    /// The user skipped setup
+    Cancelled,
+}
+
+/// State of the setup wizard, building up an [`AppConfig`].
+pub struct SetupWizard {
+    /// `(id, title)` of every channel
+    channels: Vec<(String, String)>,
+    config_path: PathBuf,
+    step: WizardStep,
+    selected: usize,
+    config: AppConfig,
+}
+
+impl SetupWizard {
+    /// Starts the wizard from the default settings. `config_path` is only
+    /// shown to the user.
+    pub fn new(channels: &[Channel], config_path: PathBuf) -> Self {
+        let mut wizard = Self {
+            channels: channels.iter().map(|c| (c.id.clone(), c.title.clone())).collect(),
+            config_path,
+            step: WizardStep::Channel,
+            selected: 0,
+            config: AppConfig::default(),
+        };
+        if wizard.channels.is_empty() {
+            // Nothing to choose from, e.g. when the channel list is unavailable
+            wizard.step = WizardStep::Volume;
+        }
+        wizard.selected = wizard.current_choice();
+        wizard
+    }
+
+    pub fn step(&self) -> WizardStep {
+        self.step
+    }
+
+    /// The settings chosen so far
+    pub fn into_config(self) -> AppConfig {
+        self.config
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> WizardOutcome {
+        match key.code {
+            KeyCode::Esc => return WizardOutcome::Cancelled,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return WizardOutcome::Cancelled;
+            }
+            KeyCode::Enter => {
+                if self.step == WizardStep::Summary {
+                    return WizardOutcome::Finished;
+                }
+                self.apply_choice();
+                self.go_to(WizardStep::ORDER[self.step.position() + 1]);
+            }
+            KeyCode::Backspace => {
+                let position = self.step.position();
+                if position > 0 {
+                    let previous = WizardStep::ORDER[position - 1];
+                    if previous != WizardStep::Channel || !self.channels.is_empty() {
+                        self.go_to(previous);
+                    }
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::Right | KeyCode::Char('+')
+                if self.step == WizardStep::Volume =>
+            {
+                self.adjust_volume(true);
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Left | KeyCode::Char('-')
+                if self.step == WizardStep::Volume =>
+            {
+                self.adjust_volume(false);
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                let count = self.choices().len();
+                if self.selected + 1 < count {
+                    self.selected += 1;
+                }
+            }
+            _ => {}
+        }
+        WizardOutcome::Continue
+    }
+
+    fn go_to(&mut self, step: WizardStep) {
+        self.step = step;
+        self.selected = self.current_choice();
+    }
+
+    fn adjust_volume(&mut self, up: bool) {
+        let volume = self.config.volume.unwrap_or(50);
+        let volume = if up {
+            volume.saturating_add(VOLUME_STEP).min(100)
+        } else {
+            volume.saturating_sub(VOLUME_STEP)
+        };
+        self.config.volume = Some(volume);
+    }
+
+    /// Options of the current step, or nothing for non-list steps
+    fn choices(&self) -> Vec<String> {
+        match self.step {
+            WizardStep::Channel => self.channels.iter().map(|(_, title)| title.clone()).collect(),
+            WizardStep::Quality => StreamQuality::ALL
+                .iter()
+                .map(|quality| match quality {
+                    StreamQuality::Highest => "Highest - best bitrate the channel offers",
+                    StreamQuality::High => "High - 128 kbps MP3 (recommended)",
+                    StreamQuality::Low => "Low - for slow or metered connections",
+                }.to_string())
+                .collect(),
+            WizardStep::Theme => Theme::ALL
+                .iter()
+                .map(|theme| match theme {
+                    Theme::Default => "Default - for dark terminals",
+                    Theme::Light => "Light - for light terminals",
+                    Theme::Monochrome => "Monochrome - no colors",
+                }.to_string())
+                .collect(),
+            WizardStep::AutoStart => vec![
+                "No - show the channel list on startup".to_string(),
+                "Yes - play the default channel on startup".to_string(),
+            ],
+            WizardStep::Logging => vec![
+                "Yes - keep logs in ~/.config/soma-player/logs".to_string(),
+                "No - don't write log files".to_string(),
+            ],
+            WizardStep::Volume | WizardStep::Summary => Vec::new(),
+        }
+    }
+
+    /// Index of the option matching the current config
+    fn current_choice(&self) -> usize {
+        match self.step {
+            WizardStep::Channel => self.config.last_channel_id
+                .as_ref()
+                .and_then(|id| self.channels.iter().position(|(c, _)| c == id))
+                .unwrap_or(0),
+            WizardStep::Quality => StreamQuality::ALL.iter().position(|q| *q == self.config.quality).unwrap_or(0),
+            WizardStep::Theme => Theme::ALL.iter().position(|t| *t == self.config.theme).unwrap_or(0),
+            WizardStep::AutoStart => usize::from(self.config.auto_start),
+            WizardStep::Logging => usize::from(!self.config.log_to_file),
+            WizardStep::Volume | WizardStep::Summary => 0,
+        }
+    }
+
+    fn apply_choice(&mut self) {
+        let selected = self.selected;
+        match self.step {
+            WizardStep::Channel => {
+                self.config.last_channel_id = self.channels.get(selected).map(|(id, _)| id.clone());
+            }
+            WizardStep::Quality => self.config.quality = StreamQuality::ALL[selected],
+            WizardStep::Theme => self.config.theme = Theme::ALL[selected],
+            WizardStep::AutoStart => self.config.auto_start = selected == 1,
+            WizardStep::Logging => self.config.log_to_file = selected == 0,
+            WizardStep::Volume | WizardStep::Summary => {}
+        }
+    }
+
+    /// Theme to draw with, previewing the highlighted one on the theme step
+    fn preview_theme(&self) -> Theme {
+        match self.step {
+            WizardStep::Theme => Theme::ALL[self.selected],
+            _ => self.config.theme,
+        }
+    }
+
+    fn summary(&self) -> Vec<Line<'static>> {
+        let channel = self.config.last_channel_id
+            .as_ref()
+            .and_then(|id| self.channels.iter().find(|(c, _)| c == id))
+            .map(|(_, title)| title.clone())
+            .unwrap_or_else(|| "(first in list)".to_string());
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+
+        // Each row names the config.toml key it is saved as
+        let setting = |name: &str, key: &str, value: String| {
+            debug_assert!(schema::key(key).is_some(), "{} is not a config key", key);
+            Line::from(vec![
+                Span::styled(format!("  {}", pad(name, 16)), Style::default().fg(Color::Yellow)),
+                Span::raw(pad(&value, 24)),
+                Span::styled(key.to_string(), Style::default().fg(Color::DarkGray)),
+            ])
+        };
+
+        vec![
+            setting("Default channel", "last_channel_id", channel),
+            setting("Volume", "volume", format!("{}%", self.config.volume.unwrap_or(50))),
+            setting("Stream quality", "quality", self.config.quality.to_string()),
+            setting("Theme", "theme", self.config.theme.to_string()),
+            setting("Auto-start", "auto_start", yes_no(self.config.auto_start).to_string()),
+            setting("File logging", "log_to_file", yes_no(self.config.log_to_file).to_string()),
+            Line::from(""),
+            Line::from(format!("Settings are saved to {}", self.config_path.display())),
+            Line::from("Edit that file at any time to change them; `soma-player config edit` opens it."),
+            Line::from(format!(
+                "`soma-player config print-default` explains all {} keys.",
+                schema::KEYS.len()
+            )),
+        ]
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let theme = self.preview_theme();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([
+                Constraint::Length(3), // Header
+                Constraint::Length(3), // Prompt
+                Constraint::Min(0),    // Options
+                Constraint::Length(3), // Controls
+            ])
+            .split(frame.area());
+
+        let header = Paragraph::new(format!(
+            "🎵 Welcome to SomaFM Player - Setup ({}/{}) 🎵",
+            self.step.position() + 1,
+            WizardStep::ORDER.len()
+        ))
+        .style(theme.accent())
+        .block(Block::default().borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+        frame.render_widget(header, chunks[0]);
+
+        let prompt = Paragraph::new(self.step.prompt())
+            .block(Block::default().borders(Borders::ALL).title(self.step.title()))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(prompt, chunks[1]);
+
+        match self.step {
+            WizardStep::Volume => {
+                let volume = self.config.volume.unwrap_or(50);
+                let gauge = Gauge::default()
+                    .block(Block::default().borders(Borders::ALL))
+                    .gauge_style(theme.playing())
+                    .percent(volume.into())
+                    .label(format!("{}%", volume));
+                let area = Layout::default()
+                    .constraints([Constraint::Length(3), Constraint::Min(0)])
+                    .split(chunks[2])[0];
+                frame.render_widget(gauge, area);
+            }
+            WizardStep::Summary => {
+                let summary = Paragraph::new(self.summary())
+                    .block(Block::default().borders(Borders::ALL))
+                    .wrap(Wrap { trim: true });
+                frame.render_widget(summary, chunks[2]);
+            }
+            _ => {
+                let items: Vec<ListItem> = self.choices().into_iter().map(ListItem::new).collect();
+                let mut list_state = ListState::default();
+                list_state.select(Some(self.selected));
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL))
+                    .highlight_style(theme.highlight())
+                    .highlight_symbol("► ");
+                frame.render_stateful_widget(list, chunks[2], &mut list_state);
+            }
+        }
+
+        let (select_keys, confirm) = match self.step {
+            WizardStep::Volume => ("←→", "Next"),
+            WizardStep::Summary => ("", "Save"),
+            _ => ("↑↓", "Next"),
+        };
+        let key_style = |color: Color| Style::default().fg(color).add_modifier(Modifier::BOLD);
+        let mut controls = Vec::new();
+        if !select_keys.is_empty() {
+            controls.push(Span::styled(select_keys, key_style(Color::Cyan)));
+            controls.push(Span::raw(" - Adjust  |  "));
+        }
+        controls.extend([
+            Span::styled("Enter", key_style(Color::Green)),
+            Span::raw(format!(" - {}  |  ", confirm)),
+            Span::styled("Backspace", key_style(Color::Yellow)),
+            Span::raw(" - Back  |  "),
+            Span::styled("Esc", key_style(Color::Red)),
+            Span::raw(" - Skip setup"),
+        ]);
+        let controls_widget = Paragraph::new(Line::from(controls))
+            .block(Block::default().borders(Borders::ALL).title("Controls"))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(controls_widget, chunks[3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEventKind;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new_with_kind(code, KeyModifiers::NONE, KeyEventKind::Press)
+    }
+
+    #[test]
+    fn test_wizard_collects_choices() {
+        let channels = [Channel::named("groovesalad", "Groove Salad"), Channel::named("dronezone", "Drone Zone")];
+        let mut wizard = SetupWizard::new(&channels, PathBuf::from("config.toml"));
+
+        let presses = [
+            KeyCode::Down, KeyCode::Enter,                 // Drone Zone
+            KeyCode::Right, KeyCode::Right, KeyCode::Enter, // 60%
+            KeyCode::Up, KeyCode::Enter,                   // Highest quality
+            KeyCode::Down, KeyCode::Down, KeyCode::Enter,  // Monochrome
+            KeyCode::Down, KeyCode::Enter,                 // Auto-start
+            KeyCode::Down, KeyCode::Enter,                 // No file logging
+        ];
+        for code in presses {
+            assert_eq!(wizard.handle_key(key(code)), WizardOutcome::Continue);
+        }
+        assert_eq!(wizard.step(), WizardStep::Summary);
+        assert_eq!(wizard.handle_key(key(KeyCode::Enter)), WizardOutcome::Finished);
+
+        let config = wizard.into_config();
+        assert_eq!(config.last_channel_id.as_deref(), Some("dronezone"));
+        assert_eq!(config.volume, Some(60));
+        assert_eq!(config.quality, StreamQuality::Highest);
+        assert_eq!(config.theme, Theme::Monochrome);
+        assert!(config.auto_start);
+        assert!(!config.log_to_file);
+    }
+
+    #[test]
+    fn test_wizard_back_and_cancel() {
+        let mut wizard = SetupWizard::new(&[Channel::named("groovesalad", "Groove Salad")], PathBuf::from("config.toml"));
+        wizard.handle_key(key(KeyCode::Enter));
+        assert_eq!(wizard.step(), WizardStep::Volume);
+        wizard.handle_key(key(KeyCode::Backspace));
+        assert_eq!(wizard.step(), WizardStep::Channel);
+        assert_eq!(wizard.handle_key(key(KeyCode::Esc)), WizardOutcome::Cancelled);
+    }
+
+    #[test]
+    fn test_wizard_skips_channel_step_without_channels() {
+        let mut wizard = SetupWizard::new(&[], PathBuf::from("config.toml"));
+        assert_eq!(wizard.step(), WizardStep::Volume);
+        wizard.handle_key(key(KeyCode::Backspace));
+        assert_eq!(wizard.step(), WizardStep::Volume);
+    }
+}
@@ -0,0 +1,265 @@
+//! Generic now-playing webhook.
+//!
+//! When a `[webhook]` URL is configured, the player POSTs a JSON payload on
+//! every track or playback state change. Failed requests are retried with
+//! exponential backoff. With a `secret` set, each request carries an
+//! `X-Soma-Signature: sha256=<hex>` header holding the HMAC-SHA256 of the
+//! body, so receivers can verify the sender.
+//!
+//! ```json
+//! {
+//!   "event": "track_changed",
+//!   "timestamp": 1760000000,
+//!   "pid": 4242,
+//!   "channel_id": "groovesalad",
+//!   "channel": "Groove Salad",
+//!   "artist": "Thievery Corporation",
+//!   "title": "Lebanese Blonde",
+//!   "volume": 75,
+//!   "paused": false
+//! }
+//! ```
+
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::{debug, warn};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::config::WebhookConfig;
+use crate::state_file::PlayerStatus;
+
+/// Header carrying the payload signature
+pub const SIGNATURE_HEADER: &str = "X-Soma-Signature";
+
+/// Per-request timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before the first retry, doubled for each further attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// What changed to trigger a webhook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    TrackChanged,
+    Paused,
+    Resumed,
+    VolumeChanged,
+    Stopped,
+}
+
+impl WebhookEvent {
+    /// Works out which event, if any, leads from `previous` to `current`.
+    /// Statuses without a known track are skipped until metadata arrives.
+    pub fn between(previous: Option<&PlayerStatus>, current: &PlayerStatus) -> Option<Self> {
+        let was_playing = previous.is_some_and(|p| p.channel_id.is_some());
+
+        if current.channel_id.is_none() {
+            return was_playing.then_some(WebhookEvent::Stopped);
+        }
+        current.title.as_ref()?;
+
+        let Some(previous) = previous.filter(|_| was_playing) else {
+            return Some(WebhookEvent::TrackChanged);
+        };
+
+        if previous.channel_id != current.channel_id
+            || previous.artist != current.artist
+            || previous.title != current.title
+        {
+            Some(WebhookEvent::TrackChanged)
+        } else if previous.paused != current.paused {
+            Some(if current.paused { WebhookEvent::Paused } else { WebhookEvent::Resumed })
+        } else if previous.volume != current.volume {
+            Some(WebhookEvent::VolumeChanged)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    event: WebhookEvent,
+    timestamp: i64,
+    #[serde(flatten)]
+    status: &'a PlayerStatus,
+}
+
+/// Returns the hex HMAC-SHA256 of `body` keyed with `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Posts status changes to the configured webhook.
+#[derive(Debug)]
+pub struct WebhookPoster {
+    client: reqwest::Client,
+    url: String,
+    secret: Option<String>,
+    retries: u32,
+    last_status: Option<PlayerStatus>,
+}
+
+impl WebhookPoster {
+    /// Returns a poster, or `None` when no URL is configured.
+    pub fn new(config: &WebhookConfig) -> Option<Self> {
+        let url = config.url.clone().filter(|u| !u.trim().is_empty())?;
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| warn!("Webhook disabled: {}", e))
+            .ok()?;
+
+        Some(Self {
+            client,
+            url,
+            secret: config.secret.clone().filter(|s| !s.is_empty()),
+            retries: config.retries,
+            last_status: None,
+        })
+    }
+
+    /// Posts `status` in the background if it differs meaningfully from the
+    /// last one. Must be called within a Tokio runtime.
+    pub fn update(&mut self, status: &PlayerStatus) {
+        let Some(event) = WebhookEvent::between(self.last_status.as_ref(), status) else {
+            if status.title.is_some() || status.channel_id.is_none() {
+                self.last_status = Some(status.clone());
+            }
+            return;
+        };
+        self.last_status = Some(status.clone());
+
+        match self.request(event, status) {
+            Ok(request) => {
+                tokio::spawn(send_with_retries(self.client.clone(), request, self.retries));
+            }
+            Err(e) => warn!("Failed to build webhook request: {}", e),
+        }
+    }
+
+    /// Posts the final `stopped` event and waits for it to be delivered,
+    /// without retrying.
+    pub async fn finish(mut self) {
+        let stopped = PlayerStatus::idle();
+        if WebhookEvent::between(self.last_status.as_ref(), &stopped).is_none() {
+            return;
+        }
+        self.last_status = None;
+
+        match self.request(WebhookEvent::Stopped, &stopped) {
+            Ok(request) => send_with_retries(self.client.clone(), request, 0).await,
+            Err(e) => warn!("Failed to build webhook request: {}", e),
+        }
+    }
+
+    fn request(&self, event: WebhookEvent, status: &PlayerStatus) -> Result<reqwest::Request, String> {
+        let body = serde_json::to_vec(&WebhookPayload {
+            event,
+            timestamp: Utc::now().timestamp(),
+            status,
+        })
+        .map_err(|e| e.to_string())?;
+
+        let mut builder = self.client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = &self.secret {
+            builder = builder.header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, &body)));
+        }
+        builder.body(body).build().map_err(|e| e.to_string())
+    }
+}
+
+async fn send_with_retries(client: reqwest::Client, request: reqwest::Request, retries: u32) {
+    let mut delay = RETRY_BASE_DELAY;
+
+    for attempt in 0..=retries {
+        let Some(request) = request.try_clone() else {
+            break;
+        };
+        match client.execute(request).await.and_then(|r| r.error_for_status()) {
+            Ok(_) => {
+                debug!("Webhook delivered");
+                return;
+            }
+            Err(e) if attempt < retries => {
+                debug!("Webhook attempt {} failed: {}, retrying in {:?}", attempt + 1, e, delay);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => warn!("Webhook failed after {} attempts: {}", attempt + 1, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing(title: &str) -> PlayerStatus {
+        PlayerStatus {
+            channel_id: Some("groovesalad".to_string()),
+            channel: Some("Groove Salad".to_string()),
+            artist: Some("Artist".to_string()),
+            title: Some(title.to_string()),
+            volume: Some(50),
+            ..PlayerStatus::idle()
+        }
+    }
+
+    #[test]
+    fn test_sign_matches_rfc_4231() {
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_event_detection() {
+        let idle = PlayerStatus::idle();
+        let first = playing("One");
+
+        assert_eq!(WebhookEvent::between(None, &idle), None);
+        assert_eq!(WebhookEvent::between(Some(&idle), &first), Some(WebhookEvent::TrackChanged));
+        assert_eq!(WebhookEvent::between(Some(&first), &first), None);
+        assert_eq!(WebhookEvent::between(Some(&first), &playing("Two")), Some(WebhookEvent::TrackChanged));
+
+        let paused = PlayerStatus { paused: true, ..first.clone() };
+        assert_eq!(WebhookEvent::between(Some(&first), &paused), Some(WebhookEvent::Paused));
+        assert_eq!(WebhookEvent::between(Some(&paused), &first), Some(WebhookEvent::Resumed));
+
+        let louder = PlayerStatus { volume: Some(55), ..first.clone() };
+        assert_eq!(WebhookEvent::between(Some(&first), &louder), Some(WebhookEvent::VolumeChanged));
+
+        assert_eq!(WebhookEvent::between(Some(&first), &idle), Some(WebhookEvent::Stopped));
+    }
+
+    #[test]
+    fn test_event_waits_for_metadata() {
+        let loading = PlayerStatus { artist: None, title: None, ..playing("One") };
+        assert_eq!(WebhookEvent::between(None, &loading), None);
+    }
+
+    #[test]
+    fn test_payload_shape() {
+        let status = playing("One");
+        let payload = serde_json::to_value(WebhookPayload {
+            event: WebhookEvent::TrackChanged,
+            timestamp: 1,
+            status: &status,
+        })
+        .unwrap();
+        assert_eq!(payload["event"], "track_changed");
+        assert_eq!(payload["channel_id"], "groovesalad");
+        assert_eq!(payload["title"], "One");
+    }
+}